@@ -148,12 +148,14 @@ pub(crate) fn impl_derive_control(input: TokenStream, primitives: &HashSet<Ident
 
         let mut id_bodies = Vec::default();
         let mut setter_bodies = Vec::default();
+        let mut getter_bodies = Vec::default();
         attr_fields.iter().for_each(|(ident, ident_type, is_leaf)| {
             let id = ident.to_string().to_case(Case::Kebab);
             if primitives.contains(ident_type) || *is_leaf {
                 let name_const = format_ident!("set_{}", ident);
                 id_bodies.push(quote! {Some(#id.to_string())});
                 setter_bodies.push(quote! {self.#name_const(value.into());});
+                getter_bodies.push(quote! {Some(self.#ident.into())});
             } else {
                 let field_index_name = index_const_id(ident);
                 let name_const = name_const_id(ident);
@@ -161,6 +163,8 @@ pub(crate) fn impl_derive_control(input: TokenStream, primitives: &HashSet<Ident
                 id_bodies.push(quote! { Some(format!("{}-{}", Self::#name_const, self.#ident.control_name_for_index(#main_crate::automation::ControlIndex(index.0 - Self::#field_index_name)).unwrap()))});
                 setter_bodies
                     .push(quote! {self.#ident.control_set_param_by_index(#main_crate::automation::ControlIndex(index.0 - Self::#field_index_name), value); self.#notify_const(); });
+                getter_bodies
+                    .push(quote! {self.#ident.control_get_param_by_index(#main_crate::automation::ControlIndex(index.0 - Self::#field_index_name))});
             }
         });
         let control_name_for_index_body = quote! {
@@ -179,6 +183,14 @@ pub(crate) fn impl_derive_control(input: TokenStream, primitives: &HashSet<Ident
                 }
             }
         };
+        let control_get_param_by_index_bodies = quote! {
+            fn control_get_param_by_index(&self, index: #main_crate::automation::ControlIndex) -> Option<#main_crate::automation::ControlValue> {
+                match index.0 {
+                    #( Self::#index_const_ids..=Self::#index_const_range_end_ids => {#getter_bodies}, )*
+                    _ => {None},
+                }
+            }
+        };
 
         // These need to be separate vecs because we divide the fields into
         // groups of maybe different sizes, which is a repetitions no-no.
@@ -243,6 +255,7 @@ pub(crate) fn impl_derive_control(input: TokenStream, primitives: &HashSet<Ident
                 #control_name_for_index_body
                 #control_index_for_name_body
                 #control_set_param_by_index_bodies
+                #control_get_param_by_index_bodies
             }
         };
         quote
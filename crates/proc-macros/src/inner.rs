@@ -50,6 +50,7 @@ pub(crate) fn impl_derive_inner_controllable(input: TokenStream) -> TokenStream
                         fn control_name_for_index(&self, index: ControlIndex) -> Option<String>;
                         fn control_set_param_by_name(&mut self, name: &str, value: #crate_name::automation::ControlValue);
                         fn control_set_param_by_index(&mut self, index: #crate_name::automation::ControlIndex, value: #crate_name::automation::ControlValue);
+                        fn control_get_param_by_index(&self, index: #crate_name::automation::ControlIndex) -> Option<#crate_name::automation::ControlValue>;
                     }
                 }
             }
@@ -74,10 +75,12 @@ pub(crate) fn impl_derive_inner_controls(input: TokenStream) -> TokenStream {
                         fn time_range(&self) -> Option<TimeRange>;
                         fn update_time_range(&mut self, time_range: &TimeRange);
                         fn work(&mut self, control_events_fn: &mut ControlEventsFn);
+                        fn work_timed(&mut self, control_events_fn: &mut #crate_name::automation::TimedControlEventsFn);
                         fn is_finished(&self) -> bool;
                         fn play(&mut self);
                         fn stop(&mut self);
                         fn skip_to_start(&mut self);
+                        fn seek(&mut self, position: #crate_name::types::MusicalTime);
                     }
                 }
             }
@@ -99,7 +102,9 @@ pub(crate) fn impl_derive_inner_effect(input: TokenStream) -> TokenStream {
             impl #generics #crate_name::traits::TransformsAudio for #struct_name #ty_generics {
                 delegate::delegate! {
                     to self.inner {
+                        fn transform(&mut self, samples: &mut [StereoSample]);
                         fn transform_channel(&mut self, channel: usize, input_sample: Sample) -> Sample;
+                        fn set_sidechain(&mut self, samples: &[StereoSample]);
                     }
                 }
             }
@@ -196,6 +201,7 @@ pub(crate) fn impl_inner_transforms_audio_derive(input: TokenStream) -> TokenStr
                     to self.inner {
                         fn transform(&mut self, samples: &mut [StereoSample]);
                         fn transform_channel(&mut self, channel: usize, input_sample: Sample) -> Sample;
+                        fn set_sidechain(&mut self, samples: &[StereoSample]);
                     }
                 }
             }
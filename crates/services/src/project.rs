@@ -6,7 +6,11 @@ use crossbeam_channel::{Receiver, Sender};
 use eframe::egui::Key;
 #[cfg(feature = "egui")]
 use egui::KeyHandler;
-use ensnare::{orchestration::AudioSenderFn, prelude::*, types::VisualizationQueue};
+use ensnare::{
+    orchestration::{AudioSenderFn, ProjectCommand},
+    prelude::*,
+    types::{TempoChange, TimeSignatureChange, VisualizationQueue},
+};
 use std::{
     path::PathBuf,
     sync::{Arc, RwLock},
@@ -21,15 +25,28 @@ pub enum ProjectServiceInput {
     KeyEvent(Key, bool, Option<Key>),
     Midi(MidiChannel, MidiMessage),
     NextTimelineDisplayer,
+    ProjectAddTempoChange(TempoChange),
+    ProjectAddTimeSignatureChange(TimeSignatureChange),
+    ProjectExecute(ProjectCommand),
     ProjectExportToWav(Option<PathBuf>),
     ProjectLinkControl(Uid, Uid, ControlIndex),
     ProjectLoad(PathBuf),
     ProjectNew,
     ProjectPlay,
+    ProjectRedo,
     ProjectRemoveEntity(Uid),
+    ProjectRemoveTempoChange(MusicalTime),
+    ProjectRemoveTimeSignatureChange(MusicalTime),
     ProjectSave(Option<PathBuf>),
+    ProjectSeek(MusicalTime),
+    ProjectSetAutomationRecordMode(Option<AutomationRecordMode>),
+    ProjectSetLoopRange(Option<TimeRange>),
+    ProjectSetMidiRecordOptions(MidiRecordOptions),
+    ProjectSetMidiRecordTrack(Option<TrackUid>),
+    ProjectSetPunchRange(Option<TimeRange>),
     ProjectSetSampleRate(SampleRate),
     ProjectStop,
+    ProjectUndo,
     ServiceInit,
     ServiceQuit,
     TrackAddEntity(TrackUid, EntityKey),
@@ -42,6 +59,7 @@ pub enum ProjectServiceInput {
 #[allow(missing_docs)]
 #[derive(Debug)]
 pub enum ProjectServiceEvent {
+    ExecuteFailed(Error),
     ExportFailed(Error),
     Exported(PathBuf),
     IsPerformingChanged(bool),
@@ -49,9 +67,11 @@ pub enum ProjectServiceEvent {
     Loaded(Arc<RwLock<Project>>), // The supplied Project is for the recipient to keep. No need to Arc::clone().
     Midi(MidiChannel, MidiMessage), // Handled by EnsnareEventAggregationService, never sent to app.
     Quit,
+    RedoFailed(Error),
     SaveFailed(Error),
     Saved(PathBuf),
     TitleChanged(ProjectTitle),
+    UndoFailed(Error),
 }
 
 /// A wrapper around a [Project] that provides a channel-based interface to it.
@@ -149,6 +169,13 @@ impl ProjectServiceDaemon {
         self.notify_new_project();
     }
 
+    // Applies an edit through the project's undo history.
+    fn execute_command(&self, command: ProjectCommand) {
+        if let Err(e) = self.project.write().unwrap().execute(command) {
+            let _ = self.sender.send(ProjectServiceEvent::ExecuteFailed(e));
+        }
+    }
+
     fn execute(&mut self) {
         while let Ok(input) = self.receiver.recv() {
             match input {
@@ -184,6 +211,45 @@ impl ProjectServiceDaemon {
                     let _ = self.sender.send(ProjectServiceEvent::Quit);
                     break;
                 }
+                ProjectServiceInput::ProjectSeek(position) => {
+                    self.project.write().unwrap().seek(position);
+                }
+                ProjectServiceInput::ProjectSetAutomationRecordMode(mode) => {
+                    self.project
+                        .write()
+                        .unwrap()
+                        .set_automation_record_mode(mode);
+                }
+                ProjectServiceInput::ProjectSetLoopRange(loop_range) => {
+                    self.execute_command(ProjectCommand::SetLoopRange(loop_range));
+                }
+                ProjectServiceInput::ProjectSetMidiRecordOptions(options) => {
+                    self.project
+                        .write()
+                        .unwrap()
+                        .set_midi_record_options(options);
+                }
+                ProjectServiceInput::ProjectSetMidiRecordTrack(track_uid) => {
+                    self.project
+                        .write()
+                        .unwrap()
+                        .set_midi_record_track(track_uid);
+                }
+                ProjectServiceInput::ProjectSetPunchRange(punch_range) => {
+                    self.execute_command(ProjectCommand::SetPunchRange(punch_range));
+                }
+                ProjectServiceInput::ProjectAddTempoChange(change) => {
+                    self.execute_command(ProjectCommand::AddTempoChange(change));
+                }
+                ProjectServiceInput::ProjectRemoveTempoChange(time) => {
+                    self.execute_command(ProjectCommand::RemoveTempoChange(time));
+                }
+                ProjectServiceInput::ProjectAddTimeSignatureChange(change) => {
+                    self.execute_command(ProjectCommand::AddTimeSignatureChange(change));
+                }
+                ProjectServiceInput::ProjectRemoveTimeSignatureChange(time) => {
+                    self.execute_command(ProjectCommand::RemoveTimeSignatureChange(time));
+                }
                 ProjectServiceInput::ProjectSetSampleRate(sample_rate) => {
                     self.project
                         .write()
@@ -203,21 +269,15 @@ impl ProjectServiceDaemon {
                         .send(ProjectServiceEvent::IsPerformingChanged(false));
                 }
                 ProjectServiceInput::TrackAddEntity(track_uid, key) => {
-                    if let Ok(mut project) = self.project.write() {
-                        let uid = project.mint_entity_uid();
-                        if let Some(entity) = self.factory.new_entity(&key, uid) {
-                            let _ = project.add_entity(track_uid, entity);
-                        } else {
-                            eprintln!("ProjectServiceInput::TrackAddEntity failed");
-                        }
+                    let uid = self.project.read().unwrap().mint_entity_uid();
+                    if let Some(entity) = self.factory.new_entity(&key, uid) {
+                        self.execute_command(ProjectCommand::AddEntity(track_uid, entity));
+                    } else {
+                        eprintln!("ProjectServiceInput::TrackAddEntity failed");
                     }
                 }
                 ProjectServiceInput::ProjectLinkControl(source_uid, target_uid, index) => {
-                    let _ = self
-                        .project
-                        .write()
-                        .unwrap()
-                        .link(source_uid, target_uid, index);
+                    self.execute_command(ProjectCommand::Link(source_uid, target_uid, index));
                 }
                 #[cfg(feature = "egui")]
                 ProjectServiceInput::KeyEvent(key, pressed, _physical_key) => {
@@ -251,16 +311,29 @@ impl ProjectServiceDaemon {
                         eprintln!("TODO: {c:?} {m:?}");
                     }),
                 ProjectServiceInput::ProjectRemoveEntity(uid) => {
-                    let _ = self.project.write().unwrap().remove_entity(uid);
+                    self.execute_command(ProjectCommand::RemoveEntity(uid));
+                }
+                ProjectServiceInput::ProjectExecute(command) => {
+                    self.execute_command(command);
+                }
+                ProjectServiceInput::ProjectUndo => {
+                    if let Err(e) = self.project.write().unwrap().undo() {
+                        let _ = self.sender.send(ProjectServiceEvent::UndoFailed(e));
+                    }
+                }
+                ProjectServiceInput::ProjectRedo => {
+                    if let Err(e) = self.project.write().unwrap().redo() {
+                        let _ = self.sender.send(ProjectServiceEvent::RedoFailed(e));
+                    }
                 }
                 ProjectServiceInput::TrackNewAudio => {
-                    let _ = self.project.write().unwrap().new_audio_track();
+                    self.execute_command(ProjectCommand::NewAudioTrack);
                 }
                 ProjectServiceInput::TrackNewAux => {
-                    let _ = self.project.write().unwrap().new_aux_track();
+                    self.execute_command(ProjectCommand::NewAuxTrack);
                 }
                 ProjectServiceInput::TrackNewMidi => {
-                    let _ = self.project.write().unwrap().new_midi_track();
+                    self.execute_command(ProjectCommand::NewMidiTrack);
                 }
                 ProjectServiceInput::ProjectExportToWav(path) => {
                     let path = path.unwrap_or(PathBuf::from("exported-project.wav"));
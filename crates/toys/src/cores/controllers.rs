@@ -225,9 +225,13 @@ impl Controls for ToySequencerCore {
     }
 
     fn work(&mut self, control_events_fn: &mut ControlEventsFn) {
+        self.work_timed(&mut |_, event| control_events_fn(event));
+    }
+
+    fn work_timed(&mut self, control_events_fn: &mut TimedControlEventsFn) {
         self.events.iter().for_each(|e| {
             if self.time_range.0.contains(&e.time) {
-                control_events_fn(WorkEvent::Midi(MidiChannel::default(), e.message))
+                control_events_fn(e.time, WorkEvent::Midi(MidiChannel::default(), e.message))
             }
         });
     }
@@ -224,6 +224,11 @@ mod tests {
         );
         synth.control_set_param_by_index(param_index, ControlValue(0.22));
         assert_eq!(synth.dca().gain().0, 0.22);
+        assert_eq!(
+            synth.control_get_param_by_index(param_index),
+            Some(ControlValue(0.22)),
+            "nested params should be readable"
+        );
         synth.inner.voices().for_each(|v| {
             assert_eq!(
                 synth.dca().gain(),
@@ -233,6 +238,76 @@ mod tests {
         });
     }
 
+    // Puts a whole synth one level below a leaf, so that the synth's params
+    // are two levels deep.
+    #[derive(Control)]
+    struct ToySynthRack {
+        #[control]
+        level: Normal,
+
+        #[control]
+        synth: ToySynthCore,
+    }
+    impl ToySynthRack {
+        fn set_level(&mut self, level: Normal) {
+            self.level = level;
+        }
+
+        fn notify_change_synth(&mut self) {}
+    }
+
+    #[test]
+    fn toy_synth_rack_reads_nested_params() {
+        let mut rack = ToySynthRack {
+            level: Normal::from(0.5),
+            synth: ToySynthCore::new_with(
+                OscillatorBuilder::default().build().unwrap(),
+                EnvelopeBuilder::safe_default().build().unwrap(),
+                Dca::default(),
+            ),
+        };
+
+        assert_eq!(
+            rack.control_get_param_by_index(ControlIndex(ToySynthRack::LEVEL_INDEX)),
+            Some(ControlValue(0.5))
+        );
+
+        let gain_index = rack.control_index_for_name("synth-dca-gain").unwrap();
+        rack.control_set_param_by_index(gain_index, ControlValue(0.33));
+        assert_eq!(rack.synth.dca().gain().0, 0.33);
+        assert_eq!(
+            rack.control_get_param_by_index(gain_index),
+            Some(ControlValue(0.33)),
+            "params two levels deep should be readable"
+        );
+
+        let last_index = ControlIndex(ToySynthRack::SYNTH_RANGE_END);
+        assert_eq!(
+            rack.control_name_for_index(last_index),
+            Some("synth-dca-pan".to_string()),
+            "the end of the synth's range should be its last param"
+        );
+        rack.control_set_param_by_index(last_index, ControlValue(0.75));
+        assert_eq!(rack.synth.dca().pan(), BipolarNormal::from(0.5));
+        assert_eq!(
+            rack.control_get_param_by_index(last_index),
+            Some(ControlValue(0.75)),
+            "the last index of a node's range should be readable"
+        );
+
+        assert_eq!(
+            rack.synth
+                .control_get_param_by_index(ControlIndex(ToySynthCore::STRUCT_SIZE)),
+            None,
+            "an index past a nested struct's params should read as None"
+        );
+        assert_eq!(
+            rack.control_get_param_by_index(ControlIndex(ToySynthRack::STRUCT_SIZE)),
+            None,
+            "an index past all the params should read as None"
+        );
+    }
+
     impl GeneratesStereoSampleAndHandlesMidi for ToySynthCore {}
 
     #[test]
@@ -2,6 +2,7 @@
 
 use ensnare::prelude::*;
 use std::sync::Arc;
+use strum::IntoEnumIterator;
 use strum_macros::Display;
 
 #[derive(Clone, Debug, Display)]
@@ -11,6 +12,9 @@ pub(crate) enum MenuBarAction {
     ProjectOpen,
     ProjectSave,
     ProjectExportToWav,
+    ProjectUndo,
+    ProjectRedo,
+    ProjectSetAutomationRecordMode(Option<AutomationRecordMode>),
     TrackNewMidi,
     TrackNewAudio,
     TrackNewAux,
@@ -108,6 +112,9 @@ impl Displays for MenuBar {
                         MenuBarItem::leaf("Open", MenuBarAction::ProjectOpen, true),
                         MenuBarItem::leaf("Save", MenuBarAction::ProjectSave, true),
                         MenuBarItem::leaf("Export to WAV", MenuBarAction::ProjectExportToWav, true),
+                        MenuBarItem::leaf("Undo", MenuBarAction::ProjectUndo, true),
+                        MenuBarItem::leaf("Redo", MenuBarAction::ProjectRedo, true),
+                        MenuBarItem::node("Record Automation", self.automation_record_menu()),
                         MenuBarItem::leaf("Quit", MenuBarAction::Quit, true),
                     ],
                 ),
@@ -170,6 +177,22 @@ impl MenuBar {
         )]
     }
 
+    fn automation_record_menu(&self) -> Vec<MenuBarItem> {
+        let mut items = vec![MenuBarItem::leaf(
+            "Off",
+            MenuBarAction::ProjectSetAutomationRecordMode(None),
+            true,
+        )];
+        items.extend(AutomationRecordMode::iter().map(|mode| {
+            MenuBarItem::leaf(
+                &mode.to_string(),
+                MenuBarAction::ProjectSetAutomationRecordMode(Some(mode)),
+                true,
+            )
+        }));
+        items
+    }
+
     #[allow(dead_code)]
     pub(crate) fn set_is_any_track_selected(&mut self, is_any_track_selected: bool) {
         self.is_track_selected = is_any_track_selected;
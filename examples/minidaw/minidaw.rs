@@ -22,7 +22,7 @@ use ensnare::{
         ComposerWidget, ControlBar, ControlBarAction, ControlBarWidget, EntityPaletteWidget,
         ObliqueStrategiesWidget, ProjectAction, ProjectWidget, TransportWidget,
     },
-    orchestration::AudioSenderFn,
+    orchestration::{AudioSenderFn, ProjectCommand},
     prelude::*,
     types::BoundedCrossbeamChannel,
 };
@@ -121,18 +121,46 @@ impl<'a> TabViewer for MiniDawTabViewer<'a> {
                         ui.heading(title);
                         ui.separator();
                         let mut action = None;
+
+                        // If we're recording automation, then find out which
+                        // parameters the user changes.
+                        let snapshot = if project.automator.is_recording() {
+                            Some(project.param_snapshot(*uid))
+                        } else {
+                            None
+                        };
                         if let Some(entity) = project.orchestrator.entity_repo.entity_mut(*uid) {
                             entity.ui(ui);
                             action = entity.take_action();
                         }
+                        if let Some(snapshot) = snapshot {
+                            let params: Vec<(Uid, ControlIndex)> = snapshot
+                                .iter()
+                                .map(|(param_uid, param, _)| (*param_uid, *param))
+                                .collect();
+                            project.record_param_changes_since(snapshot);
+                            if ui.input(|i| i.pointer.any_released()) {
+                                params.into_iter().for_each(|(param_uid, param)| {
+                                    project.release_param(param_uid, param)
+                                });
+                            }
+                        }
                         if let Some(action) = action {
                             match action {
                                 DisplaysAction::Link(source, index) => match source {
                                     ControlLinkSource::Entity(source_uid) => {
-                                        let _ = project.link(source_uid, *uid, index);
+                                        let _ = project
+                                            .execute(ProjectCommand::Link(source_uid, *uid, index));
                                     }
                                     ControlLinkSource::Path(path_uid) => {
-                                        let _ = project.link_path(path_uid, *uid, index);
+                                        let _ = project.execute(ProjectCommand::LinkPath(
+                                            path_uid, *uid, index,
+                                        ));
+                                    }
+                                    ControlLinkSource::MidiCc(channel, cc) => {
+                                        let _ = project.execute(ProjectCommand::BindMidiCc(
+                                            channel, cc, *uid, index,
+                                        ));
                                     }
                                 },
                             }
@@ -417,6 +445,21 @@ impl MiniDaw {
                             .error(format!("Error exporting {}", e).to_string())
                             .duration(Some(Duration::from_secs(5)));
                     }
+                    ProjectServiceEvent::UndoFailed(e) => {
+                        self.toasts
+                            .error(format!("Error undoing {}", e).to_string())
+                            .duration(Some(Duration::from_secs(5)));
+                    }
+                    ProjectServiceEvent::RedoFailed(e) => {
+                        self.toasts
+                            .error(format!("Error redoing {}", e).to_string())
+                            .duration(Some(Duration::from_secs(5)));
+                    }
+                    ProjectServiceEvent::ExecuteFailed(e) => {
+                        self.toasts
+                            .error(format!("Error editing {}", e).to_string())
+                            .duration(Some(Duration::from_secs(5)));
+                    }
                     ProjectServiceEvent::Midi(..) => {
                         panic!("ProjectServiceEvent::Midi should be handled by the aggregation service and never forwarded")
                     }
@@ -650,6 +693,11 @@ impl MiniDaw {
             MenuBarAction::ProjectOpen => self.handle_ui_load_action(),
             MenuBarAction::ProjectSave => self.handle_ui_save_action(),
             MenuBarAction::ProjectExportToWav => self.handle_ui_export_action(),
+            MenuBarAction::ProjectUndo => self.send_to_project(ProjectServiceInput::ProjectUndo),
+            MenuBarAction::ProjectRedo => self.send_to_project(ProjectServiceInput::ProjectRedo),
+            MenuBarAction::ProjectSetAutomationRecordMode(mode) => {
+                self.send_to_project(ProjectServiceInput::ProjectSetAutomationRecordMode(mode))
+            }
             MenuBarAction::TrackNewMidi => self.send_to_project(ProjectServiceInput::TrackNewMidi),
             MenuBarAction::TrackNewAudio => {
                 self.send_to_project(ProjectServiceInput::TrackNewAudio)
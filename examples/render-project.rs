@@ -3,7 +3,10 @@
 //! This example generates a WAV file from a serialized [Project].
 
 use clap::Parser;
-use ensnare::prelude::*;
+use ensnare::{
+    orchestration::{WavExportOptions, WavSampleFormat},
+    prelude::*,
+};
 
 #[derive(Parser, Debug, Default)]
 #[clap(author, about, long_about = None)]
@@ -15,6 +18,22 @@ struct Args {
     #[clap(short = 'w', long, value_parser)]
     wav: bool,
 
+    /// Also render each track as a separate WAVE file
+    #[clap(short = 's', long, value_parser)]
+    stems: bool,
+
+    /// Bits per sample of rendered WAVE files: 16, 24, or 32 (floating point)
+    #[clap(short = 'b', long, value_parser, default_value_t = 16)]
+    bit_depth: u16,
+
+    /// Add dither when rendering to an integer format
+    #[clap(long, value_parser)]
+    dither: bool,
+
+    /// Render at this sample rate rather than the default
+    #[clap(short = 'r', long, value_parser)]
+    sample_rate: Option<usize>,
+
     /// Enable debug mode
     #[clap(short = 'd', long, value_parser)]
     debug: bool,
@@ -26,6 +45,16 @@ struct Args {
 
 fn main() -> anyhow::Result<()> {
     let args = Args::parse();
+    let options = WavExportOptions {
+        format: match args.bit_depth {
+            16 => WavSampleFormat::Int16,
+            24 => WavSampleFormat::Int24,
+            32 => WavSampleFormat::Float32,
+            _ => return Err(anyhow::anyhow!("unsupported bit depth {}", args.bit_depth)),
+        },
+        dither: args.dither,
+        sample_rate: args.sample_rate.map(SampleRate::new),
+    };
 
     for input_filename in args.input {
         match std::fs::File::open(input_filename.clone()) {
@@ -44,11 +73,27 @@ fn main() -> anyhow::Result<()> {
                             panic!("would overwrite input file; couldn't generate output filename");
                         }
                         let output_path = std::path::PathBuf::from(output_filename.to_string());
-                        if let Err(e) = project.export_to_wav(output_path) {
+                        if let Err(e) = project.export_to_wav_with_options(output_path, &options) {
                             eprintln!("error while writing {input_filename} render to {output_filename}: {e:?}");
                             return Err(e);
                         }
                     }
+                    if args.stems {
+                        let re = regex::Regex::new(r"\.json$").unwrap();
+                        let path_prefix =
+                            std::path::PathBuf::from(re.replace(&input_filename, "").to_string());
+                        match project.export_stems(path_prefix, &options) {
+                            Ok(paths) => {
+                                for (track_uid, path) in paths {
+                                    eprintln!("Wrote track {track_uid} to {path:?}");
+                                }
+                            }
+                            Err(e) => {
+                                eprintln!("error while writing {input_filename} stems: {e:?}");
+                                return Err(e);
+                            }
+                        }
+                    }
                 }
                 Err(e) => eprintln!("error while parsing {input_filename}: {e:?}"),
             },
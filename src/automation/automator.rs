@@ -1,7 +1,7 @@
 // Copyright (c) 2024 Mike Tsao
 
 use crate::{
-    automation::{PathUid, PathUidFactory},
+    automation::{recorder::AutomationTake, PathUid, PathUidFactory},
     orchestration::EntityRepository,
     prelude::*,
 };
@@ -26,22 +26,171 @@ pub struct Automator {
     is_finished: bool,
     #[serde(skip)]
     time_range: TimeRange,
+    #[serde(skip)]
+    modulated_params: FxHashMap<(Uid, ControlIndex), ModulatedParam>,
+    #[serde(skip)]
+    recorder: AutomationRecorder,
+}
+
+// Tracks automation that's being recorded from live parameter changes.
+#[derive(Debug, Default)]
+struct AutomationRecorder {
+    // None if recording isn't armed.
+    mode: Option<AutomationRecordMode>,
+    is_performing: bool,
+    takes: FxHashMap<PathUid, AutomationTake>,
+    // A copy of [Transport::punch_range].
+    punch_range: Option<TimeRange>,
+}
+
+// The current state of a parameter that's linked to at least one
+// [ControlLinkRole::Modulation] link.
+#[derive(Debug, Default)]
+struct ModulatedParam {
+    // None until a base link sends a value, or a modulation needs one.
+    base: Option<ControlValue>,
+    modulations: FxHashMap<ControlLinkSource, f64>,
+    // What the param read back as after it was last set here. If it later
+    // reads as something else, then it was set directly, such as from the UI,
+    // and that value becomes the new base.
+    readback: Option<ControlValue>,
+}
+impl ModulatedParam {
+    fn value(&self) -> ControlValue {
+        let base = self.base.unwrap_or(ControlValue(0.5));
+        ControlValue((base.0 + self.modulations.values().sum::<f64>()).clamp(0.0, 1.0))
+    }
 }
+
 impl Automator {
+    /// In [AutomationRecordMode::Touch], how long a control can sit still
+    /// before the recorder decides that it's been released.
+    pub const TOUCH_TIMEOUT: MusicalTime = MusicalTime::ONE_BEAT;
+
     /// Links a source to a target's parameter, allowing the source's output to
     /// control that parameter.
     pub fn link(&mut self, source: Uid, target: Uid, param: ControlIndex) -> Result<()> {
         self.controllables
             .entry(source)
             .or_default()
-            .push(ControlLink { uid: target, param });
+            .push(ControlLink::new_with(target, param));
         Ok(())
     }
 
     /// Removes an existing link.
     pub fn unlink(&mut self, source: Uid, target: Uid, param: ControlIndex) {
         if let Some(controllables) = self.controllables.get_mut(&source) {
-            controllables.retain(|rlink| (ControlLink { uid: target, param }) != *rlink);
+            controllables.retain(|rlink| !rlink.is_for(target, param));
+        }
+        self.forget_modulation(source.into(), target, param);
+    }
+
+    /// Sets how an existing link's values combine with those of other links to
+    /// the same parameter.
+    pub fn set_link_role(
+        &mut self,
+        source: ControlLinkSource,
+        target: Uid,
+        param: ControlIndex,
+        role: ControlLinkRole,
+    ) -> Result<()> {
+        if let Some(link) = self.link_mut(source, target, param) {
+            link.role = role;
+            self.forget_modulation(source, target, param);
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "Couldn't find link from {source} to {target}-{param}"
+            ))
+        }
+    }
+
+    // Removes a source's contribution to a parameter. If nothing modulates
+    // the parameter anymore, then it goes back to being set directly.
+    fn forget_modulation(&mut self, source: ControlLinkSource, target: Uid, param: ControlIndex) {
+        if let Some(modulated) = self.modulated_params.get_mut(&(target, param)) {
+            modulated.modulations.remove(&source);
+            if modulated.modulations.is_empty() {
+                self.modulated_params.remove(&(target, param));
+            }
+        }
+    }
+
+    /// Sets how values are transformed as they pass through an existing link,
+    /// or passes them through unchanged if mapping is None.
+    pub fn set_link_mapping(
+        &mut self,
+        source: ControlLinkSource,
+        target: Uid,
+        param: ControlIndex,
+        mapping: Option<ControlMapping>,
+    ) -> Result<()> {
+        if let Some(link) = self.link_mut(source, target, param) {
+            link.mapping = mapping;
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "Couldn't find link from {source} to {target}-{param}"
+            ))
+        }
+    }
+
+    /// Returns the mapping of an existing link, or None if the link doesn't
+    /// exist or has no mapping.
+    pub fn link_mapping(
+        &self,
+        source: ControlLinkSource,
+        target: Uid,
+        param: ControlIndex,
+    ) -> Option<ControlMapping> {
+        self.find_link(source, target, param)
+            .and_then(|link| link.mapping)
+    }
+
+    /// Returns the role of an existing link, or None if the link doesn't
+    /// exist.
+    pub fn link_role(
+        &self,
+        source: ControlLinkSource,
+        target: Uid,
+        param: ControlIndex,
+    ) -> Option<ControlLinkRole> {
+        self.find_link(source, target, param).map(|link| link.role)
+    }
+
+    fn find_link(
+        &self,
+        source: ControlLinkSource,
+        target: Uid,
+        param: ControlIndex,
+    ) -> Option<&ControlLink> {
+        let links = match source {
+            ControlLinkSource::Entity(uid) => self.controllables.get(&uid),
+            ControlLinkSource::Path(path_uid) => self.path_links.get(&path_uid),
+            ControlLinkSource::MidiCc(..) => None,
+        };
+        if let Some(links) = links {
+            links.iter().find(|link| link.is_for(target, param))
+        } else {
+            None
+        }
+    }
+
+    fn link_mut(
+        &mut self,
+        source: ControlLinkSource,
+        target: Uid,
+        param: ControlIndex,
+    ) -> Option<&mut ControlLink> {
+        let links = match source {
+            ControlLinkSource::Entity(uid) => self.controllables.get_mut(&uid),
+            ControlLinkSource::Path(path_uid) => self.path_links.get_mut(&path_uid),
+            ControlLinkSource::MidiCc(..) => None,
+        };
+        if let Some(links) = links {
+            links.iter_mut().find(|link| link.is_for(target, param))
+        } else {
+            None
         }
     }
 
@@ -51,27 +200,101 @@ impl Automator {
     }
 
     /// Updates all the target parameters linked to the given source with the
-    /// given new value.
+    /// given new value, as transformed by each link's [ControlMapping]. Links
+    /// to targets that aren't in entity_repo are passed to not_found_fn, along
+    /// with the transformed value.
+    ///
+    /// A parameter that's linked only to [ControlLinkRole::Base] links is set
+    /// directly, so the last source to send a value wins. Once a
+    /// [ControlLinkRole::Modulation] link sends a value, the parameter is
+    /// instead set to its base value plus the sum of its modulations. If no
+    /// base link has sent a value, then the base is the parameter's value at
+    /// the time it was first modulated. If the parameter is later set some
+    /// other way, then the new value becomes the base.
     pub fn route(
         &mut self,
         entity_repo: &mut EntityRepository,
-        mut not_found_fn: Option<&mut dyn FnMut(&ControlLink)>,
+        not_found_fn: Option<&mut dyn FnMut(&ControlLink, ControlValue)>,
         source: ControlLinkSource,
         value: ControlValue,
     ) {
-        if let Some(controllables) = match source {
+        if let Some(links) = match source {
             ControlLinkSource::Entity(uid) => self.controllables.get(&uid),
             ControlLinkSource::Path(path_uid) => self.path_links.get(&path_uid),
+            ControlLinkSource::MidiCc(..) => None,
         } {
-            controllables.iter().for_each(|link| {
-                if let Some(entity) = entity_repo.entity_mut(link.uid) {
-                    entity.control_set_param_by_index(link.param, value);
+            Self::route_to_links(
+                &mut self.modulated_params,
+                entity_repo,
+                not_found_fn,
+                source,
+                links,
+                value,
+            );
+        }
+    }
+
+    /// Like [Automator::route()], but for links that the caller keeps, such as
+    /// [MidiCcBindings](crate::orchestration::MidiCcBindings).
+    pub fn route_links(
+        &mut self,
+        entity_repo: &mut EntityRepository,
+        not_found_fn: Option<&mut dyn FnMut(&ControlLink, ControlValue)>,
+        source: ControlLinkSource,
+        links: &[ControlLink],
+        value: ControlValue,
+    ) {
+        Self::route_to_links(
+            &mut self.modulated_params,
+            entity_repo,
+            not_found_fn,
+            source,
+            links,
+            value,
+        );
+    }
+
+    fn route_to_links(
+        modulated_params: &mut FxHashMap<(Uid, ControlIndex), ModulatedParam>,
+        entity_repo: &mut EntityRepository,
+        mut not_found_fn: Option<&mut dyn FnMut(&ControlLink, ControlValue)>,
+        source: ControlLinkSource,
+        links: &[ControlLink],
+        value: ControlValue,
+    ) {
+        for link in links.iter() {
+            let mut value = link.map(value);
+            let key = (link.uid, link.param);
+            let is_modulated = !link.role.is_base() || modulated_params.contains_key(&key);
+            if is_modulated {
+                let current = entity_repo
+                    .entity(link.uid)
+                    .and_then(|entity| entity.control_get_param_by_index(link.param));
+                let modulated = modulated_params.entry(key).or_default();
+                if current.is_some() && current != modulated.readback {
+                    modulated.base = current;
+                }
+                if let ControlLinkRole::Modulation(amount) = link.role {
+                    modulated
+                        .modulations
+                        .insert(source, (value.0 - 0.5) * amount.0);
                 } else {
-                    if let Some(not_found_fn) = not_found_fn.as_mut() {
-                        not_found_fn(link);
+                    modulated.base = Some(value);
+                }
+                value = modulated.value();
+            }
+            if let Some(entity) = entity_repo.entity_mut(link.uid) {
+                entity.control_set_param_by_index(link.param, value);
+                if is_modulated {
+                    if let Some(modulated) = modulated_params.get_mut(&key) {
+                        modulated.readback = entity.control_get_param_by_index(link.param);
                     }
                 }
-            });
+            } else {
+                if let Some(not_found_fn) = not_found_fn.as_mut() {
+                    not_found_fn(link, value);
+                }
+            }
         }
     }
 
@@ -84,6 +307,13 @@ impl Automator {
 
     /// Removes an existing [SignalPath].
     pub fn remove_path(&mut self, path_uid: PathUid) -> Option<SignalPath> {
+        self.recorder.takes.remove(&path_uid);
+        if let Some(links) = self.path_links.get(&path_uid) {
+            let links = links.clone();
+            links.iter().for_each(|link| {
+                self.forget_modulation(path_uid.into(), link.uid, link.param);
+            });
+        }
         self.paths.remove(&path_uid)
     }
 
@@ -98,10 +328,7 @@ impl Automator {
             self.path_links
                 .entry(path_uid)
                 .or_default()
-                .push(ControlLink {
-                    uid: target_uid,
-                    param,
-                });
+                .push(ControlLink::new_with(target_uid, param));
             Ok(())
         } else {
             Err(anyhow!("Couldn't find path {path_uid}"))
@@ -111,17 +338,17 @@ impl Automator {
     /// Removes a link between a [SignalPath] and a target parameter.
     pub fn unlink_path(&mut self, path_uid: PathUid, target_uid: Uid, param: ControlIndex) {
         if let Some(links) = self.path_links.get_mut(&path_uid) {
-            links.retain(|link| link.uid != target_uid && link.param != param);
+            links.retain(|link| link.uid != target_uid || link.param != param);
         }
+        self.forget_modulation(path_uid.into(), target_uid, param);
     }
 
     // TODO: reduce to pub(crate) when caller is moved
     #[allow(missing_docs)]
     pub fn is_path_linked(&self, path_uid: PathUid, uid: Uid, param: ControlIndex) -> bool {
         if let Some(links) = self.path_links.get(&path_uid) {
-            let test_link = ControlLink { uid, param };
             // TODO: slow
-            links.contains(&test_link)
+            links.iter().any(|link| link.is_for(uid, param))
         } else {
             false
         }
@@ -131,6 +358,124 @@ impl Automator {
     pub fn paths_mut(&mut self) -> &mut FxHashMap<PathUid, SignalPath> {
         &mut self.paths
     }
+
+    /// Returns the first [SignalPath] linked to the given target's parameter.
+    pub fn path_for_param(&self, uid: Uid, param: ControlIndex) -> Option<PathUid> {
+        self.path_links
+            .iter()
+            .find(|(_, links)| links.iter().any(|link| link.is_for(uid, param)))
+            .map(|(path_uid, _)| *path_uid)
+    }
+
+    /// Arms recording of live parameter changes in the given mode, or disarms
+    /// it if mode is None. Anything recorded so far is kept.
+    pub fn set_record_mode(&mut self, mode: Option<AutomationRecordMode>) {
+        self.commit_takes(self.time_range.start(), false);
+        self.recorder.mode = mode;
+    }
+
+    #[allow(missing_docs)]
+    pub fn record_mode(&self) -> Option<AutomationRecordMode> {
+        self.recorder.mode
+    }
+
+    /// Returns true if recording is armed and the project is playing.
+    pub fn is_recording(&self) -> bool {
+        self.recorder.mode.is_some() && self.recorder.is_performing
+    }
+
+    /// Limits recording to the given range, or allows it anywhere if None.
+    /// Takes that are in progress when time leaves the range end there. This
+    /// should match [Transport::punch_range].
+    pub fn set_punch_range(&mut self, punch_range: Option<TimeRange>) {
+        self.recorder.punch_range = punch_range;
+    }
+
+    /// Returns true if the current time is within the punch range, or if
+    /// there isn't one.
+    pub(crate) fn is_punched_in(&self) -> bool {
+        match self.recorder.punch_range.as_ref() {
+            Some(punch_range) => punch_range.contains(&self.time_range.start()),
+            None => true,
+        }
+    }
+
+    /// Records that the parameter driven by the given [SignalPath] was changed
+    /// to the given value at the current time. Does nothing unless
+    /// [Automator::is_recording()], or if the current time is outside the
+    /// punch range. While a path is being recorded, it stops playing back, so
+    /// that it doesn't fight the user.
+    ///
+    /// Values are recorded as the parameter sees them, so if the path's link
+    /// has a [ControlMapping], then the mapping will be applied again on
+    /// playback.
+    pub fn record(&mut self, path_uid: PathUid, value: ControlValue) -> Result<()> {
+        if !self.is_recording() || !self.is_punched_in() {
+            return Ok(());
+        }
+        if !self.paths.contains_key(&path_uid) {
+            return Err(anyhow!("Couldn't find path {path_uid}"));
+        }
+        let when = self.time_range.start();
+        self.recorder
+            .takes
+            .entry(path_uid)
+            .or_insert_with(|| AutomationTake::new_with(when))
+            .push(when, value.into());
+        Ok(())
+    }
+
+    /// Tells the recorder that the user let go of the control driving the
+    /// given path's parameter. In [AutomationRecordMode::Touch], this ends the
+    /// recording of that path. The other modes keep recording until playback
+    /// stops.
+    pub fn release(&mut self, path_uid: PathUid) {
+        if self.recorder.mode == Some(AutomationRecordMode::Touch) {
+            self.commit_take(path_uid, self.time_range.start(), false);
+        }
+    }
+
+    // Writes a take into its path, replacing the region it covers. An
+    // overwrite take replaces everything after its start, unless it ended at
+    // a punch-out.
+    fn commit_take(&mut self, path_uid: PathUid, end: MusicalTime, is_punch_out: bool) {
+        if let Some(take) = self.recorder.takes.remove(&path_uid) {
+            if let Some(path) = self.paths.get_mut(&path_uid) {
+                let start = take.start();
+                match self.recorder.mode {
+                    Some(AutomationRecordMode::Overwrite) if !is_punch_out => {
+                        path.replace_points(start..MusicalTime::TIME_MAX, take.finish(start));
+                    }
+                    _ => {
+                        let end = end.max(take.last_change());
+                        path.replace_points(start..end, take.finish(end));
+                    }
+                }
+            }
+        }
+    }
+
+    // Committing a take removes it, so these loops run until nothing's left
+    // to commit. That way, they don't need to collect the takes first, which
+    // would allocate during playback.
+    fn commit_takes(&mut self, end: MusicalTime, is_punch_out: bool) {
+        while let Some(path_uid) = self.recorder.takes.keys().next().copied() {
+            self.commit_take(path_uid, end, is_punch_out);
+        }
+    }
+
+    // Commits the takes whose controls have been still for too long.
+    fn commit_idle_takes(&mut self, now: MusicalTime) {
+        while let Some((path_uid, end)) = self
+            .recorder
+            .takes
+            .iter()
+            .find(|(_, take)| now > take.last_change() + Self::TOUCH_TIMEOUT)
+            .map(|(path_uid, take)| (*path_uid, take.last_change()))
+        {
+            self.commit_take(path_uid, end, false);
+        }
+    }
 }
 impl Serializable for Automator {
     fn before_ser(&mut self) {}
@@ -143,21 +488,63 @@ impl Controls for Automator {
     }
 
     fn update_time_range(&mut self, time_range: &TimeRange) {
+        // Time went backward, probably because of a loop, so this is a new
+        // pass.
+        if time_range.start() < self.time_range.start() {
+            self.commit_takes(self.time_range.end(), false);
+        }
         self.time_range = time_range.clone();
         self.paths.values_mut().for_each(|path| {
             path.update_time_range(time_range);
         });
+
+        // MIDI controllers can't tell us when they're released, so a touch
+        // ends when the control has been still for a while.
+        if self.recorder.mode == Some(AutomationRecordMode::Touch) {
+            self.commit_idle_takes(time_range.start());
+        }
+
+        if let Some(punch_end) = self.recorder.punch_range.as_ref().map(|r| r.end()) {
+            if time_range.start() >= punch_end {
+                self.commit_takes(punch_end, true);
+            }
+        }
     }
 
     fn is_finished(&self) -> bool {
         self.is_finished
     }
+
+    fn play(&mut self) {
+        self.recorder.is_performing = true;
+    }
+
+    fn stop(&mut self) {
+        self.commit_takes(self.time_range.end(), false);
+        self.recorder.is_performing = false;
+    }
+
+    fn seek(&mut self, position: MusicalTime) {
+        // Playback got as far as the end of the last time range, which is also
+        // where a loop jumps back from.
+        self.commit_takes(self.time_range.end(), false);
+        self.time_range = TimeRange(position..position);
+        self.paths.values_mut().for_each(|path| {
+            path.seek(position);
+        });
+    }
 }
 impl ControlsAsProxy for Automator {
     fn work_as_proxy(&mut self, control_events_fn: &mut ControlProxyEventsFn) {
-        self.paths.iter_mut().for_each(|(path_uid, path)| {
-            path.work(&mut |event| control_events_fn((*path_uid).into(), event));
-        });
+        let takes = &self.recorder.takes;
+        self.paths
+            .iter_mut()
+            .filter(|(path_uid, _)| !takes.contains_key(path_uid))
+            .for_each(|(path_uid, path)| {
+                path.work_timed(&mut |when, event| {
+                    control_events_fn((*path_uid).into(), when, event)
+                });
+            });
     }
 }
 
@@ -183,13 +570,31 @@ mod tests {
         uid: Uid,
         #[serde(skip)]
         tracker: Arc<RwLock<Vec<(Uid, ControlIndex, ControlValue)>>>,
+        // If Some, then params read back as they were last set.
+        #[serde(skip)]
+        values: Option<FxHashMap<ControlIndex, ControlValue>>,
     }
     impl TestControllable {
         pub fn new_with(
             uid: Uid,
             tracker: Arc<RwLock<Vec<(Uid, ControlIndex, ControlValue)>>>,
         ) -> Self {
-            Self { uid, tracker }
+            Self {
+                uid,
+                tracker,
+                values: None,
+            }
+        }
+
+        pub fn new_remembering_with(
+            uid: Uid,
+            tracker: Arc<RwLock<Vec<(Uid, ControlIndex, ControlValue)>>>,
+        ) -> Self {
+            Self {
+                uid,
+                tracker,
+                values: Some(FxHashMap::default()),
+            }
         }
     }
     impl Controllable for TestControllable {
@@ -197,6 +602,15 @@ mod tests {
             if let Ok(mut tracker) = self.tracker.write() {
                 tracker.push((self.uid, index, value));
             }
+            if let Some(values) = self.values.as_mut() {
+                values.insert(index, value);
+            }
+        }
+
+        fn control_get_param_by_index(&self, index: ControlIndex) -> Option<ControlValue> {
+            self.values
+                .as_ref()
+                .and_then(|values| values.get(&index).copied())
         }
     }
 
@@ -299,6 +713,160 @@ mod tests {
             );
             assert_eq!(t[0], (target_2_uid, ControlIndex(1), ControlValue(0.5)));
         };
+
+        // Mappings transform values on their way to the target.
+        if let Ok(mut t) = tracker.write() {
+            t.clear();
+        }
+        let mapping = ControlMappingBuilder::default()
+            .min(ControlValue(0.2))
+            .max(ControlValue(0.4))
+            .build()
+            .unwrap();
+        assert!(
+            automator
+                .set_link_mapping(
+                    source_1_uid.into(),
+                    target_1_uid,
+                    ControlIndex(0),
+                    Some(mapping)
+                )
+                .is_err(),
+            "Can't map a link that doesn't exist"
+        );
+        assert!(automator
+            .set_link_mapping(
+                source_1_uid.into(),
+                target_2_uid,
+                ControlIndex(1),
+                Some(mapping)
+            )
+            .is_ok());
+        assert_eq!(
+            automator.link_mapping(source_1_uid.into(), target_2_uid, ControlIndex(1)),
+            Some(mapping)
+        );
+        let _ = automator.route(&mut repo, None, source_1_uid.into(), ControlValue(0.5));
+        if let Ok(t) = tracker.read() {
+            assert_eq!(t.len(), 1);
+            assert_eq!(t[0].1, ControlIndex(1));
+            assert!((t[0].2 .0 - 0.3).abs() < 1e-9);
+        };
+
+        let json = serde_json::to_string(&automator).unwrap();
+        let restored: Automator = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            restored.link_mapping(source_1_uid.into(), target_2_uid, ControlIndex(1)),
+            Some(mapping),
+            "Mappings should be saved with the project"
+        );
+    }
+
+    #[test]
+    fn automator_sums_modulation() {
+        let mut automator = Automator::default();
+        let base_source_uid = Uid(1);
+        let lfo_source_uid = Uid(2);
+        let target_uid = Uid(3);
+        let param = ControlIndex(0);
+        let tracker = Arc::new(RwLock::new(Vec::default()));
+        let mut repo = EntityRepository::default();
+        let _ = repo.add_entity(
+            TrackUid(1),
+            Box::new(TestControllable::new_with(target_uid, Arc::clone(&tracker))),
+        );
+        let last_value = || tracker.read().unwrap().last().unwrap().2 .0;
+
+        assert!(automator.link(base_source_uid, target_uid, param).is_ok());
+        assert!(automator.link(lfo_source_uid, target_uid, param).is_ok());
+        assert!(automator
+            .set_link_role(
+                lfo_source_uid.into(),
+                target_uid,
+                param,
+                ControlLinkRole::Modulation(Normal::from(0.5))
+            )
+            .is_ok());
+
+        let _ = automator.route(&mut repo, None, base_source_uid.into(), ControlValue(0.6));
+        assert_eq!(
+            last_value(),
+            0.6,
+            "Before any modulation, a base link sets the param directly"
+        );
+        let _ = automator.route(&mut repo, None, lfo_source_uid.into(), ControlValue(1.0));
+        assert!(
+            (last_value() - 0.75).abs() < 1e-9,
+            "Without a captured base, modulation is centered"
+        );
+        let _ = automator.route(&mut repo, None, base_source_uid.into(), ControlValue(0.6));
+        assert!(
+            (last_value() - 0.85).abs() < 1e-9,
+            "Modulation should ride on top of the base"
+        );
+        let _ = automator.route(&mut repo, None, base_source_uid.into(), ControlValue(0.2));
+        assert!((last_value() - 0.45).abs() < 1e-9);
+        let _ = automator.route(&mut repo, None, lfo_source_uid.into(), ControlValue(0.0));
+        assert_eq!(last_value(), 0.0, "The sum should be clamped");
+
+        automator.unlink(lfo_source_uid, target_uid, param);
+        let _ = automator.route(&mut repo, None, base_source_uid.into(), ControlValue(0.3));
+        assert_eq!(
+            last_value(),
+            0.3,
+            "Without modulation, the param should go back to being set directly"
+        );
+    }
+
+    #[test]
+    fn automator_modulation_follows_direct_changes() {
+        let mut automator = Automator::default();
+        let lfo_source_uid = Uid(1);
+        let target_uid = Uid(2);
+        let param = ControlIndex(0);
+        let tracker = Arc::new(RwLock::new(Vec::default()));
+        let mut repo = EntityRepository::default();
+        let _ = repo.add_entity(
+            TrackUid(1),
+            Box::new(TestControllable::new_remembering_with(
+                target_uid,
+                Arc::clone(&tracker),
+            )),
+        );
+        let last_value = || tracker.read().unwrap().last().unwrap().2 .0;
+
+        assert!(automator.link(lfo_source_uid, target_uid, param).is_ok());
+        assert!(automator
+            .set_link_role(
+                lfo_source_uid.into(),
+                target_uid,
+                param,
+                ControlLinkRole::Modulation(Normal::from(0.5))
+            )
+            .is_ok());
+
+        repo.entity_mut(target_uid)
+            .unwrap()
+            .control_set_param_by_index(param, ControlValue(0.2));
+        let _ = automator.route(&mut repo, None, lfo_source_uid.into(), ControlValue(1.0));
+        assert!(
+            (last_value() - 0.45).abs() < 1e-9,
+            "The param's value should become the base"
+        );
+        let _ = automator.route(&mut repo, None, lfo_source_uid.into(), ControlValue(0.6));
+        assert!(
+            (last_value() - 0.25).abs() < 1e-9,
+            "The automator's own changes shouldn't move the base"
+        );
+
+        repo.entity_mut(target_uid)
+            .unwrap()
+            .control_set_param_by_index(param, ControlValue(0.7));
+        let _ = automator.route(&mut repo, None, lfo_source_uid.into(), ControlValue(0.6));
+        assert!(
+            (last_value() - 0.75).abs() < 1e-9,
+            "Setting the param directly should move the base"
+        );
     }
 
     #[test]
@@ -324,4 +892,32 @@ mod tests {
 
         // TODO: finish this
     }
+
+    #[test]
+    fn automator_unlinks_only_one_path_link() {
+        let mut automator = Automator::default();
+        let path_uid = automator.add_path(SignalPath::default()).unwrap();
+        let target_uid = Uid(1024);
+        let other_uid = Uid(1025);
+        assert!(automator
+            .link_path(path_uid, target_uid, ControlIndex(1))
+            .is_ok());
+        assert!(automator
+            .link_path(path_uid, target_uid, ControlIndex(2))
+            .is_ok());
+        assert!(automator
+            .link_path(path_uid, other_uid, ControlIndex(1))
+            .is_ok());
+
+        automator.unlink_path(path_uid, target_uid, ControlIndex(1));
+        assert!(!automator.is_path_linked(path_uid, target_uid, ControlIndex(1)));
+        assert!(
+            automator.is_path_linked(path_uid, target_uid, ControlIndex(2)),
+            "Another param of the same entity should stay linked"
+        );
+        assert!(
+            automator.is_path_linked(path_uid, other_uid, ControlIndex(1)),
+            "The same param of another entity should stay linked"
+        );
+    }
 }
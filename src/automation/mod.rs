@@ -25,26 +25,34 @@
 /// The most commonly used imports.
 pub mod prelude {
     pub use super::{
-        Automator, ControlEventsFn, ControlIndex, ControlLink, ControlLinkSource, ControlName,
-        ControlProxyEventsFn, ControlRange, ControlValue, Controllable, Controls, ControlsAsProxy,
-        PathUid, SignalPath, SignalPathBuilder, SignalPoint, SignalPointBuilder,
+        AutomationRecordMode, Automator, ControlCurve, ControlEventsFn, ControlIndex, ControlLink,
+        ControlLinkRole, ControlLinkSource, ControlMapping, ControlMappingBuilder, ControlName,
+        ControlPolarity, ControlProxyEventsFn, ControlRange, ControlValue, Controllable, Controls,
+        ControlsAsProxy, PathUid, SignalCurve, SignalPath, SignalPathBuilder, SignalPoint,
+        SignalPointBuilder, TimedControlEventsFn,
     };
 }
 
 pub use {
     automator::Automator,
+    recorder::AutomationRecordMode,
     signal_path::{
-        PathUid, PathUidFactory, SignalPath, SignalPathBuilder, SignalPoint, SignalPointBuilder,
+        PathUid, PathUidFactory, SignalCurve, SignalPath, SignalPathBuilder, SignalPoint,
+        SignalPointBuilder,
     },
     traits::{
         ControlEventsFn, ControlLinkSource, ControlProxyEventsFn, Controllable, Controls,
-        ControlsAsProxy,
+        ControlsAsProxy, TimedControlEventsFn,
+    },
+    types::{
+        ControlCurve, ControlIndex, ControlLink, ControlLinkRole, ControlMapping,
+        ControlMappingBuilder, ControlName, ControlPolarity, ControlRange, ControlValue,
     },
-    types::{ControlIndex, ControlLink, ControlName, ControlRange, ControlValue},
 };
 
 mod automator;
 mod controllers;
+mod recorder;
 mod signal_path;
 mod traits;
 mod types;
@@ -0,0 +1,171 @@
+// Copyright (c) 2024 Mike Tsao
+
+use crate::prelude::*;
+use serde::{Deserialize, Serialize};
+use strum_macros::EnumIter;
+
+/// Decides how much of a [SignalPath] a recording replaces when the user
+/// changes the path's parameter during playback.
+#[derive(
+    Clone, Copy, Debug, Default, EnumIter, PartialEq, Serialize, Deserialize, strum_macros::Display,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum AutomationRecordMode {
+    /// Once the parameter changes, everything after that point in the path is
+    /// replaced by the recording.
+    #[default]
+    Overwrite,
+    /// Once the parameter changes, the path is replaced until playback stops.
+    /// The parameter holds its last value in the meantime.
+    Latch,
+    /// The path is replaced only while the user is touching the control.
+    /// Afterward, the existing automation takes over again.
+    Touch,
+}
+
+/// The points recorded into one [SignalPath] during one pass, which replace the
+/// path's existing points when the take is committed.
+#[derive(Debug)]
+pub(crate) struct AutomationTake {
+    start: MusicalTime,
+    points: Vec<SignalPoint>,
+}
+impl AutomationTake {
+    // Changes closer together than this are joined by a ramp. If they're
+    // farther apart, then the earlier value holds until just before the later
+    // one, because the user wasn't moving the control in between.
+    const HOLD_THRESHOLD: MusicalTime = MusicalTime::ONE_PART;
+
+    // How far a point can stray from the line connecting its neighbors and
+    // still be discarded as redundant.
+    const THINNING_TOLERANCE: f64 = 0.005;
+
+    pub(crate) fn new_with(start: MusicalTime) -> Self {
+        Self {
+            start,
+            points: Vec::default(),
+        }
+    }
+
+    pub(crate) fn start(&self) -> MusicalTime {
+        self.start
+    }
+
+    /// When the most recent change happened.
+    pub(crate) fn last_change(&self) -> MusicalTime {
+        if let Some(point) = self.points.last() {
+            point.when
+        } else {
+            self.start
+        }
+    }
+
+    /// Records that the parameter changed to the given value at the given time.
+    pub(crate) fn push(&mut self, when: MusicalTime, value: BipolarNormal) {
+        if let Some(last) = self.points.last_mut() {
+            if when <= last.when {
+                last.value = value;
+                return;
+            }
+            if when - last.when > Self::HOLD_THRESHOLD {
+                let hold = SignalPoint {
+                    when: when - MusicalTime::ONE_UNIT,
+                    value: last.value,
+                    ..Default::default()
+                };
+                self.push_point(hold);
+            }
+        }
+        self.push_point(SignalPoint {
+            when,
+            value,
+            ..Default::default()
+        });
+    }
+
+    fn push_point(&mut self, point: SignalPoint) {
+        self.points.push(point);
+
+        // If the middle of the last three points lies on the line connecting
+        // the other two, then it doesn't change the shape of the signal.
+        let len = self.points.len();
+        if len >= 3 {
+            let (a, b, c) = (
+                &self.points[len - 3],
+                &self.points[len - 2],
+                &self.points[len - 1],
+            );
+            let percent =
+                (b.when - a.when).total_units() as f64 / (c.when - a.when).total_units() as f64;
+            let expected = a.value.0 + (c.value.0 - a.value.0) * percent;
+            if (b.value.0 - expected).abs() <= Self::THINNING_TOLERANCE {
+                self.points.remove(len - 2);
+            }
+        }
+    }
+
+    /// Returns the recorded points, with the last value held until the given
+    /// time.
+    pub(crate) fn finish(mut self, end: MusicalTime) -> Vec<SignalPoint> {
+        if let Some(last) = self.points.last() {
+            if end > last.when {
+                let hold = SignalPoint {
+                    when: end,
+                    value: last.value,
+                    ..Default::default()
+                };
+                self.push_point(hold);
+            }
+        }
+        self.points
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn automation_take_thins_points() {
+        let mut take = AutomationTake::new_with(MusicalTime::START);
+        for i in 0..10 {
+            take.push(
+                MusicalTime::new_with_units(i * 100),
+                BipolarNormal::from(i as f64 / 10.0),
+            );
+        }
+        assert_eq!(
+            take.points.len(),
+            2,
+            "A steady ramp needs only its endpoints"
+        );
+
+        take.push(MusicalTime::new_with_units(900), BipolarNormal::from(-0.5));
+        assert_eq!(
+            take.points.last().unwrap().value,
+            BipolarNormal::from(-0.5),
+            "A second change at the same time should replace the first"
+        );
+
+        take.push(MusicalTime::ONE_BEAT, BipolarNormal::from(0.5));
+        let points = take.finish(MusicalTime::new_with_beats(2));
+        assert_eq!(
+            points[points.len() - 3],
+            SignalPoint {
+                when: MusicalTime::ONE_BEAT - MusicalTime::ONE_UNIT,
+                value: BipolarNormal::from(-0.5),
+                ..Default::default()
+            },
+            "The value should hold across a pause in changes"
+        );
+        assert_eq!(
+            points.last().unwrap(),
+            &SignalPoint {
+                when: MusicalTime::new_with_beats(2),
+                value: BipolarNormal::from(0.5),
+                ..Default::default()
+            },
+            "The last value should hold until the take ends"
+        );
+    }
+}
@@ -6,8 +6,62 @@ use delegate::delegate;
 use derive_builder::Builder;
 use nonoverlapping_interval_tree::NonOverlappingIntervalTree;
 use serde::{Deserialize, Serialize};
+use strum_macros::EnumIter;
 use synonym::Synonym;
 
+/// The shape of a [SignalPath] between one [SignalPoint] and the next.
+#[derive(
+    Clone, Copy, Debug, Default, EnumIter, PartialEq, Serialize, Deserialize, strum_macros::Display,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum SignalCurve {
+    /// Stays at this point's value, then jumps to the next point's value.
+    Hold,
+    /// Moves in a straight line to the next point.
+    #[default]
+    Linear,
+    /// Changes slowly at first and quickly at the end. Good for filter
+    /// sweeps.
+    Exponential,
+    /// Changes quickly at first and slowly at the end. Good for fade-outs.
+    Logarithmic,
+    /// Eases out of this point and into the next one. At zero tension, it's
+    /// the same as [SignalCurve::Linear]. Higher tension makes the ends
+    /// flatter and the middle steeper.
+    #[strum(serialize = "S-Curve")]
+    SCurve(Normal),
+}
+impl SignalCurve {
+    // The S-curve's easing exponent at full tension.
+    const MAX_S_CURVE_EXPONENT: f64 = 5.0;
+
+    /// Given how far along a segment we are (0.0..=1.0), returns how far the
+    /// signal has moved from the segment's starting value toward its ending
+    /// value (also 0.0..=1.0).
+    pub fn shape(&self, percent: f64) -> f64 {
+        match self {
+            SignalCurve::Hold => {
+                if percent < 1.0 {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+            SignalCurve::Linear => percent,
+            SignalCurve::Exponential => ControlCurve::Exponential.apply(percent),
+            SignalCurve::Logarithmic => ControlCurve::Logarithmic.apply(percent),
+            SignalCurve::SCurve(tension) => {
+                let exponent = 1.0 + tension.0 * (Self::MAX_S_CURVE_EXPONENT - 1.0);
+                if percent < 0.5 {
+                    0.5 * (2.0 * percent).powf(exponent)
+                } else {
+                    1.0 - 0.5 * (2.0 * (1.0 - percent)).powf(exponent)
+                }
+            }
+        }
+    }
+}
+
 /// A representation of a single point in a [SignalPath].
 #[derive(Clone, Debug, Default, Serialize, Deserialize, Builder, PartialEq)]
 #[serde(rename_all = "kebab-case")]
@@ -18,28 +72,32 @@ pub struct SignalPoint {
     /// The value the signal should have at the given time.
     #[builder(default)]
     pub(crate) value: BipolarNormal,
+    /// The shape of the signal between this point and the next one.
+    #[builder(default)]
+    #[serde(default)]
+    pub(crate) curve: SignalCurve,
 }
 
-/// A representation of a line segment connecting two [SignalPoint]s. In the
-/// case of leftmost and rightmost points, there is a virtual extra line
-/// segment, level, connecting time zero to the leftmost, and time max to the
-/// rightmost.
+/// A representation of a segment connecting two [SignalPoint]s. In the case of
+/// leftmost and rightmost points, there is a virtual extra line segment, level,
+/// connecting time zero to the leftmost, and time max to the rightmost.
 #[derive(Clone, Debug)]
 pub struct SignalStep {
     when: Range<MusicalTime>,
     value: Range<BipolarNormal>,
+    curve: SignalCurve,
 }
 impl SignalStep {
     fn interpolated_value(&self, percent: f64) -> BipolarNormal {
         let value_range = (self.value.end - self.value.start).0;
-        let interpolated_value = self.value.start.0 + value_range * percent;
+        let interpolated_value = self.value.start.0 + value_range * self.curve.shape(percent);
         interpolated_value.into()
     }
 }
 
 /// Emits a signal that varies over time. The signal is defined by a set of
-/// distinct points, ordered by time. The signal moves linearly from point
-/// to point.
+/// distinct points, ordered by time. The signal moves from point to point
+/// along each point's [SignalCurve].
 #[derive(Debug, Default, Serialize, Deserialize, Builder)]
 #[serde(rename_all = "kebab-case")]
 #[builder(build_fn(private, name = "build_from_builder"))]
@@ -80,6 +138,7 @@ impl SignalPathBuilder {
             let point = SignalPoint {
                 when: cursor,
                 value: BipolarNormal::new(rng.rand_i64() as f64 / i64::MAX as f64),
+                ..Default::default()
             };
             self.point(point);
             cursor += MusicalTime::DURATION_QUARTER;
@@ -107,17 +166,20 @@ impl Serializable for SignalPath {
         self.e.steps.clear();
         let mut last_when = MusicalTime::START;
         let mut last_value = None;
+        let mut last_curve = SignalCurve::default();
         self.points.iter().for_each(|p| {
             let when = last_when..p.when;
             let step = SignalStep {
                 when: when.clone(),
                 value: last_value.unwrap_or_else(|| p.value)..p.value,
+                curve: last_curve,
             };
             if when.end != MusicalTime::START {
                 self.e.steps.insert(when, step);
             }
             last_when = p.when;
             last_value = Some(p.value);
+            last_curve = p.curve;
         });
         if !self.points.is_empty() && last_when != MusicalTime::TIME_MAX {
             let when = last_when..MusicalTime::TIME_MAX;
@@ -125,6 +187,7 @@ impl Serializable for SignalPath {
             let step = SignalStep {
                 when: when.clone(),
                 value: last_value..last_value,
+                curve: SignalCurve::default(),
             };
             self.e.steps.insert(when, step);
         }
@@ -144,6 +207,28 @@ impl Controls for SignalPath {
         self.broadcast_value(control_events_fn);
     }
 
+    // Values are sampled on a fixed grid rather than once per call. That keeps
+    // automation smooth within a long time range, and it means that the values
+    // don't depend on how playback happened to be divided into ranges. Targets
+    // that haven't heard from the path since a seek get a value right away.
+    fn work_timed(&mut self, control_events_fn: &mut TimedControlEventsFn) {
+        let interval = Self::CONTROL_INTERVAL.total_units();
+        let time_range = self.e.time_range.clone();
+        let mut when = time_range.start();
+        if self.e.broadcasted_value.is_some() {
+            when = MusicalTime::new_with_units(
+                (when.total_units() + interval - 1) / interval * interval,
+            );
+        }
+        while when < time_range.end() {
+            if !self.e.steps.is_empty() {
+                self.e.value = self.calculate_value(when);
+            }
+            self.broadcast_value(&mut |event| control_events_fn(when, event));
+            when = MusicalTime::new_with_units((when.total_units() / interval + 1) * interval);
+        }
+    }
+
     fn is_finished(&self) -> bool {
         true
     }
@@ -153,6 +238,14 @@ impl Controls for SignalPath {
     fn stop(&mut self) {}
 
     fn skip_to_start(&mut self) {}
+
+    fn seek(&mut self, position: MusicalTime) {
+        self.e.time_range = TimeRange(position..position);
+
+        // Targets might have been left at any value, so make sure they get
+        // the one for the new position.
+        self.e.broadcasted_value = None;
+    }
 }
 impl Configurable for SignalPath {
     fn reset(&mut self) {
@@ -162,6 +255,11 @@ impl Configurable for SignalPath {
     }
 }
 impl SignalPath {
+    /// How often a path sends a new value during playback, which is 256 times
+    /// per beat.
+    pub const CONTROL_INTERVAL: MusicalTime =
+        MusicalTime::new_with_units(MusicalTime::UNITS_IN_PART / 16);
+
     fn update_value(&mut self) {
         if self.e.steps.is_empty() {
             return;
@@ -169,7 +267,7 @@ impl SignalPath {
         self.e.value = self.calculate_value(self.e.time_range.start());
     }
 
-    fn calculate_value(&self, when: MusicalTime) -> Option<BipolarNormal> {
+    pub(crate) fn calculate_value(&self, when: MusicalTime) -> Option<BipolarNormal> {
         if self.e.steps.is_empty() {
             return None;
         }
@@ -188,6 +286,16 @@ impl SignalPath {
         }
     }
 
+    // Returns the curve of the segment that the given time falls within, so
+    // that a point inserted there keeps the segment's shape.
+    fn curve_at(&self, when: MusicalTime) -> SignalCurve {
+        if let Some(step) = self.e.steps.get(&when) {
+            step.curve
+        } else {
+            SignalCurve::default()
+        }
+    }
+
     fn broadcast_value(&mut self, control_events_fn: &mut ControlEventsFn) {
         if self.e.value != self.e.broadcasted_value {
             if let Some(value) = self.e.value {
@@ -204,7 +312,8 @@ impl SignalPath {
 
     pub(crate) fn add_point(&mut self, when: MusicalTime) {
         let value = self.calculate_value(when).unwrap_or_default();
-        let new_signal_point = SignalPoint { when, value };
+        let curve = self.curve_at(when);
+        let new_signal_point = SignalPoint { when, value, curve };
         if let Some((next_index, _)) = self
             .points
             .iter()
@@ -220,6 +329,45 @@ impl SignalPath {
         self.after_deser();
     }
 
+    /// Replaces the points within the given range (including its end) with
+    /// new ones, which must be in time order. Just outside the range, the
+    /// signal keeps the values it had before.
+    pub(crate) fn replace_points(
+        &mut self,
+        range: Range<MusicalTime>,
+        new_points: Vec<SignalPoint>,
+    ) {
+        let mut anchors = Vec::default();
+        if range.start > MusicalTime::START {
+            let when = range.start - MusicalTime::ONE_UNIT;
+            if let Some(value) = self.calculate_value(when) {
+                anchors.push(SignalPoint {
+                    when,
+                    value,
+                    ..Default::default()
+                });
+            }
+        }
+        if range.end < MusicalTime::TIME_MAX {
+            let when = range.end + MusicalTime::ONE_UNIT;
+            if let Some(value) = self.calculate_value(when) {
+                let curve = self.curve_at(when);
+                anchors.push(SignalPoint { when, value, curve });
+            }
+        }
+        self.points
+            .retain(|p| p.when < range.start || p.when > range.end);
+        self.points.extend(anchors);
+        self.points.extend(new_points);
+
+        // The sort is stable, so an existing point wins over an anchor at the
+        // same time.
+        self.points.sort_by_key(|p| p.when);
+        self.points.dedup_by_key(|p| p.when);
+        self.after_deser();
+        self.e.broadcasted_value = None;
+    }
+
     pub(crate) fn is_empty(&self) -> bool {
         self.points.is_empty()
     }
@@ -251,3 +399,67 @@ impl PathUidFactory {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use strum::IntoEnumIterator;
+
+    fn one_beat_ramp(curve: SignalCurve) -> SignalPath {
+        SignalPathBuilder::default()
+            .point(
+                SignalPointBuilder::default()
+                    .when(MusicalTime::START)
+                    .value(BipolarNormal::from(-1.0))
+                    .curve(curve)
+                    .build()
+                    .unwrap(),
+            )
+            .point(
+                SignalPointBuilder::default()
+                    .when(MusicalTime::ONE_BEAT)
+                    .value(BipolarNormal::from(1.0))
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn signal_path_curves() {
+        let halfway = MusicalTime::new_with_fractional_beats(0.5);
+        let quarter = MusicalTime::new_with_fractional_beats(0.25);
+        let value_at = |curve: SignalCurve, when: MusicalTime| {
+            one_beat_ramp(curve).calculate_value(when).unwrap().0
+        };
+
+        assert_eq!(value_at(SignalCurve::Hold, halfway), -1.0);
+        assert_eq!(value_at(SignalCurve::Linear, halfway), 0.0);
+        assert!(value_at(SignalCurve::Exponential, halfway) < 0.0);
+        assert!(value_at(SignalCurve::Logarithmic, halfway) > 0.0);
+        assert!((value_at(SignalCurve::SCurve(Normal::from(0.0)), quarter) + 0.5).abs() < 1e-9);
+        assert!(
+            value_at(SignalCurve::SCurve(Normal::from(1.0)), quarter) < -0.5,
+            "Tension should flatten the start of an S-curve"
+        );
+        assert_eq!(
+            value_at(SignalCurve::SCurve(Normal::from(1.0)), halfway),
+            0.0,
+            "An S-curve is symmetric"
+        );
+        for curve in SignalCurve::iter() {
+            assert!(
+                (value_at(curve, MusicalTime::ONE_BEAT) - 1.0).abs() < 1e-9,
+                "{curve} should arrive at the next point's value"
+            );
+        }
+
+        let point: SignalPoint = serde_json::from_str(r#"{"when": 0, "value": 0.5}"#).unwrap();
+        assert_eq!(
+            point.curve,
+            SignalCurve::Linear,
+            "Points from older projects should be linear"
+        );
+    }
+}
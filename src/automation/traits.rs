@@ -40,11 +40,20 @@ pub trait Controllable {
     fn control_set_param_by_index(&mut self, index: ControlIndex, value: ControlValue) {
         unimplemented!()
     }
+    /// Given a parameter index, return that parameter's current value, or None
+    /// if there isn't a parameter at that index.
+    fn control_get_param_by_index(&self, index: ControlIndex) -> Option<ControlValue> {
+        None
+    }
 }
 
 /// Passes [WorkEvent]s to the caller. Used in [Controls::work()].
 pub type ControlEventsFn<'a> = dyn FnMut(WorkEvent) + 'a;
 
+/// Passes [WorkEvent]s to the caller, along with the time within the current
+/// [TimeRange] at which each one happens. Used in [Controls::work_timed()].
+pub type TimedControlEventsFn<'a> = dyn FnMut(MusicalTime, WorkEvent) + 'a;
+
 /// A device that [Controls] produces [WorkEvent]s that control other things. It
 /// also has a concept of a performance that has a beginning and an end. It
 /// knows how to respond to requests to start, stop, restart, and seek within
@@ -77,6 +86,19 @@ pub trait Controls: Send {
     /// handled right away, or later.
     fn work(&mut self, control_events_fn: &mut ControlEventsFn) {}
 
+    /// Like [Controls::work()], but also says when each event happens, so that
+    /// the system can apply it at the right audio frame rather than at the
+    /// start of the batch. The default implementation says that everything
+    /// happens at the start of the time range. Devices that know better, such
+    /// as sequencers, should override it.
+    fn work_timed(&mut self, control_events_fn: &mut TimedControlEventsFn) {
+        let when = self
+            .time_range()
+            .map(|time_range| time_range.start())
+            .unwrap_or_default();
+        self.work(&mut |event| control_events_fn(when, event));
+    }
+
     /// Indicates whether this entity has completed all its scheduled work.
     ///
     /// The framework ends the performance only when all entities indicate that
@@ -100,16 +122,25 @@ pub trait Controls: Send {
 
     /// Resets cursors to the beginning.
     fn skip_to_start(&mut self) {}
+
+    /// Moves cursors to the given position, so that the next
+    /// [Controls::update_time_range()] picks up the performance from there.
+    /// Like [Controls::skip_to_start()], this doesn't change whether the
+    /// device is performing.
+    fn seek(&mut self, position: MusicalTime) {}
 }
 
-/// A wrapper for identifiers of ControlLink sources. Both entities and paths
-/// can generate Control events, so we express them here as variants.
-#[derive(Debug, Display, Copy, Clone)]
+/// A wrapper for identifiers of ControlLink sources. Entities, paths, and
+/// external MIDI controllers can generate Control events, so we express them
+/// here as variants.
+#[derive(Debug, Display, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum ControlLinkSource {
     /// An Entity source.
     Entity(Uid),
     /// A Path source.
     Path(PathUid),
+    /// A MIDI continuous controller (CC) number on a channel.
+    MidiCc(MidiChannel, u8),
 }
 impl From<Uid> for ControlLinkSource {
     fn from(uid: Uid) -> Self {
@@ -122,8 +153,10 @@ impl From<PathUid> for ControlLinkSource {
     }
 }
 
-/// Callback for [ControlsAsProxy::work_as_proxy()].
-pub type ControlProxyEventsFn<'a> = dyn FnMut(ControlLinkSource, WorkEvent) + 'a;
+/// Callback for [ControlsAsProxy::work_as_proxy()]. Passes each event along
+/// with its source and the time at which it happens, as in
+/// [Controls::work_timed()].
+pub type ControlProxyEventsFn<'a> = dyn FnMut(ControlLinkSource, MusicalTime, WorkEvent) + 'a;
 
 /// A version of [Controls] for collections of entities.
 #[allow(unused_variables)]
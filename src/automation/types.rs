@@ -2,8 +2,11 @@
 
 use crate::{prelude::*, types::Seconds, util::Rng};
 use core::ops::{Add, Mul, Range, Sub};
+use derivative::Derivative;
+use derive_builder::Builder;
 use derive_more::Display;
 use serde::{Deserialize, Serialize};
+use strum_macros::EnumIter;
 use synonym::Synonym;
 
 /// A human-readable description of the parameter being controlled. Not suitable
@@ -179,13 +182,173 @@ impl Sub<ControlValue> for ControlValue {
 /// Represents a target of a source of control events. For example, if the user
 /// wanted Lfo 1 to control Synth 2's pan parameter, then Lfo 1 might have a
 /// ControlLink(2, 33) (assume that #33 represents the Synth's pan parameter).
-#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+///
+/// A link is identified by its uid and param. Its optional [ControlMapping]
+/// shapes the source's values before they reach the target.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub struct ControlLink {
     /// The [Uid] of the entity to be controlled.
     pub uid: Uid,
     /// The index of the entity parameter to be controlled.
     pub param: ControlIndex,
+    /// How to transform the source's value for this target. None passes it
+    /// through unchanged.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mapping: Option<ControlMapping>,
+    /// How the source's value combines with other sources linked to the same
+    /// target.
+    #[serde(default, skip_serializing_if = "ControlLinkRole::is_base")]
+    pub role: ControlLinkRole,
+}
+impl ControlLink {
+    /// Creates a link that passes values through unchanged.
+    pub fn new_with(uid: Uid, param: ControlIndex) -> Self {
+        Self {
+            uid,
+            param,
+            mapping: None,
+            role: ControlLinkRole::default(),
+        }
+    }
+
+    /// Returns true if this link goes to the given target parameter.
+    pub fn is_for(&self, uid: Uid, param: ControlIndex) -> bool {
+        self.uid == uid && self.param == param
+    }
+
+    /// Transforms a source's value according to this link's mapping.
+    pub fn map(&self, value: ControlValue) -> ControlValue {
+        if let Some(mapping) = self.mapping.as_ref() {
+            mapping.map(value)
+        } else {
+            value
+        }
+    }
+}
+
+/// Decides how a [ControlLink]'s value combines with the values of other links
+/// to the same parameter. The parameter ends up at its base value plus the sum
+/// of all its modulations, clamped to the range of [ControlValue].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ControlLinkRole {
+    /// The link sets the parameter's base value. This is what a lone link
+    /// usually wants. If several links set the base, the last one wins.
+    #[default]
+    Base,
+    /// The link adds to the base value. Its value is treated as bipolar,
+    /// centered on 0.5, and scaled by the given amount, so an amount of 1.0
+    /// can move the parameter by up to half its range in either direction.
+    Modulation(Normal),
+}
+impl ControlLinkRole {
+    #[allow(missing_docs)]
+    pub fn is_base(&self) -> bool {
+        matches!(self, ControlLinkRole::Base)
+    }
+}
+
+/// The shape of the response of a [ControlMapping].
+#[derive(
+    Clone, Copy, Debug, Default, EnumIter, PartialEq, Serialize, Deserialize, strum_macros::Display,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum ControlCurve {
+    /// Output changes in proportion to input.
+    #[default]
+    Linear,
+    /// Output changes slowly at first and quickly at the end. Good for
+    /// sweeping frequencies.
+    Exponential,
+    /// Output changes quickly at first and slowly at the end. The inverse of
+    /// [ControlCurve::Exponential].
+    Logarithmic,
+}
+impl ControlCurve {
+    // How sharply the nonlinear curves bend.
+    const STEEPNESS: f64 = 4.0;
+
+    pub(crate) fn apply(&self, value: f64) -> f64 {
+        match self {
+            ControlCurve::Linear => value,
+            ControlCurve::Exponential => {
+                (Self::STEEPNESS * value).exp_m1() / Self::STEEPNESS.exp_m1()
+            }
+            ControlCurve::Logarithmic => {
+                (Self::STEEPNESS.exp_m1() * value).ln_1p() / Self::STEEPNESS
+            }
+        }
+    }
+}
+
+/// How a [ControlMapping] interprets its source. [ControlValue]s are always
+/// 0..=1.0, but a bipolar source such as an LFO uses 0.5 as its center.
+#[derive(
+    Clone, Copy, Debug, Default, EnumIter, PartialEq, Serialize, Deserialize, strum_macros::Display,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum ControlPolarity {
+    /// Use the source's value as-is.
+    #[default]
+    Unipolar,
+    /// Treat the source as bipolar, and use its distance from center, so that
+    /// both extremes map to the top of the range.
+    Rectified,
+    /// Treat the source as bipolar, and use only its positive half. The
+    /// negative half maps to the bottom of the range.
+    PositiveHalf,
+}
+
+/// Shapes the values that pass through a [ControlLink]. A mapping can narrow
+/// the range that a source sweeps, invert it, and bend its response.
+#[derive(Clone, Copy, Debug, Builder, Derivative, PartialEq, Serialize, Deserialize)]
+#[derivative(Default)]
+#[builder(default)]
+#[serde(rename_all = "kebab-case")]
+pub struct ControlMapping {
+    /// The value that the bottom of the source's range maps to.
+    #[serde(default)]
+    pub min: ControlValue,
+
+    /// The value that the top of the source's range maps to. It can be less
+    /// than min.
+    #[derivative(Default(value = "ControlValue::MAX"))]
+    #[serde(default = "ControlMapping::default_max")]
+    pub max: ControlValue,
+
+    /// Whether to flip the source upside down.
+    #[serde(default)]
+    pub invert: bool,
+
+    /// The shape of the response.
+    #[serde(default)]
+    pub curve: ControlCurve,
+
+    /// How to interpret the source.
+    #[serde(default)]
+    pub polarity: ControlPolarity,
+}
+impl ControlMapping {
+    fn default_max() -> ControlValue {
+        ControlValue::MAX
+    }
+
+    /// Transforms a source's value. The steps are polarity, then inversion,
+    /// then curve, and finally scaling to min..max.
+    pub fn map(&self, value: ControlValue) -> ControlValue {
+        let mut value = value.0.clamp(0.0, 1.0);
+        value = match self.polarity {
+            ControlPolarity::Unipolar => value,
+            ControlPolarity::Rectified => (value * 2.0 - 1.0).abs(),
+            ControlPolarity::PositiveHalf => (value * 2.0 - 1.0).max(0.0),
+        };
+        if self.invert {
+            value = 1.0 - value;
+        }
+        value = self.curve.apply(value);
+        ControlValue(self.min.0 + (self.max.0 - self.min.0) * value)
+    }
 }
 
 /// A newtype that represents how a value should change, usually over time.
@@ -212,6 +375,96 @@ impl From<Range<ControlValue>> for ControlRange {
 mod tests {
     use super::*;
 
+    #[test]
+    fn control_mapping() {
+        let link = ControlLink::new_with(Uid(1), ControlIndex(2));
+        assert_eq!(
+            link.map(ControlValue(0.3)),
+            ControlValue(0.3),
+            "A link without a mapping passes values through"
+        );
+        assert_eq!(
+            ControlMapping::default().map(ControlValue(0.3)),
+            ControlValue(0.3),
+            "The default mapping does nothing"
+        );
+
+        let mapping = ControlMappingBuilder::default()
+            .min(ControlValue(0.2))
+            .max(ControlValue(0.6))
+            .build()
+            .unwrap();
+        assert_eq!(mapping.map(ControlValue::MIN), ControlValue(0.2));
+        assert_eq!(mapping.map(ControlValue::MAX), ControlValue(0.6));
+        assert!((mapping.map(ControlValue(0.5)).0 - 0.4).abs() < 1e-9);
+        assert_eq!(
+            mapping.map(ControlValue(2.0)),
+            ControlValue(0.6),
+            "Out-of-range input is clamped"
+        );
+
+        let mapping = ControlMappingBuilder::default()
+            .invert(true)
+            .build()
+            .unwrap();
+        assert_eq!(mapping.map(ControlValue(0.25)), ControlValue(0.75));
+
+        for curve in [ControlCurve::Exponential, ControlCurve::Logarithmic] {
+            let mapping = ControlMappingBuilder::default()
+                .curve(curve)
+                .build()
+                .unwrap();
+            assert!((mapping.map(ControlValue::MIN).0).abs() < 1e-9);
+            assert!((mapping.map(ControlValue::MAX).0 - 1.0).abs() < 1e-9);
+            let midpoint = mapping.map(ControlValue(0.5)).0;
+            if curve == ControlCurve::Exponential {
+                assert!(midpoint < 0.5, "Exponential should start slow");
+            } else {
+                assert!(midpoint > 0.5, "Logarithmic should start fast");
+            }
+        }
+        let exponential = ControlMappingBuilder::default()
+            .curve(ControlCurve::Exponential)
+            .build()
+            .unwrap();
+        let logarithmic = ControlMappingBuilder::default()
+            .curve(ControlCurve::Logarithmic)
+            .build()
+            .unwrap();
+        assert!(
+            (logarithmic.map(exponential.map(ControlValue(0.3))).0 - 0.3).abs() < 1e-9,
+            "The curves should be inverses of each other"
+        );
+
+        let rectified = ControlMappingBuilder::default()
+            .polarity(ControlPolarity::Rectified)
+            .build()
+            .unwrap();
+        assert_eq!(rectified.map(ControlValue(0.5)), ControlValue::MIN);
+        assert_eq!(rectified.map(ControlValue::MIN), ControlValue::MAX);
+        assert_eq!(rectified.map(ControlValue::MAX), ControlValue::MAX);
+        let positive_half = ControlMappingBuilder::default()
+            .polarity(ControlPolarity::PositiveHalf)
+            .build()
+            .unwrap();
+        assert_eq!(positive_half.map(ControlValue(0.25)), ControlValue::MIN);
+        assert_eq!(positive_half.map(ControlValue(0.75)), ControlValue(0.5));
+
+        let json = serde_json::to_string(&link).unwrap();
+        assert!(
+            !json.contains("mapping") && !json.contains("role"),
+            "Unmapped links should serialize as they always have"
+        );
+        let link = ControlLink {
+            mapping: Some(rectified),
+            role: ControlLinkRole::Modulation(Normal::from(0.5)),
+            ..link
+        };
+        let restored: ControlLink =
+            serde_json::from_str(&serde_json::to_string(&link).unwrap()).unwrap();
+        assert_eq!(restored, link);
+    }
+
     #[test]
     fn usize_ok() {
         let a = usize::MAX;
@@ -443,6 +443,10 @@ impl Controls for Composer {
     }
 
     fn work(&mut self, control_events_fn: &mut ControlEventsFn) {
+        self.work_timed(&mut |_, event| control_events_fn(event));
+    }
+
+    fn work_timed(&mut self, control_events_fn: &mut TimedControlEventsFn) {
         if self.e.is_performing {
             // TODO: no duplicate time range detection
             // No note killer
@@ -450,15 +454,14 @@ impl Controls for Composer {
                 .tracks_to_sequencers
                 .iter_mut()
                 .for_each(|(track_uid, sequencer)| {
-                    sequencer.work(&mut |event| match event {
+                    sequencer.work_timed(&mut |when, event| match event {
                         WorkEvent::Midi(channel, message) => {
-                            control_events_fn(WorkEvent::MidiForTrack(
-                                track_uid.clone(),
-                                channel,
-                                message,
-                            ));
+                            control_events_fn(
+                                when,
+                                WorkEvent::MidiForTrack(track_uid.clone(), channel, message),
+                            );
                         }
-                        _ => control_events_fn(event),
+                        _ => control_events_fn(when, event),
                     });
                 });
         }
@@ -480,6 +483,15 @@ impl Controls for Composer {
 
     // TODO: this doesn't fit. Ignore here? Or problem with trait?
     fn skip_to_start(&mut self) {}
+
+    fn seek(&mut self, position: MusicalTime) {
+        self.e
+            .tracks_to_sequencers
+            .values_mut()
+            .for_each(|s| s.seek(position));
+        self.e.time_range = TimeRange(position..position);
+        self.update_is_finished();
+    }
 }
 impl Serializable for Composer {
     fn before_ser(&mut self) {
@@ -6,8 +6,8 @@
 pub mod prelude {
     pub use super::{
         sequencers::{PatternSequencer, PatternSequencerBuilder},
-        ArrangementUid, Composer, MidiNoteRange, Note, Pattern, PatternBuilder, PatternUid,
-        PatternUidFactory,
+        ArrangementUid, Composer, MidiNoteRange, MidiRecordOptions, Note, Pattern, PatternBuilder,
+        PatternUid, PatternUidFactory,
     };
 }
 
@@ -15,6 +15,7 @@ pub use arrangement::*;
 pub use composer::*;
 pub use note::*;
 pub use pattern::*;
+pub use recorder::*;
 pub use sequencers::*;
 pub use types::*;
 
@@ -22,5 +23,6 @@ mod arrangement;
 mod composer;
 mod note;
 mod pattern;
+mod recorder;
 mod sequencers;
 mod types;
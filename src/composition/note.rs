@@ -13,6 +13,18 @@ pub struct Note {
     pub key: u8,
     /// The range of time when this note should play.
     pub extent: TimeRange,
+    /// How hard the note is struck (the MIDI note-on velocity, 1..=127).
+    /// There's no zero, because a MIDI note-on with velocity zero means
+    /// note-off.
+    #[serde(
+        default = "Note::default_velocity",
+        deserialize_with = "Note::deserialize_velocity"
+    )]
+    pub velocity: u8,
+    /// How quickly the note is released (the MIDI note-off velocity,
+    /// 0..=127).
+    #[serde(default = "Note::default_velocity")]
+    pub release_velocity: u8,
 }
 impl HasExtent for Note {
     fn extent(&self) -> TimeRange {
@@ -24,11 +36,46 @@ impl HasExtent for Note {
     }
 }
 impl Note {
+    /// The velocity of a [Note] that doesn't specify one. This is also what
+    /// notes in projects saved before [Note] had velocity get.
+    pub const DEFAULT_VELOCITY: u8 = 127;
+
+    /// The quietest note-on velocity a [Note] can have.
+    pub const MIN_VELOCITY: u8 = 1;
+
+    /// The loudest note-on velocity a [Note] can have.
+    pub const MAX_VELOCITY: u8 = 127;
+
+    fn default_velocity() -> u8 {
+        Self::DEFAULT_VELOCITY
+    }
+
+    fn deserialize_velocity<'de, D>(deserializer: D) -> Result<u8, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(Self::clamp_velocity(u8::deserialize(deserializer)?))
+    }
+
+    /// Returns the closest note-on velocity to the given one that a [Note]
+    /// can have.
+    pub const fn clamp_velocity(velocity: u8) -> u8 {
+        if velocity < Self::MIN_VELOCITY {
+            Self::MIN_VELOCITY
+        } else if velocity > Self::MAX_VELOCITY {
+            Self::MAX_VELOCITY
+        } else {
+            velocity
+        }
+    }
+
     /// Creates a [Note] from a u8 and a start/end (inclusive start, exclusive end).
     pub const fn new_with_start_and_end(key: u8, start: MusicalTime, end: MusicalTime) -> Self {
         Self {
             key,
             extent: TimeRange(start..end),
+            velocity: Self::DEFAULT_VELOCITY,
+            release_velocity: Self::DEFAULT_VELOCITY,
         }
     }
 
@@ -38,6 +85,19 @@ impl Note {
         Self::new_with_start_and_end(key, start, end)
     }
 
+    /// Creates a [Note] from a u8, start/duration, and note-on velocity. The
+    /// velocity is clamped to [Note::MIN_VELOCITY]..=[Note::MAX_VELOCITY].
+    pub const fn new_with_velocity(
+        key: u8,
+        start: MusicalTime,
+        duration: MusicalTime,
+        velocity: u8,
+    ) -> Self {
+        let mut r = Self::new_with(key, start, duration);
+        r.velocity = Self::clamp_velocity(velocity);
+        r
+    }
+
     /// Creates a [Note] from a [MidiNote].
     pub fn new_with_midi_note(key: MidiNote, start: MusicalTime, duration: MusicalTime) -> Self {
         Self::new_with(key as u8, start, duration)
@@ -46,7 +106,16 @@ impl Note {
     /// Moves the note's start/end by the specified amount without changing its
     /// duration.
     pub fn shift_right(&self, rhs: MusicalTime) -> Self {
-        Self::new_with_start_and_end(self.key, self.extent.0.start + rhs, self.extent.0.end + rhs)
+        let mut r = self.clone();
+        r.extent = self.extent.translate(rhs);
+        r
+    }
+
+    /// Returns true if the two notes differ only in their expression data
+    /// (such as velocity), which is useful for finding the [Note] that a user
+    /// pointed at.
+    pub fn is_same_placement(&self, other: &Note) -> bool {
+        self.key == other.key && self.extent == other.extent
     }
 }
 
@@ -59,14 +128,14 @@ impl Into<Vec<MidiEvent>> for Note {
             MidiEvent {
                 message: MidiMessage::NoteOn {
                     key: u7::from(self.key),
-                    vel: u7::from(127),
+                    vel: u7::from(Self::clamp_velocity(self.velocity)),
                 },
                 time: self.extent.0.start,
             },
             MidiEvent {
                 message: MidiMessage::NoteOff {
                     key: u7::from(self.key),
-                    vel: u7::from(127),
+                    vel: u7::from(self.release_velocity.min(127)),
                 },
                 time: self.extent.0.end,
             },
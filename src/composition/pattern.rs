@@ -276,6 +276,15 @@ impl Pattern {
         self.replace_note(note, new_note)
     }
 
+    /// Sets a new note-on velocity for all notes in the Pattern matching the
+    /// given [Note]. The velocity is clamped to the range that a [Note] can
+    /// have. If any are found, returns the new version.
+    pub fn change_note_velocity(&mut self, note: &Note, new_velocity: u8) -> anyhow::Result<Note> {
+        let mut new_note = note.clone();
+        new_note.velocity = Note::clamp_velocity(new_velocity);
+        self.replace_note(note, new_note)
+    }
+
     /// Replaces all notes in the Pattern matching the given [Note] with a new
     /// [Note]. If any are found, returns the new version.
     pub fn replace_note(&mut self, note: &Note, new_note: Note) -> anyhow::Result<Note> {
@@ -830,4 +839,68 @@ mod tests {
             .unwrap();
         assert_eq!(p.duration(), MusicalTime::new_with_beats(2));
     }
+
+    #[test]
+    fn note_velocity() {
+        let note = Note::new_with_velocity(60, MusicalTime::START, MusicalTime::ONE_BEAT, 33);
+        let events: Vec<MidiEvent> = note.clone().into();
+        assert_eq!(
+            events[0].message,
+            MidiMessage::NoteOn {
+                key: u7::from(60),
+                vel: u7::from(33)
+            }
+        );
+        assert_eq!(
+            events[1].message,
+            MidiMessage::NoteOff {
+                key: u7::from(60),
+                vel: u7::from(Note::DEFAULT_VELOCITY)
+            }
+        );
+
+        let mut p = PatternBuilder::default()
+            .note(note.clone())
+            .build()
+            .unwrap();
+        let louder = p.change_note_velocity(&note, 99).unwrap();
+        assert_eq!(p.notes()[0].velocity, 99);
+        assert!(louder.is_same_placement(&note));
+        assert_ne!(louder, note);
+        assert!(
+            p.change_note_velocity(&note, 1).is_err(),
+            "The original note should no longer be in the pattern"
+        );
+
+        let silent = p.change_note_velocity(&louder, 0).unwrap();
+        assert_eq!(
+            silent.velocity,
+            Note::MIN_VELOCITY,
+            "A note-on velocity of zero means note-off, so a note can't have it"
+        );
+        assert_eq!(
+            Note::new_with_velocity(60, MusicalTime::START, MusicalTime::ONE_BEAT, 0).velocity,
+            Note::MIN_VELOCITY
+        );
+        assert_eq!(
+            Note::new_with_velocity(60, MusicalTime::START, MusicalTime::ONE_BEAT, 200).velocity,
+            Note::MAX_VELOCITY
+        );
+        let note: Note =
+            serde_json::from_str(r#"{"key":60,"extent":{"start":0,"end":65536},"velocity":0}"#)
+                .unwrap();
+        assert_eq!(note.velocity, Note::MIN_VELOCITY);
+    }
+
+    #[test]
+    fn note_velocity_defaults_when_deserialized() {
+        let json = r#"{"key":60,"extent":{"start":0,"end":65536}}"#;
+        let note: Note = serde_json::from_str(json).unwrap();
+        assert_eq!(note.velocity, Note::DEFAULT_VELOCITY);
+        assert_eq!(note.release_velocity, Note::DEFAULT_VELOCITY);
+        assert_eq!(
+            note,
+            Note::new_with(60, MusicalTime::START, MusicalTime::ONE_BEAT)
+        );
+    }
 }
@@ -0,0 +1,255 @@
+// Copyright (c) 2024 Mike Tsao
+
+use super::{MidiSequencer, Note, Pattern, PatternBuilder};
+use crate::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Decides how the notes played during MIDI recording are written into the
+/// recorded [Pattern].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct MidiRecordOptions {
+    /// If set, the start and end of each note snap to the nearest multiple of
+    /// this duration, counting from the start of the song.
+    pub quantize: Option<MusicalTime>,
+    /// If set, every note gets this velocity instead of the one it was played
+    /// with.
+    pub fixed_velocity: Option<u8>,
+}
+
+/// The notes played during one pass of MIDI recording. They're captured by a
+/// [MidiSequencer] and become a [Pattern] when the take is finished.
+#[derive(Debug)]
+pub(crate) struct MidiTake {
+    start: MusicalTime,
+    sequencer: MidiSequencer,
+
+    // Keys that have been struck but not yet released.
+    held_keys: Vec<u7>,
+}
+impl MidiTake {
+    pub(crate) fn new_with(start: MusicalTime) -> Self {
+        let mut sequencer = MidiSequencer::default();
+        sequencer.start_recording();
+        Self {
+            start,
+            sequencer,
+            held_keys: Vec::default(),
+        }
+    }
+
+    /// Where the recorded [Pattern] belongs in the song.
+    pub(crate) fn start(&self) -> MusicalTime {
+        self.start
+    }
+
+    /// Records a message that arrived at the given time. Only notes become part
+    /// of a [Pattern], so other messages are ignored. Outside the punch range,
+    /// new notes are ignored, but notes that started inside it can still end.
+    pub(crate) fn record(
+        &mut self,
+        channel: MidiChannel,
+        message: MidiMessage,
+        time: MusicalTime,
+        is_punched_in: bool,
+    ) {
+        match message {
+            MidiMessage::NoteOn { key, vel } if vel.as_int() != 0 => {
+                if !is_punched_in {
+                    return;
+                }
+                if !self.held_keys.contains(&key) {
+                    self.held_keys.push(key);
+                }
+            }
+            MidiMessage::NoteOn { key, .. } | MidiMessage::NoteOff { key, .. } => {
+                if !self.held_keys.contains(&key) {
+                    return;
+                }
+                self.held_keys.retain(|k| *k != key);
+            }
+            _ => return,
+        }
+        let _ = self.sequencer.record_midi_message(channel, message, time);
+    }
+
+    /// Ends every held note at the end of a loop, because playback is about
+    /// to jump back to the given loop start. Later passes add to the same
+    /// take.
+    pub(crate) fn wrap(&mut self, loop_end: MusicalTime, loop_start: MusicalTime) {
+        for key in std::mem::take(&mut self.held_keys) {
+            let _ = self.sequencer.record_midi_message(
+                MidiChannel::default(),
+                MidiMessage::NoteOff { key, vel: 0.into() },
+                loop_end,
+            );
+        }
+        self.start = self.start.min(loop_start);
+    }
+
+    /// Returns the recorded notes as a [Pattern] that starts at
+    /// [MidiTake::start()], or None if nothing was played. Notes that are
+    /// still held end at the given time.
+    pub(crate) fn finish(
+        self,
+        end: MusicalTime,
+        options: &MidiRecordOptions,
+        time_signature: TimeSignature,
+    ) -> Option<Pattern> {
+        // (key, start, velocity) of each note that hasn't ended yet
+        let mut sounding: Vec<(u8, MusicalTime, u8)> = Vec::default();
+        let mut notes = Vec::default();
+        for (_, event) in self.sequencer.events() {
+            let (key, velocity) = match event.message {
+                MidiMessage::NoteOn { key, vel } | MidiMessage::NoteOff { key, vel } => {
+                    (key.as_int(), vel.as_int())
+                }
+                _ => continue,
+            };
+            let is_note_on = matches!(event.message, MidiMessage::NoteOn { .. }) && velocity != 0;
+            // A key that's struck again while it's held ends its earlier note.
+            if let Some(index) = sounding.iter().position(|(k, _, _)| *k == key) {
+                let (_, start, start_velocity) = sounding.remove(index);
+                let release_velocity = if matches!(event.message, MidiMessage::NoteOff { .. }) {
+                    velocity
+                } else {
+                    // A note-on doesn't carry a release velocity.
+                    Note::DEFAULT_VELOCITY
+                };
+                notes.push(self.note(
+                    options,
+                    key,
+                    start..event.time,
+                    start_velocity,
+                    release_velocity,
+                ));
+            }
+            if is_note_on {
+                sounding.push((key, event.time, velocity));
+            }
+        }
+        for (key, start, velocity) in sounding {
+            notes.push(self.note(options, key, start..end, velocity, Note::DEFAULT_VELOCITY));
+        }
+        if notes.is_empty() {
+            return None;
+        }
+        PatternBuilder::default()
+            .time_signature(time_signature)
+            .notes(notes)
+            .build()
+            .ok()
+    }
+
+    // Makes a note that's positioned relative to the start of the take.
+    fn note(
+        &self,
+        options: &MidiRecordOptions,
+        key: u8,
+        range: core::ops::Range<MusicalTime>,
+        velocity: u8,
+        release_velocity: u8,
+    ) -> Note {
+        let (start, end) = if let Some(quantum) = options
+            .quantize
+            .filter(|q| *q != MusicalTime::DURATION_ZERO)
+        {
+            let start = range.start.quantized(quantum).max(self.start);
+            (start, range.end.quantized(quantum).max(start + quantum))
+        } else {
+            let start = range.start.max(self.start);
+            (start, range.end.max(start + MusicalTime::ONE_UNIT))
+        };
+        Note {
+            key,
+            extent: TimeRange((start - self.start)..(end - self.start)),
+            velocity: Note::clamp_velocity(options.fixed_velocity.unwrap_or(velocity)),
+            release_velocity,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn midi_take_becomes_pattern() {
+        let start = MusicalTime::new_with_beats(4);
+        let mut take = MidiTake::new_with(start);
+        let channel = MidiChannel::default();
+        let beat = |beats: f64| start + MusicalTime::new_with_fractional_beats(beats);
+
+        take.record(
+            channel,
+            MidiMessage::NoteOn {
+                key: 60.into(),
+                vel: 100.into(),
+            },
+            beat(0.1),
+            true,
+        );
+        take.record(
+            channel,
+            MidiMessage::NoteOff {
+                key: 60.into(),
+                vel: 30.into(),
+            },
+            beat(0.9),
+            true,
+        );
+        take.record(
+            channel,
+            MidiMessage::NoteOn {
+                key: 64.into(),
+                vel: 90.into(),
+            },
+            beat(1.0),
+            false,
+        );
+        take.record(
+            channel,
+            MidiMessage::NoteOn {
+                key: 67.into(),
+                vel: 80.into(),
+            },
+            beat(2.0),
+            true,
+        );
+
+        let options = MidiRecordOptions {
+            quantize: Some(MusicalTime::ONE_BEAT),
+            fixed_velocity: None,
+        };
+        let pattern = take
+            .finish(beat(3.0), &options, TimeSignature::default())
+            .unwrap();
+        assert_eq!(
+            pattern.notes,
+            vec![
+                Note {
+                    key: 60,
+                    extent: TimeRange(MusicalTime::START..MusicalTime::ONE_BEAT),
+                    velocity: 100,
+                    release_velocity: 30,
+                },
+                Note {
+                    key: 67,
+                    extent: TimeRange(
+                        MusicalTime::new_with_beats(2)..MusicalTime::new_with_beats(3)
+                    ),
+                    velocity: 80,
+                    release_velocity: Note::DEFAULT_VELOCITY,
+                },
+            ],
+            "Notes should be quantized, notes outside the punch range should be skipped, and held notes should end with the take"
+        );
+
+        let take = MidiTake::new_with(start);
+        assert!(
+            take.finish(beat(1.0), &options, TimeSignature::default())
+                .is_none(),
+            "An empty take shouldn't produce a pattern"
+        );
+    }
+}
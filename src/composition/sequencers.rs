@@ -71,10 +71,14 @@ impl Controls for MidiSequencer {
 
     //    #[deprecated = "FIX THE CHANNEL!"]
     fn work(&mut self, control_events_fn: &mut ControlEventsFn) {
+        self.work_timed(&mut |_, event| control_events_fn(event));
+    }
+
+    fn work_timed(&mut self, control_events_fn: &mut TimedControlEventsFn) {
         // OMG this is O(n^2)
         self.events.iter().for_each(|(channel, event)| {
             if self.time_range.0.contains(&event.time) {
-                control_events_fn(WorkEvent::Midi(*channel, event.message))
+                control_events_fn(event.time, WorkEvent::Midi(*channel, event.message))
             }
         });
     }
@@ -96,6 +100,10 @@ impl Controls for MidiSequencer {
     fn skip_to_start(&mut self) {
         self.time_range = TimeRange(MusicalTime::default()..MusicalTime::default())
     }
+
+    fn seek(&mut self, position: MusicalTime) {
+        self.time_range = TimeRange(position..position)
+    }
 }
 impl HandlesMidi for MidiSequencer {
     fn handle_midi_message(
@@ -122,6 +130,11 @@ impl MidiSequencer {
     pub fn time_range(&self) -> &TimeRange {
         &self.time_range
     }
+
+    /// Returns the recorded events in the order they were recorded.
+    pub fn events(&self) -> &[(MidiChannel, MidiEvent)] {
+        &self.events
+    }
 }
 
 impl NoteSequencerBuilder {
@@ -199,22 +212,10 @@ impl Sequences for NoteSequencer {
         position: MusicalTime,
     ) -> anyhow::Result<()> {
         let note = note.shift_right(position);
-        let _ = self.e.inner.remove_midi_message(
-            channel,
-            MidiMessage::NoteOn {
-                key: u7::from(note.key),
-                vel: u7::from(127),
-            },
-            note.extent.0.start,
-        );
-        let _ = self.e.inner.remove_midi_message(
-            channel,
-            MidiMessage::NoteOff {
-                key: u7::from(note.key),
-                vel: u7::from(127),
-            },
-            note.extent.0.end,
-        );
+        let events: Vec<MidiEvent> = note.clone().into();
+        events.iter().for_each(|e| {
+            let _ = self.e.inner.remove_midi_event(channel, *e);
+        });
         self.notes.retain(|n| *n != note);
         self.recalculate_extent();
         Ok(())
@@ -234,6 +235,10 @@ impl Controls for NoteSequencer {
         self.e.inner.work(control_events_fn)
     }
 
+    fn work_timed(&mut self, control_events_fn: &mut TimedControlEventsFn) {
+        self.e.inner.work_timed(control_events_fn)
+    }
+
     fn is_finished(&self) -> bool {
         self.e.inner.is_finished()
     }
@@ -249,6 +254,10 @@ impl Controls for NoteSequencer {
     fn skip_to_start(&mut self) {
         self.e.inner.skip_to_start()
     }
+
+    fn seek(&mut self, position: MusicalTime) {
+        self.e.inner.seek(position)
+    }
 }
 impl Serializable for NoteSequencer {
     fn before_ser(&mut self) {}
@@ -402,6 +411,10 @@ impl Controls for PatternSequencer {
         self.e.inner.work(control_events_fn)
     }
 
+    fn work_timed(&mut self, control_events_fn: &mut TimedControlEventsFn) {
+        self.e.inner.work_timed(control_events_fn)
+    }
+
     fn is_finished(&self) -> bool {
         self.e.inner.is_finished()
     }
@@ -417,6 +430,10 @@ impl Controls for PatternSequencer {
     fn skip_to_start(&mut self) {
         self.e.inner.skip_to_start()
     }
+
+    fn seek(&mut self, position: MusicalTime) {
+        self.e.inner.seek(position)
+    }
 }
 impl Serializable for PatternSequencer {
     fn after_deser(&mut self) {
@@ -449,6 +466,80 @@ mod tests {
         validate_sequences_notes_trait(&mut s);
     }
 
+    #[test]
+    fn note_sequencer_plays_velocity() {
+        let mut s = NoteSequencer::default();
+        let mut note = Note::new_with_velocity(60, MusicalTime::START, MusicalTime::ONE_BEAT, 45);
+        note.release_velocity = 12;
+        assert!(s
+            .record(MidiChannel::default(), &note, MusicalTime::START)
+            .is_ok());
+        assert_eq!(
+            replay_all_units::<Note>(&mut s),
+            vec![
+                (
+                    MidiChannel::default(),
+                    MidiMessage::NoteOn {
+                        key: u7::from(60),
+                        vel: u7::from(45)
+                    }
+                ),
+                (
+                    MidiChannel::default(),
+                    MidiMessage::NoteOff {
+                        key: u7::from(60),
+                        vel: u7::from(12)
+                    }
+                ),
+            ]
+        );
+
+        assert!(s
+            .remove(MidiChannel::default(), &note, MusicalTime::START)
+            .is_ok());
+        assert!(
+            replay_all_units::<Note>(&mut s).is_empty(),
+            "Removing a note should remove its events regardless of velocity"
+        );
+    }
+
+    #[test]
+    fn note_sequencer_seeks() {
+        let mut s = NoteSequencer::default();
+        let note = Note::new_with(60, MusicalTime::new_with_beats(4), MusicalTime::ONE_BEAT);
+        assert!(s
+            .record(MidiChannel::default(), &note, MusicalTime::START)
+            .is_ok());
+        s.play();
+
+        s.seek(MusicalTime::new_with_beats(8));
+        assert!(
+            s.is_finished(),
+            "Seeking past the last event should finish the sequencer"
+        );
+        s.seek(MusicalTime::new_with_beats(3));
+        assert!(
+            !s.is_finished(),
+            "Seeking back before the last event should resume the sequencer"
+        );
+        assert!(replay_units(
+            &mut s,
+            MusicalTime::new_with_beats(3),
+            MusicalTime::ONE_BEAT
+        )
+        .is_empty());
+        assert_eq!(
+            replay_units(
+                &mut s,
+                MusicalTime::new_with_beats(4),
+                MusicalTime::ONE_BEAT
+            )
+            .len(),
+            1,
+            "Playback after a seek should pick up where the seek left off"
+        );
+    }
+
     /////////////////////////////////////////////////////////////////////////
     /// BEGIN tests taken from the old sequencer. These are here to scavenge
     /// good testing ideas.
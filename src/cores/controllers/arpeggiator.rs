@@ -35,6 +35,11 @@ pub enum ArpeggioMode {
 /// quickly sounded in a progressive rising or descending order." You can also
 /// think of it as a hybrid MIDI instrument and MIDI controller; you play it
 /// with MIDI, but instead of producing audio, it produces more MIDI.
+///
+/// The notes of an arpeggio land on their own frames, like a sequencer's. But
+/// the notes that it's played with arrive while the project renders, after
+/// its work for that time range is done, so an arpeggio can't begin until the
+/// next time range. How long that takes depends on the buffer size.
 #[derive(Clone, Builder, Debug, Default, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 #[builder(default)]
@@ -91,6 +96,10 @@ impl Controls for ArpeggiatorCore {
         self.e.sequencer.work(control_events_fn)
     }
 
+    fn work_timed(&mut self, control_events_fn: &mut TimedControlEventsFn) {
+        self.e.sequencer.work_timed(control_events_fn)
+    }
+
     fn is_finished(&self) -> bool {
         self.e.sequencer.is_finished()
     }
@@ -0,0 +1,449 @@
+// Copyright (c) 2024 Mike Tsao
+
+use crate::prelude::*;
+use delegate::delegate;
+use derivative::Derivative;
+use derive_builder::Builder;
+use ensnare_proc_macros::Control;
+use serde::{Deserialize, Serialize};
+
+/// Follows the amplitude of the audio passing through it, and emits a control
+/// signal that rises and falls with it. Unlike
+/// [SignalPassthroughControllerCore](crate::cores::SignalPassthroughControllerCore),
+/// which follows the waveform itself, the signal changes only as quickly as
+/// the attack and release allow, so it's suitable for ducking a
+/// [Gain](crate::entities::Gain) or sweeping a filter cutoff.
+///
+/// The audio isn't changed.
+///
+/// The signal is issued at the start of each time range that the project
+/// renders, and it reflects the audio that passed through before then. So,
+/// unlike a sequencer's notes, it isn't sample-accurate: it trails the audio
+/// by up to one buffer, and exactly when it changes depends on the buffer
+/// size. That's inaudible for the slow changes that the attack and release
+/// produce.
+#[derive(Clone, Debug, Derivative, Builder, Control, Serialize, Deserialize)]
+#[derivative(Default)]
+#[builder(default, build_fn(private, name = "build_from_builder"))]
+#[serde(default, rename_all = "kebab-case")]
+pub struct EnvelopeFollowerCore {
+    /// How quickly the signal rises when the input gets louder. Expressed as a
+    /// [Normal] that is scaled to an amount of time, with 1.0 being
+    /// [EnvelopeFollowerCore::MAX_ATTACK].
+    #[control]
+    #[derivative(Default(value = "Normal::from(0.02)"))]
+    attack: Normal,
+
+    /// How quickly the signal falls when the input gets quieter. Expressed as
+    /// a [Normal] that is scaled to an amount of time, with 1.0 being
+    /// [EnvelopeFollowerCore::MAX_RELEASE].
+    #[control]
+    #[derivative(Default(value = "Normal::from(0.1)"))]
+    release: Normal,
+
+    /// The span of recent input that's averaged to measure its level.
+    /// Expressed as a [Normal] that is scaled to an amount of time, with 1.0
+    /// being [EnvelopeFollowerCore::MAX_WINDOW]. 0.0 follows the peaks of the
+    /// input rather than its average (RMS) level.
+    #[control]
+    #[derivative(Default(value = "Normal::from(0.1)"))]
+    window: Normal,
+
+    /// The level at or below which the input counts as silent.
+    #[control]
+    #[derivative(Default(value = "Normal::zero()"))]
+    threshold: Normal,
+
+    /// The level at or above which the input counts as fully loud. Levels
+    /// between the threshold and the ceiling are scaled proportionally.
+    #[control]
+    ceiling: Normal,
+
+    /// The value of the signal when the input is silent.
+    #[control]
+    #[derivative(Default(value = "Normal::zero()"))]
+    silent_output: Normal,
+
+    /// The value of the signal when the input is fully loud. Set this lower
+    /// than [EnvelopeFollowerCore::silent_output()] for a signal that falls as
+    /// the input gets louder, which is what ducking needs.
+    #[control]
+    loud_output: Normal,
+
+    #[serde(skip)]
+    #[builder(setter(skip))]
+    e: EnvelopeFollowerCoreEphemerals,
+}
+#[derive(Clone, Debug, Default)]
+pub struct EnvelopeFollowerCoreEphemerals {
+    // The running average of the input's squared level, for RMS detection.
+    mean_square: f64,
+
+    // The detected level, after attack and release.
+    envelope: f64,
+
+    // Per-sample smoothing coefficients, which depend on the sample rate.
+    attack_coefficient: f64,
+    release_coefficient: f64,
+    window_coefficient: f64,
+
+    control_value: ControlValue,
+    // We don't issue consecutive identical events, so we need to remember
+    // whether we've sent the current value.
+    has_value_been_issued: bool,
+
+    is_performing: bool,
+    time_range: TimeRange,
+
+    c: Configurables,
+}
+impl EnvelopeFollowerCoreBuilder {
+    /// The overridden Builder build() method.
+    pub fn build(&self) -> Result<EnvelopeFollowerCore, EnvelopeFollowerCoreBuilderError> {
+        match self.build_from_builder() {
+            Ok(mut s) => {
+                s.after_deser();
+                Ok(s)
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+impl Serializable for EnvelopeFollowerCore {
+    fn after_deser(&mut self) {
+        self.update_coefficients();
+        self.update_control_value();
+    }
+}
+impl Configurable for EnvelopeFollowerCore {
+    delegate! {
+        to self.e.c {
+            fn sample_rate(&self) -> SampleRate;
+            fn tempo(&self) -> Tempo;
+            fn update_tempo(&mut self, tempo: Tempo);
+            fn time_signature(&self) -> TimeSignature;
+            fn update_time_signature(&mut self, time_signature: TimeSignature);
+        }
+    }
+
+    fn update_sample_rate(&mut self, sample_rate: SampleRate) {
+        self.e.c.update_sample_rate(sample_rate);
+        self.update_coefficients();
+    }
+}
+impl Controls for EnvelopeFollowerCore {
+    fn time_range(&self) -> Option<TimeRange> {
+        Some(self.e.time_range.clone())
+    }
+
+    fn update_time_range(&mut self, range: &TimeRange) {
+        // We don't need the range to decide what to issue, because we already
+        // have our own de-duplicating logic, but it says when we issue it.
+        self.e.time_range = range.clone();
+    }
+
+    fn work(&mut self, control_events_fn: &mut ControlEventsFn) {
+        if !self.e.is_performing {
+            return;
+        }
+        if !self.e.has_value_been_issued {
+            self.e.has_value_been_issued = true;
+            control_events_fn(WorkEvent::Control(self.e.control_value))
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        true
+    }
+
+    fn play(&mut self) {
+        self.e.is_performing = true;
+    }
+
+    fn stop(&mut self) {
+        self.e.is_performing = false;
+    }
+
+    fn skip_to_start(&mut self) {
+        self.e.mean_square = 0.0;
+        self.e.envelope = 0.0;
+        self.update_control_value();
+    }
+}
+impl HandlesMidi for EnvelopeFollowerCore {}
+impl TransformsAudio for EnvelopeFollowerCore {
+    fn transform(&mut self, samples: &mut [StereoSample]) {
+        for sample in samples.iter() {
+            let level = if self.e.window_coefficient > 0.0 {
+                let square = (sample.0 .0 * sample.0 .0 + sample.1 .0 * sample.1 .0) / 2.0;
+                self.e.mean_square =
+                    square + self.e.window_coefficient * (self.e.mean_square - square);
+                self.e.mean_square.sqrt()
+            } else {
+                sample.0 .0.abs().max(sample.1 .0.abs())
+            };
+            let coefficient = if level > self.e.envelope {
+                self.e.attack_coefficient
+            } else {
+                self.e.release_coefficient
+            };
+            self.e.envelope = level + coefficient * (self.e.envelope - level);
+        }
+        self.update_control_value();
+
+        // We don't alter the input. We just look at it.
+    }
+}
+impl EnvelopeFollowerCore {
+    /// The attack time when [EnvelopeFollowerCore::attack()] is 1.0.
+    pub const MAX_ATTACK: Seconds = Seconds(0.5);
+
+    /// The release time when [EnvelopeFollowerCore::release()] is 1.0.
+    pub const MAX_RELEASE: Seconds = Seconds(2.0);
+
+    /// The averaging window when [EnvelopeFollowerCore::window()] is 1.0.
+    pub const MAX_WINDOW: Seconds = Seconds(0.1);
+
+    fn update_coefficients(&mut self) {
+        let sample_rate = f64::from(self.e.c.sample_rate());
+        self.e.attack_coefficient =
+            Self::smoothing_coefficient(self.attack.0 * Self::MAX_ATTACK.0, sample_rate);
+        self.e.release_coefficient =
+            Self::smoothing_coefficient(self.release.0 * Self::MAX_RELEASE.0, sample_rate);
+        self.e.window_coefficient =
+            Self::smoothing_coefficient(self.window.0 * Self::MAX_WINDOW.0, sample_rate);
+    }
+
+    // The coefficient of a one-pole smoother that takes about the given number
+    // of seconds to settle. Zero seconds means no smoothing at all.
+    fn smoothing_coefficient(seconds: f64, sample_rate: f64) -> f64 {
+        if seconds > 0.0 {
+            (-1.0 / (seconds * sample_rate)).exp()
+        } else {
+            0.0
+        }
+    }
+
+    // Maps the current envelope to the output range, and arranges for the
+    // result to be issued if it changed.
+    fn update_control_value(&mut self) {
+        let span = self.ceiling.0 - self.threshold.0;
+        let loudness = if span > 0.0 {
+            ((self.e.envelope - self.threshold.0) / span).clamp(0.0, 1.0)
+        } else if self.e.envelope > self.threshold.0 {
+            1.0
+        } else {
+            0.0
+        };
+        let control_value = ControlValue(
+            self.silent_output.0 + (self.loud_output.0 - self.silent_output.0) * loudness,
+        );
+        if self.e.control_value != control_value {
+            self.e.has_value_been_issued = false;
+            self.e.control_value = control_value;
+        }
+    }
+}
+#[allow(missing_docs)]
+impl EnvelopeFollowerCore {
+    pub fn attack(&self) -> Normal {
+        self.attack
+    }
+
+    pub fn set_attack(&mut self, attack: Normal) {
+        self.attack = attack;
+        self.update_coefficients();
+    }
+
+    pub fn release(&self) -> Normal {
+        self.release
+    }
+
+    pub fn set_release(&mut self, release: Normal) {
+        self.release = release;
+        self.update_coefficients();
+    }
+
+    pub fn window(&self) -> Normal {
+        self.window
+    }
+
+    pub fn set_window(&mut self, window: Normal) {
+        self.window = window;
+        self.update_coefficients();
+    }
+
+    pub fn threshold(&self) -> Normal {
+        self.threshold
+    }
+
+    pub fn set_threshold(&mut self, threshold: Normal) {
+        self.threshold = threshold;
+        self.update_control_value();
+    }
+
+    pub fn ceiling(&self) -> Normal {
+        self.ceiling
+    }
+
+    pub fn set_ceiling(&mut self, ceiling: Normal) {
+        self.ceiling = ceiling;
+        self.update_control_value();
+    }
+
+    pub fn silent_output(&self) -> Normal {
+        self.silent_output
+    }
+
+    pub fn set_silent_output(&mut self, silent_output: Normal) {
+        self.silent_output = silent_output;
+        self.update_control_value();
+    }
+
+    pub fn loud_output(&self) -> Normal {
+        self.loud_output
+    }
+
+    pub fn set_loud_output(&mut self, loud_output: Normal) {
+        self.loud_output = loud_output;
+        self.update_control_value();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Runs the follower over a constant input for the given number of frames,
+    // and returns the control value that it issues afterward, if any.
+    fn follow(
+        follower: &mut EnvelopeFollowerCore,
+        level: f64,
+        frames: usize,
+    ) -> Option<ControlValue> {
+        let mut samples = vec![StereoSample::from(level); frames];
+        follower.transform(&mut samples);
+        assert!(
+            samples.iter().all(|s| *s == StereoSample::from(level)),
+            "The audio should pass through unchanged"
+        );
+        let mut issued = None;
+        follower.work(&mut |event| {
+            if let WorkEvent::Control(value) = event {
+                issued = Some(value);
+            }
+        });
+        issued
+    }
+
+    #[test]
+    fn envelope_follower_rises_and_falls_gradually() {
+        let mut follower = EnvelopeFollowerCoreBuilder::default()
+            .attack(0.02.into())
+            .release(0.1.into())
+            .window(0.1.into())
+            .build()
+            .unwrap();
+        follower.update_sample_rate(SampleRate::DEFAULT);
+        assert!(
+            follow(&mut follower, 0.5, 64).is_none(),
+            "Nothing should be issued until playback starts"
+        );
+        follower.skip_to_start();
+        follower.play();
+
+        let start = follow(&mut follower, 0.5, 64).unwrap();
+        assert!(
+            start.0 < 0.1,
+            "The signal shouldn't jump when the input does ({})",
+            start.0
+        );
+        let risen = follow(&mut follower, 0.5, 44100).unwrap();
+        assert!(
+            (risen.0 - 0.5).abs() < 0.01,
+            "The signal should settle at the input's level ({})",
+            risen.0
+        );
+        assert!(
+            follow(&mut follower, 0.5, 64).is_none(),
+            "An unchanged value shouldn't be issued again"
+        );
+
+        let falling = follow(&mut follower, 0.0, 441).unwrap();
+        assert!(
+            falling.0 > 0.3,
+            "The signal should fall gradually after the input stops ({})",
+            falling.0
+        );
+        let fallen = follow(&mut follower, 0.0, 44100).unwrap();
+        assert!(
+            fallen.0 < 0.01,
+            "The signal should eventually fall to silence ({})",
+            fallen.0
+        );
+    }
+
+    #[test]
+    fn envelope_follower_scales_output() {
+        let mut follower = EnvelopeFollowerCoreBuilder::default()
+            .attack(0.0.into())
+            .release(0.0.into())
+            .window(0.0.into())
+            .threshold(0.1.into())
+            .ceiling(0.5.into())
+            .silent_output(Normal::maximum())
+            .loud_output(0.2.into())
+            .build()
+            .unwrap();
+        follower.play();
+        for (level, expected) in [(0.05, 1.0), (0.3, 0.6), (0.9, 0.2)] {
+            let value = follow(&mut follower, level, 1).unwrap();
+            assert!(
+                (value.0 - expected).abs() < 0.000001,
+                "Level {level} should produce {expected}, not {}",
+                value.0
+            );
+        }
+    }
+
+    #[test]
+    fn envelope_follower_issues_at_start_of_time_range() {
+        let mut follower = EnvelopeFollowerCoreBuilder::default()
+            .attack(0.0.into())
+            .release(0.0.into())
+            .window(0.0.into())
+            .build()
+            .unwrap();
+        follower.play();
+        follower.update_time_range(&TimeRange(
+            MusicalTime::ONE_BEAT..MusicalTime::new_with_beats(2),
+        ));
+
+        // The follower doesn't see this audio until after the time range's
+        // work is done, which is why its signal trails the audio.
+        let mut issued = Vec::default();
+        follower.work_timed(&mut |when, event| issued.push((when, event)));
+        follower.transform(&mut [StereoSample::from(0.5)]);
+        follower.work_timed(&mut |when, event| issued.push((when, event)));
+
+        let values: Vec<f64> = issued
+            .into_iter()
+            .map(|(when, event)| {
+                assert_eq!(
+                    when,
+                    MusicalTime::ONE_BEAT,
+                    "Values should be issued at the start of the time range"
+                );
+                match event {
+                    WorkEvent::Control(value) => value.0,
+                    _ => panic!("Only control events should be issued"),
+                }
+            })
+            .collect();
+        assert_eq!(
+            values,
+            vec![0.0, 0.5],
+            "The signal should change only after the follower sees the audio"
+        );
+    }
+}
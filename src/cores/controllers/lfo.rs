@@ -29,6 +29,7 @@ pub struct LfoControllerCoreEphemerals {
     is_performing: bool,
     time_range: TimeRange,
     last_frame: usize,
+    last_value: BipolarNormal,
     pub osc_buffer: GenerationBuffer<BipolarNormal>,
 }
 impl Serializable for LfoControllerCore {}
@@ -50,39 +51,23 @@ impl Controls for LfoControllerCore {
     }
 
     fn work(&mut self, control_events_fn: &mut ControlEventsFn) {
-        let frames = self
-            .e
-            .time_range
-            .0
-            .start
-            .as_frames(Tempo::from(120), self.oscillator.sample_rate());
-
-        let mut last_value = BipolarNormal::default();
-        if frames != self.e.last_frame {
-            let tick_count = if frames >= self.e.last_frame {
-                // normal case; oscillator should advance the calculated number
-                // of frames
-                //
-                // TODO: this is unlikely to be frame-accurate, because
-                // Orchestrator is currently going from frames -> beats
-                // (inaccurate), and then we're going from beats -> frames. We
-                // could include frame count in update_time(), as discussed in
-                // #132, which would mean we don't have to be smart at all about
-                // it.
-                frames - self.e.last_frame
-            } else {
-                self.e.last_frame = frames;
-                0
-            };
-            self.e.last_frame += tick_count;
+        let value = self.value_at(self.e.time_range.start());
+        control_events_fn(WorkEvent::Control(value.into()));
+    }
 
-            self.e.osc_buffer.resize(tick_count);
-            self.oscillator.generate(self.e.osc_buffer.buffer_mut());
-            if tick_count != 0 {
-                last_value = *self.e.osc_buffer.buffer().last().unwrap();
-            }
+    fn work_timed(&mut self, control_events_fn: &mut TimedControlEventsFn) {
+        // Values are issued on a fixed grid of musical time, rather than once
+        // at the start of the time range, so that the signal is the same no
+        // matter how the project slices its buffers.
+        let interval = Self::CONTROL_INTERVAL.total_units();
+        let end = self.e.time_range.end().total_units();
+        let mut units = self.e.time_range.start().total_units().div_ceil(interval) * interval;
+        while units < end {
+            let when = MusicalTime::new_with_units(units);
+            let value = self.value_at(when);
+            control_events_fn(when, WorkEvent::Control(value.into()));
+            units += interval;
         }
-        control_events_fn(WorkEvent::Control(last_value.into()));
     }
 
     fn is_finished(&self) -> bool {
@@ -103,9 +88,36 @@ impl Controls for LfoControllerCore {
 }
 impl HandlesMidi for LfoControllerCore {}
 impl LfoControllerCore {
+    /// How often the LFO issues a new value during playback. At 120 BPM, this
+    /// is a little under 2 milliseconds.
+    pub const CONTROL_INTERVAL: MusicalTime =
+        MusicalTime::new_with_units(MusicalTime::UNITS_IN_PART / 16);
+
     /// Informs the controller that something changed.
     pub fn notify_change_oscillator(&mut self) {}
 
+    // Runs the oscillator up to the given time, and returns its value there.
+    fn value_at(&mut self, time: MusicalTime) -> BipolarNormal {
+        // TODO: this is unlikely to be frame-accurate, because Orchestrator is
+        // currently going from frames -> beats (inaccurate), and then we're
+        // going from beats -> frames. We could include frame count in
+        // update_time(), as discussed in #132, which would mean we don't have
+        // to be smart at all about it.
+        let frames = time.as_frames(Tempo::from(120), self.oscillator.sample_rate());
+        if frames < self.e.last_frame {
+            // We jumped backward, so pick up from here.
+            self.e.last_frame = frames;
+        }
+        let tick_count = frames - self.e.last_frame;
+        if tick_count != 0 {
+            self.e.last_frame = frames;
+            self.e.osc_buffer.resize(tick_count);
+            self.oscillator.generate(self.e.osc_buffer.buffer_mut());
+            self.e.last_value = *self.e.osc_buffer.buffer().last().unwrap();
+        }
+        self.e.last_value
+    }
+
     // /// Returns the ???????
     // pub const fn frequency_range() -> core::ops::RangeInclusive<ParameterType> {
     //     0.0..=100.0
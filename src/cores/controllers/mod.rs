@@ -4,12 +4,14 @@
 //! A good example is an arpeggiator, which produces MIDI messages.
 
 pub use arpeggiator::{ArpeggiatorCore, ArpeggiatorCoreBuilder, ArpeggioMode};
+pub use envelope_follower::{EnvelopeFollowerCore, EnvelopeFollowerCoreBuilder};
 pub use lfo::{LfoControllerCore, LfoControllerCoreBuilder};
 pub use passthrough::{SignalPassthroughControllerCore, SignalPassthroughControllerCoreBuilder};
 pub use timer::{TimerCore, TimerCoreBuilder};
 pub use trigger::{TriggerCore, TriggerCoreBuilder};
 
 mod arpeggiator;
+mod envelope_follower;
 mod lfo;
 mod passthrough;
 mod timer;
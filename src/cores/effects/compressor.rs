@@ -2,14 +2,51 @@
 
 use crate::prelude::*;
 use delegate::delegate;
+use derivative::Derivative;
 use derive_builder::Builder;
 use ensnare_proc_macros::Control;
 use serde::{Deserialize, Serialize};
+use strum_macros::EnumIter;
 
-/// A compressor effect.
-#[derive(Debug, Default, Builder, Control, Serialize, Deserialize)]
-#[builder(default)]
+/// How a [CompressorCore] measures the level of the signal that drives it.
+#[derive(
+    Clone, Copy, Debug, Default, EnumIter, PartialEq, Serialize, Deserialize, strum_macros::Display,
+)]
 #[serde(rename_all = "kebab-case")]
+pub enum CompressorDetection {
+    /// Follows the signal's peaks, which catches fast transients.
+    #[default]
+    Peak,
+    /// Follows the signal's average power over a short window, which is closer
+    /// to how loud it sounds.
+    Rms,
+}
+impl From<ControlValue> for CompressorDetection {
+    fn from(value: ControlValue) -> Self {
+        if value.0 < 0.5 {
+            CompressorDetection::Peak
+        } else {
+            CompressorDetection::Rms
+        }
+    }
+}
+impl From<CompressorDetection> for ControlValue {
+    fn from(value: CompressorDetection) -> Self {
+        match value {
+            CompressorDetection::Peak => ControlValue(0.0),
+            CompressorDetection::Rms => ControlValue(1.0),
+        }
+    }
+}
+
+/// A dynamic range compressor. It follows the level of its input, or of a
+/// sidechain signal if one was provided with
+/// [TransformsAudio::set_sidechain()], and turns down the input by an amount
+/// that depends on how far that level is above the threshold.
+#[derive(Debug, Derivative, Builder, Control, Serialize, Deserialize)]
+#[derivative(Default)]
+#[builder(default, build_fn(private, name = "build_from_builder"))]
+#[serde(default, rename_all = "kebab-case")]
 pub struct CompressorCore {
     /// The level above which compression takes effect. Range is 0.0..=1.0, 0.0
     /// corresponds to quietest, and 1.0 corresponds to 0dB.
@@ -26,48 +63,225 @@ pub struct CompressorCore {
     ratio: Ratio,
 
     /// How soon the compressor activates after the level exceeds the threshold.
-    /// Expressed as a [Normal] that is scaled to an amount of time.
+    /// Expressed as a [Normal] that is scaled to an amount of time, with 1.0
+    /// being [CompressorCore::MAX_ATTACK].
     #[control]
+    #[derivative(Default(value = "Normal::from(0.05)"))]
     attack: Normal,
 
     /// How soon the compressor deactivates after the level drops below the
-    /// Expressed as a [Normal] that is scaled to an amount of time.
+    /// threshold. Expressed as a [Normal] that is scaled to an amount of time,
+    /// with 1.0 being [CompressorCore::MAX_RELEASE].
     #[control]
+    #[derivative(Default(value = "Normal::from(0.1)"))]
     release: Normal,
 
+    /// How gradually compression begins as the level nears the threshold. 0.0
+    /// is a hard knee, where compression starts abruptly at the threshold, and
+    /// 1.0 spreads the transition across [CompressorCore::MAX_KNEE_DB] centered
+    /// on the threshold.
+    #[control]
+    #[derivative(Default(value = "Normal::zero()"))]
+    knee: Normal,
+
+    /// How much to boost the output after compression, to make up for the
+    /// level that compression took away. 0.0 is no boost, and 1.0 is
+    /// [CompressorCore::MAX_MAKEUP_DB].
+    #[control]
+    #[derivative(Default(value = "Normal::zero()"))]
+    makeup: Normal,
+
+    /// How the level is measured.
+    #[control(leaf = true)]
+    detection: CompressorDetection,
+
+    /// If true, then both channels are turned down by the same amount, which
+    /// is decided by the louder channel, so that the stereo image doesn't
+    /// shift. If false, then each channel is compressed on its own.
+    #[control]
+    #[derivative(Default(value = "true"))]
+    stereo_link: bool,
+
     #[serde(skip)]
     #[builder(setter(skip))]
+    e: CompressorCoreEphemerals,
+}
+#[derive(Debug, Clone, Default)]
+struct CompressorCoreEphemerals {
+    // The detected level of each channel, as a linear amplitude.
+    envelopes: [f64; 2],
+
+    // The running average of each channel's squared signal, for RMS detection.
+    mean_squares: [f64; 2],
+
+    // Per-sample smoothing coefficients, which depend on the sample rate.
+    attack_coefficient: f64,
+    release_coefficient: f64,
+    rms_coefficient: f64,
+
+    // If not empty, then this audio drives level detection during the next
+    // transform() instead of the input.
+    sidechain: Vec<StereoSample>,
+
     c: Configurables,
 }
-impl Serializable for CompressorCore {}
+impl CompressorCoreBuilder {
+    /// The overridden Builder build() method.
+    pub fn build(&self) -> Result<CompressorCore, CompressorCoreBuilderError> {
+        match self.build_from_builder() {
+            Ok(mut s) => {
+                s.after_deser();
+                Ok(s)
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+impl Serializable for CompressorCore {
+    fn after_deser(&mut self) {
+        self.update_coefficients();
+    }
+}
 impl TransformsAudio for CompressorCore {
-    fn transform_channel(&mut self, _channel: usize, input_sample: Sample) -> Sample {
-        let input_sample_positive = input_sample.0.abs();
-        let threshold = self.threshold.0;
-        if input_sample_positive > threshold {
-            // TODO: this expression is (a + b - a) * c * d, which is just b * c
-            // * d, which is clearly wrong. Fix it. (Too tired right now to look
-            //   into how compression should work)
-            Sample::from(
-                (threshold + (input_sample_positive - threshold) * self.ratio)
-                    * input_sample.0.signum(),
-            )
-        } else {
-            input_sample
+    fn transform(&mut self, samples: &mut [StereoSample]) {
+        let sidechain = core::mem::take(&mut self.e.sidechain);
+        for (i, sample) in samples.iter_mut().enumerate() {
+            let key = sidechain.get(i).copied().unwrap_or(*sample);
+            let levels = [self.detect(0, key.0), self.detect(1, key.1)];
+            let gains = if self.stereo_link {
+                let gain = self.gain_for_level(levels[0].max(levels[1]));
+                [gain, gain]
+            } else {
+                [
+                    self.gain_for_level(levels[0]),
+                    self.gain_for_level(levels[1]),
+                ]
+            };
+            *sample = StereoSample(sample.0 * gains[0], sample.1 * gains[1]);
         }
+
+        // Keep the allocation for the next batch.
+        self.e.sidechain = sidechain;
+        self.e.sidechain.clear();
+    }
+
+    fn transform_channel(&mut self, channel: usize, input_sample: Sample) -> Sample {
+        let level = self.detect(channel, input_sample);
+        input_sample * self.gain_for_level(level)
+    }
+
+    fn set_sidechain(&mut self, samples: &[StereoSample]) {
+        self.e.sidechain.clear();
+        self.e.sidechain.extend_from_slice(samples);
     }
 }
 impl Configurable for CompressorCore {
     delegate! {
-        to self.c {
+        to self.e.c {
             fn sample_rate(&self) -> SampleRate;
-            fn update_sample_rate(&mut self, sample_rate: SampleRate);
             fn tempo(&self) -> Tempo;
             fn update_tempo(&mut self, tempo: Tempo);
             fn time_signature(&self) -> TimeSignature;
             fn update_time_signature(&mut self, time_signature: TimeSignature);
         }
     }
+
+    fn update_sample_rate(&mut self, sample_rate: SampleRate) {
+        self.e.c.update_sample_rate(sample_rate);
+        self.update_coefficients();
+    }
+}
+impl CompressorCore {
+    /// The attack time when [CompressorCore::attack()] is 1.0.
+    pub const MAX_ATTACK: Seconds = Seconds(0.2);
+
+    /// The release time when [CompressorCore::release()] is 1.0.
+    pub const MAX_RELEASE: Seconds = Seconds(2.0);
+
+    /// The width of the knee when [CompressorCore::knee()] is 1.0.
+    pub const MAX_KNEE_DB: f64 = 24.0;
+
+    /// The boost when [CompressorCore::makeup()] is 1.0.
+    pub const MAX_MAKEUP_DB: f64 = 24.0;
+
+    // The window over which RMS detection averages.
+    const RMS_WINDOW: Seconds = Seconds(0.01);
+
+    // Levels below this are treated as this, to keep the decibel math finite.
+    const MIN_DB: f64 = -100.0;
+
+    fn update_coefficients(&mut self) {
+        let sample_rate = f64::from(self.e.c.sample_rate());
+        self.e.attack_coefficient =
+            Self::smoothing_coefficient(self.attack.0 * Self::MAX_ATTACK.0, sample_rate);
+        self.e.release_coefficient =
+            Self::smoothing_coefficient(self.release.0 * Self::MAX_RELEASE.0, sample_rate);
+        self.e.rms_coefficient = Self::smoothing_coefficient(Self::RMS_WINDOW.0, sample_rate);
+    }
+
+    // The coefficient of a one-pole smoother that takes about the given number
+    // of seconds to settle. Zero seconds means no smoothing at all.
+    fn smoothing_coefficient(seconds: f64, sample_rate: f64) -> f64 {
+        if seconds > 0.0 {
+            (-1.0 / (seconds * sample_rate)).exp()
+        } else {
+            0.0
+        }
+    }
+
+    fn amplitude_to_db(amplitude: f64) -> f64 {
+        if amplitude > 0.0 {
+            (20.0 * amplitude.log10()).max(Self::MIN_DB)
+        } else {
+            Self::MIN_DB
+        }
+    }
+
+    fn db_to_amplitude(db: f64) -> f64 {
+        10.0f64.powf(db / 20.0)
+    }
+
+    // Feeds one sample of the signal into the given channel's level detector,
+    // and returns the detected level.
+    fn detect(&mut self, channel: usize, sample: Sample) -> f64 {
+        let level = match self.detection {
+            CompressorDetection::Peak => sample.0.abs(),
+            CompressorDetection::Rms => {
+                let square = sample.0 * sample.0;
+                let mean_square = &mut self.e.mean_squares[channel];
+                *mean_square = square + self.e.rms_coefficient * (*mean_square - square);
+                mean_square.sqrt()
+            }
+        };
+        let envelope = &mut self.e.envelopes[channel];
+        let coefficient = if level > *envelope {
+            self.e.attack_coefficient
+        } else {
+            self.e.release_coefficient
+        };
+        *envelope = level + coefficient * (*envelope - level);
+        *envelope
+    }
+
+    // The amount, as a linear factor, by which to scale the signal when its
+    // detected level is the given amplitude.
+    fn gain_for_level(&self, level: f64) -> f64 {
+        let level_db = Self::amplitude_to_db(level);
+        let threshold_db = Self::amplitude_to_db(self.threshold.0);
+        let knee_db = self.knee.0 * Self::MAX_KNEE_DB;
+        let overshoot = level_db - threshold_db;
+        let compressed_db = if knee_db > 0.0 && 2.0 * overshoot.abs() <= knee_db {
+            // Inside the knee, the slope changes gradually from 1:1 to the
+            // ratio.
+            let distance = overshoot + knee_db / 2.0;
+            level_db + (self.ratio.0 - 1.0) * distance * distance / (2.0 * knee_db)
+        } else if overshoot > 0.0 {
+            threshold_db + overshoot * self.ratio.0
+        } else {
+            level_db
+        };
+        Self::db_to_amplitude(compressed_db - level_db + self.makeup.0 * Self::MAX_MAKEUP_DB)
+    }
 }
 #[allow(missing_docs)]
 impl CompressorCore {
@@ -93,6 +307,7 @@ impl CompressorCore {
 
     pub fn set_attack(&mut self, attack: Normal) {
         self.attack = attack;
+        self.update_coefficients();
     }
 
     pub fn release(&self) -> Normal {
@@ -101,12 +316,46 @@ impl CompressorCore {
 
     pub fn set_release(&mut self, release: Normal) {
         self.release = release;
+        self.update_coefficients();
+    }
+
+    pub fn knee(&self) -> Normal {
+        self.knee
+    }
+
+    pub fn set_knee(&mut self, knee: Normal) {
+        self.knee = knee;
+    }
+
+    pub fn makeup(&self) -> Normal {
+        self.makeup
+    }
+
+    pub fn set_makeup(&mut self, makeup: Normal) {
+        self.makeup = makeup;
+    }
+
+    pub fn detection(&self) -> CompressorDetection {
+        self.detection
+    }
+
+    pub fn set_detection(&mut self, detection: CompressorDetection) {
+        self.detection = detection;
+    }
+
+    pub fn stereo_link(&self) -> bool {
+        self.stereo_link
+    }
+
+    pub fn set_stereo_link(&mut self, stereo_link: bool) {
+        self.stereo_link = stereo_link;
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use float_cmp::approx_eq;
 
     #[test]
     fn basic_compressor() {
@@ -118,9 +367,37 @@ mod tests {
             .release(0.0.into())
             .build()
             .unwrap();
+
+        // At 2:1, the output is halfway between the input and the threshold in
+        // decibels, which is their geometric mean in amplitude.
+        assert!(approx_eq!(
+            f64,
+            fx.transform_channel(0, Sample::from(0.35)).0,
+            (0.35 * THRESHOLD).sqrt(),
+            epsilon = 0.000001
+        ));
         assert_eq!(
-            fx.transform_channel(0, Sample::from(0.35)),
-            Sample::from((0.35 - THRESHOLD) * 0.5 + THRESHOLD)
+            fx.transform_channel(0, Sample::from(0.2)),
+            Sample::from(0.2),
+            "Levels below the threshold should pass through"
+        );
+    }
+
+    #[test]
+    fn compressor_controls_detection_and_stereo_link() {
+        let mut fx = CompressorCoreBuilder::default().build().unwrap();
+
+        fx.control_set_param_by_name("detection", ControlValue(1.0));
+        assert_eq!(fx.detection(), CompressorDetection::Rms);
+        fx.control_set_param_by_name("detection", ControlValue(0.0));
+        assert_eq!(fx.detection(), CompressorDetection::Peak);
+
+        fx.control_set_param_by_name("stereo-link", ControlValue(0.0));
+        assert!(!fx.stereo_link());
+        let index = fx.control_index_for_name("stereo-link").unwrap();
+        assert_eq!(
+            fx.control_get_param_by_index(index),
+            Some(ControlValue(0.0))
         );
     }
 
@@ -133,10 +410,12 @@ mod tests {
             .release(0.0.into())
             .build()
             .unwrap();
-        assert_eq!(
-            fx.transform_channel(0, Sample::from(0.35f32)),
-            Sample::from(0.35f32)
-        );
+        assert!(approx_eq!(
+            f64,
+            fx.transform_channel(0, Sample::from(0.35)).0,
+            0.35,
+            epsilon = 0.000001
+        ));
     }
 
     #[test]
@@ -148,9 +427,102 @@ mod tests {
             .release(0.0.into())
             .build()
             .unwrap();
+        assert!(approx_eq!(
+            f64,
+            fx.transform_channel(0, Sample::from(0.35)).0,
+            0.25,
+            epsilon = 0.000001
+        ));
+    }
+
+    #[test]
+    fn compressor_knee_and_makeup() {
+        let mut fx = CompressorCoreBuilder::default()
+            .threshold(0.25.into())
+            .ratio(0.5.into())
+            .attack(0.0.into())
+            .release(0.0.into())
+            .knee(Normal::maximum())
+            .build()
+            .unwrap();
+        let at_threshold = fx.transform_channel(0, Sample::from(0.25)).0;
+        assert!(
+            at_threshold < 0.25,
+            "A soft knee should start compressing below the threshold"
+        );
+        assert!(
+            at_threshold > (0.25f64 * 0.25).sqrt() * 0.5,
+            "A soft knee should compress gently near the threshold"
+        );
+
+        fx.set_knee(Normal::zero());
+        fx.set_makeup(Normal::from(6.0 / CompressorCore::MAX_MAKEUP_DB));
+        assert!(approx_eq!(
+            f64,
+            fx.transform_channel(0, Sample::from(0.1)).0,
+            0.1 * 10.0f64.powf(6.0 / 20.0),
+            epsilon = 0.000001
+        ));
+    }
+
+    #[test]
+    fn compressor_follows_envelope() {
+        let mut fx = CompressorCoreBuilder::default()
+            .threshold(0.1.into())
+            .ratio(0.0.into())
+            .attack(0.1.into())
+            .release(0.5.into())
+            .detection(CompressorDetection::Rms)
+            .build()
+            .unwrap();
+        fx.update_sample_rate(SampleRate::DEFAULT);
+
+        let mut buffer = [StereoSample::from(0.5); 64];
+        fx.transform(&mut buffer);
+        assert!(
+            buffer[0].0 .0 > 0.4,
+            "The attack should let the start of a loud sound through"
+        );
+
+        let mut buffer = [StereoSample::from(0.5); 4410];
+        fx.transform(&mut buffer);
+        assert!(
+            buffer.last().unwrap().0 .0 < 0.15,
+            "A sustained loud sound should be turned down after the attack"
+        );
+
+        let mut buffer = [StereoSample::from(0.05); 64];
+        fx.transform(&mut buffer);
+        assert!(
+            buffer[0].0 .0 < 0.03,
+            "The release should keep quiet sounds turned down for a while"
+        );
+    }
+
+    #[test]
+    fn compressor_uses_sidechain() {
+        let mut fx = CompressorCoreBuilder::default()
+            .threshold(0.1.into())
+            .ratio(0.0.into())
+            .attack(0.0.into())
+            .release(0.0.into())
+            .build()
+            .unwrap();
+        let mut buffer = [StereoSample::from(0.05); 4];
+
+        fx.set_sidechain(&[StereoSample::from(0.8); 4]);
+        fx.transform(&mut buffer);
+        assert!(
+            buffer.iter().all(|s| s.0 .0 < 0.01 && s.1 .0 < 0.01),
+            "A loud sidechain should duck a quiet input"
+        );
+
+        let mut buffer = [StereoSample::from(0.05); 4];
+        fx.transform(&mut buffer);
         assert_eq!(
-            fx.transform_channel(0, Sample::from(0.35)),
-            Sample::from(0.25)
+            buffer,
+            [StereoSample::from(0.05); 4],
+            "Without a sidechain, the compressor should follow its own input"
         );
     }
 }
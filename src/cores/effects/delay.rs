@@ -1,6 +1,11 @@
 // Copyright (c) 2024 Mike Tsao
 
-use crate::prelude::*;
+use super::filter::{BiQuadFilterLowPass12dbCore, BiQuadFilterLowPass12dbCoreBuilder};
+use crate::{
+    cores::{BiQuadFilterHighPassCore, BiQuadFilterHighPassCoreBuilder},
+    prelude::*,
+    types::NoteDivision,
+};
 use delegate::delegate;
 use derivative::Derivative;
 use derive_builder::Builder;
@@ -208,23 +213,109 @@ impl Configurable for AllPassDelayLine {
     }
 }
 
-/// A delay effect.
+/// A delay effect. Each channel repeats its input after a delay that is either
+/// a fixed number of seconds or a [NoteDivision] that follows the tempo. Some
+/// of each repeat can be fed back to repeat again, passing through low- and
+/// high-cut filters each time so that the repeats grow darker and thinner as
+/// they fade. The delay time can wander slowly, like a tape machine's.
+///
+/// Delay times are limited to [DelayCore::MAX_DELAY]. The buffers are sized for
+/// that limit up front, so changing the delay time or the tempo while playing
+/// doesn't allocate.
 #[derive(Clone, Debug, Builder, Derivative, Control, Deserialize, Serialize)]
 #[derivative(Default)]
-#[serde(rename_all = "kebab-case")]
+#[serde(default, rename_all = "kebab-case")]
 #[builder(default, build_fn(private, name = "build_from_builder"))]
 pub struct DelayCore {
-    /// The number of seconds of delay.
+    /// The number of seconds of delay. If the right channel has its own
+    /// delay time, then this is the left channel's.
     #[control]
     seconds: Seconds,
 
+    /// The number of seconds of delay in the right channel. Used only if
+    /// [DelayCore::separate_times()] is true.
+    #[control]
+    right_seconds: Seconds,
+
+    /// If set, then the delay follows the tempo, and
+    /// [DelayCore::seconds()] is ignored.
+    #[builder(setter(strip_option))]
+    division: Option<NoteDivision>,
+
+    /// If set, and [DelayCore::separate_times()] is true, then the right
+    /// channel's delay follows the tempo, and [DelayCore::right_seconds()] is
+    /// ignored.
+    #[builder(setter(strip_option))]
+    right_division: Option<NoteDivision>,
+
+    /// If true, then the right channel has its own delay time. Otherwise, both
+    /// channels use the left channel's.
+    #[control]
+    separate_times: bool,
+
     /// How fast the signal decays.
     #[control]
     decay: Normal,
 
+    /// How much of each repeat is fed back into the delay to repeat again. 0.0
+    /// means a single repeat.
+    #[control]
+    #[derivative(Default(value = "Normal::zero()"))]
+    feedback: Normal,
+
+    /// If true, then the repeats bounce between the channels. The input is
+    /// mixed to mono and enters on the left, and each repeat crosses to the
+    /// other side.
+    #[control]
+    ping_pong: bool,
+
+    /// Frequencies below this are removed from the repeats each time they are
+    /// fed back.
+    #[control]
+    #[derivative(Default(value = "20.0.into()"))]
+    low_cut: FrequencyHz,
+
+    /// Frequencies above this are removed from the repeats each time they are
+    /// fed back.
+    #[control]
+    #[derivative(Default(value = "20000.0.into()"))]
+    high_cut: FrequencyHz,
+
+    /// How far the delay time wanders. Expressed as a [Normal] that is scaled
+    /// to an amount of time, with 1.0 being [DelayCore::MAX_MODULATION].
+    #[control]
+    #[derivative(Default(value = "Normal::zero()"))]
+    modulation_depth: Normal,
+
+    /// How often the delay time wanders back and forth.
+    #[control]
+    #[derivative(Default(value = "0.5.into()"))]
+    modulation_rate: FrequencyHz,
+
     #[serde(skip)]
     #[builder(setter(skip))]
-    delay: DelayLine,
+    e: DelayCoreEphemerals,
+}
+#[derive(Clone, Debug, Default)]
+struct DelayCoreEphemerals {
+    // One ring buffer per channel, long enough for the longest delay that
+    // modulation can reach. Empty means that they haven't been allocated yet.
+    buffers: [Vec<Sample>; 2],
+    write_indexes: [usize; 2],
+
+    // Each channel's unmodulated delay, in frames. Can be fractional. Zero
+    // means that the channel passes through.
+    delay_frames: [f64; 2],
+
+    // Each channel's position in the modulation cycle, 0.0..1.0. The channels
+    // are offset from each other, which widens the stereo image.
+    modulation_phases: [f64; 2],
+
+    // The filters in the feedback loop.
+    low_cut: BiQuadFilterHighPassCore,
+    high_cut: BiQuadFilterLowPass12dbCore,
+
+    c: Configurables,
 }
 impl DelayCoreBuilder {
     /// The overridden Builder build() method.
@@ -240,51 +331,311 @@ impl DelayCoreBuilder {
 }
 impl Serializable for DelayCore {
     fn after_deser(&mut self) {
-        self.delay = DelayLine::new_with(self.seconds, self.decay)
+        self.e.low_cut = BiQuadFilterHighPassCoreBuilder::default()
+            .q(Self::FILTER_Q)
+            .build()
+            .unwrap();
+        self.e.high_cut = BiQuadFilterLowPass12dbCoreBuilder::default()
+            .q(Self::FILTER_Q)
+            .build()
+            .unwrap();
+        self.e.modulation_phases = [0.0, 0.25];
+        self.update_filters();
+        self.allocate_buffers();
+        self.update_delay_times();
     }
 }
 impl Configurable for DelayCore {
     delegate! {
-        to self.delay {
+        to self.e.c {
             fn sample_rate(&self) -> SampleRate;
-            fn update_sample_rate(&mut self, sample_rate: SampleRate);
             fn tempo(&self) -> Tempo;
-            fn update_tempo(&mut self, tempo: Tempo);
             fn time_signature(&self) -> TimeSignature;
-            fn update_time_signature(&mut self, time_signature: TimeSignature);
         }
     }
+
+    fn update_sample_rate(&mut self, sample_rate: SampleRate) {
+        self.e.c.update_sample_rate(sample_rate);
+        self.update_filters();
+        self.allocate_buffers();
+        self.update_delay_times();
+    }
+
+    fn update_tempo(&mut self, tempo: Tempo) {
+        self.e.c.update_tempo(tempo);
+        self.update_delay_times();
+    }
+
+    fn update_time_signature(&mut self, time_signature: TimeSignature) {
+        self.e.c.update_time_signature(time_signature);
+        self.update_delay_times();
+    }
 }
 impl TransformsAudio for DelayCore {
-    fn transform_channel(&mut self, _channel: usize, input_sample: Sample) -> Sample {
-        self.delay.pop_output(input_sample)
+    fn transform(&mut self, samples: &mut [StereoSample]) {
+        if !self.ping_pong || self.passes_through(0) || self.passes_through(1) {
+            for sample in samples {
+                *sample = StereoSample(
+                    self.transform_channel(0, sample.0),
+                    self.transform_channel(1, sample.1),
+                );
+            }
+            return;
+        }
+
+        // Ping-pong needs both channels at once, because each channel feeds
+        // the other.
+        for sample in samples {
+            let delayed = [self.read(0), self.read(1)];
+            let feedback = [
+                self.feedback_sample(0, delayed[0]),
+                self.feedback_sample(1, delayed[1]),
+            ];
+            self.write(0, (sample.0 + sample.1) * 0.5 + feedback[1]);
+            self.write(1, feedback[0]);
+            *sample = StereoSample(delayed[0] * self.decay, delayed[1] * self.decay);
+        }
+    }
+
+    fn transform_channel(&mut self, channel: usize, input_sample: Sample) -> Sample {
+        if self.passes_through(channel) {
+            return input_sample;
+        }
+        let delayed = self.read(channel);
+        let feedback = self.feedback_sample(channel, delayed);
+        self.write(channel, input_sample + feedback);
+        delayed * self.decay
     }
 }
 impl DelayCore {
-    #[allow(missing_docs)]
+    /// How far the delay time wanders when [DelayCore::modulation_depth()] is
+    /// 1.0.
+    pub const MAX_MODULATION: Seconds = Seconds(0.005);
+
+    /// The longest delay time. Longer ones, including note divisions at very
+    /// slow tempos, are shortened to this. It allows a dotted whole note down
+    /// to 72 BPM.
+    pub const MAX_DELAY: Seconds = Seconds(5.0);
+
+    // Butterworth response, so that the feedback filters don't ring.
+    const FILTER_Q: ParameterType = core::f64::consts::FRAC_1_SQRT_2;
+
+    fn update_filters(&mut self) {
+        let sample_rate = self.e.c.sample_rate();
+        self.e.low_cut.update_sample_rate(sample_rate);
+        self.e.high_cut.update_sample_rate(sample_rate);
+
+        // A cutoff at or above the Nyquist frequency would make the filters
+        // unstable.
+        let highest_cutoff = f64::from(sample_rate) * 0.45;
+        self.e
+            .low_cut
+            .set_cutoff(self.low_cut.0.min(highest_cutoff).into());
+        self.e
+            .high_cut
+            .set_cutoff(self.high_cut.0.min(highest_cutoff).into());
+    }
+
+    fn update_delay_times(&mut self) {
+        let tempo = self.e.c.tempo();
+        let time_signature = self.e.c.time_signature();
+        let left = self
+            .division
+            .map_or(self.seconds, |d| d.to_seconds(tempo, time_signature));
+        let right = if self.separate_times {
+            self.right_division
+                .map_or(self.right_seconds, |d| d.to_seconds(tempo, time_signature))
+        } else {
+            left
+        };
+
+        // The buffers already have room for the longest delay, so a new delay
+        // time only moves where they're read.
+        let sample_rate = f64::from(self.e.c.sample_rate());
+        for (channel, seconds) in [left, right].into_iter().enumerate() {
+            self.e.delay_frames[channel] =
+                (seconds.0 * sample_rate).clamp(0.0, Self::MAX_DELAY.0 * sample_rate);
+        }
+    }
+
+    // Sizes the buffers for the current sample rate. This is the only place
+    // that allocates them.
+    fn allocate_buffers(&mut self) {
+        let len = ((Self::MAX_DELAY.0 + Self::MAX_MODULATION.0) * f64::from(self.e.c.sample_rate()))
+            .ceil() as usize
+            + 2;
+        for (buffer, write_index) in self
+            .e
+            .buffers
+            .iter_mut()
+            .zip(self.e.write_indexes.iter_mut())
+        {
+            if buffer.len() != len {
+                *buffer = vec![Sample::SILENCE; len];
+                *write_index = 0;
+            }
+        }
+    }
+
+    fn passes_through(&self, channel: usize) -> bool {
+        self.e.delay_frames[channel] == 0.0 || self.e.buffers[channel].is_empty()
+    }
+
+    // Returns the sample that was written the current delay time ago,
+    // interpolating between samples if the delay isn't a whole number of
+    // frames.
+    fn read(&self, channel: usize) -> Sample {
+        let buffer = &self.e.buffers[channel];
+        let len = buffer.len();
+        let modulation = self.modulation_depth.0
+            * Self::MAX_MODULATION.0
+            * f64::from(self.e.c.sample_rate())
+            * 0.5
+            * (1.0 - (self.e.modulation_phases[channel] * 2.0 * core::f64::consts::PI).cos());
+        let delay = (self.e.delay_frames[channel] + modulation).max(1.0);
+        let position = (self.e.write_indexes[channel] + len) as f64 - delay;
+        let index = position.floor();
+        let fraction = (position - index) as SampleType;
+        let index = index as usize;
+        let a = buffer[index % len].0;
+        let b = buffer[(index + 1) % len].0;
+        Sample(a + (b - a) * fraction)
+    }
+
+    fn write(&mut self, channel: usize, sample: Sample) {
+        let len = self.e.buffers[channel].len();
+        let write_index = &mut self.e.write_indexes[channel];
+        self.e.buffers[channel][*write_index] = sample;
+        *write_index = (*write_index + 1) % len;
+
+        let phase = &mut self.e.modulation_phases[channel];
+        *phase = (*phase + self.modulation_rate.0 / f64::from(self.e.c.sample_rate())).fract();
+    }
+
+    // The part of a repeat that goes back into the delay.
+    fn feedback_sample(&mut self, channel: usize, delayed: Sample) -> Sample {
+        if self.feedback.0 == 0.0 {
+            return Sample::SILENCE;
+        }
+        let filtered = self
+            .e
+            .high_cut
+            .transform_channel(channel, self.e.low_cut.transform_channel(channel, delayed));
+        filtered * self.feedback
+    }
+}
+#[allow(missing_docs)]
+impl DelayCore {
     pub fn seconds(&self) -> Seconds {
-        self.delay.delay()
+        self.seconds
     }
 
-    #[allow(missing_docs)]
     pub fn set_seconds(&mut self, seconds: Seconds) {
-        self.delay.set_delay(seconds);
+        self.seconds = seconds;
+        self.update_delay_times();
+    }
+
+    pub fn right_seconds(&self) -> Seconds {
+        self.right_seconds
+    }
+
+    pub fn set_right_seconds(&mut self, right_seconds: Seconds) {
+        self.right_seconds = right_seconds;
+        self.update_delay_times();
+    }
+
+    pub fn division(&self) -> Option<NoteDivision> {
+        self.division
+    }
+
+    pub fn set_division(&mut self, division: Option<NoteDivision>) {
+        self.division = division;
+        self.update_delay_times();
+    }
+
+    pub fn right_division(&self) -> Option<NoteDivision> {
+        self.right_division
+    }
+
+    pub fn set_right_division(&mut self, right_division: Option<NoteDivision>) {
+        self.right_division = right_division;
+        self.update_delay_times();
+    }
+
+    pub fn separate_times(&self) -> bool {
+        self.separate_times
+    }
+
+    pub fn set_separate_times(&mut self, separate_times: bool) {
+        self.separate_times = separate_times;
+        self.update_delay_times();
     }
 
-    #[allow(missing_docs)]
     pub fn decay(&self) -> Normal {
         self.decay
     }
 
-    #[allow(missing_docs)]
     pub fn set_decay(&mut self, decay: Normal) {
-        self.delay.set_decay(decay);
+        self.decay = decay;
+    }
+
+    pub fn feedback(&self) -> Normal {
+        self.feedback
+    }
+
+    pub fn set_feedback(&mut self, feedback: Normal) {
+        self.feedback = feedback;
+    }
+
+    pub fn ping_pong(&self) -> bool {
+        self.ping_pong
+    }
+
+    pub fn set_ping_pong(&mut self, ping_pong: bool) {
+        self.ping_pong = ping_pong;
+    }
+
+    pub fn low_cut(&self) -> FrequencyHz {
+        self.low_cut
+    }
+
+    pub fn set_low_cut(&mut self, low_cut: FrequencyHz) {
+        self.low_cut = low_cut;
+        self.update_filters();
+    }
+
+    pub fn high_cut(&self) -> FrequencyHz {
+        self.high_cut
+    }
+
+    pub fn set_high_cut(&mut self, high_cut: FrequencyHz) {
+        self.high_cut = high_cut;
+        self.update_filters();
+    }
+
+    pub fn modulation_depth(&self) -> Normal {
+        self.modulation_depth
+    }
+
+    pub fn set_modulation_depth(&mut self, modulation_depth: Normal) {
+        self.modulation_depth = modulation_depth;
+    }
+
+    pub fn modulation_rate(&self) -> FrequencyHz {
+        self.modulation_rate
+    }
+
+    pub fn set_modulation_rate(&mut self, modulation_rate: FrequencyHz) {
+        self.modulation_rate = modulation_rate;
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::util::Rng;
+    use crate::{
+        types::{BeatValue, BeatValueModifier},
+        util::Rng,
+    };
 
     use super::*;
     use float_cmp::approx_eq;
@@ -344,6 +695,150 @@ mod tests {
         }
     }
 
+    // Returns the index of each frame whose output isn't silent.
+    fn echoes(fx: &mut DelayCore, channel: usize, impulse_at: &[usize], len: usize) -> Vec<usize> {
+        (0..len)
+            .filter(|i| {
+                let input = if impulse_at.contains(i) {
+                    Sample::MAX
+                } else {
+                    Sample::SILENCE
+                };
+                fx.transform_channel(channel, input) != Sample::SILENCE
+            })
+            .collect()
+    }
+
+    #[test]
+    fn delay_follows_tempo() {
+        let mut fx = DelayCoreBuilder::default()
+            .division(NoteDivision::new_with(
+                BeatValue::Eighth,
+                BeatValueModifier::Dotted,
+            ))
+            .build()
+            .unwrap();
+        fx.update_sample_rate(SampleRate::new(1000));
+        fx.update_tempo(Tempo(120.0));
+
+        // A dotted eighth at 120 BPM is 0.375 seconds.
+        assert_eq!(echoes(&mut fx, 0, &[0], 1000), vec![375]);
+
+        // Halving the tempo doubles the delay.
+        fx.update_tempo(Tempo(60.0));
+        assert_eq!(echoes(&mut fx, 0, &[0], 1000), vec![750]);
+
+        // A triplet fits three in the space of two.
+        assert!(approx_eq!(
+            f64,
+            NoteDivision::new_with(BeatValue::Quarter, BeatValueModifier::Triplet)
+                .to_seconds(Tempo(60.0), TimeSignature::default())
+                .0,
+            2.0 / 3.0,
+            epsilon = 0.000001
+        ));
+    }
+
+    #[test]
+    fn delay_time_changes_dont_allocate() {
+        let mut fx = DelayCoreBuilder::default()
+            .division(NoteDivision::new_with(
+                BeatValue::Quarter,
+                BeatValueModifier::Straight,
+            ))
+            .build()
+            .unwrap();
+        fx.update_sample_rate(SampleRate::new(1000));
+        fx.update_tempo(Tempo(120.0));
+        let buffer_ptr = fx.e.buffers[0].as_ptr();
+
+        // A very slow tempo asks for more than the longest delay.
+        fx.update_tempo(Tempo(1.0));
+        fx.set_seconds(4.0.into());
+        assert_eq!(
+            fx.e.buffers[0].as_ptr(),
+            buffer_ptr,
+            "Changing the delay time shouldn't reallocate the buffer"
+        );
+        assert_eq!(echoes(&mut fx, 0, &[0], 6000), vec![5000]);
+    }
+
+    #[test]
+    fn delay_separate_times() {
+        let mut fx = DelayCoreBuilder::default()
+            .seconds(0.25.into())
+            .right_seconds(0.5.into())
+            .separate_times(true)
+            .build()
+            .unwrap();
+        fx.update_sample_rate(SampleRate::new(1000));
+        assert_eq!(echoes(&mut fx, 0, &[0], 1000), vec![250]);
+        assert_eq!(echoes(&mut fx, 1, &[0], 1000), vec![500]);
+
+        fx.set_separate_times(false);
+        assert_eq!(echoes(&mut fx, 1, &[0], 1000), vec![250]);
+    }
+
+    #[test]
+    fn ping_pong_delay() {
+        let mut fx = DelayCoreBuilder::default()
+            .seconds(0.25.into())
+            .feedback(0.5.into())
+            .ping_pong(true)
+            .build()
+            .unwrap();
+        fx.update_sample_rate(SampleRate::new(1000));
+
+        let mut buffer = vec![StereoSample::SILENCE; 1000];
+        buffer[0] = StereoSample::from(0.5);
+        fx.transform(&mut buffer);
+
+        // The first repeat is on the left, the next is on the right, and so
+        // on.
+        assert_eq!(
+            buffer[250],
+            StereoSample(Sample::from(0.5), Sample::SILENCE)
+        );
+        assert!(buffer[..500].iter().all(|s| s.1 == Sample::SILENCE));
+        assert!(buffer[251..750].iter().all(|s| s.0 == Sample::SILENCE));
+        assert_ne!(buffer[500].1, Sample::SILENCE);
+        assert_ne!(buffer[750].0, Sample::SILENCE);
+        assert_lt!(
+            buffer[750].0 .0.abs(),
+            buffer[500].1 .0.abs(),
+            "Each repeat should be quieter than the last"
+        );
+    }
+
+    #[test]
+    fn delay_modulation() {
+        let mut fx = DelayCoreBuilder::default()
+            .seconds(0.25.into())
+            .modulation_depth(Normal::maximum())
+            .modulation_rate(2.0.into())
+            .build()
+            .unwrap();
+        fx.update_sample_rate(SampleRate::new(1000));
+
+        // Send an impulse every 100 frames, and note when each comes back.
+        let impulses: Vec<usize> = (0..20).map(|i| i * 100).collect();
+        let returns = echoes(&mut fx, 0, &impulses, 2250);
+        let delays: Vec<usize> = impulses
+            .iter()
+            .filter_map(|i| returns.iter().find(|e| **e >= *i + 250).map(|e| e - i))
+            .collect();
+        assert_eq!(delays.len(), impulses.len());
+        assert!(
+            delays.iter().all(|d| (250..=256).contains(d)),
+            "The delay should stay within the modulation depth: {delays:?}"
+        );
+        assert_ne!(
+            delays.iter().min(),
+            delays.iter().max(),
+            "The delay time should wander"
+        );
+    }
+
     #[test]
     fn delay_line() {
         // It's very simple: it should return an input sample, attenuated, after
@@ -0,0 +1,472 @@
+// Copyright (c) 2024 Mike Tsao
+
+use crate::prelude::*;
+use delegate::delegate;
+use derivative::Derivative;
+use derive_builder::Builder;
+use ensnare_proc_macros::Control;
+use serde::{Deserialize, Serialize};
+
+/// A stereo reverb based on Jezar's Freeverb. Each channel has eight parallel
+/// comb filters, each with a low-pass filter in its feedback loop, feeding a
+/// series of four all-pass filters. The right channel's filters are slightly
+/// longer than the left's, which decorrelates the channels. A set of early
+/// reflections, which are the first echoes from the walls of a room, comes
+/// before the dense tail.
+///
+/// Like [ReverbCore](super::ReverbCore), it produces only the reverberated
+/// signal, so it should be used with a wet/dry mix below 100%.
+#[derive(Debug, Derivative, Control, Builder, Serialize, Deserialize)]
+#[derivative(Default)]
+#[serde(default, rename_all = "kebab-case")]
+#[builder(default, build_fn(private, name = "build_from_builder"))]
+pub struct FreeverbCore {
+    /// How long before the reverb begins. Expressed as a [Normal] that is
+    /// scaled to an amount of time, with 1.0 being
+    /// [FreeverbCore::MAX_PRE_DELAY].
+    #[control]
+    #[derivative(Default(value = "Normal::from(0.1)"))]
+    pre_delay: Normal,
+
+    /// How big the room sounds. Larger rooms have longer tails and later
+    /// early reflections.
+    #[control]
+    #[derivative(Default(value = "Normal::from(0.5)"))]
+    room_size: Normal,
+
+    /// How quickly high frequencies fade compared to low ones. 0.0 is a
+    /// bright room, and 1.0 is a dull one.
+    #[control]
+    #[derivative(Default(value = "Normal::from(0.5)"))]
+    damping: Normal,
+
+    /// How wide the tail is. 0.0 is mono, and 1.0 keeps the channels fully
+    /// apart.
+    #[control]
+    width: Normal,
+
+    /// The level of the early reflections.
+    #[control]
+    #[derivative(Default(value = "Normal::from(0.5)"))]
+    early_reflections: Normal,
+
+    #[serde(skip)]
+    #[builder(setter(skip))]
+    e: FreeverbCoreEphemerals,
+}
+#[derive(Debug, Default)]
+struct FreeverbCoreEphemerals {
+    // The mono input, which is tapped for both the pre-delay and the early
+    // reflections.
+    input_line: TappedDelayLine,
+
+    combs: [Vec<DampedCombFilter>; 2],
+    allpasses: [Vec<AllPassFilter>; 2],
+
+    // Values derived from the parameters and the sample rate.
+    pre_delay_frames: usize,
+    early_reflection_taps: [[(usize, f64); 6]; 2],
+    comb_feedback: f64,
+    comb_damping: f64,
+
+    c: Configurables,
+}
+impl FreeverbCoreBuilder {
+    /// The overridden Builder build() method.
+    pub fn build(&self) -> Result<FreeverbCore, FreeverbCoreBuilderError> {
+        match self.build_from_builder() {
+            Ok(mut s) => {
+                s.after_deser();
+                Ok(s)
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+impl Serializable for FreeverbCore {
+    fn after_deser(&mut self) {
+        self.allocate();
+    }
+}
+impl Configurable for FreeverbCore {
+    delegate! {
+        to self.e.c {
+            fn sample_rate(&self) -> SampleRate;
+            fn tempo(&self) -> Tempo;
+            fn update_tempo(&mut self, tempo: Tempo);
+            fn time_signature(&self) -> TimeSignature;
+            fn update_time_signature(&mut self, time_signature: TimeSignature);
+        }
+    }
+
+    fn update_sample_rate(&mut self, sample_rate: SampleRate) {
+        self.e.c.update_sample_rate(sample_rate);
+        self.allocate();
+    }
+}
+impl TransformsAudio for FreeverbCore {
+    // The reverb mixes its input to mono and spreads it back out to stereo, so
+    // it doesn't implement transform_channel().
+    fn transform(&mut self, samples: &mut [StereoSample]) {
+        if self.e.combs[0].is_empty() {
+            self.allocate();
+        }
+        let wet_same = (1.0 + self.width.0) / 2.0 * Self::WET_SCALE;
+        let wet_other = (1.0 - self.width.0) / 2.0 * Self::WET_SCALE;
+        for sample in samples.iter_mut() {
+            self.e.input_line.write((sample.0 + sample.1) * 0.5);
+
+            let mut early = [Sample::SILENCE; 2];
+            for (channel, taps) in self.e.early_reflection_taps.iter().enumerate() {
+                for (frames, gain) in taps {
+                    early[channel] +=
+                        self.e.input_line.tap(self.e.pre_delay_frames + *frames) * *gain;
+                }
+            }
+
+            let late_input = self.e.input_line.tap(self.e.pre_delay_frames) * Self::INPUT_GAIN;
+            let mut late = [Sample::SILENCE; 2];
+            for (channel, output) in late.iter_mut().enumerate() {
+                for comb in self.e.combs[channel].iter_mut() {
+                    *output += comb.process(late_input, self.e.comb_feedback, self.e.comb_damping);
+                }
+                for allpass in self.e.allpasses[channel].iter_mut() {
+                    *output = allpass.process(*output);
+                }
+            }
+
+            *sample = StereoSample(
+                late[0] * wet_same + late[1] * wet_other + early[0] * self.early_reflections,
+                late[1] * wet_same + late[0] * wet_other + early[1] * self.early_reflections,
+            );
+        }
+    }
+}
+impl FreeverbCore {
+    /// The pre-delay when [FreeverbCore::pre_delay()] is 1.0.
+    pub const MAX_PRE_DELAY: Seconds = Seconds(0.2);
+
+    // Freeverb's tunings, in frames at 44.1KHz.
+    const COMB_TUNINGS: [usize; 8] = [1116, 1188, 1277, 1356, 1422, 1491, 1557, 1617];
+    const ALLPASS_TUNINGS: [usize; 4] = [556, 441, 341, 225];
+    const STEREO_SPREAD: usize = 23;
+    const TUNING_SAMPLE_RATE: f64 = 44100.0;
+
+    // Freeverb's gains, which keep the sum of the combs in range.
+    const INPUT_GAIN: f64 = 0.015;
+    const WET_SCALE: f64 = 3.0;
+
+    // Freeverb's mapping from room size to comb feedback, and from damping to
+    // the combs' low-pass coefficient.
+    const ROOM_SCALE: f64 = 0.28;
+    const ROOM_OFFSET: f64 = 0.7;
+    const DAMPING_SCALE: f64 = 0.4;
+
+    // The smallest room stretches the early reflections by this factor, and
+    // the largest by one more than this.
+    const MIN_ROOM_SCALE: f64 = 0.5;
+
+    // The early reflections, as (seconds, gain), for a medium room. Smaller
+    // rooms bring them closer together, and larger ones spread them out.
+    const EARLY_REFLECTIONS: [[(f64, f64); 6]; 2] = [
+        [
+            (0.0079, 0.30),
+            (0.0131, 0.22),
+            (0.0197, 0.17),
+            (0.0263, 0.13),
+            (0.0337, 0.10),
+            (0.0411, 0.08),
+        ],
+        [
+            (0.0089, 0.29),
+            (0.0149, 0.23),
+            (0.0181, 0.16),
+            (0.0283, 0.13),
+            (0.0317, 0.11),
+            (0.0433, 0.08),
+        ],
+    ];
+
+    // Creates the delay lines and filters for the current sample rate, which
+    // also clears the tail.
+    fn allocate(&mut self) {
+        let sample_rate = f64::from(self.e.c.sample_rate());
+        let scale = |frames: usize| {
+            ((frames as f64 * sample_rate / Self::TUNING_SAMPLE_RATE).round() as usize).max(1)
+        };
+        self.e.combs = core::array::from_fn(|channel| {
+            Self::COMB_TUNINGS
+                .iter()
+                .map(|frames| {
+                    DampedCombFilter::new_with(scale(frames + channel * Self::STEREO_SPREAD))
+                })
+                .collect()
+        });
+        self.e.allpasses = core::array::from_fn(|channel| {
+            Self::ALLPASS_TUNINGS
+                .iter()
+                .map(|frames| {
+                    AllPassFilter::new_with(scale(frames + channel * Self::STEREO_SPREAD))
+                })
+                .collect()
+        });
+
+        let longest_early_reflection = Self::EARLY_REFLECTIONS
+            .iter()
+            .flatten()
+            .map(|(seconds, _)| *seconds)
+            .fold(0.0, f64::max)
+            * (Self::MIN_ROOM_SCALE + 1.0);
+        self.e.input_line = TappedDelayLine::new_with(
+            ((Self::MAX_PRE_DELAY.0 + longest_early_reflection) * sample_rate).ceil() as usize + 1,
+        );
+        self.update_pre_delay();
+        self.update_room();
+        self.update_damping();
+    }
+
+    // How much the room size stretches the early reflections.
+    fn room_scale(&self) -> f64 {
+        Self::MIN_ROOM_SCALE + self.room_size.0
+    }
+
+    fn update_pre_delay(&mut self) {
+        self.e.pre_delay_frames =
+            (self.pre_delay.0 * Self::MAX_PRE_DELAY.0 * f64::from(self.e.c.sample_rate())).round()
+                as usize;
+    }
+
+    fn update_room(&mut self) {
+        self.e.comb_feedback = self.room_size.0 * Self::ROOM_SCALE + Self::ROOM_OFFSET;
+        let sample_rate = f64::from(self.e.c.sample_rate());
+        let room_scale = self.room_scale();
+        // This runs whenever the room size changes, so it fills the taps in
+        // place rather than allocating.
+        for (taps, reflections) in self
+            .e
+            .early_reflection_taps
+            .iter_mut()
+            .zip(Self::EARLY_REFLECTIONS.iter())
+        {
+            for (tap, (seconds, gain)) in taps.iter_mut().zip(reflections.iter()) {
+                *tap = ((seconds * room_scale * sample_rate).round() as usize, *gain);
+            }
+        }
+    }
+
+    fn update_damping(&mut self) {
+        self.e.comb_damping = self.damping.0 * Self::DAMPING_SCALE;
+    }
+}
+#[allow(missing_docs)]
+impl FreeverbCore {
+    pub fn pre_delay(&self) -> Normal {
+        self.pre_delay
+    }
+
+    pub fn set_pre_delay(&mut self, pre_delay: Normal) {
+        self.pre_delay = pre_delay;
+        self.update_pre_delay();
+    }
+
+    pub fn room_size(&self) -> Normal {
+        self.room_size
+    }
+
+    pub fn set_room_size(&mut self, room_size: Normal) {
+        self.room_size = room_size;
+        self.update_room();
+    }
+
+    pub fn damping(&self) -> Normal {
+        self.damping
+    }
+
+    pub fn set_damping(&mut self, damping: Normal) {
+        self.damping = damping;
+        self.update_damping();
+    }
+
+    pub fn width(&self) -> Normal {
+        self.width
+    }
+
+    pub fn set_width(&mut self, width: Normal) {
+        self.width = width;
+    }
+
+    pub fn early_reflections(&self) -> Normal {
+        self.early_reflections
+    }
+
+    pub fn set_early_reflections(&mut self, early_reflections: Normal) {
+        self.early_reflections = early_reflections;
+    }
+}
+
+// A delay line that can be read at any point within its length.
+#[derive(Debug, Default)]
+struct TappedDelayLine {
+    buffer: Vec<Sample>,
+    write_index: usize,
+}
+impl TappedDelayLine {
+    fn new_with(len: usize) -> Self {
+        Self {
+            buffer: vec![Sample::SILENCE; len],
+            write_index: 0,
+        }
+    }
+
+    fn write(&mut self, sample: Sample) {
+        self.write_index = (self.write_index + 1) % self.buffer.len();
+        self.buffer[self.write_index] = sample;
+    }
+
+    // Returns the sample written the given number of frames before the most
+    // recent one. Zero returns the most recent one.
+    fn tap(&self, frames_ago: usize) -> Sample {
+        let len = self.buffer.len();
+        self.buffer[(self.write_index + len - frames_ago.min(len - 1)) % len]
+    }
+}
+
+// A feedback comb filter with a one-pole low-pass filter in its loop.
+#[derive(Debug, Default)]
+struct DampedCombFilter {
+    buffer: Vec<Sample>,
+    index: usize,
+    filter_store: Sample,
+}
+impl DampedCombFilter {
+    fn new_with(len: usize) -> Self {
+        Self {
+            buffer: vec![Sample::SILENCE; len],
+            index: 0,
+            filter_store: Sample::SILENCE,
+        }
+    }
+
+    fn process(&mut self, input: Sample, feedback: f64, damping: f64) -> Sample {
+        let output = self.buffer[self.index];
+        self.filter_store = output * (1.0 - damping) + self.filter_store * damping;
+        self.buffer[self.index] = input + self.filter_store * feedback;
+        self.index = (self.index + 1) % self.buffer.len();
+        output
+    }
+}
+
+// Freeverb's approximation of an all-pass filter.
+#[derive(Debug, Default)]
+struct AllPassFilter {
+    buffer: Vec<Sample>,
+    index: usize,
+}
+impl AllPassFilter {
+    const FEEDBACK: f64 = 0.5;
+
+    fn new_with(len: usize) -> Self {
+        Self {
+            buffer: vec![Sample::SILENCE; len],
+            index: 0,
+        }
+    }
+
+    fn process(&mut self, input: Sample) -> Sample {
+        let delayed = self.buffer[self.index];
+        self.buffer[self.index] = input + delayed * Self::FEEDBACK;
+        self.index = (self.index + 1) % self.buffer.len();
+        delayed - input
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render_impulse(fx: &mut FreeverbCore, len: usize) -> Vec<StereoSample> {
+        let mut samples = vec![StereoSample::SILENCE; len];
+        samples[0] = StereoSample::from(1.0);
+        fx.transform(&mut samples);
+        samples
+    }
+
+    fn rms(samples: &[StereoSample]) -> f64 {
+        (samples.iter().map(|s| s.0 .0 * s.0 .0).sum::<f64>() / samples.len() as f64).sqrt()
+    }
+
+    #[test]
+    fn freeverb_waits_for_pre_delay() {
+        let mut fx = FreeverbCoreBuilder::default()
+            .pre_delay(0.5.into())
+            .early_reflections(Normal::zero())
+            .build()
+            .unwrap();
+        fx.update_sample_rate(SampleRate::DEFAULT);
+        let output = render_impulse(&mut fx, 44100);
+
+        // The tail starts when the shortest comb filter first comes around.
+        let pre_delay_frames = 4410;
+        assert_eq!(
+            output.iter().position(|s| s.0 != Sample::SILENCE),
+            Some(pre_delay_frames + FreeverbCore::COMB_TUNINGS[0])
+        );
+    }
+
+    #[test]
+    fn freeverb_early_reflections_come_first() {
+        let mut fx = FreeverbCoreBuilder::default()
+            .pre_delay(Normal::zero())
+            .early_reflections(Normal::maximum())
+            .build()
+            .unwrap();
+        fx.update_sample_rate(SampleRate::DEFAULT);
+        let output = render_impulse(&mut fx, 44100);
+
+        let first_left = output.iter().position(|s| s.0 != Sample::SILENCE).unwrap();
+        let first_right = output.iter().position(|s| s.1 != Sample::SILENCE).unwrap();
+        assert!(first_left < FreeverbCore::COMB_TUNINGS[0]);
+        assert!(first_right < FreeverbCore::COMB_TUNINGS[0]);
+        assert_ne!(
+            first_left, first_right,
+            "The reflections should reach each ear at different times"
+        );
+    }
+
+    #[test]
+    fn freeverb_room_size_and_width() {
+        let mut small = FreeverbCoreBuilder::default()
+            .room_size(0.2.into())
+            .build()
+            .unwrap();
+        small.update_sample_rate(SampleRate::DEFAULT);
+        let mut large = FreeverbCoreBuilder::default()
+            .room_size(0.9.into())
+            .build()
+            .unwrap();
+        large.update_sample_rate(SampleRate::DEFAULT);
+        let small_output = render_impulse(&mut small, 44100 * 2);
+        let large_output = render_impulse(&mut large, 44100 * 2);
+        assert!(
+            rms(&large_output[44100..]) > rms(&small_output[44100..]) * 10.0,
+            "A larger room should ring longer"
+        );
+
+        let mut mono = FreeverbCoreBuilder::default()
+            .width(Normal::zero())
+            .early_reflections(Normal::zero())
+            .build()
+            .unwrap();
+        mono.update_sample_rate(SampleRate::DEFAULT);
+        let output = render_impulse(&mut mono, 44100);
+        assert!(output.iter().any(|s| s.0 != Sample::SILENCE));
+        assert!(
+            output.iter().all(|s| s.0 == s.1),
+            "Zero width should be mono"
+        );
+        assert!(
+            large_output.iter().any(|s| s.0 != s.1),
+            "Full width should be stereo"
+        );
+    }
+}
@@ -0,0 +1,365 @@
+// Copyright (c) 2024 Mike Tsao
+
+use crate::prelude::*;
+use delegate::delegate;
+use derivative::Derivative;
+use derive_builder::Builder;
+use ensnare_proc_macros::Control;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// A brickwall limiter for mastering. Unlike [LimiterCore](super::LimiterCore),
+/// which clips anything above its maximum, it turns down the audio just enough
+/// to keep its peaks under the ceiling. It looks ahead at the audio to come, so
+/// the gain starts falling before a peak arrives, and it estimates the peaks
+/// that fall between samples, which a clipper can't see but a DAC will produce.
+///
+/// Looking ahead delays the audio by [LookAheadLimiterCore::LOOK_AHEAD], which
+/// is 5 milliseconds. Nothing else in the project compensates for that delay,
+/// so the limited audio lags any track that doesn't pass through the limiter.
+#[derive(Debug, Derivative, Builder, Control, Serialize, Deserialize)]
+#[derivative(Default)]
+#[builder(default, build_fn(private, name = "build_from_builder"))]
+#[serde(default, rename_all = "kebab-case")]
+pub struct LookAheadLimiterCore {
+    /// The highest level that the output may reach. 1.0 is 0dB. The default
+    /// is about -1dB, which leaves room for lossy encoding.
+    #[control]
+    #[derivative(Default(value = "Normal::from(0.89)"))]
+    ceiling: Normal,
+
+    /// How soon the gain recovers after a peak has passed. Expressed as a
+    /// [Normal] that is scaled to an amount of time, with 1.0 being
+    /// [LookAheadLimiterCore::MAX_RELEASE].
+    #[control]
+    #[derivative(Default(value = "Normal::from(0.1)"))]
+    release: Normal,
+
+    #[serde(skip)]
+    #[builder(setter(skip))]
+    e: LookAheadLimiterCoreEphemerals,
+}
+#[derive(Debug, Default)]
+struct LookAheadLimiterCoreEphemerals {
+    // The number of frames that the audio is delayed.
+    look_ahead_frames: usize,
+
+    // Frames waiting to be output, each with the gain that it alone requires.
+    delay_line: VecDeque<(StereoSample, f64)>,
+
+    // The most recent input samples of each channel, oldest first, for
+    // estimating inter-sample peaks.
+    history: [[f64; 4]; 2],
+
+    // The smallest required gain across the look-ahead window, maintained as a
+    // monotonic queue of (frame, gain).
+    window_minimums: VecDeque<(usize, f64)>,
+    frame: usize,
+
+    // The recent window minimums, and their sum, which are averaged to make
+    // the gain change smoothly.
+    recent_minimums: VecDeque<f64>,
+    recent_minimums_sum: f64,
+
+    // The gain that is currently applied, and how quickly it can rise.
+    gain: f64,
+    release_coefficient: f64,
+
+    // The most gain reduction, in decibels, during the last transform().
+    gain_reduction: f64,
+
+    c: Configurables,
+}
+impl LookAheadLimiterCoreBuilder {
+    /// The overridden Builder build() method.
+    pub fn build(&self) -> Result<LookAheadLimiterCore, LookAheadLimiterCoreBuilderError> {
+        match self.build_from_builder() {
+            Ok(mut s) => {
+                s.after_deser();
+                Ok(s)
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+impl Serializable for LookAheadLimiterCore {
+    fn after_deser(&mut self) {
+        self.reset();
+    }
+}
+impl Configurable for LookAheadLimiterCore {
+    delegate! {
+        to self.e.c {
+            fn sample_rate(&self) -> SampleRate;
+            fn tempo(&self) -> Tempo;
+            fn update_tempo(&mut self, tempo: Tempo);
+            fn time_signature(&self) -> TimeSignature;
+            fn update_time_signature(&mut self, time_signature: TimeSignature);
+        }
+    }
+
+    fn update_sample_rate(&mut self, sample_rate: SampleRate) {
+        self.e.c.update_sample_rate(sample_rate);
+        self.reset();
+    }
+}
+impl TransformsAudio for LookAheadLimiterCore {
+    // The limiter works on whole frames, so it doesn't implement
+    // transform_channel().
+    fn transform(&mut self, samples: &mut [StereoSample]) {
+        if self.e.recent_minimums.is_empty() {
+            self.reset();
+        }
+        let mut gain_reduction: f64 = 0.0;
+        for sample in samples.iter_mut() {
+            let (output, gain) = self.limit_frame(*sample);
+            *sample = output;
+            gain_reduction = gain_reduction.max(-20.0 * gain.max(Self::MIN_GAIN).log10());
+        }
+        self.e.gain_reduction = gain_reduction;
+    }
+}
+impl LookAheadLimiterCore {
+    /// How far ahead the limiter looks, which is also how long it delays the
+    /// audio.
+    pub const LOOK_AHEAD: Seconds = Seconds(0.005);
+
+    /// The release time when [LookAheadLimiterCore::release()] is 1.0.
+    pub const MAX_RELEASE: Seconds = Seconds(1.0);
+
+    // The gain, about -100dB, below which the metered reduction stops growing.
+    // A zero ceiling asks for zero gain, whose logarithm is infinite.
+    const MIN_GAIN: f64 = 1e-5;
+
+    /// How much the limiter turned down the audio during the most recent batch,
+    /// in decibels. Zero means that the audio passed through untouched. This
+    /// is meant for metering.
+    pub fn gain_reduction(&self) -> f64 {
+        self.e.gain_reduction
+    }
+
+    /// The number of frames by which the output lags the input. The project
+    /// doesn't compensate for this latency.
+    pub fn latency(&self) -> usize {
+        self.e.look_ahead_frames
+    }
+
+    // Clears all state and sizes the buffers for the current sample rate.
+    fn reset(&mut self) {
+        let sample_rate = f64::from(self.e.c.sample_rate());
+        let look_ahead_frames = ((Self::LOOK_AHEAD.0 * sample_rate).round() as usize).max(1);
+        self.e.look_ahead_frames = look_ahead_frames;
+        self.e.delay_line.clear();
+        self.e
+            .delay_line
+            .resize(look_ahead_frames, (StereoSample::SILENCE, 1.0));
+        self.e.history = Default::default();
+        self.e.window_minimums.clear();
+        self.e.frame = 0;
+        self.e.recent_minimums.clear();
+        self.e.recent_minimums.resize(look_ahead_frames + 1, 1.0);
+        self.e.recent_minimums_sum = (look_ahead_frames + 1) as f64;
+        self.e.gain = 1.0;
+        self.e.gain_reduction = 0.0;
+        self.update_release_coefficient();
+    }
+
+    fn update_release_coefficient(&mut self) {
+        let frames = self.release.0 * Self::MAX_RELEASE.0 * f64::from(self.e.c.sample_rate());
+        self.e.release_coefficient = if frames > 0.0 {
+            (-1.0 / frames).exp()
+        } else {
+            0.0
+        };
+    }
+
+    // Estimates the true peak of the signal around the newest sample of the
+    // given channel. Besides the sample itself, it checks points between the
+    // two previous samples, interpolated with a Catmull-Rom spline, which is
+    // enough to catch most inter-sample overs.
+    fn true_peak(&mut self, channel: usize, sample: f64) -> f64 {
+        let history = &mut self.e.history[channel];
+        history.rotate_left(1);
+        history[3] = sample;
+        let [p0, p1, p2, p3] = *history;
+        let mut peak = sample.abs();
+        for t in [0.25, 0.5, 0.75] {
+            let value = 0.5
+                * (2.0 * p1
+                    + (p2 - p0) * t
+                    + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t * t
+                    + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t * t * t);
+            peak = peak.max(value.abs());
+        }
+        peak
+    }
+
+    // Accepts one frame of input, and returns the frame that leaves the delay
+    // line along with the gain that was applied to it.
+    fn limit_frame(&mut self, input: StereoSample) -> (StereoSample, f64) {
+        let ceiling = self.ceiling.0;
+        let peak = self
+            .true_peak(0, input.0 .0)
+            .max(self.true_peak(1, input.1 .0));
+        let required = if peak > ceiling { ceiling / peak } else { 1.0 };
+
+        // The gain for the frame leaving the delay line must be no higher than
+        // what any frame in the window requires.
+        let window_len = self.e.look_ahead_frames + 1;
+        let frame = self.e.frame;
+        self.e.frame += 1;
+        while let Some((_, minimum)) = self.e.window_minimums.back() {
+            if *minimum >= required {
+                self.e.window_minimums.pop_back();
+            } else {
+                break;
+            }
+        }
+        self.e.window_minimums.push_back((frame, required));
+        while let Some((oldest, _)) = self.e.window_minimums.front() {
+            if oldest + window_len <= frame {
+                self.e.window_minimums.pop_front();
+            } else {
+                break;
+            }
+        }
+        let window_minimum = self.e.window_minimums.front().unwrap().1;
+
+        // Averaging the window minimums turns each drop in gain into a ramp
+        // that spans the look-ahead, and that still reaches the required gain
+        // in time.
+        self.e.recent_minimums_sum += window_minimum;
+        self.e.recent_minimums.push_back(window_minimum);
+        if let Some(oldest) = self.e.recent_minimums.pop_front() {
+            self.e.recent_minimums_sum -= oldest;
+        }
+        let smoothed = self.e.recent_minimums_sum / window_len as f64;
+        self.e.gain = if smoothed > self.e.gain {
+            smoothed + self.e.release_coefficient * (self.e.gain - smoothed)
+        } else {
+            smoothed
+        };
+
+        self.e.delay_line.push_back((input, required));
+        let (delayed, delayed_required) = self.e.delay_line.pop_front().unwrap();
+
+        // Rounding in the running sum mustn't let a peak slip through.
+        let gain = self.e.gain.min(delayed_required);
+        (StereoSample(delayed.0 * gain, delayed.1 * gain), gain)
+    }
+}
+#[allow(missing_docs)]
+impl LookAheadLimiterCore {
+    pub fn ceiling(&self) -> Normal {
+        self.ceiling
+    }
+
+    pub fn set_ceiling(&mut self, ceiling: Normal) {
+        self.ceiling = ceiling;
+    }
+
+    pub fn release(&self) -> Normal {
+        self.release
+    }
+
+    pub fn set_release(&mut self, release: Normal) {
+        self.release = release;
+        self.update_release_coefficient();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f64::consts::PI;
+
+    fn limiter() -> LookAheadLimiterCore {
+        let mut limiter = LookAheadLimiterCoreBuilder::default()
+            .ceiling(0.5.into())
+            .release(0.1.into())
+            .build()
+            .unwrap();
+        limiter.update_sample_rate(SampleRate::DEFAULT);
+        limiter
+    }
+
+    #[test]
+    fn look_ahead_limiter_passes_quiet_audio() {
+        let mut limiter = limiter();
+        let latency = limiter.latency();
+        assert!(latency > 0);
+
+        let input: Vec<StereoSample> = (0..1024)
+            .map(|i| StereoSample::from(0.4 * (i as f64 * 0.05).sin()))
+            .collect();
+        let mut output = input.clone();
+        limiter.transform(&mut output);
+        assert!(
+            output[..latency]
+                .iter()
+                .all(|s| *s == StereoSample::SILENCE),
+            "The output should be delayed by the look-ahead"
+        );
+        assert_eq!(
+            output[latency..],
+            input[..input.len() - latency],
+            "Audio under the ceiling should be unchanged"
+        );
+        assert_eq!(limiter.gain_reduction(), 0.0);
+    }
+
+    #[test]
+    fn look_ahead_limiter_holds_ceiling() {
+        let mut limiter = limiter();
+        let latency = limiter.latency();
+
+        // A sudden, loud burst after silence, which is the hardest case for a
+        // limiter that can't see ahead.
+        let mut output: Vec<StereoSample> = (0..4096)
+            .map(|i| {
+                if i < 1000 {
+                    StereoSample::SILENCE
+                } else {
+                    StereoSample::from(2.0 * (i as f64 * 2.0 * PI * 0.23).sin())
+                }
+            })
+            .collect();
+        limiter.transform(&mut output);
+        assert!(
+            output
+                .iter()
+                .all(|s| s.0 .0.abs() <= 0.5 + 0.000001 && s.1 .0.abs() <= 0.5 + 0.000001),
+            "No sample should exceed the ceiling"
+        );
+        assert!(
+            output[1000 + latency..].iter().any(|s| s.0 .0.abs() > 0.35),
+            "The limited audio should stay close to the ceiling"
+        );
+        assert!(
+            limiter.gain_reduction() > 6.0,
+            "The meter should show about 12dB of reduction, not {}",
+            limiter.gain_reduction()
+        );
+
+        // After the burst, the gain should recover.
+        let mut output = vec![StereoSample::from(0.25); 44100];
+        limiter.transform(&mut output);
+        assert!((output.last().unwrap().0 .0 - 0.25).abs() < 0.001);
+        assert!(limiter.gain_reduction() < 0.01);
+    }
+
+    #[test]
+    fn look_ahead_limiter_meters_zero_ceiling() {
+        let mut limiter = LookAheadLimiterCoreBuilder::default()
+            .ceiling(Normal::zero())
+            .build()
+            .unwrap();
+        limiter.update_sample_rate(SampleRate::DEFAULT);
+        let mut output = vec![StereoSample::from(0.5); 1024];
+        limiter.transform(&mut output);
+        assert!(
+            limiter.gain_reduction().is_finite(),
+            "Silencing everything should still show a finite reduction"
+        );
+    }
+}
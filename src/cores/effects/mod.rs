@@ -7,7 +7,7 @@
 pub use {
     bitcrusher::{BitcrusherCore, BitcrusherCoreBuilder},
     chorus::{ChorusCore, ChorusCoreBuilder},
-    compressor::{CompressorCore, CompressorCoreBuilder},
+    compressor::{CompressorCore, CompressorCoreBuilder, CompressorDetection},
     delay::{DelayCore, DelayCoreBuilder, DelayLine, Delays},
     filter::{
         BiQuadFilterAllPassCore, BiQuadFilterAllPassCoreBuilder, BiQuadFilterBandPassCore,
@@ -18,8 +18,10 @@ pub use {
         BiQuadFilterLowShelfCoreBuilder, BiQuadFilterNoneCoreBuilder, BiQuadFilterPeakingEqCore,
         BiQuadFilterPeakingEqCoreBuilder,
     },
+    freeverb::{FreeverbCore, FreeverbCoreBuilder},
     gain::{GainCore, GainCoreBuilder},
     limiter::{LimiterCore, LimiterCoreBuilder},
+    look_ahead_limiter::{LookAheadLimiterCore, LookAheadLimiterCoreBuilder},
     reverb::{ReverbCore, ReverbCoreBuilder},
     test::TestEffectNegatesInputCore,
 };
@@ -29,7 +31,9 @@ mod chorus;
 mod compressor;
 mod delay;
 mod filter;
+mod freeverb;
 mod gain;
 mod limiter;
+mod look_ahead_limiter;
 mod reverb;
 mod test;
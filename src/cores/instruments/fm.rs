@@ -34,6 +34,18 @@ pub struct FmVoice {
     note_on_velocity: u7,
     steal_is_underway: bool,
 
+    // How loud the current note is, from its note-on velocity.
+    velocity: Normal,
+
+    // The frequency of the current note before pitch bend.
+    frequency: FrequencyHz,
+    pitch_bend: f64,
+
+    // Pressing harder deepens the modulation, which brightens the sound. The
+    // mod wheel does the same, and whichever is further along wins.
+    pressure: Normal,
+    modulation: Normal,
+
     sample_rate: SampleRate,
 
     modulator_buffer: GenerationBuffer<BipolarNormal>,
@@ -58,20 +70,36 @@ impl PlaysNotes for FmVoice {
             self.carrier_envelope.trigger_shutdown();
             self.modulator_envelope.trigger_shutdown();
         } else {
+            self.velocity = Normal::from(velocity.as_int() as f64 / 127.0);
             self.set_frequency_hz(MidiNote::from_repr(key.as_int() as usize).unwrap().into());
             self.carrier_envelope.trigger_attack();
             self.modulator_envelope.trigger_attack();
         }
     }
 
-    fn aftertouch(&mut self, _velocity: u7) {
-        todo!()
+    fn aftertouch(&mut self, velocity: u7) {
+        self.pressure = Normal::from(velocity.as_int() as f64 / 127.0);
     }
 
     fn note_off(&mut self, _velocity: u7) {
         self.carrier_envelope.trigger_release();
         self.modulator_envelope.trigger_release();
     }
+
+    fn set_pitch_bend(&mut self, semitones: f64) {
+        self.pitch_bend = semitones;
+        self.update_oscillator_frequencies();
+    }
+
+    fn set_modulation(&mut self, modulation: Normal) {
+        self.modulation = modulation;
+    }
+
+    fn stop_sound(&mut self) {
+        self.steal_is_underway = false;
+        self.carrier_envelope.trigger_shutdown();
+        self.modulator_envelope.trigger_shutdown();
+    }
 }
 impl Generates<StereoSample> for FmVoice {
     fn generate(&mut self, values: &mut [StereoSample]) -> bool {
@@ -86,6 +114,8 @@ impl Generates<StereoSample> for FmVoice {
 
             self.mono_buffer.resize(values.len());
 
+            let expression = self.pressure.0.max(self.modulation.0);
+            let depth = Normal::from(self.depth.0 + (1.0 - self.depth.0) * expression);
             self.modulator.generate(self.modulator_buffer.buffer_mut());
             self.modulator_envelope
                 .generate(self.modulator_envelope_buffer.buffer_mut());
@@ -99,7 +129,7 @@ impl Generates<StereoSample> for FmVoice {
                         .zip(self.modulator_envelope_buffer.buffer().iter()),
                 )
                 .for_each(|(dst, (modulator, mod_env))| {
-                    *dst = *modulator * *mod_env * self.depth;
+                    *dst = *modulator * *mod_env * depth;
                 });
             let mut one_buffer = [BipolarNormal::default(); 1];
             self.carrier_buffer
@@ -115,6 +145,7 @@ impl Generates<StereoSample> for FmVoice {
             // self.carrier.generate(self.carrier_buffer.buffer_mut()); // TODO: magnitude
             self.carrier_envelope
                 .generate(self.carrier_envelope_buffer.buffer_mut());
+            let velocity = self.velocity;
             self.mono_buffer
                 .buffer_mut()
                 .iter_mut()
@@ -125,7 +156,7 @@ impl Generates<StereoSample> for FmVoice {
                         .zip(self.carrier_envelope_buffer.buffer().iter()),
                 )
                 .for_each(|(dst, (carrier, car_envelope))| {
-                    let sample: Sample = (*carrier * *car_envelope).into();
+                    let sample: Sample = (*carrier * *car_envelope * velocity).into();
                     generated_signal |= sample != Sample::default();
                     *dst = sample
                 });
@@ -172,6 +203,8 @@ impl FmVoice {
             ratio,
             beta,
             dca: dca.make_another(),
+            pressure: Normal::zero(),
+            modulation: Normal::zero(),
             ..Default::default()
         }
     }
@@ -187,6 +220,12 @@ impl FmVoice {
     }
 
     fn set_frequency_hz(&mut self, frequency_hz: FrequencyHz) {
+        self.frequency = frequency_hz;
+        self.update_oscillator_frequencies();
+    }
+
+    fn update_oscillator_frequencies(&mut self) {
+        let frequency_hz = FrequencyHz(self.frequency.0 * 2.0f64.powf(self.pitch_bend / 12.0));
         self.carrier.set_frequency(frequency_hz);
         self.modulator.set_frequency(frequency_hz * self.ratio);
     }
@@ -234,7 +273,8 @@ impl FmVoice {
     }
 }
 
-/// An FM synthesizer.
+/// An FM synthesizer. It has no bank of built-in patches, so it ignores MIDI
+/// program changes.
 #[derive(Debug, Default, Builder, Control, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 #[builder(default, build_fn(private, name = "build_from_builder"))]
@@ -271,6 +311,10 @@ pub struct FmSynthCore {
     #[control]
     pub dca: Dca,
 
+    /// How far a full pitch bend moves the notes.
+    #[serde(default)]
+    pitch_bend_range: PitchBendRange,
+
     /// Voice management
     #[serde(skip)]
     #[builder(setter(skip))]
@@ -306,7 +350,8 @@ impl Serializable for FmSynthCore {
             self.ratio,
             self.beta,
             &self.dca,
-        )))
+        )));
+        self.inner.set_pitch_bend_range(self.pitch_bend_range);
     }
 }
 impl Configurable for FmSynthCore {
@@ -322,14 +367,24 @@ impl Configurable for FmSynthCore {
     }
 }
 impl HandlesMidi for FmSynthCore {
-    delegate! {
-        to self.inner {
-            fn handle_midi_message(
-                &mut self,
-                channel: MidiChannel,
-                message: MidiMessage,
-                midi_messages_fn: &mut MidiMessagesFn,
-            );
+    fn handle_midi_message(
+        &mut self,
+        channel: MidiChannel,
+        message: MidiMessage,
+        midi_messages_fn: &mut MidiMessagesFn,
+    ) {
+        match message {
+            // Volume and pan go to the Dca so that they show up alongside the
+            // patch's own settings.
+            MidiMessage::Controller { controller, value } if controller.as_int() == 7 => {
+                self.set_gain(Normal::from(value.as_int() as f64 / 127.0));
+            }
+            MidiMessage::Controller { controller, value } if controller.as_int() == 10 => {
+                self.set_pan(Synthesizer::<FmVoice>::cc_to_pan(value));
+            }
+            _ => self
+                .inner
+                .handle_midi_message(channel, message, midi_messages_fn),
         }
     }
 }
@@ -392,6 +447,17 @@ impl FmSynthCore {
         self.beta
     }
 
+    #[allow(missing_docs)]
+    pub fn pitch_bend_range(&self) -> PitchBendRange {
+        self.pitch_bend_range
+    }
+
+    #[allow(missing_docs)]
+    pub fn set_pitch_bend_range(&mut self, pitch_bend_range: PitchBendRange) {
+        self.pitch_bend_range = pitch_bend_range;
+        self.inner.set_pitch_bend_range(pitch_bend_range);
+    }
+
     #[allow(missing_docs)]
     pub fn notify_change_carrier(&mut self) {
         self.inner.voices_mut().for_each(|v| {
@@ -8,8 +8,8 @@ pub use {
 pub use {
     fm::{FmSynthCore, FmSynthCoreBuilder},
     subtractive::{
-        LfoRouting, SubtractiveSynthCore, SubtractiveSynthCoreBuilder, SubtractiveSynthVoice,
-        PATCH_DIR as SUBTRACTIVE_PATCH_DIR,
+        LfoRouting, ProgramChangeError, SubtractiveSynthCore, SubtractiveSynthCoreBuilder,
+        SubtractiveSynthVoice, PATCH_DIR as SUBTRACTIVE_PATCH_DIR,
     },
     test::{
         TestAudioSourceCore, TestAudioSourceCoreBuilder, TestControllerAlwaysSendsMidiMessageCore,
@@ -2,15 +2,13 @@
 
 use crate::{
     prelude::*,
-    util::{
-        Paths, {SampleLibrary, SampleSource},
-    },
+    util::{Paths, SampleSource},
 };
 use anyhow::{anyhow, Result};
 use ensnare_proc_macros::Control;
 use hound::WavReader;
 use serde::{Deserialize, Serialize};
-use std::{fs::File, io::BufReader, path::Path, sync::Arc};
+use std::{fs::File, io::BufReader, sync::Arc};
 
 /// One sampler voice. Combine multiple of these to make a sampling synth.
 #[derive(Debug, Default)]
@@ -25,6 +23,17 @@ pub struct SamplerVoice {
     is_playing: bool,
     sample_pointer: ParameterType,
     sample_pointer_delta: ParameterType,
+
+    pitch_bend: f64,
+
+    // How loud the current note is, from its note-on velocity.
+    velocity: Normal,
+
+    // Pressing harder adds vibrato, as does the mod wheel. Whichever is
+    // further along wins.
+    pressure: Normal,
+    modulation: Normal,
+    vibrato_phase: f64,
 }
 impl IsVoice<StereoSample> for SamplerVoice {}
 impl IsStereoSampleVoice for SamplerVoice {}
@@ -33,17 +42,16 @@ impl PlaysNotes for SamplerVoice {
         self.is_playing
     }
 
-    #[allow(unused_variables)]
     fn note_on(&mut self, key: u7, velocity: u7) {
         self.is_playing = true;
+        self.velocity = Normal::from(velocity.as_int() as f64 / 127.0);
         self.sample_pointer = 0.0;
         self.frequency = MidiNote::from_repr(key.as_int() as usize).unwrap().into();
         self.sample_pointer_delta = (self.frequency / self.root_frequency).into();
     }
 
-    #[allow(unused_variables)]
     fn aftertouch(&mut self, velocity: u7) {
-        todo!()
+        self.pressure = Normal::from(velocity.as_int() as f64 / 127.0);
     }
 
     #[allow(unused_variables)]
@@ -51,10 +59,26 @@ impl PlaysNotes for SamplerVoice {
         self.is_playing = false;
         self.sample_pointer = 0.0;
     }
+
+    fn set_pitch_bend(&mut self, semitones: f64) {
+        self.pitch_bend = semitones;
+    }
+
+    fn set_modulation(&mut self, modulation: Normal) {
+        self.modulation = modulation;
+    }
 }
 impl Generates<StereoSample> for SamplerVoice {
     fn generate(&mut self, values: &mut [StereoSample]) -> bool {
         let mut generated_signal = false;
+        let bend = 2.0f64.powf(self.pitch_bend / 12.0);
+        let vibrato_phase_delta = if self.sample_rate.0 != 0 {
+            Self::VIBRATO_FREQUENCY / self.sample_rate.0 as f64
+        } else {
+            0.0
+        };
+
+        let expression = self.pressure.0.max(self.modulation.0);
 
         for value in values {
             *value = {
@@ -62,7 +86,7 @@ impl Generates<StereoSample> for SamplerVoice {
                     if let Some(samples) = self.samples.as_ref() {
                         if samples.len() != 0 {
                             generated_signal = true;
-                            samples[self.sample_pointer as usize]
+                            samples[self.sample_pointer as usize] * self.velocity
                         } else {
                             StereoSample::SILENCE
                         }
@@ -76,7 +100,15 @@ impl Generates<StereoSample> for SamplerVoice {
 
             if self.is_playing {
                 if !self.was_reset {
-                    self.sample_pointer += self.sample_pointer_delta;
+                    let mut delta = self.sample_pointer_delta * bend;
+                    if expression != 0.0 {
+                        let vibrato = (self.vibrato_phase * 2.0 * std::f64::consts::PI).sin()
+                            * expression
+                            * Self::VIBRATO_DEPTH;
+                        delta *= 2.0f64.powf(vibrato / 12.0);
+                        self.vibrato_phase = (self.vibrato_phase + vibrato_phase_delta).fract();
+                    }
+                    self.sample_pointer += delta;
                 }
                 if let Some(samples) = self.samples.as_ref() {
                     debug_assert_ne!(samples.len(), 0);
@@ -102,6 +134,11 @@ impl Configurable for SamplerVoice {
 }
 #[allow(missing_docs)]
 impl SamplerVoice {
+    // The speed and the most extreme pitch change, in semitones, of the
+    // vibrato that pressure adds.
+    const VIBRATO_FREQUENCY: f64 = 5.5;
+    const VIBRATO_DEPTH: f64 = 0.5;
+
     pub fn new_with_samples(samples: Arc<Vec<StereoSample>>, root_frequency: FrequencyHz) -> Self {
         if !root_frequency.0.is_normal() {
             panic!("strange number given for root frequency: {root_frequency}");
@@ -120,6 +157,11 @@ impl SamplerVoice {
             is_playing: Default::default(),
             sample_pointer: Default::default(),
             sample_pointer_delta: Default::default(),
+            pitch_bend: Default::default(),
+            velocity: Normal::maximum(),
+            pressure: Normal::zero(),
+            modulation: Normal::zero(),
+            vibrato_phase: Default::default(),
         }
     }
 
@@ -128,7 +170,8 @@ impl SamplerVoice {
     }
 }
 
-/// A sampling synthesizer.
+/// A sampling synthesizer. It plays a single sample, so it ignores MIDI
+/// program changes.
 #[derive(Debug, Control, Default, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct SamplerCore {
@@ -137,6 +180,9 @@ pub struct SamplerCore {
     #[control]
     root: FrequencyHz,
 
+    #[serde(default)]
+    pitch_bend_range: PitchBendRange,
+
     #[serde(skip)]
     e: SamplerEphemerals,
 }
@@ -194,18 +240,7 @@ impl Configurable for SamplerCore {
 #[allow(missing_docs)]
 impl SamplerCore {
     pub fn load(&mut self) -> anyhow::Result<()> {
-        let path = match &self.source {
-            SampleSource::SampleLibrary(index) => {
-                if let Some(path) = SampleLibrary::global().path(*index) {
-                    Paths::global().build_sample(&Vec::default(), Path::new(&path))
-                } else {
-                    return Err(anyhow!("Couldn't find sample {index} in library"));
-                }
-            }
-            SampleSource::Path(path_buf) => {
-                Paths::global().build_sample(&Vec::default(), Path::new(&path_buf))
-            }
-        };
+        let path = self.source.build_path()?;
         let file = Paths::global().search_and_open(path.as_path())?;
         let samples = Self::read_samples_from_file(&file)?;
         let samples = Arc::new(samples);
@@ -225,6 +260,7 @@ impl SamplerCore {
                 SamplerVoice::new_with_samples(Arc::clone(&samples), self.e.calculated_root)
             }),
         ));
+        self.e.inner.set_pitch_bend_range(self.pitch_bend_range);
 
         Ok(())
     }
@@ -246,6 +282,7 @@ impl SamplerCore {
             e,
             source,
             root: calculated_root,
+            pitch_bend_range: Default::default(),
         }
     }
 
@@ -347,18 +384,28 @@ impl SamplerCore {
     }
 
     pub fn read_samples_from_file(file: &File) -> anyhow::Result<Vec<StereoSample>> {
+        let (samples, _) = Self::read_samples_and_sample_rate_from_file(file)?;
+        Ok(samples)
+    }
+
+    /// Like [SamplerCore::read_samples_from_file()], but also returns the
+    /// sample rate at which the file was recorded.
+    pub fn read_samples_and_sample_rate_from_file(
+        file: &File,
+    ) -> anyhow::Result<(Vec<StereoSample>, SampleRate)> {
         let mut reader = hound::WavReader::new(BufReader::new(file))?;
         let spec = reader.spec();
         let itype_max: SampleType = 2.0f64.powi(spec.bits_per_sample as i32 - 1);
 
-        match spec.sample_format {
+        let samples = match spec.sample_format {
             hound::SampleFormat::Float => {
                 Self::read_samples::<f32>(&mut reader, spec.channels, itype_max)
             }
             hound::SampleFormat::Int => {
                 Self::read_samples::<i32>(&mut reader, spec.channels, itype_max)
             }
-        }
+        }?;
+        Ok((samples, SampleRate::new(spec.sample_rate as usize)))
     }
 
     pub fn root(&self) -> FrequencyHz {
@@ -373,6 +420,15 @@ impl SamplerCore {
             .for_each(|v| v.set_root_frequency(root));
     }
 
+    pub fn pitch_bend_range(&self) -> PitchBendRange {
+        self.pitch_bend_range
+    }
+
+    pub fn set_pitch_bend_range(&mut self, pitch_bend_range: PitchBendRange) {
+        self.pitch_bend_range = pitch_bend_range;
+        self.e.inner.set_pitch_bend_range(pitch_bend_range);
+    }
+
     pub fn calculated_root(&self) -> FrequencyHz {
         self.e.calculated_root
     }
@@ -397,7 +453,7 @@ impl SamplerCore {
 mod tests {
     use super::*;
     use crate::util::FileType;
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
 
     fn paths_with_test_data_dir() -> Paths {
         let mut paths = Paths::default();
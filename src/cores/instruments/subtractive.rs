@@ -7,6 +7,7 @@ use delegate::delegate;
 use derive_builder::Builder;
 use ensnare_proc_macros::Control;
 use include_dir::{include_dir, Dir};
+use once_cell::sync::OnceCell;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use strum_macros::{Display, EnumCount, EnumIter, FromRepr};
@@ -14,6 +15,43 @@ use strum_macros::{Display, EnumCount, EnumIter, FromRepr};
 /// The source directory for the subtractive synth's patch files.
 pub static PATCH_DIR: Dir = include_dir!("$CARGO_MANIFEST_DIR/assets/patches/subtractive");
 
+// The sounds of the built-in patches, indexed by program number, or None for a
+// patch that couldn't be read. Parsed when the first synth is built, so that
+// program changes never have to do it on the audio thread.
+static PATCH_BANK: OnceCell<Vec<Option<SubtractiveSynthSound>>> = OnceCell::new();
+
+/// Why [SubtractiveSynthCore::load_program()] couldn't change the sound.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ProgramChangeError {
+    /// No built-in patch has that program number.
+    #[error("There's no patch for that program")]
+    NoSuchProgram,
+    /// The built-in patch for that program couldn't be read.
+    #[error("The patch for that program couldn't be read")]
+    UnreadablePatch,
+}
+
+// The parts of a patch that describe its sound, as opposed to the state of a
+// performance. Program changes copy these into a running synth.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct SubtractiveSynthSound {
+    preset_name: Option<String>,
+    oscillator_1: Oscillator,
+    oscillator_2: Oscillator,
+    oscillator_2_sync: bool,
+    oscillator_mix: Normal,
+    amp_envelope: Envelope,
+    dca: Dca,
+    lfo: Oscillator,
+    lfo_routing: LfoRouting,
+    lfo_depth: Normal,
+    filter: BiQuadFilterLowPass24dbCore,
+    filter_cutoff_start: Normal,
+    filter_cutoff_end: Normal,
+    filter_envelope: Envelope,
+}
+
 /// Possible modulation targets.
 #[allow(missing_docs)]
 #[derive(
@@ -64,6 +102,18 @@ pub struct SubtractiveSynthVoice {
     note_on_velocity: u7,
     steal_is_underway: bool,
 
+    // How loud the current note is, from its note-on velocity.
+    velocity: Normal,
+
+    // The frequency of the current note before pitch bend.
+    frequency: FrequencyHz,
+    pitch_bend: f64,
+
+    // Pressing harder deepens the LFO. The mod wheel does the same, and
+    // whichever is further along wins.
+    pressure: Normal,
+    modulation: Normal,
+
     sample: StereoSample,
 
     amp_envelope_buffer: GenerationBuffer<Normal>,
@@ -84,18 +134,30 @@ impl PlaysNotes for SubtractiveSynthVoice {
             self.note_on_velocity = velocity;
             self.amp_envelope.trigger_shutdown();
         } else {
+            self.velocity = Normal::from(velocity.as_int() as f64 / 127.0);
             self.amp_envelope.trigger_attack();
             self.filter_envelope.trigger_attack();
             self.set_frequency_hz(MidiNote::from_repr(key.as_int() as usize).unwrap().into());
         }
     }
-    fn aftertouch(&mut self, _velocity: u7) {
-        // TODO: do something
+    fn aftertouch(&mut self, velocity: u7) {
+        self.pressure = Normal::from(velocity.as_int() as f64 / 127.0);
     }
     fn note_off(&mut self, _velocity: u7) {
         self.amp_envelope.trigger_release();
         self.filter_envelope.trigger_release();
     }
+    fn set_pitch_bend(&mut self, semitones: f64) {
+        self.pitch_bend = semitones;
+        self.update_oscillator_frequencies();
+    }
+    fn set_modulation(&mut self, modulation: Normal) {
+        self.modulation = modulation;
+    }
+    fn stop_sound(&mut self) {
+        self.steal_is_underway = false;
+        self.amp_envelope.trigger_shutdown();
+    }
 }
 impl Generates<StereoSample> for SubtractiveSynthVoice {
     fn generate(&mut self, values: &mut [StereoSample]) -> bool {
@@ -121,6 +183,8 @@ impl Generates<StereoSample> for SubtractiveSynthVoice {
             self.steal_is_underway = false;
             self.note_on(self.note_on_key, self.note_on_velocity);
         }
+        let expression = self.pressure.0.max(self.modulation.0);
+        let lfo_depth = Normal::from(self.lfo_depth.0 + (1.0 - self.lfo_depth.0) * expression);
 
         for (i, v) in self.mono_buffer.buffer_mut().iter_mut().enumerate() {
             *v = if is_playing {
@@ -132,14 +196,14 @@ impl Generates<StereoSample> for SubtractiveSynthVoice {
 
                 // LFO
                 if matches!(self.lfo_routing, LfoRouting::Pitch) {
-                    let lfo_for_pitch = lfo * self.lfo_depth;
+                    let lfo_for_pitch = lfo * lfo_depth;
                     self.oscillator_1.set_frequency_modulation(lfo_for_pitch);
                     self.oscillator_2.set_frequency_modulation(lfo_for_pitch);
                 } else if matches!(self.lfo_routing, LfoRouting::Pitch2) {
-                    let lfo_for_pitch = lfo * self.lfo_depth;
+                    let lfo_for_pitch = lfo * lfo_depth;
                     self.oscillator_2.set_frequency_modulation(lfo_for_pitch);
                 } else if matches!(self.lfo_routing, LfoRouting::PulseWidth2) {
-                    let lfo_for_pitch = lfo * self.lfo_depth;
+                    let lfo_for_pitch = lfo * lfo_depth;
                     self.oscillator_2
                         .set_waveform(Waveform::PulseWidth(lfo_for_pitch.into()));
                 }
@@ -166,14 +230,14 @@ impl Generates<StereoSample> for SubtractiveSynthVoice {
                             * filter_env_amplitude;
                     self.filter.set_cutoff(new_cutoff_percentage.into());
                 } else if matches!(self.lfo_routing, LfoRouting::FilterCutoff) {
-                    let lfo_for_cutoff = lfo * self.lfo_depth;
+                    let lfo_for_cutoff = lfo * lfo_depth;
                     self.filter
                         .set_cutoff((self.filter_cutoff_start * (lfo_for_cutoff.0 + 1.0)).into());
                 } else if matches!(self.lfo_routing, LfoRouting::FilterResonance) {
                     // TODO - it's unlikely this is correct. I copied/pasted
                     // while converting old patches and for the first time
                     // encountered a resonance setting.
-                    let lfo_for_resonance = lfo * self.lfo_depth;
+                    let lfo_for_resonance = lfo * lfo_depth;
                     self.filter.set_passband_ripple(
                         (self.filter_cutoff_start * (lfo_for_resonance.0 + 1.0)).into(),
                     );
@@ -183,13 +247,15 @@ impl Generates<StereoSample> for SubtractiveSynthVoice {
                 // LFO amplitude modulation
                 let lfo_for_amplitude =
                     Normal::from(if matches!(self.lfo_routing, LfoRouting::Amplitude) {
-                        lfo * self.lfo_depth
+                        lfo * lfo_depth
                     } else {
                         BipolarNormal::zero()
                     });
 
                 // Final
-                let sample = Sample(filtered_mix * amp_env_amplitude.0 * lfo_for_amplitude.0);
+                let sample = Sample(
+                    filtered_mix * amp_env_amplitude.0 * lfo_for_amplitude.0 * self.velocity.0,
+                );
                 generated_signal |= sample != Sample::default();
                 sample
             } else {
@@ -246,13 +312,21 @@ impl SubtractiveSynthVoice {
             filter_cutoff_start,
             filter_cutoff_end,
             filter_envelope: filter_envelope.make_another(),
+            pressure: Normal::zero(),
+            modulation: Normal::zero(),
             ..Default::default()
         }
     }
 
     fn set_frequency_hz(&mut self, frequency_hz: FrequencyHz) {
+        self.frequency = frequency_hz;
+        self.update_oscillator_frequencies();
+    }
+
+    fn update_oscillator_frequencies(&mut self) {
         // It's safe to set the frequency on a fixed-frequency oscillator; the
         // fixed frequency is stored separately and takes precedence.
+        let frequency_hz = FrequencyHz(self.frequency.0 * 2.0f64.powf(self.pitch_bend / 12.0));
         self.oscillator_1.set_frequency(frequency_hz);
         self.oscillator_2.set_frequency(frequency_hz);
     }
@@ -367,6 +441,9 @@ pub struct SubtractiveSynthCore {
     #[control]
     pub filter_envelope: Envelope,
 
+    #[serde(default)]
+    pub pitch_bend_range: PitchBendRange,
+
     #[serde(skip)]
     #[builder(setter(skip))]
     pub inner: Synthesizer<SubtractiveSynthVoice>,
@@ -386,6 +463,115 @@ impl SubtractiveSynthCoreBuilder {
 impl SubtractiveSynthCore {
     const VOICE_CAPACITY: usize = 8;
 
+    /// The built-in patches that MIDI program changes select, by program
+    /// number. New patches go at the end, so that existing programs keep
+    /// their numbers.
+    pub const PROGRAM_PATCHES: [&'static str; 103] = [
+        "accordion",
+        "acid-bass",
+        "android-dreams",
+        "angels",
+        "aurora",
+        "bagpipes",
+        "banjo",
+        "bass-clarinet",
+        "bass-drum",
+        "bass-of-the-time-lords",
+        "bassoon",
+        "bell",
+        "bongos",
+        "brass-section",
+        "castanets",
+        "cat",
+        "celeste",
+        "celestial-wash",
+        "cello",
+        "choir",
+        "clap",
+        "clarinet",
+        "claves",
+        "clavichord",
+        "conch-shell",
+        "conga",
+        "contrabassoon",
+        "cowbell",
+        "cowbell-analog",
+        "cymbal",
+        "dark-city",
+        "debug-envelope",
+        "detroit-bass",
+        "deutsche-bass",
+        "didgeridoo",
+        "digital-alarm-clock",
+        "digital-bass",
+        "double-bass",
+        "dulcimer",
+        "electric-piano",
+        "english-horn",
+        "flute",
+        "french-horn",
+        "funk-bass",
+        "galactic-cathedral",
+        "glockenspiel",
+        "growling-bass",
+        "guitar-acoustic",
+        "guitar-electric",
+        "harmonica",
+        "harp",
+        "harpsichord",
+        "hurdy-gurdy",
+        "journey-to-the-core",
+        "kazoo",
+        "kora",
+        "laser",
+        "lute",
+        "mandocello",
+        "mandolin",
+        "marimba",
+        "mellow-70s-lead",
+        "mono-solo",
+        "motor",
+        "nerd-o-tron-2000",
+        "new-age-lead",
+        "oboe",
+        "ocean-waves",
+        "ocean-waves-with-foghorn",
+        "organ",
+        "penny-whistle",
+        "piano",
+        "piccolo",
+        "portus",
+        "post-apocalyptic-sync-sweep",
+        "r&b-slide",
+        "rez-bass",
+        "riti",
+        "saxophone",
+        "screaming-sync",
+        "side-stick",
+        "sitar",
+        "snare-drum",
+        "space-attack!",
+        "standup-bass",
+        "strings-pwm",
+        "tambourine",
+        "terra-enceladus",
+        "test-lfo-pitch",
+        "timpani",
+        "toad",
+        "trance-5th",
+        "trombone",
+        "trumpet",
+        "tuba",
+        "viola",
+        "violin",
+        "vocal-female",
+        "vocal-male",
+        "wheels-of-steel",
+        "whistling",
+        "wind",
+        "xylophone",
+    ];
+
     fn new_voice_store(&self) -> StealingVoiceStore<SubtractiveSynthVoice> {
         StealingVoiceStore::<SubtractiveSynthVoice>::new_with_voice(Self::VOICE_CAPACITY, || {
             SubtractiveSynthVoice::new_with(
@@ -448,6 +634,88 @@ impl SubtractiveSynthCore {
     pub fn preset_name(&self) -> Option<&String> {
         self.preset_name.as_ref()
     }
+
+    /// The names of the built-in patches, in the order that MIDI program
+    /// changes select them. Program 0 is the first patch.
+    pub fn patch_bank() -> &'static [&'static str] {
+        &Self::PROGRAM_PATCHES
+    }
+
+    fn parsed_patch_bank() -> &'static [Option<SubtractiveSynthSound>] {
+        PATCH_BANK.get_or_init(|| {
+            Self::PROGRAM_PATCHES
+                .iter()
+                .map(|name| {
+                    PATCH_DIR
+                        .get_file(format!("{name}.json"))
+                        .and_then(|file| file.contents_utf8())
+                        .and_then(|json| serde_json::from_str(json).ok())
+                })
+                .collect()
+        })
+    }
+
+    /// Replaces the current sound with the built-in patch that the given MIDI
+    /// program selects from [SubtractiveSynthCore::patch_bank()]. Settings
+    /// that belong to the performance rather than the sound, like the pitch
+    /// bend range, stay as they are.
+    ///
+    /// This happens on the audio thread, so the bank is parsed when the synth
+    /// is built, and the new sound is copied into the existing voices rather
+    /// than replacing them.
+    pub fn load_program(&mut self, program: u7) -> Result<(), ProgramChangeError> {
+        match Self::parsed_patch_bank().get(program.as_int() as usize) {
+            Some(Some(sound)) => {
+                self.apply_sound(sound);
+                Ok(())
+            }
+            Some(None) => Err(ProgramChangeError::UnreadablePatch),
+            None => Err(ProgramChangeError::NoSuchProgram),
+        }
+    }
+
+    // The parts of the sound were deserialized but never configured, so each
+    // one is brought up to date before it's passed on to the voices.
+    fn apply_sound(&mut self, sound: &SubtractiveSynthSound) {
+        let sample_rate = self.sample_rate();
+        self.preset_name.clone_from(&sound.preset_name);
+        self.oscillator_1.clone_from(&sound.oscillator_1);
+        self.oscillator_1.update_sample_rate(sample_rate);
+        self.notify_change_oscillator_1();
+        self.oscillator_2.clone_from(&sound.oscillator_2);
+        self.oscillator_2.update_sample_rate(sample_rate);
+        self.notify_change_oscillator_2();
+        self.set_oscillator_2_sync(sound.oscillator_2_sync);
+        self.set_oscillator_mix(sound.oscillator_mix);
+        self.amp_envelope.clone_from(&sound.amp_envelope);
+        self.amp_envelope.update_sample_rate(sample_rate);
+        self.notify_change_amp_envelope();
+        self.dca = sound.dca;
+        self.notify_change_dca();
+        self.lfo.clone_from(&sound.lfo);
+        self.lfo.update_sample_rate(sample_rate);
+        self.notify_change_lfo();
+        self.lfo_routing = sound.lfo_routing;
+        self.inner
+            .voices_mut()
+            .for_each(|v| v.lfo_routing = sound.lfo_routing);
+        self.set_lfo_depth(sound.lfo_depth);
+        self.filter.clone_from(&sound.filter);
+        self.filter.after_deser();
+        self.filter.update_sample_rate(sample_rate);
+        self.notify_change_filter();
+        self.set_filter_cutoff_start(sound.filter_cutoff_start);
+        self.set_filter_cutoff_end(sound.filter_cutoff_end);
+        self.filter_envelope.clone_from(&sound.filter_envelope);
+        self.filter_envelope.update_sample_rate(sample_rate);
+        self.notify_change_filter_envelope();
+    }
+
+    #[allow(missing_docs)]
+    pub fn set_pitch_bend_range(&mut self, pitch_bend_range: PitchBendRange) {
+        self.pitch_bend_range = pitch_bend_range;
+        self.inner.set_pitch_bend_range(pitch_bend_range);
+    }
 }
 impl Generates<StereoSample> for SubtractiveSynthCore {
     fn generate(&mut self, values: &mut [StereoSample]) -> bool {
@@ -460,6 +728,11 @@ impl Serializable for SubtractiveSynthCore {
     fn after_deser(&mut self) {
         self.inner =
             Synthesizer::<SubtractiveSynthVoice>::new_with(Box::new(self.new_voice_store()));
+        self.inner.set_pitch_bend_range(self.pitch_bend_range);
+
+        // Parse the bank now so that the first program change doesn't have to
+        // do it on the audio thread.
+        let _ = Self::parsed_patch_bank();
     }
 }
 impl Configurable for SubtractiveSynthCore {
@@ -482,17 +755,22 @@ impl HandlesMidi for SubtractiveSynthCore {
         midi_messages_fn: &mut MidiMessagesFn,
     ) {
         match message {
-            #[allow(unused_variables)]
             MidiMessage::ProgramChange { program } => {
-                todo!()
-                // if let Some(program) = GeneralMidiProgram::from_u8(program.as_int()) {
-                //     if let Ok(_preset) = SubtractiveSynth::general_midi_preset(&program) {
-                //         //  self.preset = preset;
-                //     } else {
-                //         println!("unrecognized patch from MIDI program change: {}", &program);
-                //     }
-                // }
-                // None
+                // A program outside the bank, or a patch that couldn't be
+                // read, leaves the current sound alone.
+                let _ = self.load_program(program);
+            }
+            // Volume and pan go to the Dca so that they show up alongside the
+            // patch's own settings.
+            MidiMessage::Controller { controller, value } if controller.as_int() == 7 => {
+                self.dca
+                    .set_gain(Normal::from(value.as_int() as f64 / 127.0));
+                self.notify_change_dca();
+            }
+            MidiMessage::Controller { controller, value } if controller.as_int() == 10 => {
+                self.dca
+                    .set_pan(Synthesizer::<SubtractiveSynthVoice>::cc_to_pan(value));
+                self.notify_change_dca();
             }
             _ => self
                 .inner
@@ -585,3 +863,114 @@ impl SubtractiveSynthCore {
             .for_each(|v| v.set_filter_cutoff_end(self.filter_cutoff_end));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subtractive_program_change() {
+        let bank = SubtractiveSynthCore::patch_bank();
+        assert!(
+            bank.len() > 100,
+            "The bank should hold the built-in patches"
+        );
+        assert!(
+            bank.iter()
+                .all(|name| SubtractiveSynthCore::load_internal_patch(name).is_ok()),
+            "Every program should name a readable patch"
+        );
+        assert_eq!(
+            SubtractiveSynthCore::default().load_program(127.into()),
+            Err(ProgramChangeError::NoSuchProgram)
+        );
+
+        let mut synth = SubtractiveSynthCore::load_internal_patch(&bank[0]).unwrap();
+        synth.update_sample_rate(SampleRate(22050));
+        synth.set_pitch_bend_range(PitchBendRange(7));
+        synth.handle_midi_message(
+            MidiChannel::default(),
+            MidiMessage::ProgramChange { program: 1.into() },
+            &mut |_, _| {},
+        );
+        let mut expected = SubtractiveSynthCore::load_internal_patch(&bank[1]).unwrap();
+        expected.set_pitch_bend_range(PitchBendRange(7));
+        assert_eq!(
+            serde_json::to_string(&synth).unwrap(),
+            serde_json::to_string(&expected).unwrap(),
+            "Program 1 should select the second patch, keeping the pitch bend range"
+        );
+        assert_eq!(synth.sample_rate(), SampleRate(22050));
+
+        synth.handle_midi_message(
+            MidiChannel::default(),
+            MidiMessage::ProgramChange {
+                program: 127.into(),
+            },
+            &mut |_, _| {},
+        );
+        assert_eq!(
+            serde_json::to_string(&synth).unwrap(),
+            serde_json::to_string(&expected).unwrap(),
+            "A program outside the bank should leave the sound alone"
+        );
+
+        // The voices that were already allocated should play the new patch
+        // just as a freshly loaded one does.
+        expected.update_sample_rate(SampleRate(22050));
+        let note_on = MidiMessage::NoteOn {
+            key: 60.into(),
+            vel: 127.into(),
+        };
+        synth.handle_midi_message(MidiChannel::default(), note_on, &mut |_, _| {});
+        expected.handle_midi_message(MidiChannel::default(), note_on, &mut |_, _| {});
+        let mut actual_samples = [StereoSample::SILENCE; 512];
+        let mut expected_samples = [StereoSample::SILENCE; 512];
+        synth.generate(&mut actual_samples);
+        expected.generate(&mut expected_samples);
+        assert!(
+            actual_samples.iter().any(|s| *s != StereoSample::SILENCE),
+            "The new patch should sound"
+        );
+        assert!(
+            actual_samples
+                .iter()
+                .zip(expected_samples.iter())
+                .all(|(a, e)| (a.0 .0 - e.0 .0).abs() < 0.000001
+                    && (a.1 .0 - e.1 .0).abs() < 0.000001),
+            "The voices should sound like the new patch"
+        );
+    }
+
+    #[test]
+    fn subtractive_velocity_scales_loudness() {
+        let bank = SubtractiveSynthCore::patch_bank();
+        let peak_for_velocity = |velocity: u8| {
+            let mut synth = SubtractiveSynthCore::load_internal_patch(&bank[1]).unwrap();
+            synth.update_sample_rate(SampleRate(22050));
+            synth.handle_midi_message(
+                MidiChannel::default(),
+                MidiMessage::NoteOn {
+                    key: 60.into(),
+                    vel: velocity.into(),
+                },
+                &mut |_, _| {},
+            );
+            let mut samples = [StereoSample::SILENCE; 512];
+            synth.generate(&mut samples);
+            samples
+                .iter()
+                .map(|s| s.0 .0.abs().max(s.1 .0.abs()))
+                .fold(0.0, SampleType::max)
+        };
+
+        let loud = peak_for_velocity(127);
+        let soft = peak_for_velocity(32);
+        assert!(loud > 0.0, "A full-velocity note should sound");
+        assert!(soft > 0.0, "A soft note should still sound");
+        assert!(
+            (soft - loud * 32.0 / 127.0).abs() < loud * 0.0001,
+            "A note's peak level should be proportional to its velocity ({soft} vs. {loud})"
+        );
+    }
+}
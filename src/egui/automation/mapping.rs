@@ -0,0 +1,56 @@
+// Copyright (c) 2024 Mike Tsao
+
+use crate::prelude::*;
+use eframe::egui::{Slider, Widget};
+use strum::IntoEnumIterator;
+
+/// An egui widget that edits the [ControlMapping] of a [ControlLink]. None
+/// means that the link passes values through unchanged.
+#[derive(Debug)]
+pub struct ControlMappingWidget<'a> {
+    mapping: &'a mut Option<ControlMapping>,
+}
+impl<'a> ControlMappingWidget<'a> {
+    fn new(mapping: &'a mut Option<ControlMapping>) -> Self {
+        Self { mapping }
+    }
+
+    /// Instantiates a widget suitable for adding to a [Ui](eframe::egui::Ui).
+    pub fn widget(mapping: &'a mut Option<ControlMapping>) -> impl Widget + 'a {
+        move |ui: &mut eframe::egui::Ui| ControlMappingWidget::new(mapping).ui(ui)
+    }
+}
+impl<'a> Widget for ControlMappingWidget<'a> {
+    fn ui(self, ui: &mut eframe::egui::Ui) -> eframe::egui::Response {
+        let original = *self.mapping;
+        let mut response = ui
+            .vertical(|ui| {
+                let mut is_mapped = self.mapping.is_some();
+                if ui.checkbox(&mut is_mapped, "Map values").changed() {
+                    *self.mapping = if is_mapped {
+                        Some(ControlMapping::default())
+                    } else {
+                        None
+                    };
+                }
+                if let Some(mapping) = self.mapping.as_mut() {
+                    ui.add(Slider::new(&mut mapping.min.0, 0.0..=1.0).text("Min"));
+                    ui.add(Slider::new(&mut mapping.max.0, 0.0..=1.0).text("Max"));
+                    ui.checkbox(&mut mapping.invert, "Invert");
+                    ui.separator();
+                    for curve in ControlCurve::iter() {
+                        ui.radio_value(&mut mapping.curve, curve, curve.to_string());
+                    }
+                    ui.separator();
+                    for polarity in ControlPolarity::iter() {
+                        ui.radio_value(&mut mapping.polarity, polarity, polarity.to_string());
+                    }
+                }
+            })
+            .response;
+        if *self.mapping != original {
+            response.mark_changed();
+        }
+        response
+    }
+}
@@ -1,5 +1,9 @@
 // Copyright (c) 2024 Mike Tsao
 
-pub use signal_path::{SignalPathWidget, SignalPathWidgetAction, TargetInstrument};
+pub use {
+    mapping::ControlMappingWidget,
+    signal_path::{SignalPathWidget, SignalPathWidgetAction, TargetInstrument},
+};
 
+mod mapping;
 mod signal_path;
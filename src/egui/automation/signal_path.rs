@@ -5,11 +5,12 @@ use eframe::{
     egui::{
         Align2, FontId, Response, Sense,
         Shape::{self, LineSegment},
-        Vec2, Widget,
+        Slider, Vec2, Widget,
     },
     emath::RectTransform,
     epaint::{pos2, Rect},
 };
+use strum::IntoEnumIterator;
 
 /// A container of information about a Controllable target that's used to help
 /// egui draw the context menu for [SignalPath]s.
@@ -139,6 +140,8 @@ impl<'a> eframe::egui::Widget for SignalPathWidget<'a> {
         let mut point_shapes = Vec::default();
         let mut prior_when = None;
         let mut prior_value = None;
+        let mut prior_curve = SignalCurve::default();
+        let mut is_curve_changed = false;
         let mut point_to_remove = None;
         let mut point_to_add = None;
 
@@ -203,10 +206,26 @@ impl<'a> eframe::egui::Widget for SignalPathWidget<'a> {
                     (start_pos, end_pos)
                 };
 
-                shapes.push(LineSegment {
-                    points: [start_pos, end_pos],
-                    stroke: stroke.into(),
-                });
+                if prior_curve == SignalCurve::Linear {
+                    shapes.push(LineSegment {
+                        points: [start_pos, end_pos],
+                        stroke: stroke.into(),
+                    });
+                } else {
+                    const CURVE_SEGMENTS: usize = 32;
+                    let curve_points = (0..=CURVE_SEGMENTS)
+                        .map(|i| {
+                            let percent = i as f32 / CURVE_SEGMENTS as f32;
+                            pos2(
+                                start_pos.x + (end_pos.x - start_pos.x) * percent,
+                                start_pos.y
+                                    + (end_pos.y - start_pos.y)
+                                        * prior_curve.shape(percent as f64) as f32,
+                            )
+                        })
+                        .collect();
+                    shapes.push(Shape::line(curve_points, stroke));
+                }
 
                 const CONTROL_POINT_RADIUS: f32 = 6.0;
                 const CONTROL_POINT_VISUAL_RADIUS: f32 = 4.0;
@@ -219,6 +238,23 @@ impl<'a> eframe::egui::Widget for SignalPathWidget<'a> {
                         ui.close_menu();
                         point_to_remove = Some(point.clone());
                     }
+                    ui.separator();
+                    for curve in SignalCurve::iter() {
+                        let is_selected = core::mem::discriminant(&point.curve)
+                            == core::mem::discriminant(&curve);
+                        if ui.radio(is_selected, curve.to_string()).clicked() && !is_selected {
+                            point.curve = curve;
+                            is_curve_changed = true;
+                        }
+                    }
+                    if let SignalCurve::SCurve(tension) = &mut point.curve {
+                        if ui
+                            .add(Slider::new(&mut tension.0, 0.0..=1.0).text("Tension"))
+                            .changed()
+                        {
+                            is_curve_changed = true;
+                        }
+                    }
                 });
                 if point_response.dragged() {
                     let updated_point_pos = end_pos + point_response.drag_delta();
@@ -269,7 +305,11 @@ impl<'a> eframe::egui::Widget for SignalPathWidget<'a> {
 
                 prior_when = Some(point.when);
                 prior_value = Some(point.value);
+                prior_curve = point.curve;
             });
+        if is_curve_changed {
+            self.signal_path.after_deser();
+        }
         if let Some(point) = point_to_remove {
             self.signal_path.remove_point(point);
         }
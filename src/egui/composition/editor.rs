@@ -318,6 +318,7 @@ impl<'a> eframe::egui::Widget for ComposerWidget<'a> {
         }
 
         // Check for mouse input.
+        let mut hovered_velocity = None;
         if let Some(hover_pos) = response.hover_pos() {
             // Handle zoom wheel
             let zoom_factor = ui.input(|i| i.zoom_delta());
@@ -344,9 +345,34 @@ impl<'a> eframe::egui::Widget for ComposerWidget<'a> {
                 response.mark_changed();
             } else if response.clicked_by(PointerButton::Secondary) {
                 let note_to_remove = Self::create_note(note, section);
-                self.notes.retain(|n| *n != note_to_remove);
+                self.notes.retain(|n| !n.is_same_placement(&note_to_remove));
                 response.mark_changed();
             }
+
+            // Scrolling over a note (without zooming) changes its velocity.
+            let hover_time = MusicalTime::new_with_fractional_beats(hover_pos_data.x as f64 / 4.0);
+            if let Some(hovered_note) = self
+                .notes
+                .iter_mut()
+                .find(|n| n.key == note as u8 && n.extent.contains(&hover_time))
+            {
+                let scroll_delta = ui.input(|i| i.raw_scroll_delta.y);
+                if zoom_factor == 1.0 && scroll_delta != 0.0 {
+                    hovered_note.velocity = if scroll_delta > 0.0 {
+                        hovered_note
+                            .velocity
+                            .saturating_add(Self::VELOCITY_STEP)
+                            .min(Note::MAX_VELOCITY)
+                    } else {
+                        hovered_note
+                            .velocity
+                            .saturating_sub(Self::VELOCITY_STEP)
+                            .max(Note::MIN_VELOCITY)
+                    };
+                    response.mark_changed();
+                }
+                hovered_velocity = Some(hovered_note.velocity);
+            }
         }
         if response.dragged_by(PointerButton::Primary) {
             let vert_only_drag_delta = vec2(0.0, response.drag_delta().y);
@@ -367,10 +393,11 @@ impl<'a> eframe::egui::Widget for ComposerWidget<'a> {
                     note.extent.end().total_units() as f32,
                     (note.key + 1) as f32,
                 );
+            // Quieter notes are drawn fainter.
             shapes.push(Shape::rect_filled(
                 Rect::from_two_pos(lt, br),
                 Rounding::default(),
-                fg_color,
+                fg_color.linear_multiply(0.25 + 0.75 * note.velocity.min(127) as f32 / 127.0),
             ));
         }
 
@@ -378,6 +405,9 @@ impl<'a> eframe::egui::Widget for ComposerWidget<'a> {
         ui.painter_at(rect).extend(shapes);
         fill_remaining_ui_space(ui);
 
+        if let Some(velocity) = hovered_velocity {
+            response = response.on_hover_text(format!("Velocity: {velocity}"));
+        }
         response
     }
 }
@@ -430,14 +460,15 @@ impl<'a> ComposerWidget<'a> {
         self
     }
 
+    /// How much one scroll step changes a note's velocity.
+    const VELOCITY_STEP: u8 = 8;
+
     fn create_note(midi_note: MidiNote, section: usize) -> Note {
-        Note {
-            key: midi_note as u8,
-            extent: TimeRange::new_with_start_and_duration(
-                MusicalTime::new_with_fractional_beats(section as f64 / 4.0),
-                MusicalTime::DURATION_QUARTER,
-            ),
-        }
+        Note::new_with(
+            midi_note as u8,
+            MusicalTime::new_with_fractional_beats(section as f64 / 4.0),
+            MusicalTime::DURATION_QUARTER,
+        )
     }
 
     /// Instantiates a widget suitable for adding to a [Ui](eframe::egui::Ui).
@@ -12,7 +12,9 @@ pub use {
     audio::{
         analyze_spectrum, FrequencyDomainWidget, FrequencyWidget, TimeDomainWidget, WaveformWidget,
     },
-    automation::{SignalPathWidget, SignalPathWidgetAction, TargetInstrument},
+    automation::{
+        ControlMappingWidget, SignalPathWidget, SignalPathWidgetAction, TargetInstrument,
+    },
     chrome::{ControlBar, ControlBarAction, ControlBarWidget, TransportWidget},
     composition::{ComposerWidget, NoteLabeler, TimeLabeler},
     controllers::{ArpeggiatorWidget, LfoControllerWidget, NoteSequencerWidget},
@@ -7,7 +7,7 @@ use super::{
     LegendWidget,
 };
 use crate::{
-    orchestration::{Project, TrackViewMode},
+    orchestration::{Project, ProjectCommand, ProjectCommandOutcome, TrackViewMode},
     prelude::*,
 };
 use eframe::{egui::Widget, epaint::Galley};
@@ -107,10 +107,12 @@ impl<'a> eframe::egui::Widget for ProjectWidget<'a> {
                                 self.project.advance_track_view_mode(track_uid);
                             }
                             TrackWidgetAction::CreateAutomationLane(track_uid) => {
-                                if let Ok(path_uid) = self.project.add_path(
-                                    track_uid,
-                                    SignalPathBuilder::default().build().unwrap(),
-                                ) {
+                                if let Ok(ProjectCommandOutcome::Path(path_uid)) =
+                                    self.project.execute(ProjectCommand::AddPath(
+                                        track_uid,
+                                        SignalPathBuilder::default().build().unwrap(),
+                                    ))
+                                {
                                     self.project.set_track_view_mode(
                                         track_uid,
                                         TrackViewMode::Control(path_uid),
@@ -118,12 +120,12 @@ impl<'a> eframe::egui::Widget for ProjectWidget<'a> {
                                 }
                             }
                             TrackWidgetAction::ArrangePattern(pattern_uid, position) => {
-                                let _ = self.project.arrange_pattern(
+                                let _ = self.project.execute(ProjectCommand::ArrangePattern(
                                     track_uid,
                                     pattern_uid,
                                     None,
                                     position,
-                                );
+                                ));
                                 switch_to_composition = true;
                             }
                             TrackWidgetAction::MoveArrangement(
@@ -131,57 +133,66 @@ impl<'a> eframe::egui::Widget for ProjectWidget<'a> {
                                 position,
                                 is_shift_pressed,
                             ) => {
-                                let _ = self.project.move_arrangement(
+                                let _ = self.project.execute(ProjectCommand::MoveArrangement(
                                     track_uid,
                                     arrangement_uid,
                                     position,
                                     is_shift_pressed,
-                                );
+                                ));
                                 switch_to_composition = true;
                             }
                             TrackWidgetAction::LinkPath(path_uid, uid, param) => {
-                                let _ = self.project.link_path(path_uid, uid, param);
-                                self.project.regenerate_signal_chain(track_uid);
+                                let _ = self
+                                    .project
+                                    .execute(ProjectCommand::LinkPath(path_uid, uid, param));
                             }
                             TrackWidgetAction::UnlinkPath(path_uid, uid, param) => {
-                                self.project.unlink_path(path_uid, uid, param);
-                                self.project.regenerate_signal_chain(track_uid);
+                                let _ = self
+                                    .project
+                                    .execute(ProjectCommand::UnlinkPath(path_uid, uid, param));
+                            }
+                            TrackWidgetAction::SetLinkMapping(source, target, param, mapping) => {
+                                let _ = self.project.execute(ProjectCommand::SetLinkMapping(
+                                    source, target, param, mapping,
+                                ));
+                            }
+                            TrackWidgetAction::LearnMidiCc(uid, param) => {
+                                self.project.learn_midi_cc(Some((uid, param)));
                             }
                             TrackWidgetAction::Unarrange(arrangement_uid) => {
-                                self.project.unarrange(track_uid, arrangement_uid);
+                                let _ = self
+                                    .project
+                                    .execute(ProjectCommand::Unarrange(track_uid, arrangement_uid));
                             }
                             TrackWidgetAction::Duplicate(arrangement_uid) => {
-                                if let Ok(new_uid) = self
-                                    .project
-                                    .duplicate_arrangement(track_uid, arrangement_uid)
+                                if let Ok(ProjectCommandOutcome::Arrangement(new_uid)) =
+                                    self.project.execute(ProjectCommand::DuplicateArrangement(
+                                        track_uid,
+                                        arrangement_uid,
+                                    ))
                                 {
                                     self.project.set_new_arrangement_uid(track_uid, new_uid);
                                 }
                             }
                             TrackWidgetAction::AddPattern(position) => {
-                                if let Ok(pattern_uid) = self.project.add_pattern(
-                                    PatternBuilder::default()
-                                        .time_signature(self.project.time_signature())
-                                        .color_scheme(
-                                            self.project
-                                                .composer
-                                                .suggest_next_pattern_color_scheme(),
-                                        )
-                                        .build()
-                                        .unwrap(),
-                                    None,
-                                ) {
-                                    let quantized_position = position
-                                        .quantized_to_measure(&self.project.time_signature());
-                                    if let Ok(new_uid) = self.project.arrange_pattern(
+                                let pattern = PatternBuilder::default()
+                                    .time_signature(self.project.time_signature())
+                                    .color_scheme(
+                                        self.project.composer.suggest_next_pattern_color_scheme(),
+                                    )
+                                    .build()
+                                    .unwrap();
+                                let quantized_position =
+                                    position.quantized_to_measure(&self.project.time_signature());
+                                if let Ok(ProjectCommandOutcome::Arrangement(new_uid)) =
+                                    self.project.execute(ProjectCommand::ArrangeNewPattern(
                                         track_uid,
-                                        pattern_uid,
-                                        None,
+                                        pattern,
                                         quantized_position,
-                                    ) {
-                                        self.project.composer.clear_edited_pattern();
-                                        self.project.set_new_arrangement_uid(track_uid, new_uid);
-                                    }
+                                    ))
+                                {
+                                    self.project.composer.clear_edited_pattern();
+                                    self.project.set_new_arrangement_uid(track_uid, new_uid);
                                 }
                             }
                             TrackWidgetAction::ClearEditPattern => {
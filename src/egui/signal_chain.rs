@@ -1,6 +1,10 @@
 // Copyright (c) 2024 Mike Tsao
 
-use crate::{egui::fill_remaining_ui_space, orchestration::SignalChainItem, prelude::*};
+use crate::{
+    egui::{fill_remaining_ui_space, ControlMappingWidget},
+    orchestration::SignalChainItem,
+    prelude::*,
+};
 use eframe::egui::{Button, Frame, Sense, Widget};
 use strum_macros::Display;
 
@@ -9,6 +13,8 @@ pub enum SignalChainWidgetAction {
     Select(Uid, String),
     Remove(Uid),
     NewDevice(EntityKey),
+    SetLinkMapping(Uid, Uid, ControlIndex, Option<ControlMapping>),
+    LearnMidiCc(Uid, ControlIndex),
 }
 
 pub struct SignalChainWidget<'a> {
@@ -50,6 +56,35 @@ impl<'a> Widget for SignalChainWidget<'a> {
                             ui.close_menu();
                             *self.action = Some(SignalChainWidgetAction::Remove(item.uid));
                         }
+                        if !item.params.is_empty() {
+                            ui.menu_button("MIDI Learn", |ui| {
+                                for (i, name) in item.params.iter().enumerate() {
+                                    if ui.button(name.0.as_str()).clicked() {
+                                        ui.close_menu();
+                                        *self.action = Some(SignalChainWidgetAction::LearnMidiCc(
+                                            item.uid,
+                                            ControlIndex(i),
+                                        ));
+                                    }
+                                }
+                            });
+                        }
+                        if !item.links.is_empty() {
+                            ui.separator();
+                        }
+                        for link in item.links.iter() {
+                            ui.menu_button(link.target_name.as_str(), |ui| {
+                                let mut mapping = link.link.mapping;
+                                if ui.add(ControlMappingWidget::widget(&mut mapping)).changed() {
+                                    *self.action = Some(SignalChainWidgetAction::SetLinkMapping(
+                                        item.uid,
+                                        link.link.uid,
+                                        link.link.param,
+                                        mapping,
+                                    ));
+                                }
+                            });
+                        }
                     });
                     if item_response.clicked() {
                         *self.action =
@@ -181,6 +181,8 @@ pub enum TrackWidgetAction {
     MoveArrangement(ArrangementUid, MusicalTime, bool),
     LinkPath(PathUid, Uid, ControlIndex),
     UnlinkPath(PathUid, Uid, ControlIndex),
+    SetLinkMapping(ControlLinkSource, Uid, ControlIndex, Option<ControlMapping>),
+    LearnMidiCc(Uid, ControlIndex),
     Unarrange(ArrangementUid),
     Duplicate(ArrangementUid),
     AddPattern(MusicalTime),
@@ -503,6 +505,23 @@ impl<'a> Widget for TrackWidget<'a> {
                                     SignalChainWidgetAction::NewDevice(key) => {
                                         *self.action = Some(TrackWidgetAction::NewDevice(key))
                                     }
+                                    SignalChainWidgetAction::SetLinkMapping(
+                                        source_uid,
+                                        target_uid,
+                                        param,
+                                        mapping,
+                                    ) => {
+                                        *self.action = Some(TrackWidgetAction::SetLinkMapping(
+                                            ControlLinkSource::Entity(source_uid),
+                                            target_uid,
+                                            param,
+                                            mapping,
+                                        ))
+                                    }
+                                    SignalChainWidgetAction::LearnMidiCc(uid, param) => {
+                                        *self.action =
+                                            Some(TrackWidgetAction::LearnMidiCc(uid, param))
+                                    }
                                 }
                             }
                         });
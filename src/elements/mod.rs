@@ -8,7 +8,7 @@ pub mod prelude {
     pub use super::{
         generators::{Envelope, EnvelopeBuilder, Oscillator, OscillatorBuilder, Waveform},
         modulators::Dca,
-        synthesizers::Synthesizer,
+        synthesizers::{PitchBendRange, Synthesizer},
         transport::{Transport, TransportBuilder},
         voices::{StealingVoiceStore, VoiceCount, VoicePerNoteStore, VoiceStore},
     };
@@ -16,9 +16,9 @@ pub mod prelude {
 
 pub use generators::{Envelope, EnvelopeBuilder, Oscillator, OscillatorBuilder, Waveform};
 pub use modulators::Dca;
-pub use synthesizers::Synthesizer;
+pub use synthesizers::{PitchBendRange, Synthesizer};
 pub use transport::{Transport, TransportBuilder};
-pub use voices::{StealingVoiceStore, VoiceCount, VoicePerNoteStore, VoiceStore};
+pub use voices::{StealingVoiceStore, SustainPedal, VoiceCount, VoicePerNoteStore, VoiceStore};
 
 /// Building blocks for signal generation.
 mod generators;
@@ -2,7 +2,16 @@
 
 use crate::prelude::*;
 use delegate::delegate;
+use derivative::Derivative;
 use serde::{Deserialize, Serialize};
+use synonym::Synonym;
+
+/// Newtype for how many semitones a pitch-bend wheel moves a note when it's
+/// pushed all the way up or down.
+#[derive(Synonym, Derivative, Deserialize, Serialize)]
+#[derivative(Default)]
+#[synonym(skip(Default))]
+pub struct PitchBendRange(#[derivative(Default(value = "2"))] pub u8);
 
 /// [Synthesizer] provides the smallest possible functional core of a
 /// synthesizer built around [StoresVoices]. A full instrument will typically
@@ -20,9 +29,17 @@ pub struct Synthesizer<V: IsStereoSampleVoice> {
     /// Ranges from -1.0..=1.0. Applies to all notes.
     pitch_bend: f32,
 
+    /// How far a full pitch bend moves the notes.
+    #[serde(default)]
+    pitch_bend_range: PitchBendRange,
+
     /// Ranges from 0..127. Applies to all notes.
     channel_aftertouch: u8,
 
+    /// The mod wheel. Ranges from 0..127. Applies to all notes.
+    #[serde(default)]
+    modulation: u8,
+
     gain: Normal,
 
     pan: BipolarNormal,
@@ -41,6 +58,14 @@ impl<V: IsStereoSampleVoice> Generates<StereoSample> for Synthesizer<V> {
         } else {
             values.fill(StereoSample::default());
         }
+        if self.gain != Normal::maximum() || self.pan != BipolarNormal::zero() {
+            // Pan balances the two channels, leaving the center at full level.
+            let left = self.gain.0 * (1.0 - self.pan.0).min(1.0);
+            let right = self.gain.0 * (1.0 + self.pan.0).min(1.0);
+            values
+                .iter_mut()
+                .for_each(|v| *v = StereoSample::new(v.0 * left, v.1 * right));
+        }
         self.ticks_since_last_midi_input += values.len();
         generated_signal
     }
@@ -82,7 +107,9 @@ impl<V: IsStereoSampleVoice> Synthesizer<V> {
             voice_store: Some(voice_store),
             c: Default::default(),
             pitch_bend: Default::default(),
+            pitch_bend_range: Default::default(),
             channel_aftertouch: Default::default(),
+            modulation: Default::default(),
             gain: Default::default(),
             pan: Default::default(),
             ticks_since_last_midi_input: Default::default(),
@@ -114,14 +141,73 @@ impl<V: IsStereoSampleVoice> Synthesizer<V> {
         }
     }
 
+    pub fn pitch_bend(&self) -> f32 {
+        self.pitch_bend
+    }
+
+    /// Bends every note, including ones that start later. -1.0 and 1.0 bend
+    /// by the full [PitchBendRange] down and up.
     pub fn set_pitch_bend(&mut self, pitch_bend: f32) {
         self.pitch_bend = pitch_bend;
+        self.apply_pitch_bend();
+    }
+
+    pub fn pitch_bend_range(&self) -> PitchBendRange {
+        self.pitch_bend_range
+    }
+
+    pub fn set_pitch_bend_range(&mut self, pitch_bend_range: PitchBendRange) {
+        self.pitch_bend_range = pitch_bend_range;
+        self.apply_pitch_bend();
+    }
+
+    fn apply_pitch_bend(&mut self) {
+        let semitones = self.pitch_bend as f64 * self.pitch_bend_range.0 as f64;
+        if let Some(vs) = self.voice_store.as_mut() {
+            vs.voices_mut().for_each(|v| v.set_pitch_bend(semitones));
+        }
+    }
+
+    pub fn channel_aftertouch(&self) -> u8 {
+        self.channel_aftertouch
     }
 
+    /// Applies channel pressure to every playing note. Notes that start later
+    /// begin with this pressure.
     pub fn set_channel_aftertouch(&mut self, channel_aftertouch: u8) {
         self.channel_aftertouch = channel_aftertouch;
+        if let Some(vs) = self.voice_store.as_mut() {
+            vs.voices_mut()
+                .for_each(|v| v.aftertouch(u7::from(channel_aftertouch)));
+        }
+    }
+
+    pub fn modulation(&self) -> u8 {
+        self.modulation
+    }
+
+    /// Sets the mod wheel, which each kind of voice interprets in its own way.
+    pub fn set_modulation(&mut self, modulation: u8) {
+        self.modulation = modulation;
+        let modulation = Normal::from(modulation as f64 / 127.0);
+        if let Some(vs) = self.voice_store.as_mut() {
+            vs.voices_mut().for_each(|v| v.set_modulation(modulation));
+        }
+    }
+
+    /// Returns the sustain pedal and the other continuous controllers to their
+    /// resting positions. As MIDI's Recommended Practice RP-015 suggests,
+    /// volume and pan are left alone.
+    pub fn reset_controllers(&mut self) {
+        self.set_pitch_bend(0.0);
+        self.set_channel_aftertouch(0);
+        self.set_modulation(0);
+        if let Some(vs) = self.voice_store.as_mut() {
+            vs.set_sustain(false);
+        }
     }
 
+    /// The synthesizer's overall level. MIDI CC 7 (channel volume) sets it.
     pub fn gain(&self) -> Normal {
         self.gain
     }
@@ -130,6 +216,8 @@ impl<V: IsStereoSampleVoice> Synthesizer<V> {
         self.gain = gain;
     }
 
+    /// The balance between the left and right channels. MIDI CC 10 (pan)
+    /// sets it.
     pub fn pan(&self) -> BipolarNormal {
         self.pan
     }
@@ -138,6 +226,17 @@ impl<V: IsStereoSampleVoice> Synthesizer<V> {
         self.pan = pan;
     }
 
+    /// Converts a MIDI pan value, which runs from 0 (left) through 64 (center)
+    /// to 127 (right), to a [BipolarNormal].
+    pub fn cc_to_pan(value: u7) -> BipolarNormal {
+        let value = value.as_int() as f64 - 64.0;
+        BipolarNormal::from(if value < 0.0 {
+            value / 64.0
+        } else {
+            value / 63.0
+        })
+    }
+
     pub fn is_midi_recently_active(&self) -> bool {
         // Last quarter-second
         self.ticks_since_last_midi_input < self.sample_rate().0 / 4
@@ -153,13 +252,11 @@ impl<V: IsStereoSampleVoice> HandlesMidi for Synthesizer<V> {
         if let Some(vs) = self.voice_store.as_mut() {
             match message {
                 MidiMessage::NoteOff { key, vel } => {
-                    if let Ok(voice) = vs.get_voice(&key) {
-                        voice.note_off(vel);
-                    }
+                    vs.note_off(&key, vel);
                 }
                 MidiMessage::NoteOn { key, vel } => {
-                    if let Ok(voice) = vs.get_voice(&key) {
-                        voice.note_on(key, vel);
+                    if let Ok(voice) = vs.note_on(&key, vel) {
+                        voice.aftertouch(u7::from(self.channel_aftertouch));
                     }
                 }
                 MidiMessage::Aftertouch { key, vel } => {
@@ -167,20 +264,34 @@ impl<V: IsStereoSampleVoice> HandlesMidi for Synthesizer<V> {
                         voice.aftertouch(vel);
                     }
                 }
-                MidiMessage::Controller {
-                    controller,
-                    value: _,
-                } => match controller.as_int() {
-                    123 => {
-                        vs.voices_mut().for_each(|v| v.note_off(0.into()));
+                MidiMessage::Controller { controller, value } => match controller.as_int() {
+                    // Modulation wheel
+                    1 => self.set_modulation(value.as_int()),
+                    // Channel volume
+                    7 => self.set_gain(Normal::from(value.as_int() as f64 / 127.0)),
+                    // Pan, with 64 at the center
+                    10 => self.set_pan(Self::cc_to_pan(value)),
+                    // Sustain pedal
+                    64 => vs.set_sustain(value.as_int() >= 64),
+                    // All sound off
+                    120 => {
+                        vs.set_sustain(false);
+                        vs.voices_mut().for_each(|v| v.stop_sound());
                     }
+                    // Reset all controllers
+                    121 => self.reset_controllers(),
+                    // All notes off
+                    123 => vs.all_notes_off(),
                     _ => {}
                 },
-                #[allow(unused_variables)]
-                MidiMessage::ProgramChange { program } => todo!(),
-                #[allow(unused_variables)]
-                MidiMessage::ChannelAftertouch { vel } => todo!(),
-                #[allow(unused_variables)]
+                MidiMessage::ProgramChange { program: _ } => {
+                    // A Synthesizer doesn't know about patches. Instruments
+                    // that have a patch bank handle program changes before
+                    // they get here.
+                }
+                MidiMessage::ChannelAftertouch { vel } => {
+                    self.set_channel_aftertouch(vel.as_int());
+                }
                 MidiMessage::PitchBend { bend } => self.set_pitch_bend(bend.as_f32()),
             }
 
@@ -193,6 +304,7 @@ impl<V: IsStereoSampleVoice> HandlesMidi for Synthesizer<V> {
 mod tests {
     use super::*;
     use crate::{elements::voices::tests::TestVoice, util::MidiUtils};
+    use float_cmp::approx_eq;
 
     #[derive(Debug)]
     pub struct TestSynthesizer {
@@ -251,4 +363,129 @@ mod tests {
             .iter()
             .any(|s| { s != &StereoSample::from(StereoSample::SILENCE) }));
     }
+
+    #[test]
+    fn synthesizer_bends_and_presses() {
+        let mut s = TestSynthesizer::default();
+        s.handle_midi_message(
+            MidiChannel::default(),
+            MidiMessage::PitchBend {
+                bend: midly::PitchBend::max_raise(),
+            },
+            &mut |_, _| {},
+        );
+        assert!(
+            s.inner_synth
+                .voices()
+                .all(|v| approx_eq!(f64, v.pitch_bend, 2.0, epsilon = 0.01)),
+            "A full bend should move every voice by the default range"
+        );
+
+        s.inner_synth.set_pitch_bend_range(PitchBendRange(12));
+        assert!(s.inner_synth.voices().all(|v| approx_eq!(
+            f64,
+            v.pitch_bend,
+            12.0,
+            epsilon = 0.05
+        )));
+
+        s.handle_midi_message(
+            MidiChannel::default(),
+            MidiMessage::ChannelAftertouch { vel: 100.into() },
+            &mut |_, _| {},
+        );
+        assert!(s.inner_synth.voices().all(|v| v.pressure.as_int() == 100));
+
+        s.handle_midi_message(
+            MidiChannel::default(),
+            MidiMessage::ProgramChange { program: 5.into() },
+            &mut |_, _| {},
+        );
+        s.handle_midi_message(
+            MidiChannel::default(),
+            MidiUtils::new_note_on(60, 99),
+            &mut |_, _| {},
+        );
+        assert!(
+            s.inner_synth.voices().all(|v| v.pressure.as_int() == 100),
+            "A new note should start with the current channel pressure"
+        );
+    }
+
+    #[test]
+    fn synthesizer_handles_standard_controllers() {
+        let mut s = TestSynthesizer::default();
+        let mut buffer = [StereoSample::default(); 1];
+        let send_cc = |s: &mut TestSynthesizer, controller: u8, value: u8| {
+            s.handle_midi_message(
+                MidiChannel::default(),
+                MidiMessage::Controller {
+                    controller: controller.into(),
+                    value: value.into(),
+                },
+                &mut |_, _| {},
+            );
+        };
+
+        send_cc(&mut s, 1, 127);
+        assert!(s
+            .inner_synth
+            .voices()
+            .all(|v| v.modulation == Normal::maximum()));
+
+        send_cc(&mut s, 7, 0);
+        assert_eq!(s.inner_synth.gain(), Normal::zero());
+        send_cc(&mut s, 10, 0);
+        assert_eq!(s.inner_synth.pan(), BipolarNormal::minimum());
+        send_cc(&mut s, 10, 64);
+        assert_eq!(s.inner_synth.pan(), BipolarNormal::zero());
+        send_cc(&mut s, 7, 127);
+
+        send_cc(&mut s, 64, 127);
+        s.handle_midi_message(
+            MidiChannel::default(),
+            MidiUtils::new_note_on(60, 99),
+            &mut |_, _| {},
+        );
+        s.generate(&mut buffer);
+        s.handle_midi_message(
+            MidiChannel::default(),
+            MidiUtils::new_note_off(60, 0),
+            &mut |_, _| {},
+        );
+        s.generate(&mut buffer);
+        assert!(
+            s.inner_synth.voices().any(|v| v.is_playing()),
+            "The sustain pedal should hold the note"
+        );
+
+        send_cc(&mut s, 121, 0);
+        s.generate(&mut buffer);
+        assert!(
+            !s.inner_synth.voices().any(|v| v.is_playing()),
+            "Resetting controllers should lift the pedal"
+        );
+        assert!(s
+            .inner_synth
+            .voices()
+            .all(|v| v.modulation == Normal::zero()));
+        assert_eq!(
+            s.inner_synth.gain(),
+            Normal::maximum(),
+            "Resetting controllers shouldn't change the volume"
+        );
+
+        s.handle_midi_message(
+            MidiChannel::default(),
+            MidiUtils::new_note_on(60, 99),
+            &mut |_, _| {},
+        );
+        s.generate(&mut buffer);
+        send_cc(&mut s, 120, 0);
+        s.generate(&mut buffer);
+        assert!(
+            !s.inner_synth.voices().any(|v| v.is_playing()),
+            "All sound off should silence every voice"
+        );
+    }
 }
@@ -1,6 +1,6 @@
 // Copyright (c) 2024 Mike Tsao
 
-use crate::prelude::*;
+use crate::{prelude::*, types::TempoMap};
 use derive_builder::Builder;
 use ensnare_proc_macros::Control;
 use serde::{Deserialize, Serialize};
@@ -10,15 +10,39 @@ use serde::{Deserialize, Serialize};
 #[derive(Clone, Control, Debug, Default, Builder, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct Transport {
-    /// The current global time signature.
+    /// The global time signature at the start of the song.
     #[builder(default)]
     pub time_signature: TimeSignature,
 
-    /// The current beats per minute.
+    /// The beats per minute at the start of the song.
     #[builder(default)]
     #[control]
     pub tempo: Tempo,
 
+    /// Tempo and time-signature changes over the course of the song.
+    #[builder(default)]
+    #[serde(default)]
+    pub tempo_map: TempoMap,
+
+    /// If set, playback that reaches the end of this range jumps back to its
+    /// start.
+    #[builder(default)]
+    #[serde(default)]
+    pub loop_range: Option<TimeRange>,
+
+    /// How many times playback jumps back to the start of the loop range
+    /// before continuing past its end. None means forever, except when
+    /// rendering offline, where it means once, because an offline render has
+    /// to end.
+    #[builder(default)]
+    #[serde(default)]
+    pub loop_count: Option<usize>,
+
+    /// If set, recording happens only within this range.
+    #[builder(default)]
+    #[serde(default)]
+    pub punch_range: Option<TimeRange>,
+
     #[builder(setter(skip))]
     #[serde(skip)]
     e: TransportEphemerals,
@@ -34,11 +58,23 @@ pub struct TransportEphemerals {
     sample_rate: SampleRate,
 
     is_performing: bool,
+
+    /// How many times playback has jumped back to the start of the loop range.
+    loops_completed: usize,
+
+    /// Whether the performance is being rendered offline rather than played
+    /// live.
+    is_rendering_offline: bool,
 }
 
 impl PartialEq for Transport {
     fn eq(&self, other: &Self) -> bool {
-        self.time_signature == other.time_signature && self.tempo == other.tempo
+        self.time_signature == other.time_signature
+            && self.tempo == other.tempo
+            && self.tempo_map == other.tempo_map
+            && self.loop_range == other.loop_range
+            && self.loop_count == other.loop_count
+            && self.punch_range == other.punch_range
     }
 }
 impl HandlesMidi for Transport {}
@@ -46,10 +82,21 @@ impl Transport {
     /// Advances the clock by the given number of frames. Returns the time range
     /// from the prior time to now.
     pub fn advance(&mut self, frames: usize) -> TimeRange {
+        // The tempo field is public, so it might have changed without the
+        // tempo map hearing about it. This is cheap if it hasn't, and it
+        // means that conversions during this slice use the cached segments.
+        self.tempo_map.update_initial_tempo(self.tempo);
+
         // Calculate the work time range. Note that the range can be zero, which
         // will happen if frames advance faster than MusicalTime units.
         let new_frames = self.e.current_frame + frames;
-        let new_time = MusicalTime::new_with_frames(self.tempo, self.e.sample_rate, new_frames);
+        let mut new_time = self.frames_to_time(new_frames);
+        if let Some(loop_range) = self.active_loop_range() {
+            // Don't let rounding carry us past the end of the loop.
+            if self.e.current_time < loop_range.end() {
+                new_time = new_time.min(loop_range.end());
+            }
+        }
         let length = if new_time >= self.e.current_time {
             new_time - self.e.current_time
         } else {
@@ -89,6 +136,120 @@ impl Transport {
         self.time_signature
     }
 
+    /// Returns the number of frames between the start of the song and the
+    /// current time.
+    pub fn current_frame(&self) -> usize {
+        self.e.current_frame
+    }
+
+    /// Returns true if time is advancing.
+    pub fn is_performing(&self) -> bool {
+        self.e.is_performing
+    }
+
+    /// Returns the number of frames that can be generated before playback
+    /// reaches the end of the loop range and needs to jump back with
+    /// [Transport::wrap_loop()]. Returns None if playback isn't headed for a
+    /// jump.
+    ///
+    /// Callers should split their work at this point, so that no single
+    /// [TimeRange] from [Transport::advance()] crosses the end of the loop.
+    pub fn frames_until_loop_end(&self) -> Option<usize> {
+        if !self.e.is_performing {
+            return None;
+        }
+        if let Some(loop_range) = self.active_loop_range() {
+            if self.e.current_time < loop_range.end() {
+                return Some(
+                    self.time_to_frames(loop_range.end())
+                        .saturating_sub(self.e.current_frame),
+                );
+            }
+        }
+        None
+    }
+
+    /// Jumps back to the start of the loop range. Does nothing if there isn't
+    /// an active loop range.
+    pub fn wrap_loop(&mut self) {
+        if let Some(loop_range) = self.active_loop_range() {
+            self.e.current_time = loop_range.start();
+            self.e.current_frame = self.time_to_frames(loop_range.start());
+            self.e.loops_completed += 1;
+        }
+    }
+
+    /// Returns true if playback will eventually jump back to the start of the
+    /// loop range, which means that the performance won't end on its own.
+    pub fn is_looping(&self) -> bool {
+        self.active_loop_range()
+            .is_some_and(|loop_range| self.e.current_time < loop_range.end())
+    }
+
+    /// Tells the transport whether the performance is being rendered offline,
+    /// which affects how a loop range without a loop count behaves. See
+    /// [Transport::loop_count].
+    pub fn set_is_rendering_offline(&mut self, is_rendering_offline: bool) {
+        self.e.is_rendering_offline = is_rendering_offline;
+    }
+
+    /// Returns true if recording should happen at the current time, according
+    /// to the punch range.
+    pub fn is_punched_in(&self) -> bool {
+        match self.punch_range.as_ref() {
+            Some(punch_range) => punch_range.contains(&self.e.current_time),
+            None => true,
+        }
+    }
+
+    // The loop range, if looping is still in effect. A loop that's too short
+    // to last a single frame would never let time advance, so it's ignored.
+    fn active_loop_range(&self) -> Option<TimeRange> {
+        if let Some(loop_range) = self.loop_range.as_ref() {
+            let loops_remain = match self.loop_count {
+                Some(loop_count) => self.e.loops_completed < loop_count,
+                None => !self.e.is_rendering_offline || self.e.loops_completed < 1,
+            };
+            if loops_remain
+                && self.time_to_frames(loop_range.start()) < self.time_to_frames(loop_range.end())
+            {
+                return Some(loop_range.clone());
+            }
+        }
+        None
+    }
+
+    /// Returns the tempo in effect at the given time, according to the
+    /// [TempoMap].
+    pub fn tempo_at(&self, time: MusicalTime) -> Tempo {
+        self.tempo_map.tempo_at(self.tempo, time)
+    }
+
+    /// Returns the time signature in effect at the given time, according to
+    /// the [TempoMap].
+    pub fn time_signature_at(&self, time: MusicalTime) -> TimeSignature {
+        self.tempo_map.time_signature_at(self.time_signature, time)
+    }
+
+    /// Returns the tempo in effect at the current time.
+    pub fn current_tempo(&self) -> Tempo {
+        self.tempo_at(self.e.current_time)
+    }
+
+    /// Converts a frame count (from the start of the song) to [MusicalTime],
+    /// honoring the [TempoMap].
+    pub fn frames_to_time(&self, frames: usize) -> MusicalTime {
+        self.tempo_map
+            .frames_to_time(self.tempo, self.e.sample_rate, frames)
+    }
+
+    /// Converts [MusicalTime] to a frame count (from the start of the song),
+    /// honoring the [TempoMap].
+    pub fn time_to_frames(&self, time: MusicalTime) -> usize {
+        self.tempo_map
+            .time_to_frames(self.tempo, self.e.sample_rate, time)
+    }
+
     // Don't delete this! The #[derive(Control)] macro expects exactly this
     // method name.
     #[allow(missing_docs)]
@@ -96,7 +257,11 @@ impl Transport {
         self.update_tempo(tempo)
     }
 }
-impl Serializable for Transport {}
+impl Serializable for Transport {
+    fn after_deser(&mut self) {
+        self.tempo_map.update_initial_tempo(self.tempo);
+    }
+}
 impl Configurable for Transport {
     fn sample_rate(&self) -> SampleRate {
         self.e.sample_rate
@@ -111,7 +276,14 @@ impl Configurable for Transport {
     }
 
     fn update_tempo(&mut self, tempo: Tempo) {
+        // A tempo of zero would stop time, and the tempo map would divide by
+        // it, so an out-of-range tempo (such as a control at its minimum) is
+        // ignored.
+        if !tempo.is_valid() {
+            return;
+        }
         self.tempo = tempo;
+        self.tempo_map.update_initial_tempo(tempo);
     }
 
     fn time_signature(&self) -> TimeSignature {
@@ -157,5 +329,68 @@ impl Controls for Transport {
     fn skip_to_start(&mut self) {
         self.e.current_time = MusicalTime::default();
         self.e.current_frame = Default::default();
+        self.e.loops_completed = Default::default();
+    }
+
+    fn seek(&mut self, position: MusicalTime) {
+        self.e.current_time = position;
+        self.e.current_frame = self.time_to_frames(position);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{TempoChange, TempoTransition};
+
+    #[test]
+    fn transport_ignores_invalid_tempo() {
+        let mut transport = Transport::default();
+        transport.update_sample_rate(SampleRate(100));
+        transport.set_tempo(Tempo(60.0));
+        transport.set_tempo(Tempo(0.0));
+        assert_eq!(transport.tempo(), Tempo(60.0));
+        transport.set_tempo(Tempo(ParameterType::NAN));
+        transport.set_tempo(Tempo(Tempo::MAX_VALUE * 2.0));
+        assert_eq!(
+            transport.tempo(),
+            Tempo(60.0),
+            "An out-of-range tempo shouldn't replace a good one"
+        );
+        assert_eq!(transport.time_to_frames(MusicalTime::ONE_BEAT), 100);
+    }
+
+    #[test]
+    fn transport_honors_tempo_map() {
+        let mut transport = Transport::default();
+        transport.update_tempo(Tempo(60.0));
+        transport.update_sample_rate(SampleRate(100));
+        transport
+            .tempo_map
+            .add_tempo_change(TempoChange {
+                time: MusicalTime::ONE_BEAT,
+                tempo: Tempo(120.0),
+                transition: TempoTransition::Jump,
+            })
+            .unwrap();
+        transport.play();
+
+        for _ in 0..100 {
+            transport.advance(1);
+        }
+        assert_eq!(transport.current_time(), MusicalTime::ONE_BEAT);
+        assert_eq!(transport.current_tempo(), Tempo(120.0));
+        for _ in 0..50 {
+            transport.advance(1);
+        }
+        assert_eq!(
+            transport.current_time(),
+            MusicalTime::new_with_beats(2),
+            "The second beat should take half as long as the first"
+        );
+        assert_eq!(
+            transport.time_to_frames(MusicalTime::new_with_beats(2)),
+            150
+        );
     }
 }
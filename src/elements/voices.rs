@@ -13,6 +13,52 @@ use synonym::Synonym;
 #[synonym(skip(Default))]
 pub struct VoiceCount(#[derivative(Default(value = "8"))] pub usize);
 
+/// The state of a sustain pedal. While it's down, it holds the note-offs of
+/// the keys that were let go, and it releases them all when it comes up.
+#[derive(Debug, Derivative)]
+#[derivative(Default)]
+pub struct SustainPedal {
+    is_down: bool,
+    // Indexed by key number, so that holding and forgetting a key is a single
+    // store.
+    #[derivative(Default(value = "[false; 128]"))]
+    held_keys: [bool; 128],
+}
+impl SustainPedal {
+    /// Whether the pedal is down.
+    pub fn is_down(&self) -> bool {
+        self.is_down
+    }
+
+    // Returns true if the pedal holds this key's note-off.
+    pub(crate) fn hold(&mut self, key: u7) -> bool {
+        if self.is_down {
+            self.held_keys[key.as_int() as usize] = true;
+        }
+        self.is_down
+    }
+
+    // A key that's struck again is no longer waiting for release.
+    pub(crate) fn forget(&mut self, key: u7) {
+        self.held_keys[key.as_int() as usize] = false;
+    }
+
+    pub(crate) fn set(&mut self, is_down: bool) {
+        self.is_down = is_down;
+    }
+
+    // Drains the held keys once the pedal is up, one at a time so that the
+    // caller can release each one.
+    pub(crate) fn next_release(&mut self) -> Option<u7> {
+        if self.is_down {
+            return None;
+        }
+        let index = self.held_keys.iter().position(|is_held| *is_held)?;
+        self.held_keys[index] = false;
+        Some(u7::from_int_lossy(index as u8))
+    }
+}
+
 /// A [StoresVoices](crate::traits::StoresVoices) that fails when too many
 /// voices are used simultaneously.
 #[derive(Debug, Default)]
@@ -21,6 +67,7 @@ pub struct VoiceStore<V: IsStereoSampleVoice> {
     voices: Vec<Box<V>>,
     notes_playing: Vec<u7>,
     voice_buffer: GenerationBuffer<StereoSample>,
+    sustain: SustainPedal,
 }
 impl<V: IsStereoSampleVoice> StoresVoices for VoiceStore<V> {
     type Voice = V;
@@ -50,6 +97,19 @@ impl<V: IsStereoSampleVoice> StoresVoices for VoiceStore<V> {
         Err(anyhow!("out of voices"))
     }
 
+    fn voice_for_key(&mut self, key: &u7) -> Option<&mut Box<Self::Voice>> {
+        let index = self.notes_playing.iter().position(|note| *key == *note)?;
+        Some(&mut self.voices[index])
+    }
+
+    fn sustain_pedal(&self) -> &SustainPedal {
+        &self.sustain
+    }
+
+    fn sustain_pedal_mut(&mut self) -> &mut SustainPedal {
+        &mut self.sustain
+    }
+
     fn voices<'a>(&'a self) -> Box<dyn Iterator<Item = &Box<Self::Voice>> + 'a> {
         Box::new(self.voices.iter())
     }
@@ -96,6 +156,7 @@ impl<V: IsStereoSampleVoice> VoiceStore<V> {
             voices: Default::default(),
             notes_playing: Default::default(),
             voice_buffer: Default::default(),
+            sustain: Default::default(),
         }
     }
     fn add_voice(&mut self, voice: Box<V>) {
@@ -123,6 +184,7 @@ pub struct StealingVoiceStore<V: IsStereoSampleVoice> {
     voices: Vec<Box<V>>,
     notes_playing: Vec<u7>,
     voice_buffer: GenerationBuffer<StereoSample>,
+    sustain: SustainPedal,
 }
 impl<V: IsStereoSampleVoice> StoresVoices for StealingVoiceStore<V> {
     type Voice = V;
@@ -159,6 +221,19 @@ impl<V: IsStereoSampleVoice> StoresVoices for StealingVoiceStore<V> {
         Err(anyhow!("out of voices"))
     }
 
+    fn voice_for_key(&mut self, key: &u7) -> Option<&mut Box<Self::Voice>> {
+        let index = self.notes_playing.iter().position(|note| *key == *note)?;
+        Some(&mut self.voices[index])
+    }
+
+    fn sustain_pedal(&self) -> &SustainPedal {
+        &self.sustain
+    }
+
+    fn sustain_pedal_mut(&mut self) -> &mut SustainPedal {
+        &mut self.sustain
+    }
+
     fn voices<'a>(&'a self) -> Box<dyn Iterator<Item = &Box<Self::Voice>> + 'a> {
         Box::new(self.voices.iter())
     }
@@ -205,6 +280,7 @@ impl<V: IsStereoSampleVoice> StealingVoiceStore<V> {
             voices: Default::default(),
             notes_playing: Default::default(),
             voice_buffer: Default::default(),
+            sustain: Default::default(),
         }
     }
 
@@ -235,6 +311,7 @@ pub struct VoicePerNoteStore<V: IsStereoSampleVoice> {
     sample_rate: SampleRate,
     voices: FxHashMap<u7, Box<V>>,
     voice_buffer: GenerationBuffer<StereoSample>,
+    sustain: SustainPedal,
 }
 impl<V: IsStereoSampleVoice> StoresVoices for VoicePerNoteStore<V> {
     type Voice = V;
@@ -254,6 +331,18 @@ impl<V: IsStereoSampleVoice> StoresVoices for VoicePerNoteStore<V> {
         Err(anyhow!("no voice for key {}", key))
     }
 
+    fn voice_for_key(&mut self, key: &u7) -> Option<&mut Box<Self::Voice>> {
+        self.voices.get_mut(key)
+    }
+
+    fn sustain_pedal(&self) -> &SustainPedal {
+        &self.sustain
+    }
+
+    fn sustain_pedal_mut(&mut self) -> &mut SustainPedal {
+        &mut self.sustain
+    }
+
     fn voices<'a>(&'a self) -> Box<dyn Iterator<Item = &Box<Self::Voice>> + 'a> {
         Box::new(self.voices.values())
     }
@@ -301,6 +390,7 @@ impl<V: IsStereoSampleVoice> VoicePerNoteStore<V> {
             sample_rate: Default::default(),
             voices: Default::default(),
             voice_buffer: Default::default(),
+            sustain: Default::default(),
         }
     }
 
@@ -334,6 +424,11 @@ pub(crate) mod tests {
         note_on_key: u7,
         note_on_velocity: u7,
         steal_is_underway: bool,
+        velocity: Normal,
+
+        pub(crate) pitch_bend: f64,
+        pub(crate) pressure: u7,
+        pub(crate) modulation: Normal,
 
         osc_buffer: GenerationBuffer<BipolarNormal>,
         env_buffer: GenerationBuffer<Normal>,
@@ -352,18 +447,27 @@ pub(crate) mod tests {
                 self.note_on_velocity = velocity;
                 self.envelope.trigger_shutdown();
             } else {
+                self.velocity = Normal::from(velocity.as_int() as f64 / 127.0);
                 self.set_frequency_hz(key.into());
                 self.envelope.trigger_attack();
             }
         }
 
-        fn aftertouch(&mut self, _velocity: u7) {
-            todo!()
+        fn aftertouch(&mut self, velocity: u7) {
+            self.pressure = velocity;
         }
 
         fn note_off(&mut self, _velocity: u7) {
             self.envelope.trigger_release();
         }
+
+        fn set_pitch_bend(&mut self, semitones: f64) {
+            self.pitch_bend = semitones;
+        }
+
+        fn set_modulation(&mut self, modulation: Normal) {
+            self.modulation = modulation;
+        }
     }
     impl Generates<StereoSample> for TestVoice {
         fn generate(&mut self, values: &mut [StereoSample]) -> bool {
@@ -386,7 +490,7 @@ pub(crate) mod tests {
                             .zip(self.env_buffer.buffer().iter()),
                     )
                     .for_each(|(dst, (osc, env))| {
-                        let sample: StereoSample = (*osc * *env).into();
+                        let sample: StereoSample = (*osc * *env * self.velocity).into();
                         generated_signal |= sample != StereoSample::default();
                         *dst = sample;
                     });
@@ -422,6 +526,10 @@ pub(crate) mod tests {
                 note_on_key: Default::default(),
                 note_on_velocity: Default::default(),
                 steal_is_underway: Default::default(),
+                velocity: Normal::maximum(),
+                pitch_bend: Default::default(),
+                pressure: Default::default(),
+                modulation: Normal::zero(),
                 osc_buffer: Default::default(),
                 env_buffer: Default::default(),
             }
@@ -476,6 +584,69 @@ pub(crate) mod tests {
         }
     }
 
+    #[test]
+    fn voice_store_sustain_pedal() {
+        let mut voice_store =
+            VoiceStore::<TestVoice>::new_with_voice(VoiceCount(2), || TestVoice::new());
+        let mut buffer = [StereoSample::default(); 1];
+
+        let _ = voice_store.note_on(&u7::from(60), u7::from(127));
+        let _ = voice_store.note_on(&u7::from(61), u7::from(127));
+        voice_store.generate(&mut buffer);
+        assert_eq!(voice_store.active_voice_count(), 2);
+
+        voice_store.set_sustain(true);
+        assert!(voice_store.is_sustaining());
+        voice_store.note_off(&u7::from(60), u7::from(0));
+        voice_store.generate(&mut buffer);
+        assert_eq!(
+            voice_store.active_voice_count(),
+            2,
+            "A held pedal should keep a released key sounding"
+        );
+
+        // Striking the key again while the pedal is down should cancel its
+        // pending release.
+        let _ = voice_store.note_on(&u7::from(60), u7::from(127));
+        voice_store.note_off(&u7::from(61), u7::from(0));
+        voice_store.generate(&mut buffer);
+        voice_store.set_sustain(false);
+        voice_store.generate(&mut buffer);
+        assert_eq!(
+            voice_store.active_voice_count(),
+            1,
+            "Lifting the pedal should release only the keys that were let go"
+        );
+
+        voice_store.note_off(&u7::from(60), u7::from(0));
+        voice_store.generate(&mut buffer);
+        assert_eq!(
+            voice_store.active_voice_count(),
+            0,
+            "With the pedal up, note-offs should take effect immediately"
+        );
+
+        // All notes off (CC 123) is a note-off for every key, so a held pedal
+        // should hold it too.
+        let _ = voice_store.note_on(&u7::from(60), u7::from(127));
+        let _ = voice_store.note_on(&u7::from(61), u7::from(127));
+        voice_store.set_sustain(true);
+        voice_store.all_notes_off();
+        voice_store.generate(&mut buffer);
+        assert_eq!(
+            voice_store.active_voice_count(),
+            2,
+            "A held pedal should keep notes sounding through all notes off"
+        );
+        voice_store.set_sustain(false);
+        voice_store.generate(&mut buffer);
+        assert_eq!(
+            voice_store.active_voice_count(),
+            0,
+            "Lifting the pedal should release the notes that all notes off let go"
+        );
+    }
+
     #[test]
     fn stealing_voice_store_mainline() {
         let mut voice_store =
@@ -0,0 +1,114 @@
+// Copyright (c) 2024 Mike Tsao
+
+use crate::{cores::EnvelopeFollowerCore, prelude::*};
+use ensnare_proc_macros::{
+    InnerConfigurable, InnerControllable, InnerControls, InnerHandlesMidi, InnerSerializable,
+    InnerTransformsAudio, IsEntity, Metadata,
+};
+use serde::{Deserialize, Serialize};
+
+/// Entity wrapper for [EnvelopeFollowerCore]
+#[derive(
+    Debug,
+    Default,
+    InnerConfigurable,
+    InnerControllable,
+    InnerControls,
+    InnerHandlesMidi,
+    InnerSerializable,
+    InnerTransformsAudio,
+    IsEntity,
+    Metadata,
+    Serialize,
+    Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+#[entity(GeneratesStereoSample)]
+pub struct EnvelopeFollower {
+    uid: Uid,
+    inner: EnvelopeFollowerCore,
+}
+impl EnvelopeFollower {
+    #[allow(missing_docs)]
+    pub fn new_with(uid: Uid, inner: EnvelopeFollowerCore) -> Self {
+        Self { uid, inner }
+    }
+}
+
+#[cfg(feature = "egui")]
+impl crate::traits::Displays for EnvelopeFollower {
+    fn ui(&mut self, ui: &mut eframe::egui::Ui) -> eframe::egui::Response {
+        let mut attack = self.inner.attack().0;
+        let mut release = self.inner.release().0;
+        let mut window = self.inner.window().0;
+        let mut threshold = self.inner.threshold().0;
+        let mut ceiling = self.inner.ceiling().0;
+        let mut silent_output = self.inner.silent_output().0;
+        let mut loud_output = self.inner.loud_output().0;
+        let attack_response = ui.add(
+            eframe::egui::Slider::new(&mut attack, Normal::range())
+                .fixed_decimals(2)
+                .text("Attack"),
+        );
+        if attack_response.changed() {
+            self.inner.set_attack(attack.into());
+        }
+        let release_response = ui.add(
+            eframe::egui::Slider::new(&mut release, Normal::range())
+                .fixed_decimals(2)
+                .text("Release"),
+        );
+        if release_response.changed() {
+            self.inner.set_release(release.into());
+        }
+        let window_response = ui.add(
+            eframe::egui::Slider::new(&mut window, Normal::range())
+                .fixed_decimals(2)
+                .text("RMS window"),
+        );
+        if window_response.changed() {
+            self.inner.set_window(window.into());
+        }
+        let threshold_response = ui.add(
+            eframe::egui::Slider::new(&mut threshold, Normal::range())
+                .fixed_decimals(2)
+                .text("Threshold"),
+        );
+        if threshold_response.changed() {
+            self.inner.set_threshold(threshold.into());
+        }
+        let ceiling_response = ui.add(
+            eframe::egui::Slider::new(&mut ceiling, Normal::range())
+                .fixed_decimals(2)
+                .text("Ceiling"),
+        );
+        if ceiling_response.changed() {
+            self.inner.set_ceiling(ceiling.into());
+        }
+        let silent_output_response = ui.add(
+            eframe::egui::Slider::new(&mut silent_output, Normal::range())
+                .fixed_decimals(2)
+                .text("Output when silent"),
+        );
+        if silent_output_response.changed() {
+            self.inner.set_silent_output(silent_output.into());
+        }
+        let loud_output_response = ui.add(
+            eframe::egui::Slider::new(&mut loud_output, Normal::range())
+                .fixed_decimals(2)
+                .text("Output when loud"),
+        );
+        if loud_output_response.changed() {
+            self.inner.set_loud_output(loud_output.into());
+        }
+        attack_response
+            | release_response
+            | window_response
+            | threshold_response
+            | ceiling_response
+            | silent_output_response
+            | loud_output_response
+    }
+}
+#[cfg(not(feature = "egui"))]
+impl crate::traits::Displays for EnvelopeFollower {}
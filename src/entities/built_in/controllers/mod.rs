@@ -1,11 +1,12 @@
 // Copyright (c) 2024 Mike Tsao
 
 pub use {
-    arpeggiator::Arpeggiator, lfo_controller::LfoController,
+    arpeggiator::Arpeggiator, envelope_follower::EnvelopeFollower, lfo_controller::LfoController,
     passthrough::SignalPassthroughController, timer::Timer, trigger::Trigger,
 };
 
 mod arpeggiator;
+mod envelope_follower;
 mod lfo_controller;
 mod passthrough;
 mod timer;
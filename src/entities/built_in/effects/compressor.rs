@@ -71,7 +71,50 @@ impl crate::traits::Displays for Compressor {
         if release_response.changed() {
             self.inner.set_release(release.into());
         };
-        threshold_response | ratio_response | attack_response | release_response
+        let mut knee = self.inner.knee().0;
+        let knee_response = ui.add(
+            eframe::egui::Slider::new(&mut knee, Normal::range())
+                .fixed_decimals(2)
+                .text("Knee"),
+        );
+        if knee_response.changed() {
+            self.inner.set_knee(knee.into());
+        };
+        let mut makeup = self.inner.makeup().0;
+        let makeup_response = ui.add(
+            eframe::egui::Slider::new(&mut makeup, Normal::range())
+                .fixed_decimals(2)
+                .text("Makeup"),
+        );
+        if makeup_response.changed() {
+            self.inner.set_makeup(makeup.into());
+        };
+        let mut detection = self.inner.detection();
+        let detection_response = ui
+            .horizontal(|ui| {
+                use strum::IntoEnumIterator;
+                crate::cores::CompressorDetection::iter()
+                    .map(|d| ui.radio_value(&mut detection, d, d.to_string()))
+                    .reduce(|a, b| a | b)
+                    .unwrap()
+            })
+            .inner;
+        if detection_response.changed() {
+            self.inner.set_detection(detection);
+        }
+        let mut stereo_link = self.inner.stereo_link();
+        let stereo_link_response = ui.checkbox(&mut stereo_link, "Stereo link");
+        if stereo_link_response.changed() {
+            self.inner.set_stereo_link(stereo_link);
+        }
+        threshold_response
+            | ratio_response
+            | attack_response
+            | release_response
+            | knee_response
+            | makeup_response
+            | detection_response
+            | stereo_link_response
     }
 }
 #[cfg(not(feature = "egui"))]
@@ -0,0 +1,90 @@
+// Copyright (c) 2024 Mike Tsao
+
+use crate::{cores::FreeverbCore, prelude::*};
+use ensnare_proc_macros::{
+    InnerConfigurable, InnerControllable, InnerEffect, InnerSerializable, IsEntity, Metadata,
+};
+use serde::{Deserialize, Serialize};
+
+/// Entity wrapper for [FreeverbCore]
+#[derive(
+    Debug,
+    Default,
+    InnerControllable,
+    InnerConfigurable,
+    InnerEffect,
+    InnerSerializable,
+    IsEntity,
+    Metadata,
+    Serialize,
+    Deserialize,
+)]
+#[entity(Controls, GeneratesStereoSample, HandlesMidi, SkipInner)]
+pub struct Freeverb {
+    uid: Uid,
+    inner: FreeverbCore,
+}
+impl Freeverb {
+    #[allow(missing_docs)]
+    pub fn new_with(uid: Uid, inner: FreeverbCore) -> Self {
+        Self { uid, inner }
+    }
+}
+
+#[cfg(feature = "egui")]
+impl crate::traits::Displays for Freeverb {
+    fn ui(&mut self, ui: &mut eframe::egui::Ui) -> eframe::egui::Response {
+        let mut pre_delay = self.inner.pre_delay().0;
+        let mut room_size = self.inner.room_size().0;
+        let mut damping = self.inner.damping().0;
+        let mut width = self.inner.width().0;
+        let mut early_reflections = self.inner.early_reflections().0;
+        let pre_delay_response = ui.add(
+            eframe::egui::Slider::new(&mut pre_delay, Normal::range())
+                .fixed_decimals(2)
+                .text("Pre-delay"),
+        );
+        if pre_delay_response.changed() {
+            self.inner.set_pre_delay(pre_delay.into());
+        }
+        let room_size_response = ui.add(
+            eframe::egui::Slider::new(&mut room_size, Normal::range())
+                .fixed_decimals(2)
+                .text("Room size"),
+        );
+        if room_size_response.changed() {
+            self.inner.set_room_size(room_size.into());
+        }
+        let damping_response = ui.add(
+            eframe::egui::Slider::new(&mut damping, Normal::range())
+                .fixed_decimals(2)
+                .text("Damping"),
+        );
+        if damping_response.changed() {
+            self.inner.set_damping(damping.into());
+        }
+        let width_response = ui.add(
+            eframe::egui::Slider::new(&mut width, Normal::range())
+                .fixed_decimals(2)
+                .text("Width"),
+        );
+        if width_response.changed() {
+            self.inner.set_width(width.into());
+        }
+        let early_reflections_response = ui.add(
+            eframe::egui::Slider::new(&mut early_reflections, Normal::range())
+                .fixed_decimals(2)
+                .text("Early reflections"),
+        );
+        if early_reflections_response.changed() {
+            self.inner.set_early_reflections(early_reflections.into());
+        }
+        pre_delay_response
+            | room_size_response
+            | damping_response
+            | width_response
+            | early_reflections_response
+    }
+}
+#[cfg(not(feature = "egui"))]
+impl crate::traits::Displays for Freeverb {}
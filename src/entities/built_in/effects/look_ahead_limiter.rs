@@ -0,0 +1,67 @@
+// Copyright (c) 2024 Mike Tsao
+
+use crate::{cores::LookAheadLimiterCore, prelude::*};
+use ensnare_proc_macros::{
+    InnerConfigurable, InnerControllable, InnerEffect, InnerSerializable, IsEntity, Metadata,
+};
+use serde::{Deserialize, Serialize};
+
+/// Entity wrapper for [LookAheadLimiterCore]
+#[derive(
+    Debug,
+    Default,
+    InnerControllable,
+    InnerConfigurable,
+    InnerEffect,
+    InnerSerializable,
+    IsEntity,
+    Metadata,
+    Serialize,
+    Deserialize,
+)]
+#[entity(Controls, GeneratesStereoSample, HandlesMidi, SkipInner)]
+pub struct LookAheadLimiter {
+    uid: Uid,
+    inner: LookAheadLimiterCore,
+}
+impl LookAheadLimiter {
+    #[allow(missing_docs)]
+    pub fn new_with(uid: Uid, inner: LookAheadLimiterCore) -> Self {
+        Self { uid, inner }
+    }
+}
+
+#[cfg(feature = "egui")]
+impl crate::traits::Displays for LookAheadLimiter {
+    fn ui(&mut self, ui: &mut eframe::egui::Ui) -> eframe::egui::Response {
+        let mut ceiling = self.inner.ceiling().to_percentage();
+        let mut release = self.inner.release().0;
+        let ceiling_response = ui.add(
+            eframe::egui::Slider::new(&mut ceiling, 0.0..=100.0)
+                .suffix(" %")
+                .text("Ceiling")
+                .fixed_decimals(2),
+        );
+        if ceiling_response.changed() {
+            self.inner.set_ceiling(Normal::from_percentage(ceiling));
+        };
+        let release_response = ui.add(
+            eframe::egui::Slider::new(&mut release, Normal::range())
+                .fixed_decimals(2)
+                .text("Release"),
+        );
+        if release_response.changed() {
+            self.inner.set_release(release.into());
+        };
+
+        // The meter reads full when the limiter is cutting 24dB or more.
+        let gain_reduction = self.inner.gain_reduction();
+        ui.add(
+            eframe::egui::ProgressBar::new((gain_reduction / 24.0).min(1.0) as f32)
+                .text(format!("Gain reduction: {gain_reduction:.1} dB")),
+        );
+        ceiling_response | release_response
+    }
+}
+#[cfg(not(feature = "egui"))]
+impl crate::traits::Displays for LookAheadLimiter {}
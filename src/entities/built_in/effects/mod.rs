@@ -9,8 +9,10 @@ pub use {
         BiQuadFilterAllPass, BiQuadFilterBandPass, BiQuadFilterBandStop, BiQuadFilterHighPass,
         BiQuadFilterLowPass24db,
     },
+    freeverb::Freeverb,
     gain::Gain,
     limiter::Limiter,
+    look_ahead_limiter::LookAheadLimiter,
     reverb::Reverb,
 };
 
@@ -19,6 +21,8 @@ mod chorus;
 mod compressor;
 mod delay;
 mod filter;
+mod freeverb;
 mod gain;
 mod limiter;
+mod look_ahead_limiter;
 mod reverb;
@@ -2,17 +2,18 @@
 
 use super::{
     Arpeggiator, BiQuadFilterAllPass, BiQuadFilterBandPass, BiQuadFilterBandStop,
-    BiQuadFilterHighPass, BiQuadFilterLowPass24db, Bitcrusher, Chorus, Compressor, Delay, FmSynth,
-    Gain, LfoController, Limiter, Reverb, SignalPassthroughController, SubtractiveSynth, Timer,
-    Trigger,
+    BiQuadFilterHighPass, BiQuadFilterLowPass24db, Bitcrusher, Chorus, Compressor, Delay,
+    EnvelopeFollower, FmSynth, Freeverb, Gain, LfoController, Limiter, LookAheadLimiter, Reverb,
+    SignalPassthroughController, SubtractiveSynth, Timer, Trigger,
 };
 use crate::{
     cores::{
         ArpeggiatorCoreBuilder, BiQuadFilterAllPassCoreBuilder, BiQuadFilterBandPassCoreBuilder,
         BiQuadFilterBandStopCoreBuilder, BiQuadFilterHighPassCoreBuilder,
         BiQuadFilterLowPass24dbCoreBuilder, BitcrusherCoreBuilder, DelayCoreBuilder,
-        GainCoreBuilder, LfoControllerCoreBuilder, LimiterCoreBuilder, ReverbCoreBuilder,
-        TimerCore,
+        EnvelopeFollowerCoreBuilder, FreeverbCoreBuilder, GainCoreBuilder,
+        LfoControllerCoreBuilder, LimiterCoreBuilder, LookAheadLimiterCoreBuilder,
+        ReverbCoreBuilder, TimerCore,
     },
     prelude::*,
 };
@@ -48,6 +49,12 @@ impl CollectsEntities for BuiltInEntities {
                 ArpeggiatorCoreBuilder::default().build().unwrap(),
             ))
         });
+        factory.register_entity_with_str_key(EnvelopeFollower::ENTITY_KEY, |uid| {
+            Box::new(EnvelopeFollower::new_with(
+                uid,
+                EnvelopeFollowerCoreBuilder::default().build().unwrap(),
+            ))
+        });
         factory.register_entity_with_str_key(LfoController::ENTITY_KEY, |uid| {
             Box::new(LfoController::new_with(
                 uid,
@@ -96,6 +103,12 @@ impl CollectsEntities for BuiltInEntities {
                     .unwrap(),
             ))
         });
+        factory.register_entity_with_str_key(Freeverb::ENTITY_KEY, |uid| {
+            Box::new(Freeverb::new_with(
+                uid,
+                FreeverbCoreBuilder::default().build().unwrap(),
+            ))
+        });
         factory.register_entity_with_str_key(Compressor::ENTITY_KEY, |_uid| {
             Box::<Compressor>::default()
         });
@@ -153,6 +166,12 @@ impl CollectsEntities for BuiltInEntities {
                 LimiterCoreBuilder::default().build().unwrap(),
             ))
         });
+        factory.register_entity_with_str_key(LookAheadLimiter::ENTITY_KEY, |uid| {
+            Box::new(LookAheadLimiter::new_with(
+                uid,
+                LookAheadLimiterCoreBuilder::default().build().unwrap(),
+            ))
+        });
         factory.register_entity_with_str_key(Delay::ENTITY_KEY, |uid| {
             Box::new(Delay::new_with(
                 uid,
@@ -282,13 +301,10 @@ mod tests {
             "control_index_count() agrees with number of params"
         );
 
-        // The Controls trait doesn't support getting values, only setting them.
-        // So we can't actually verify that our sets are doing anything. If this
-        // becomes an issue, then we have two options: (1) extend the Controls
-        // trait to allow getting, and then worry that any errors are tested by
-        // the same generated code that has the error, or (2) come up with a
-        // wacky proc macro that converts param_name into a getter invocation. I
-        // don't think regular macros can do that because of hygiene rules.
+        // Getters and setters are generated by the same macro, so a getter
+        // can't prove that a setter did the right thing. Many params also
+        // don't map exactly to a ControlValue. But every param should at least
+        // be readable.
         for index in 0..entity.control_index_count() {
             let index = ControlIndex(index);
             let param_name = entity.control_name_for_index(index).unwrap();
@@ -296,7 +312,14 @@ mod tests {
             entity.control_set_param_by_index(index, 1.0.into());
             entity.control_set_param_by_name(&param_name, 0.0.into());
             entity.control_set_param_by_name(&param_name, 1.0.into());
+            assert!(
+                entity.control_get_param_by_index(index).is_some(),
+                "Couldn't read param {param_name}"
+            );
         }
+        assert!(entity
+            .control_get_param_by_index(ControlIndex(entity.control_index_count()))
+            .is_none());
     }
 
     fn validate_configurable(entity: &mut dyn Entity) {
@@ -253,13 +253,19 @@ impl<E: Entity + ?Sized> Controls for EntityStore<E> {
             t.skip_to_start();
         });
     }
+
+    fn seek(&mut self, position: MusicalTime) {
+        self.iter_mut().for_each(|t| {
+            t.seek(position);
+        });
+    }
 }
 impl<E: Entity + ?Sized> ControlsAsProxy for EntityStore<E> {
     fn work_as_proxy(&mut self, control_events_fn: &mut ControlProxyEventsFn) {
         self.entities.iter_mut().for_each(|(uid, entity)| {
             // if let Some(entity) = entity.as_controller_mut() {
-            entity.work(&mut |message| {
-                control_events_fn((*uid).into(), message);
+            entity.work_timed(&mut |when, message| {
+                control_events_fn((*uid).into(), when, message);
             });
             // }
         });
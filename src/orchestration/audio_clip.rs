@@ -0,0 +1,676 @@
+// Copyright (c) 2024 Mike Tsao
+
+use crate::{orchestration::TrackUid, prelude::*, types::IsUid, util::SampleSource};
+use anyhow::{anyhow, Result};
+use core::ops::Range;
+use delegate::delegate;
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use synonym::Synonym;
+
+/// A [Uid] that identifies an [AudioClip].
+#[derive(Synonym, Serialize, Deserialize)]
+pub struct AudioClipUid(usize);
+impl IsUid for AudioClipUid {
+    fn as_usize(&self) -> usize {
+        self.0
+    }
+}
+
+/// Mints unique [AudioClipUid]s.
+#[derive(Synonym, Debug, Serialize, Deserialize)]
+pub struct AudioClipUidFactory(UidFactory<AudioClipUid>);
+impl Default for AudioClipUidFactory {
+    fn default() -> Self {
+        Self(UidFactory::<AudioClipUid>::new(524288))
+    }
+}
+impl AudioClipUidFactory {
+    delegate! {
+        to self.0 {
+            /// Generates the next unique [AudioClipUid].
+            pub fn mint_next(&self) -> AudioClipUid;
+        }
+    }
+}
+
+/// A region of recorded audio that's placed on an audio track's timeline. It's
+/// the audio equivalent of an arrangement of a [Pattern].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct AudioClip {
+    /// Where the audio comes from.
+    pub source: SampleSource,
+
+    /// How far into the source audio the clip starts playing.
+    #[serde(default)]
+    pub source_offset: Seconds,
+
+    /// Where the clip is placed in the composition.
+    pub position: MusicalTime,
+
+    /// How long the clip plays. If the source audio runs out first, the rest
+    /// of the clip is silent.
+    pub duration: MusicalTime,
+
+    /// How loud the clip is.
+    #[serde(default)]
+    pub gain: Normal,
+
+    /// How long the clip takes to fade in from silence at its start.
+    #[serde(default)]
+    pub fade_in: MusicalTime,
+
+    /// How long the clip takes to fade out to silence at its end.
+    #[serde(default)]
+    pub fade_out: MusicalTime,
+
+    #[serde(skip)]
+    e: AudioClipEphemerals,
+}
+#[derive(Debug, Clone, Default)]
+struct AudioClipEphemerals {
+    samples: Arc<Vec<StereoSample>>,
+    sample_rate: SampleRate,
+
+    // The clip's placement, fade-in end, and fade-out start, converted to
+    // frames from the start of the song.
+    frames: Range<usize>,
+    fade_in_end: usize,
+    fade_out_start: usize,
+}
+impl HasExtent for AudioClip {
+    fn extent(&self) -> TimeRange {
+        TimeRange(self.position..self.position + self.duration)
+    }
+
+    fn set_extent(&mut self, extent: TimeRange) {
+        self.position = extent.start();
+        self.duration = extent.duration();
+    }
+}
+impl AudioClip {
+    /// Creates a clip that plays the given source at the given place in the
+    /// composition. Call [AudioClip::load()] before playing it.
+    pub fn new_with(source: SampleSource, position: MusicalTime, duration: MusicalTime) -> Self {
+        Self {
+            source,
+            position,
+            duration,
+            ..Default::default()
+        }
+    }
+
+    /// Creates a clip from audio that's already in memory, such as a
+    /// recording, that was captured at the given sample rate.
+    pub fn new_with_samples(
+        samples: Arc<Vec<StereoSample>>,
+        sample_rate: SampleRate,
+        position: MusicalTime,
+        duration: MusicalTime,
+    ) -> Self {
+        let mut r = Self {
+            position,
+            duration,
+            ..Default::default()
+        };
+        r.set_samples(samples, sample_rate);
+        r
+    }
+
+    /// Reads the source audio from disk.
+    #[cfg(feature = "hound")]
+    pub fn load(&mut self) -> Result<()> {
+        let path = self.source.build_path()?;
+        let file = crate::util::Paths::global().search_and_open(path.as_path())?;
+        let (samples, sample_rate) =
+            crate::cores::SamplerCore::read_samples_and_sample_rate_from_file(&file)?;
+        self.set_samples(Arc::new(samples), sample_rate);
+        Ok(())
+    }
+
+    /// Replaces the clip's audio with audio that was captured at the given
+    /// sample rate.
+    pub fn set_samples(&mut self, samples: Arc<Vec<StereoSample>>, sample_rate: SampleRate) {
+        self.e.samples = samples;
+        self.e.sample_rate = sample_rate;
+    }
+
+    #[allow(missing_docs)]
+    pub fn samples(&self) -> &Arc<Vec<StereoSample>> {
+        &self.e.samples
+    }
+
+    /// The sample rate at which the clip's audio was captured.
+    pub fn source_sample_rate(&self) -> SampleRate {
+        self.e.sample_rate
+    }
+
+    // Converts the clip's timeline positions to frames. The conversion is
+    // supplied by the caller because only it knows about tempo changes.
+    fn update_frames(&mut self, time_to_frames: &dyn Fn(MusicalTime) -> usize) {
+        let extent = self.extent();
+        let start = time_to_frames(extent.start());
+        let end = time_to_frames(extent.end()).max(start);
+        self.e.frames = start..end;
+        self.e.fade_in_end = time_to_frames(extent.start() + self.fade_in).clamp(start, end);
+        self.e.fade_out_start = if self.fade_out < self.duration {
+            time_to_frames(extent.end() - self.fade_out).clamp(start, end)
+        } else {
+            start
+        };
+    }
+
+    // Mixes this clip's audio into the buffer, which starts at the given frame
+    // of the song.
+    fn render(&self, start_frame: usize, sample_rate: SampleRate, values: &mut [StereoSample]) {
+        let samples = &self.e.samples;
+        if samples.is_empty() {
+            return;
+        }
+        let frames = &self.e.frames;
+        let rate_ratio = f64::from(self.e.sample_rate) / f64::from(sample_rate);
+        let source_start = self.source_offset.0 * f64::from(self.e.sample_rate);
+        for (i, value) in values.iter_mut().enumerate() {
+            let frame = start_frame + i;
+            if !frames.contains(&frame) {
+                continue;
+            }
+
+            // Find the source position, interpolating linearly if the clip's
+            // sample rate differs from ours.
+            let position = source_start + (frame - frames.start) as f64 * rate_ratio;
+            let index = position as usize;
+            if index >= samples.len() {
+                continue;
+            }
+            let fraction = position.fract();
+            let sample = if fraction != 0.0 && index + 1 < samples.len() {
+                samples[index] * (1.0 - fraction) + samples[index + 1] * fraction
+            } else {
+                samples[index]
+            };
+
+            let mut amplitude = self.gain.0;
+            if frame < self.e.fade_in_end {
+                amplitude *=
+                    (frame - frames.start) as f64 / (self.e.fade_in_end - frames.start) as f64;
+            }
+            if frame >= self.e.fade_out_start {
+                amplitude *=
+                    (frames.end - frame) as f64 / (frames.end - self.e.fade_out_start) as f64;
+            }
+            *value += sample * amplitude;
+        }
+    }
+}
+
+/// Owns the [AudioClip]s that have been placed on tracks, and mixes their audio
+/// into those tracks during playback.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct AudioClipRepository {
+    #[serde(default)]
+    uid_factory: AudioClipUidFactory,
+
+    /// All the clips, keyed by [AudioClipUid].
+    #[serde(default)]
+    pub clips: FxHashMap<AudioClipUid, AudioClip>,
+
+    /// Records which [TrackUid] an [AudioClip] belongs to.
+    #[serde(default)]
+    pub tracks_to_ordered_clip_uids: FxHashMap<TrackUid, Vec<AudioClipUid>>,
+
+    #[serde(skip)]
+    e: AudioClipRepositoryEphemerals,
+}
+#[derive(Debug, Default)]
+struct AudioClipRepositoryEphemerals {
+    // The song frame at which the next buffer starts, or None if we're not
+    // performing.
+    start_frame: Option<usize>,
+    time_range: TimeRange,
+
+    // Whether the clips' frames match their current positions and the current
+    // conversion from song positions to frames.
+    are_frames_current: bool,
+
+    // The clips whose audio couldn't be read the last time the repository was
+    // deserialized, and why.
+    load_errors: Vec<(AudioClipUid, anyhow::Error)>,
+}
+impl AudioClipRepository {
+    /// Returns the clips whose audio couldn't be read the last time the
+    /// repository was deserialized, along with the reason. Those clips stay
+    /// in place, but they're silent.
+    pub fn load_errors(&self) -> &[(AudioClipUid, anyhow::Error)] {
+        &self.e.load_errors
+    }
+
+    /// Places the given [AudioClip] on the given track. Fails if it would
+    /// overlap another clip on that track.
+    pub fn add_clip(&mut self, track_uid: TrackUid, clip: AudioClip) -> Result<AudioClipUid> {
+        if clip.duration == MusicalTime::START {
+            return Err(anyhow!("Audio clip can't be empty"));
+        }
+        if !self.is_clip_area_available(track_uid, &clip.extent(), None) {
+            return Err(anyhow!(
+                "Audio clip at position {} would overlap with existing clip",
+                clip.position
+            ));
+        }
+        let clip_uid = self.uid_factory.mint_next();
+        self.clips.insert(clip_uid, clip);
+        self.invalidate_frames();
+        self.tracks_to_ordered_clip_uids
+            .entry(track_uid)
+            .or_default()
+            .push(clip_uid);
+        Ok(clip_uid)
+    }
+
+    /// Moves an [AudioClip] to a new position on its track. If copy_original
+    /// is true, the original stays put and a copy goes to the new position.
+    pub fn move_clip(
+        &mut self,
+        track_uid: TrackUid,
+        clip_uid: AudioClipUid,
+        new_position: MusicalTime,
+        copy_original: bool,
+    ) -> Result<AudioClipUid> {
+        if let Some(clip) = self.clips.get(&clip_uid) {
+            if copy_original {
+                let mut new_clip = clip.clone();
+                new_clip.position = new_position;
+                self.add_clip(track_uid, new_clip)
+            } else {
+                let new_extent = clip.extent().translate_to(new_position);
+                if !self.is_clip_area_available(track_uid, &new_extent, Some(clip_uid)) {
+                    return Err(anyhow!(
+                        "Moving audio clip {clip_uid} to {new_extent:?} would overlap with existing clip"
+                    ));
+                }
+                if let Some(clip) = self.clips.get_mut(&clip_uid) {
+                    clip.position = new_position;
+                }
+                self.invalidate_frames();
+                Ok(clip_uid)
+            }
+        } else {
+            Err(anyhow!("Couldn't find audio clip {clip_uid}"))
+        }
+    }
+
+    /// Creates a copy of an [AudioClip] immediately after the original.
+    pub fn duplicate_clip(
+        &mut self,
+        track_uid: TrackUid,
+        clip_uid: AudioClipUid,
+    ) -> Result<AudioClipUid> {
+        if let Some(clip) = self.clips.get(&clip_uid) {
+            let new_position = clip.position + clip.duration;
+            self.move_clip(track_uid, clip_uid, new_position, true)
+        } else {
+            Err(anyhow!("Audio clip at {track_uid}-{clip_uid} was missing"))
+        }
+    }
+
+    /// Removes an [AudioClip] from its track and returns it.
+    pub fn remove_clip(
+        &mut self,
+        track_uid: TrackUid,
+        clip_uid: AudioClipUid,
+    ) -> Result<AudioClip> {
+        if let Some(clip_uids) = self.tracks_to_ordered_clip_uids.get_mut(&track_uid) {
+            clip_uids.retain(|uid| *uid != clip_uid);
+        }
+        self.clips
+            .remove(&clip_uid)
+            .ok_or_else(|| anyhow!("Couldn't find audio clip {clip_uid}"))
+    }
+
+    /// Removes all the clips on the given track.
+    pub fn remove_clips_for_track(&mut self, track_uid: TrackUid) {
+        if let Some(clip_uids) = self.tracks_to_ordered_clip_uids.remove(&track_uid) {
+            clip_uids.iter().for_each(|uid| {
+                self.clips.remove(uid);
+            });
+        }
+    }
+
+    #[allow(missing_docs)]
+    pub fn clip(&self, clip_uid: AudioClipUid) -> Option<&AudioClip> {
+        self.clips.get(&clip_uid)
+    }
+
+    /// Returns the clip for editing. The clips' frames are recalculated before
+    /// the next buffer, in case the edit moved this one.
+    pub fn clip_mut(&mut self, clip_uid: AudioClipUid) -> Option<&mut AudioClip> {
+        self.invalidate_frames();
+        self.clips.get_mut(&clip_uid)
+    }
+
+    /// Returns the clips on the given track, in the order they were added.
+    pub fn clip_uids(&self, track_uid: TrackUid) -> Option<&[AudioClipUid]> {
+        if let Some(clip_uids) = self.tracks_to_ordered_clip_uids.get(&track_uid) {
+            let clip_uids: &[AudioClipUid] = clip_uids;
+            Some(clip_uids)
+        } else {
+            None
+        }
+    }
+
+    fn is_clip_area_available(
+        &self,
+        track_uid: TrackUid,
+        extent: &TimeRange,
+        clip_to_skip: Option<AudioClipUid>,
+    ) -> bool {
+        if let Some(clip_uids) = self.tracks_to_ordered_clip_uids.get(&track_uid) {
+            clip_uids
+                .iter()
+                .filter(|uid| Some(*uid) != clip_to_skip.as_ref())
+                .all(|uid| {
+                    if let Some(clip) = self.clips.get(uid) {
+                        !(extent.overlaps(clip.extent()))
+                    } else {
+                        true
+                    }
+                })
+        } else {
+            true
+        }
+    }
+
+    /// Prepares for the next buffer, which starts at the given frame of the
+    /// song (or None if playback is stopped). time_to_frames converts a
+    /// position in the song to a frame count. The clips' frames are
+    /// recalculated only if something has changed since the last time; see
+    /// [AudioClipRepository::invalidate_frames()].
+    pub fn update_frames(
+        &mut self,
+        start_frame: Option<usize>,
+        time_to_frames: &dyn Fn(MusicalTime) -> usize,
+    ) {
+        self.e.start_frame = start_frame;
+        if start_frame.is_some() && !self.e.are_frames_current {
+            self.clips
+                .values_mut()
+                .for_each(|clip| clip.update_frames(time_to_frames));
+            self.e.are_frames_current = true;
+        }
+    }
+
+    /// Tells the repository that the conversion from song positions to frames
+    /// has changed, as it does after a change in tempo or sample rate, so the
+    /// clips' frames need to be recalculated.
+    pub fn invalidate_frames(&mut self) {
+        self.e.are_frames_current = false;
+    }
+
+    /// Mixes the audio of the given track's clips into the buffer.
+    pub fn render_track(
+        &self,
+        track_uid: TrackUid,
+        sample_rate: SampleRate,
+        values: &mut [StereoSample],
+    ) {
+        if let Some(start_frame) = self.e.start_frame {
+            if let Some(clip_uids) = self.tracks_to_ordered_clip_uids.get(&track_uid) {
+                clip_uids.iter().for_each(|uid| {
+                    if let Some(clip) = self.clips.get(uid) {
+                        clip.render(start_frame, sample_rate, values);
+                    }
+                });
+            }
+        }
+    }
+
+    #[allow(missing_docs)]
+    pub fn update_time_range(&mut self, time_range: &TimeRange) {
+        self.e.time_range = time_range.clone();
+    }
+
+    #[allow(missing_docs)]
+    pub fn seek(&mut self, position: MusicalTime) {
+        self.e.time_range = TimeRange(position..position);
+    }
+
+    /// Returns true if playback has passed the end of every clip.
+    pub fn is_finished(&self) -> bool {
+        self.clips
+            .values()
+            .all(|clip| clip.extent().end() <= self.e.time_range.end())
+    }
+}
+impl Serializable for AudioClipRepository {
+    fn after_deser(&mut self) {
+        // Clips that already have their audio, like those restored by undo,
+        // don't need to read it again.
+        self.e.load_errors.clear();
+        #[cfg(feature = "hound")]
+        for (clip_uid, clip) in self
+            .clips
+            .iter_mut()
+            .filter(|(_, clip)| clip.samples().is_empty())
+        {
+            if let Err(e) = clip.load() {
+                eprintln!("Couldn't load audio clip {clip_uid}: {e:?}");
+                self.e.load_errors.push((*clip_uid, e));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clip_with_constant_audio(
+        value: f64,
+        position: MusicalTime,
+        duration: MusicalTime,
+    ) -> AudioClip {
+        AudioClip::new_with_samples(
+            Arc::new(vec![StereoSample::from(value); 1000]),
+            SampleRate(100),
+            position,
+            duration,
+        )
+    }
+
+    #[test]
+    fn audio_clip_repository_arranges_like_patterns() {
+        let mut repo = AudioClipRepository::default();
+        let track_uid = TrackUid(1);
+
+        let clip_uid = repo
+            .add_clip(
+                track_uid,
+                clip_with_constant_audio(0.5, MusicalTime::START, MusicalTime::ONE_BEAT),
+            )
+            .unwrap();
+        assert!(
+            repo.add_clip(
+                track_uid,
+                clip_with_constant_audio(0.5, MusicalTime::START, MusicalTime::ONE_BEAT),
+            )
+            .is_err(),
+            "Overlapping clips should be rejected"
+        );
+        assert!(
+            repo.add_clip(
+                TrackUid(2),
+                clip_with_constant_audio(0.5, MusicalTime::START, MusicalTime::ONE_BEAT),
+            )
+            .is_ok(),
+            "Clips on different tracks don't overlap"
+        );
+
+        let dup_uid = repo.duplicate_clip(track_uid, clip_uid).unwrap();
+        assert_ne!(dup_uid, clip_uid);
+        assert_eq!(repo.clip(dup_uid).unwrap().position, MusicalTime::ONE_BEAT);
+        assert!(
+            Arc::ptr_eq(
+                repo.clip(clip_uid).unwrap().samples(),
+                repo.clip(dup_uid).unwrap().samples()
+            ),
+            "A duplicate should share the original's audio"
+        );
+
+        assert!(
+            repo.move_clip(track_uid, clip_uid, MusicalTime::ONE_BEAT, false)
+                .is_err(),
+            "Moving onto another clip should fail"
+        );
+        let moved_uid = repo
+            .move_clip(track_uid, clip_uid, MusicalTime::new_with_beats(4), false)
+            .unwrap();
+        assert_eq!(moved_uid, clip_uid);
+        assert_eq!(
+            repo.clip(clip_uid).unwrap().position,
+            MusicalTime::new_with_beats(4)
+        );
+
+        assert!(repo.remove_clip(track_uid, dup_uid).is_ok());
+        assert_eq!(repo.clip_uids(track_uid).unwrap(), &[clip_uid]);
+
+        let json = serde_json::to_string(&repo).unwrap();
+        let restored: AudioClipRepository = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.clip_uids(track_uid).unwrap(), &[clip_uid]);
+        assert_eq!(
+            restored.clip(clip_uid).unwrap().extent(),
+            repo.clip(clip_uid).unwrap().extent()
+        );
+
+        repo.remove_clips_for_track(track_uid);
+        assert!(repo.clip_uids(track_uid).is_none());
+        assert!(repo.clip(clip_uid).is_none());
+    }
+
+    #[cfg(feature = "hound")]
+    #[test]
+    fn audio_clip_repository_reports_missing_audio() {
+        crate::util::Paths::set_instance(crate::util::Paths::default());
+        let mut repo = AudioClipRepository::default();
+        let clip_uid = repo
+            .add_clip(
+                TrackUid(1),
+                AudioClip::new_with(
+                    SampleSource::Path("no-such-file.wav".into()),
+                    MusicalTime::START,
+                    MusicalTime::ONE_BEAT,
+                ),
+            )
+            .unwrap();
+        assert!(repo.load_errors().is_empty());
+
+        repo.after_deser();
+        assert_eq!(
+            repo.load_errors().len(),
+            1,
+            "A clip whose source is missing should be reported"
+        );
+        assert_eq!(repo.load_errors()[0].0, clip_uid);
+        assert!(
+            repo.clip(clip_uid).unwrap().samples().is_empty(),
+            "The clip should stay in place without audio"
+        );
+    }
+
+    #[test]
+    fn audio_clip_renders_with_fades() {
+        // At 60 BPM and 100 Hz, a beat is 100 frames.
+        let time_to_frames = |time: MusicalTime| time.as_frames(Tempo(60.0), SampleRate(100));
+        let mut clip =
+            clip_with_constant_audio(1.0, MusicalTime::ONE_BEAT, MusicalTime::new_with_beats(2));
+        clip.gain = Normal::from(0.5);
+        clip.fade_in = MusicalTime::new_with_fractional_beats(0.5);
+        clip.fade_out = MusicalTime::new_with_fractional_beats(0.5);
+        clip.update_frames(&time_to_frames);
+
+        let mut buffer = [StereoSample::SILENCE; 400];
+        clip.render(0, SampleRate(100), &mut buffer);
+        assert_eq!(buffer[99], StereoSample::SILENCE, "Nothing before the clip");
+        assert_eq!(buffer[100], StereoSample::SILENCE, "Fade-in starts silent");
+        assert_eq!(
+            buffer[125],
+            StereoSample::from(0.25),
+            "Halfway through fade-in"
+        );
+        assert_eq!(buffer[200], StereoSample::from(0.5), "Full gain mid-clip");
+        assert_eq!(
+            buffer[275],
+            StereoSample::from(0.25),
+            "Halfway through fade-out"
+        );
+        assert_eq!(buffer[300], StereoSample::SILENCE, "Nothing after the clip");
+
+        // Rendering in pieces should give the same answer.
+        let mut pieces = [StereoSample::SILENCE; 400];
+        pieces.chunks_mut(64).enumerate().for_each(|(i, chunk)| {
+            clip.render(i * 64, SampleRate(100), chunk);
+        });
+        assert_eq!(buffer, pieces);
+
+        // Audio recorded at half our rate should be stretched to play at the
+        // right speed.
+        let ramp: Vec<StereoSample> = (0..100).map(|i| StereoSample::from(i as f64)).collect();
+        let mut clip = AudioClip::new_with_samples(
+            Arc::new(ramp),
+            SampleRate(50),
+            MusicalTime::START,
+            MusicalTime::ONE_BEAT,
+        );
+        clip.update_frames(&time_to_frames);
+        let mut buffer = [StereoSample::SILENCE; 100];
+        clip.render(0, SampleRate(100), &mut buffer);
+        assert_eq!(buffer[2], StereoSample::from(1.0));
+        assert_eq!(buffer[3], StereoSample::from(1.5));
+    }
+
+    #[test]
+    fn audio_clip_repository_recalculates_frames_only_when_needed() {
+        let mut repo = AudioClipRepository::default();
+        let track_uid = TrackUid(1);
+        let clip_uid = repo
+            .add_clip(
+                track_uid,
+                clip_with_constant_audio(0.5, MusicalTime::START, MusicalTime::ONE_BEAT),
+            )
+            .unwrap();
+        // Renders the single frame that update_frames() pointed at.
+        let is_sounding = |repo: &AudioClipRepository| {
+            let mut buffer = [StereoSample::SILENCE; 1];
+            repo.render_track(track_uid, SampleRate(100), &mut buffer);
+            buffer[0] != StereoSample::SILENCE
+        };
+
+        // At 60 BPM and 100 Hz, a beat is 100 frames.
+        let slow = |time: MusicalTime| time.as_frames(Tempo(60.0), SampleRate(100));
+        let fast = |time: MusicalTime| time.as_frames(Tempo(120.0), SampleRate(100));
+        repo.update_frames(Some(75), &slow);
+        assert!(is_sounding(&repo));
+
+        // Without a reason to recalculate, the old frames stand.
+        repo.update_frames(Some(75), &fast);
+        assert!(is_sounding(&repo));
+
+        repo.invalidate_frames();
+        repo.update_frames(Some(75), &fast);
+        assert!(
+            !is_sounding(&repo),
+            "At the faster tempo, the clip should have ended by frame 75"
+        );
+
+        let _ = repo
+            .move_clip(track_uid, clip_uid, MusicalTime::ONE_BEAT, false)
+            .unwrap();
+        repo.update_frames(Some(75), &fast);
+        assert!(
+            is_sounding(&repo),
+            "Moving a clip should recalculate its frames"
+        );
+    }
+}
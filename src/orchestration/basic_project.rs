@@ -29,6 +29,19 @@ pub struct SignalChainItem {
     pub uid: Uid,
     pub name: String,
     pub is_control_source: bool,
+    /// The parameters that this item controls.
+    pub links: Vec<SignalChainLink>,
+    /// The names of this item's own parameters, in [ControlIndex] order.
+    pub params: Vec<ControlName>,
+}
+
+/// Utility
+#[allow(missing_docs)]
+#[derive(Debug)]
+pub struct SignalChainLink {
+    pub link: ControlLink,
+    /// A human-readable name for the target parameter.
+    pub target_name: String,
 }
 
 /// Temporary information associated with each track.
@@ -371,6 +384,13 @@ impl Controls for BasicProject {
             .values_mut()
             .for_each(|e| e.skip_to_start());
     }
+
+    fn seek(&mut self, position: MusicalTime) {
+        self.transport.seek(position);
+        self.entity_uid_to_entity
+            .values_mut()
+            .for_each(|e| e.seek(position));
+    }
 }
 impl BasicProject {
     fn handle_controllers(
@@ -49,7 +49,6 @@ impl BusStation {
 
     // If we want this method to be immutable and cheap, then we can't guarantee
     // that it will return a Vec. Such is life.
-    #[allow(dead_code)]
     pub fn sends_for_track(&self, track_uid: &TrackUid) -> Option<&Vec<BusRoute>> {
         self.routes.get(track_uid)
     }
@@ -0,0 +1,575 @@
+// Copyright (c) 2024 Mike Tsao
+
+use super::{AudioClip, AudioClipUid, Project};
+use crate::{
+    prelude::*,
+    types::{TempoChange, TimeSignatureChange},
+};
+use anyhow::{anyhow, Result};
+use rustc_hash::FxHashMap;
+use std::{collections::VecDeque, sync::Arc};
+
+/// An editing operation on a [Project]. Commands are applied with
+/// [Project::execute()], which records enough history to reverse them with
+/// [Project::undo()].
+///
+/// Commands that change a single setting, like [ProjectCommand::MuteTrack] or
+/// a non-copying [ProjectCommand::MoveArrangement], are reversed by another
+/// command that puts the old setting back. [ProjectCommand::ArrangeNewPattern]
+/// is reversed by removing what it created. The rest are reversed by restoring
+/// a snapshot of the whole project, which is much more expensive.
+///
+/// A command checks its arguments before changing anything, so a command that
+/// fails leaves the project as it was.
+#[derive(Debug)]
+pub enum ProjectCommand {
+    /// Adds a new MIDI track.
+    NewMidiTrack,
+    /// Adds a new audio track.
+    NewAudioTrack,
+    /// Adds a new aux track.
+    NewAuxTrack,
+    /// Deletes a track and everything on it.
+    DeleteTrack(TrackUid),
+    /// Moves a track to a new position in the track list.
+    SetTrackPosition(TrackUid, usize),
+    /// Mutes or unmutes a track.
+    MuteTrack(TrackUid, bool),
+    /// Sets the track that plays alone, or None to play all tracks.
+    SetSoloTrack(Option<TrackUid>),
+    /// Sets the MIDI channel that a track's instruments listen to.
+    SetTrackMidiChannel(TrackUid, MidiChannel),
+    /// Sets a track's output level.
+    SetTrackOutput(TrackUid, Normal),
+    /// Sends some of a track's output to an aux track.
+    AddSend(TrackUid, TrackUid, Normal),
+    /// Stops sending a track's output to an aux track.
+    RemoveSend(TrackUid, TrackUid),
+    /// Adds an entity to the end of a track's signal chain.
+    AddEntity(TrackUid, Box<dyn Entity>),
+    /// Removes an entity from its track.
+    RemoveEntity(Uid),
+    /// Moves an entity to a different track and/or position.
+    MoveEntity(Uid, Option<TrackUid>, Option<usize>),
+    /// Sets how much of an effect's output replaces its input.
+    SetHumidity(Uid, Normal),
+    /// Sets the track that drives an entity's sidechain input, or None to
+    /// disconnect it.
+    SetSidechain(Uid, Option<TrackUid>),
+    /// Adds a [Pattern] to the palette.
+    AddPattern(Pattern),
+    /// Removes a [Pattern] from the palette.
+    RemovePattern(PatternUid),
+    /// Places a [Pattern] on a track.
+    ArrangePattern(TrackUid, PatternUid, Option<MidiChannel>, MusicalTime),
+    /// Adds a [Pattern] to the palette and places it on a track.
+    ArrangeNewPattern(TrackUid, Pattern, MusicalTime),
+    /// Removes an arrangement from a track, along with its [Pattern] and any
+    /// other arrangement of that pattern.
+    RemoveArrangedPattern(TrackUid, ArrangementUid),
+    /// Moves an arrangement, optionally leaving the original in place.
+    MoveArrangement(TrackUid, ArrangementUid, MusicalTime, bool),
+    /// Removes an arrangement from a track.
+    Unarrange(TrackUid, ArrangementUid),
+    /// Copies an arrangement to just after the original.
+    DuplicateArrangement(TrackUid, ArrangementUid),
+    /// Connects a controller to a controllable parameter.
+    Link(Uid, Uid, ControlIndex),
+    /// Disconnects a controller from a controllable parameter.
+    Unlink(Uid, Uid, ControlIndex),
+    /// Sets how a link transforms its source's values.
+    SetLinkMapping(ControlLinkSource, Uid, ControlIndex, Option<ControlMapping>),
+    /// Sets how a link's values combine with those of other links to the same
+    /// parameter.
+    SetLinkRole(ControlLinkSource, Uid, ControlIndex, ControlLinkRole),
+    /// Connects an automation path to a controllable parameter.
+    LinkPath(PathUid, Uid, ControlIndex),
+    /// Disconnects an automation path from a controllable parameter.
+    UnlinkPath(PathUid, Uid, ControlIndex),
+    /// Lets an external MIDI controller (CC) on a channel drive a parameter.
+    BindMidiCc(MidiChannel, u8, Uid, ControlIndex),
+    /// Stops an external MIDI controller (CC) from driving a parameter.
+    UnbindMidiCc(MidiChannel, u8, Uid, ControlIndex),
+    /// Adds an automation path to a track.
+    AddPath(TrackUid, SignalPath),
+    /// Removes an automation path.
+    RemovePath(PathUid),
+    /// Places an [AudioClip] on an audio track.
+    AddAudioClip(TrackUid, AudioClip),
+    /// Moves an [AudioClip], optionally leaving the original in place.
+    MoveAudioClip(TrackUid, AudioClipUid, MusicalTime, bool),
+    /// Removes an [AudioClip] from its track.
+    RemoveAudioClip(TrackUid, AudioClipUid),
+    /// Sets the range of the song that playback repeats, or None to stop
+    /// looping.
+    SetLoopRange(Option<TimeRange>),
+    /// Sets the range of the song where recording happens, or None to record
+    /// everywhere.
+    SetPunchRange(Option<TimeRange>),
+    /// Adds a tempo change, replacing any at the same time.
+    AddTempoChange(TempoChange),
+    /// Removes the tempo change at the given time.
+    RemoveTempoChange(MusicalTime),
+    /// Adds a time-signature change, replacing any at the same time.
+    AddTimeSignatureChange(TimeSignatureChange),
+    /// Removes the time-signature change at the given time.
+    RemoveTimeSignatureChange(MusicalTime),
+}
+impl ProjectCommand {
+    pub(crate) fn apply(self, project: &mut Project) -> Result<ProjectCommandOutcome> {
+        match self {
+            ProjectCommand::NewMidiTrack => {
+                project.new_midi_track().map(ProjectCommandOutcome::Track)
+            }
+            ProjectCommand::NewAudioTrack => {
+                project.new_audio_track().map(ProjectCommandOutcome::Track)
+            }
+            ProjectCommand::NewAuxTrack => {
+                project.new_aux_track().map(ProjectCommandOutcome::Track)
+            }
+            ProjectCommand::DeleteTrack(track_uid) => project
+                .delete_track(track_uid)
+                .map(|_| ProjectCommandOutcome::Done),
+            ProjectCommand::SetTrackPosition(track_uid, new_position) => project
+                .set_track_position(track_uid, new_position)
+                .map(|_| ProjectCommandOutcome::Done),
+            ProjectCommand::MuteTrack(track_uid, should_mute) => {
+                project.mute_track(track_uid, should_mute);
+                Ok(ProjectCommandOutcome::Done)
+            }
+            ProjectCommand::SetSoloTrack(track_uid) => {
+                project.set_solo_track(track_uid);
+                Ok(ProjectCommandOutcome::Done)
+            }
+            ProjectCommand::SetTrackMidiChannel(track_uid, midi_channel) => {
+                project.set_track_midi_channel(track_uid, midi_channel);
+                Ok(ProjectCommandOutcome::Done)
+            }
+            ProjectCommand::SetTrackOutput(track_uid, output) => {
+                project.set_track_output(track_uid, output);
+                Ok(ProjectCommandOutcome::Done)
+            }
+            ProjectCommand::AddSend(src_uid, dst_uid, amount) => project
+                .add_send(src_uid, dst_uid, amount)
+                .map(|_| ProjectCommandOutcome::Done),
+            ProjectCommand::RemoveSend(send_track_uid, aux_track_uid) => {
+                project.remove_send(send_track_uid, aux_track_uid);
+                Ok(ProjectCommandOutcome::Done)
+            }
+            ProjectCommand::AddEntity(track_uid, entity) => project
+                .add_entity(track_uid, entity)
+                .map(ProjectCommandOutcome::Entity),
+            ProjectCommand::RemoveEntity(uid) => project
+                .remove_entity(uid)
+                .map(|_| ProjectCommandOutcome::Done),
+            ProjectCommand::MoveEntity(uid, new_track_uid, new_position) => project
+                .move_entity(uid, new_track_uid, new_position)
+                .map(|_| ProjectCommandOutcome::Done),
+            ProjectCommand::SetHumidity(uid, humidity) => {
+                project.set_humidity(uid, humidity);
+                Ok(ProjectCommandOutcome::Done)
+            }
+            ProjectCommand::SetSidechain(uid, source_track_uid) => project
+                .set_sidechain(uid, source_track_uid)
+                .map(|_| ProjectCommandOutcome::Done),
+            ProjectCommand::AddPattern(pattern) => project
+                .add_pattern(pattern, None)
+                .map(ProjectCommandOutcome::Pattern),
+            ProjectCommand::RemovePattern(pattern_uid) => project
+                .remove_pattern(pattern_uid)
+                .map(|_| ProjectCommandOutcome::Done),
+            ProjectCommand::ArrangePattern(track_uid, pattern_uid, midi_channel, position) => {
+                project
+                    .arrange_pattern(track_uid, pattern_uid, midi_channel, position)
+                    .map(ProjectCommandOutcome::Arrangement)
+            }
+            ProjectCommand::ArrangeNewPattern(track_uid, pattern, position) => {
+                let pattern_uid = project.add_pattern(pattern, None)?;
+                match project.arrange_pattern(track_uid, pattern_uid, None, position) {
+                    Ok(arrangement_uid) => Ok(ProjectCommandOutcome::Arrangement(arrangement_uid)),
+                    Err(e) => {
+                        let _ = project.remove_pattern(pattern_uid);
+                        Err(e)
+                    }
+                }
+            }
+            ProjectCommand::RemoveArrangedPattern(track_uid, arrangement_uid) => {
+                let pattern_uid = project
+                    .composer
+                    .arrangements
+                    .get(&arrangement_uid)
+                    .map(|arrangement| arrangement.pattern_uid)
+                    .ok_or_else(|| anyhow!("Arrangement {arrangement_uid} not found"))?;
+                project.unarrange(track_uid, arrangement_uid);
+                project
+                    .remove_pattern(pattern_uid)
+                    .map(|_| ProjectCommandOutcome::Done)
+            }
+            ProjectCommand::MoveArrangement(
+                track_uid,
+                arrangement_uid,
+                new_position,
+                copy_original,
+            ) => project
+                .move_arrangement(track_uid, arrangement_uid, new_position, copy_original)
+                .map(ProjectCommandOutcome::Arrangement),
+            ProjectCommand::Unarrange(track_uid, arrangement_uid) => {
+                project.unarrange(track_uid, arrangement_uid);
+                Ok(ProjectCommandOutcome::Done)
+            }
+            ProjectCommand::DuplicateArrangement(track_uid, arrangement_uid) => project
+                .duplicate_arrangement(track_uid, arrangement_uid)
+                .map(ProjectCommandOutcome::Arrangement),
+            ProjectCommand::Link(source, target, param) => project
+                .link(source, target, param)
+                .map(|_| ProjectCommandOutcome::Done),
+            ProjectCommand::Unlink(source, target, param) => {
+                project.unlink(source, target, param);
+                Ok(ProjectCommandOutcome::Done)
+            }
+            ProjectCommand::BindMidiCc(channel, cc, uid, param) => project
+                .bind_midi_cc(channel, cc, uid, param)
+                .map(|_| ProjectCommandOutcome::Done),
+            ProjectCommand::UnbindMidiCc(channel, cc, uid, param) => {
+                project.unbind_midi_cc(channel, cc, uid, param);
+                Ok(ProjectCommandOutcome::Done)
+            }
+            ProjectCommand::SetLinkMapping(source, target, param, mapping) => project
+                .set_link_mapping(source, target, param, mapping)
+                .map(|_| ProjectCommandOutcome::Done),
+            ProjectCommand::SetLinkRole(source, target, param, role) => project
+                .set_link_role(source, target, param, role)
+                .map(|_| ProjectCommandOutcome::Done),
+            ProjectCommand::LinkPath(path_uid, target_uid, param) => {
+                project.link_path(path_uid, target_uid, param)?;
+                project.regenerate_signal_chain_for_link_source(ControlLinkSource::Path(path_uid));
+                Ok(ProjectCommandOutcome::Done)
+            }
+            ProjectCommand::UnlinkPath(path_uid, target_uid, param) => {
+                project.unlink_path(path_uid, target_uid, param);
+                project.regenerate_signal_chain_for_link_source(ControlLinkSource::Path(path_uid));
+                Ok(ProjectCommandOutcome::Done)
+            }
+            ProjectCommand::AddPath(track_uid, path) => project
+                .add_path(track_uid, path)
+                .map(ProjectCommandOutcome::Path),
+            ProjectCommand::RemovePath(path_uid) => {
+                if project.remove_path(path_uid).is_some() {
+                    Ok(ProjectCommandOutcome::Done)
+                } else {
+                    Err(anyhow!("Couldn't find path {path_uid}"))
+                }
+            }
+            ProjectCommand::AddAudioClip(track_uid, clip) => project
+                .add_audio_clip(track_uid, clip)
+                .map(ProjectCommandOutcome::AudioClip),
+            ProjectCommand::MoveAudioClip(track_uid, clip_uid, new_position, copy_original) => {
+                project
+                    .move_audio_clip(track_uid, clip_uid, new_position, copy_original)
+                    .map(ProjectCommandOutcome::AudioClip)
+            }
+            ProjectCommand::RemoveAudioClip(track_uid, clip_uid) => project
+                .remove_audio_clip(track_uid, clip_uid)
+                .map(|_| ProjectCommandOutcome::Done),
+            ProjectCommand::SetLoopRange(loop_range) => {
+                project.set_loop_range(loop_range);
+                Ok(ProjectCommandOutcome::Done)
+            }
+            ProjectCommand::SetPunchRange(punch_range) => {
+                project.set_punch_range(punch_range);
+                Ok(ProjectCommandOutcome::Done)
+            }
+            ProjectCommand::AddTempoChange(change) => project
+                .add_tempo_change(change)
+                .map(|_| ProjectCommandOutcome::Done),
+            ProjectCommand::RemoveTempoChange(time) => {
+                if Self::tempo_change_at(project, time).is_some() {
+                    project.remove_tempo_change(time);
+                    Ok(ProjectCommandOutcome::Done)
+                } else {
+                    Err(anyhow!("There's no tempo change at {time}"))
+                }
+            }
+            ProjectCommand::AddTimeSignatureChange(change) => {
+                project.add_time_signature_change(change);
+                Ok(ProjectCommandOutcome::Done)
+            }
+            ProjectCommand::RemoveTimeSignatureChange(time) => {
+                if Self::time_signature_change_at(project, time).is_some() {
+                    project.remove_time_signature_change(time);
+                    Ok(ProjectCommandOutcome::Done)
+                } else {
+                    Err(anyhow!("There's no time-signature change at {time}"))
+                }
+            }
+        }
+    }
+
+    // Returns a command that reverses this one, given the project as it is
+    // before this one is applied, or None if only a snapshot will do.
+    pub(crate) fn inverse(&self, project: &mut Project) -> Option<ProjectCommand> {
+        match self {
+            ProjectCommand::SetTrackPosition(track_uid, _) => project
+                .track_uids()
+                .iter()
+                .position(|uid| uid == track_uid)
+                .map(|position| ProjectCommand::SetTrackPosition(*track_uid, position)),
+            ProjectCommand::MuteTrack(track_uid, _) => Some(ProjectCommand::MuteTrack(
+                *track_uid,
+                project.is_track_muted(*track_uid),
+            )),
+            ProjectCommand::SetSoloTrack(_) => {
+                Some(ProjectCommand::SetSoloTrack(project.solo_track()))
+            }
+            ProjectCommand::SetTrackMidiChannel(track_uid, _) => project
+                .track_midi_channel(*track_uid)
+                .map(|midi_channel| ProjectCommand::SetTrackMidiChannel(*track_uid, midi_channel)),
+            ProjectCommand::SetTrackOutput(track_uid, _) => Some(ProjectCommand::SetTrackOutput(
+                *track_uid,
+                project.track_output(*track_uid),
+            )),
+            ProjectCommand::AddSend(src_uid, dst_uid, _) => {
+                if project.send_amounts(*src_uid, *dst_uid).is_empty() {
+                    Some(ProjectCommand::RemoveSend(*src_uid, *dst_uid))
+                } else {
+                    None
+                }
+            }
+            ProjectCommand::RemoveSend(src_uid, dst_uid) => {
+                match project.send_amounts(*src_uid, *dst_uid)[..] {
+                    [amount] => Some(ProjectCommand::AddSend(*src_uid, *dst_uid, amount)),
+                    _ => None,
+                }
+            }
+            ProjectCommand::SetHumidity(uid, _) => {
+                Some(ProjectCommand::SetHumidity(*uid, project.get_humidity(uid)))
+            }
+            ProjectCommand::SetSidechain(uid, _) => {
+                Some(ProjectCommand::SetSidechain(*uid, project.sidechain(*uid)))
+            }
+            ProjectCommand::RemoveArrangedPattern(track_uid, arrangement_uid) => {
+                // Arranging the pattern anew brings back only this one
+                // arrangement, on the default channel.
+                let arrangement = project.composer.arrangements.get(arrangement_uid)?;
+                let is_only_arrangement = project
+                    .composer
+                    .patterns_to_arrangements
+                    .get(&arrangement.pattern_uid)
+                    .is_some_and(|arrangement_uids| arrangement_uids.len() == 1);
+                if is_only_arrangement && arrangement.midi_channel == MidiChannel::default() {
+                    project
+                        .composer
+                        .pattern(arrangement.pattern_uid)
+                        .map(|pattern| {
+                            ProjectCommand::ArrangeNewPattern(
+                                *track_uid,
+                                pattern.clone(),
+                                arrangement.position,
+                            )
+                        })
+                } else {
+                    None
+                }
+            }
+            ProjectCommand::MoveArrangement(track_uid, arrangement_uid, _, false) => project
+                .composer
+                .arrangements
+                .get(arrangement_uid)
+                .map(|arrangement| {
+                    ProjectCommand::MoveArrangement(
+                        *track_uid,
+                        *arrangement_uid,
+                        arrangement.position,
+                        false,
+                    )
+                }),
+            ProjectCommand::Link(source, target, param) => {
+                // Unlinking would also remove a link that was already there.
+                if project
+                    .link_role(ControlLinkSource::Entity(*source), *target, *param)
+                    .is_none()
+                {
+                    Some(ProjectCommand::Unlink(*source, *target, *param))
+                } else {
+                    None
+                }
+            }
+            ProjectCommand::BindMidiCc(channel, cc, uid, param) => {
+                // Binding the same pair twice has no effect, so only a new
+                // binding can be undone by unbinding it.
+                if project.is_midi_cc_bound(*channel, *cc, *uid, *param) {
+                    None
+                } else {
+                    Some(ProjectCommand::UnbindMidiCc(*channel, *cc, *uid, *param))
+                }
+            }
+            ProjectCommand::UnbindMidiCc(channel, cc, uid, param) => {
+                if project.is_midi_cc_bound(*channel, *cc, *uid, *param) {
+                    Some(ProjectCommand::BindMidiCc(*channel, *cc, *uid, *param))
+                } else {
+                    None
+                }
+            }
+            ProjectCommand::SetLinkMapping(source, target, param, _) => {
+                project.link_role(*source, *target, *param).map(|_| {
+                    ProjectCommand::SetLinkMapping(
+                        *source,
+                        *target,
+                        *param,
+                        project.link_mapping(*source, *target, *param),
+                    )
+                })
+            }
+            ProjectCommand::SetLinkRole(source, target, param, _) => project
+                .link_role(*source, *target, *param)
+                .map(|role| ProjectCommand::SetLinkRole(*source, *target, *param, role)),
+            ProjectCommand::MoveAudioClip(track_uid, clip_uid, _, false) => {
+                project.audio_clip(*clip_uid).map(|clip| {
+                    ProjectCommand::MoveAudioClip(*track_uid, *clip_uid, clip.position, false)
+                })
+            }
+            ProjectCommand::SetLoopRange(_) => {
+                Some(ProjectCommand::SetLoopRange(project.loop_range().cloned()))
+            }
+            ProjectCommand::SetPunchRange(_) => Some(ProjectCommand::SetPunchRange(
+                project.punch_range().cloned(),
+            )),
+            ProjectCommand::AddTempoChange(change) => {
+                Some(match Self::tempo_change_at(project, change.time) {
+                    Some(prior_change) => ProjectCommand::AddTempoChange(prior_change),
+                    None => ProjectCommand::RemoveTempoChange(change.time),
+                })
+            }
+            ProjectCommand::RemoveTempoChange(time) => {
+                Self::tempo_change_at(project, *time).map(ProjectCommand::AddTempoChange)
+            }
+            ProjectCommand::AddTimeSignatureChange(change) => {
+                Some(match Self::time_signature_change_at(project, change.time) {
+                    Some(prior_change) => ProjectCommand::AddTimeSignatureChange(prior_change),
+                    None => ProjectCommand::RemoveTimeSignatureChange(change.time),
+                })
+            }
+            ProjectCommand::RemoveTimeSignatureChange(time) => {
+                Self::time_signature_change_at(project, *time)
+                    .map(ProjectCommand::AddTimeSignatureChange)
+            }
+            _ => None,
+        }
+    }
+
+    fn tempo_change_at(project: &Project, time: MusicalTime) -> Option<TempoChange> {
+        project
+            .transport
+            .tempo_map
+            .tempo_changes()
+            .iter()
+            .find(|c| c.time == time)
+            .copied()
+    }
+
+    fn time_signature_change_at(
+        project: &Project,
+        time: MusicalTime,
+    ) -> Option<TimeSignatureChange> {
+        project
+            .transport
+            .tempo_map
+            .time_signature_changes()
+            .iter()
+            .find(|c| c.time == time)
+            .copied()
+    }
+}
+
+/// What a successful [ProjectCommand] produced.
+#[derive(Debug, PartialEq)]
+pub enum ProjectCommandOutcome {
+    /// The command succeeded and didn't create anything.
+    Done,
+    /// The command created this track.
+    Track(TrackUid),
+    /// The command added this entity.
+    Entity(Uid),
+    /// The command added this pattern.
+    Pattern(PatternUid),
+    /// The command created this arrangement.
+    Arrangement(ArrangementUid),
+    /// The command created this audio clip.
+    AudioClip(AudioClipUid),
+    /// The command added this automation path.
+    Path(PathUid),
+}
+
+// How to get from one point in a project's history to another.
+#[derive(Debug)]
+pub(crate) enum ProjectHistoryStep {
+    // A command that does it.
+    Command(ProjectCommand),
+    // The whole project, as it was at the other point.
+    Snapshot(ProjectSnapshot),
+}
+
+// A serialized copy of a [Project]. Audio clips' samples aren't serialized,
+// so they're kept alongside, which also means that restoring the snapshot
+// doesn't have to read them from disk again.
+#[derive(Debug)]
+pub(crate) struct ProjectSnapshot {
+    pub(crate) json: String,
+    pub(crate) clip_samples: FxHashMap<AudioClipUid, (Arc<Vec<StereoSample>>, SampleRate)>,
+}
+
+/// The undo and redo stacks for a [Project]. Each entry either is a
+/// [ProjectCommand] that reverses (for undo) or reapplies (for redo) a
+/// command, or is a snapshot of the project as it was before (for undo) or
+/// after (for redo) a command.
+#[derive(Debug, Default)]
+pub struct ProjectHistory {
+    undo_stack: VecDeque<ProjectHistoryStep>,
+    redo_stack: Vec<ProjectHistoryStep>,
+}
+impl ProjectHistory {
+    /// The most commands that can be undone. Older history is discarded.
+    pub const MAX_DEPTH: usize = 100;
+
+    /// Returns true if there's a command to undo.
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    /// Returns true if there's an undone command to redo.
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Forgets all history.
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+
+    // Records how to reverse a new command. A new command makes the redo
+    // history meaningless.
+    pub(crate) fn record(&mut self, step: ProjectHistoryStep) {
+        self.push_undo(step);
+        self.redo_stack.clear();
+    }
+
+    pub(crate) fn push_undo(&mut self, step: ProjectHistoryStep) {
+        if self.undo_stack.len() >= Self::MAX_DEPTH {
+            self.undo_stack.pop_front();
+        }
+        self.undo_stack.push_back(step);
+    }
+
+    pub(crate) fn pop_undo(&mut self) -> Option<ProjectHistoryStep> {
+        self.undo_stack.pop_back()
+    }
+
+    pub(crate) fn push_redo(&mut self, step: ProjectHistoryStep) {
+        self.redo_stack.push(step);
+    }
+
+    pub(crate) fn pop_redo(&mut self) -> Option<ProjectHistoryStep> {
+        self.redo_stack.pop()
+    }
+}
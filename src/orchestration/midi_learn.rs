@@ -0,0 +1,135 @@
+// Copyright (c) 2024 Mike Tsao
+
+use crate::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Connects a MIDI continuous controller (CC) on a channel to an entity's
+/// parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct MidiCcBinding {
+    /// The channel that the controller sends on.
+    pub channel: MidiChannel,
+    /// The controller number (0..=127).
+    pub cc: u8,
+    /// The parameter that the controller drives, and how its values are
+    /// transformed along the way.
+    pub link: ControlLink,
+}
+
+/// The table of [MidiCcBinding]s, along with a learn mode that binds the next
+/// controller that moves to a chosen parameter.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct MidiCcBindings {
+    bindings: Vec<MidiCcBinding>,
+
+    // The parameter waiting to be bound to the next controller that moves.
+    #[serde(skip)]
+    learning: Option<(Uid, ControlIndex)>,
+}
+impl MidiCcBindings {
+    /// Binds a controller to a parameter. A controller can drive any number of
+    /// parameters, but binding the same pair twice has no effect.
+    pub fn bind(&mut self, channel: MidiChannel, cc: u8, uid: Uid, param: ControlIndex) {
+        if !self
+            .bindings
+            .iter()
+            .any(|b| b.channel == channel && b.cc == cc && b.link.is_for(uid, param))
+        {
+            self.bindings.push(MidiCcBinding {
+                channel,
+                cc,
+                link: ControlLink::new_with(uid, param),
+            });
+        }
+    }
+
+    /// Removes every binding from the given controller to the given parameter.
+    pub fn unbind(&mut self, channel: MidiChannel, cc: u8, uid: Uid, param: ControlIndex) {
+        self.bindings
+            .retain(|b| !(b.channel == channel && b.cc == cc && b.link.is_for(uid, param)));
+    }
+
+    /// Removes every binding to any of the given entity's parameters.
+    pub fn unbind_entity(&mut self, uid: Uid) {
+        self.bindings.retain(|b| b.link.uid != uid);
+    }
+
+    #[allow(missing_docs)]
+    pub fn bindings(&self) -> &[MidiCcBinding] {
+        &self.bindings
+    }
+
+    /// Returns the links that the given controller drives.
+    pub fn links_for(&self, channel: MidiChannel, cc: u8) -> Vec<ControlLink> {
+        self.bindings
+            .iter()
+            .filter(|b| b.channel == channel && b.cc == cc)
+            .map(|b| b.link)
+            .collect()
+    }
+
+    /// Starts learn mode for the given parameter, or cancels it if None.
+    pub fn learn(&mut self, target: Option<(Uid, ControlIndex)>) {
+        self.learning = target;
+    }
+
+    /// Returns the parameter that learn mode is waiting to bind, if any.
+    pub fn learning(&self) -> Option<(Uid, ControlIndex)> {
+        self.learning
+    }
+
+    /// Tells learn mode that a controller moved. If learn mode was waiting,
+    /// then the controller is bound to its parameter, learn mode ends, and the
+    /// return value is true.
+    pub fn handle_cc(&mut self, channel: MidiChannel, cc: u8) -> bool {
+        if let Some((uid, param)) = self.learning.take() {
+            self.bind(channel, cc, uid, param);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn midi_cc_bindings_learn() {
+        let mut bindings = MidiCcBindings::default();
+        let channel = MidiChannel::default();
+        assert!(
+            !bindings.handle_cc(channel, 74),
+            "Nothing should be learned unless learn mode is on"
+        );
+        assert!(bindings.links_for(channel, 74).is_empty());
+
+        bindings.learn(Some((Uid(7), ControlIndex(2))));
+        assert!(bindings.handle_cc(channel, 74));
+        assert!(
+            bindings.learning().is_none(),
+            "Learn mode should end after binding"
+        );
+        assert_eq!(
+            bindings.links_for(channel, 74),
+            vec![ControlLink::new_with(Uid(7), ControlIndex(2))]
+        );
+        assert!(
+            bindings.links_for(MidiChannel(1), 74).is_empty(),
+            "Bindings are per channel"
+        );
+
+        bindings.bind(channel, 74, Uid(7), ControlIndex(2));
+        assert_eq!(bindings.bindings().len(), 1, "Duplicates should be ignored");
+
+        let json = serde_json::to_string(&bindings).unwrap();
+        let restored: MidiCcBindings = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.bindings(), bindings.bindings());
+
+        bindings.unbind(channel, 74, Uid(7), ControlIndex(2));
+        assert!(bindings.bindings().is_empty());
+    }
+}
@@ -82,17 +82,20 @@ impl MidiRouter {
     }
 
     /// Sends CC 123 to every instrument, which is supposed to shut all notes
-    /// off.
+    /// off. Instruments hold note-offs while the sustain pedal is down, so it
+    /// first lifts the pedal with CC 64.
     pub fn all_notes_off(&mut self, entity_repo: &mut EntityRepository) {
         for channel in MidiChannel::MIN_VALUE..=MidiChannel::MAX_VALUE {
-            let _ = self.route(
-                entity_repo,
-                MidiChannel(channel),
-                MidiMessage::Controller {
-                    controller: 123.into(),
-                    value: 0.into(),
-                },
-            );
+            for controller in [64, 123] {
+                let _ = self.route(
+                    entity_repo,
+                    MidiChannel(channel),
+                    MidiMessage::Controller {
+                        controller: controller.into(),
+                        value: 0.into(),
+                    },
+                );
+            }
         }
     }
 
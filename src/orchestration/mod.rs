@@ -8,25 +8,32 @@ pub mod prelude {
 }
 
 pub use {
-    basic_project::{BasicProject, SignalChainItem, TrackInfo, TrackViewMode},
+    audio_clip::{AudioClip, AudioClipRepository, AudioClipUid, AudioClipUidFactory},
+    basic_project::{BasicProject, SignalChainItem, SignalChainLink, TrackInfo, TrackViewMode},
+    command::{ProjectCommand, ProjectCommandOutcome, ProjectHistory},
     humidity::Humidifier,
+    midi_learn::{MidiCcBinding, MidiCcBindings},
     orchestrator::Orchestrator,
     project::{AudioSenderFn, Project, ProjectTitle, ProjectViewState},
     repositories::{EntityRepository, TrackRepository},
     track::{TrackTitle, TrackUid, TrackUidFactory},
     traits::Projects,
-    util::ProjectExporter,
+    util::{ProjectExporter, WavExportOptions, WavExportOptionsBuilder, WavSampleFormat},
 };
 
 use {bus::BusStation, midi_router::MidiRouter};
 
+mod audio_clip;
 mod basic_project;
 mod bus;
+mod command;
 mod humidity;
+mod midi_learn;
 mod midi_router;
 mod orchestrator;
 mod project;
 mod repositories;
+mod smf;
 mod track;
 mod traits;
 mod util;
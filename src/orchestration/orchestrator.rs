@@ -2,10 +2,10 @@
 
 use super::{humidity::Humidifier, BusStation};
 use crate::{
-    orchestration::{EntityRepository, TrackRepository},
+    orchestration::{AudioClipRepository, EntityRepository, TrackRepository},
     prelude::*,
 };
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use core::fmt::Debug;
 use delegate::delegate;
 use rustc_hash::FxHashMap;
@@ -22,18 +22,39 @@ pub struct Orchestrator {
     pub entity_repo: EntityRepository,
 
     pub aux_track_uids: Vec<TrackUid>,
+    /// The tracks that can hold [AudioClip](super::AudioClip)s.
+    #[serde(default)]
+    pub audio_track_uids: Vec<TrackUid>,
     pub bus_station: BusStation,
     pub humidifier: Humidifier,
     pub mixer: Mixer,
+
+    #[serde(default)]
+    pub audio_clips: AudioClipRepository,
+
+    /// For each entity that has a sidechain, the track whose output drives it.
+    #[serde(default)]
+    pub sidechains: FxHashMap<Uid, TrackUid>,
+
+    #[serde(skip)]
+    e: OrchestratorEphemerals,
+}
+#[derive(Debug, Default)]
+struct OrchestratorEphemerals {
+    // If present, then each track's output is saved here as it's mixed.
+    stems: Option<FxHashMap<TrackUid, Vec<StereoSample>>>,
+
+    // The order in which generate() renders the tracks, which puts the
+    // sources of sidechains before the tracks that they drive. It's worked
+    // out whenever the tracks or sidechains change, rather than every buffer.
+    track_order: Vec<TrackUid>,
 }
 #[allow(missing_docs)]
 impl Orchestrator {
     delegate! {
         to self.track_repo {
-            pub fn create_track(&mut self) -> Result<TrackUid>;
             #[call(uids)]
             pub fn track_uids(&self) -> &[TrackUid];
-            pub fn set_track_position(&mut self, uid: TrackUid, new_position: usize) -> Result<()>;
             pub fn mint_track_uid(&self) -> TrackUid;
         }
         to self.entity_repo {
@@ -42,14 +63,6 @@ impl Orchestrator {
                 track_uid: TrackUid,
                 entity: Box<dyn Entity>,
             ) -> Result<Uid>;
-            pub fn move_entity(
-                &mut self,
-                uid: Uid,
-                new_track_uid: Option<TrackUid>,
-                new_position: Option<usize>,
-            ) -> Result<()>;
-            pub fn delete_entity(&mut self, uid: Uid) -> Result<()>;
-            pub fn remove_entity(&mut self, uid: Uid) -> Result<Box<dyn Entity>>;
             pub fn mint_entity_uid(&self) -> Uid;
         }
         // TODO: once this has been moved to ensnare crate, go to this field and
@@ -82,9 +95,138 @@ impl Orchestrator {
         }
     }
 
+    pub fn create_track(&mut self) -> Result<TrackUid> {
+        let track_uid = self.track_repo.create_track()?;
+        self.update_track_order();
+        Ok(track_uid)
+    }
+
+    pub fn set_track_position(&mut self, uid: TrackUid, new_position: usize) -> Result<()> {
+        self.track_repo.set_track_position(uid, new_position)?;
+        self.update_track_order();
+        Ok(())
+    }
+
     pub fn delete_track(&mut self, uid: TrackUid) -> Result<()> {
         self.bus_station.remove_sends_for_track(uid);
-        self.track_repo.delete_track(uid)
+        self.audio_clips.remove_clips_for_track(uid);
+        self.sidechains.retain(|_, source| *source != uid);
+        let r = self.track_repo.delete_track(uid);
+        self.update_track_order();
+        r
+    }
+
+    pub fn delete_entity(&mut self, uid: Uid) -> Result<()> {
+        self.sidechains.remove(&uid);
+        let r = self.entity_repo.delete_entity(uid);
+        self.update_track_order();
+        r
+    }
+
+    pub fn remove_entity(&mut self, uid: Uid) -> Result<Box<dyn Entity>> {
+        self.sidechains.remove(&uid);
+        let r = self.entity_repo.remove_entity(uid);
+        self.update_track_order();
+        r
+    }
+
+    /// Moves the entity to another position, on its own track or another one.
+    /// Fails if the entity's sidechain would end up depending on its own
+    /// track's output.
+    pub fn move_entity(
+        &mut self,
+        uid: Uid,
+        new_track_uid: Option<TrackUid>,
+        new_position: Option<usize>,
+    ) -> Result<()> {
+        if let (Some(new_track_uid), Some(source_track_uid)) = (new_track_uid, self.sidechain(uid))
+        {
+            let mut edges = self.sidechain_edges(Some(uid));
+            edges.push((source_track_uid, new_track_uid));
+            if Self::order_tracks(&self.track_repo.uids, &edges).is_none() {
+                return Err(anyhow!(
+                    "Entity {uid} can't move to track {new_track_uid}, because its sidechain would depend on that track's output"
+                ));
+            }
+        }
+        self.entity_repo
+            .move_entity(uid, new_track_uid, new_position)?;
+        self.update_track_order();
+        Ok(())
+    }
+
+    /// Feeds the output of the given track to the entity's sidechain input, or
+    /// disconnects the sidechain if None. The source must be a non-aux track
+    /// other than the entity's own, and it can't itself depend on the entity's
+    /// track through other sidechains.
+    pub fn set_sidechain(&mut self, uid: Uid, source_track_uid: Option<TrackUid>) -> Result<()> {
+        let track_uid = self
+            .track_for_entity(uid)
+            .ok_or_else(|| anyhow!("Couldn't find entity {uid}"))?;
+        if let Some(source_track_uid) = source_track_uid {
+            if !self.track_repo.uids.contains(&source_track_uid) {
+                return Err(anyhow!("Couldn't find track {source_track_uid}"));
+            }
+            if source_track_uid == track_uid || self.aux_track_uids.contains(&source_track_uid) {
+                return Err(anyhow!(
+                    "Track {source_track_uid} can't be a sidechain source for entity {uid}"
+                ));
+            }
+            let mut edges = self.sidechain_edges(Some(uid));
+            edges.push((source_track_uid, track_uid));
+            if Self::order_tracks(&self.track_repo.uids, &edges).is_none() {
+                return Err(anyhow!(
+                    "Track {source_track_uid} can't be a sidechain source for entity {uid}, because that would make a loop"
+                ));
+            }
+            self.sidechains.insert(uid, source_track_uid);
+        } else {
+            self.sidechains.remove(&uid);
+        }
+        self.update_track_order();
+        Ok(())
+    }
+
+    // Each sidechain as (source track, track of the entity that it drives),
+    // leaving out the given entity's.
+    fn sidechain_edges(&self, except: Option<Uid>) -> Vec<(TrackUid, TrackUid)> {
+        self.sidechains
+            .iter()
+            .filter(|(uid, _)| Some(**uid) != except)
+            .filter_map(|(uid, source)| Some((*source, self.track_for_entity(*uid)?)))
+            .collect()
+    }
+
+    // Orders the tracks so that each one comes after the tracks that drive its
+    // sidechains, and otherwise keeps them in position order. Returns None if
+    // the sidechains form a loop.
+    fn order_tracks(
+        track_uids: &[TrackUid],
+        edges: &[(TrackUid, TrackUid)],
+    ) -> Option<Vec<TrackUid>> {
+        let mut remaining = track_uids.to_vec();
+        let mut order = Vec::with_capacity(remaining.len());
+        while !remaining.is_empty() {
+            let index = remaining.iter().position(|track_uid| {
+                !edges
+                    .iter()
+                    .any(|(source, driven)| driven == track_uid && remaining.contains(source))
+            })?;
+            order.push(remaining.remove(index));
+        }
+        Some(order)
+    }
+
+    // A loaded project could still have a loop, so this falls back to
+    // position order rather than leaving out tracks.
+    fn update_track_order(&mut self) {
+        self.e.track_order = Self::order_tracks(&self.track_repo.uids, &self.sidechain_edges(None))
+            .unwrap_or_else(|| self.track_repo.uids.clone());
+    }
+
+    /// Returns the track that drives the entity's sidechain input, if any.
+    pub fn sidechain(&self, uid: Uid) -> Option<TrackUid> {
+        self.sidechains.get(&uid).copied()
     }
 
     pub fn entity_uids(&self, uid: TrackUid) -> Option<&[Uid]> {
@@ -100,6 +242,28 @@ impl Orchestrator {
     pub fn track_for_entity(&self, uid: Uid) -> Option<TrackUid> {
         self.entity_repo.track_for_uid.get(&uid).copied()
     }
+
+    /// Starts or stops saving each track's output, as it's mixed, so that it
+    /// can be exported as a stem. Retrieve it with
+    /// [Orchestrator::take_stems()].
+    pub fn set_is_capturing_stems(&mut self, is_capturing: bool) {
+        self.e.stems = if is_capturing {
+            Some(FxHashMap::default())
+        } else {
+            None
+        };
+    }
+
+    /// Returns each track's output since the last call. A track's stem is
+    /// exactly what it contributed to the mix, so muted tracks are silent and
+    /// aux tracks include the audio sent to them.
+    pub fn take_stems(&mut self) -> FxHashMap<TrackUid, Vec<StereoSample>> {
+        if let Some(stems) = self.e.stems.as_mut() {
+            core::mem::take(stems)
+        } else {
+            FxHashMap::default()
+        }
+    }
 }
 impl Controls for Orchestrator {
     fn time_range(&self) -> Option<TimeRange> {
@@ -107,11 +271,12 @@ impl Controls for Orchestrator {
     }
 
     fn update_time_range(&mut self, time_range: &TimeRange) {
+        self.audio_clips.update_time_range(time_range);
         self.entity_repo.update_time_range(time_range)
     }
 
     fn is_finished(&self) -> bool {
-        self.entity_repo.is_finished()
+        self.entity_repo.is_finished() && self.audio_clips.is_finished()
     }
 
     fn play(&mut self) {
@@ -123,9 +288,15 @@ impl Controls for Orchestrator {
     }
 
     fn skip_to_start(&mut self) {
+        self.audio_clips.seek(MusicalTime::START);
         self.entity_repo.skip_to_start()
     }
 
+    fn seek(&mut self, position: MusicalTime) {
+        self.audio_clips.seek(position);
+        self.entity_repo.seek(position)
+    }
+
     // fn is_performing(&self) -> bool {
     //     self.entity_repo.is_performing()
     // }
@@ -139,12 +310,17 @@ impl Generates<StereoSample> for Orchestrator {
     fn generate(&mut self, values: &mut [StereoSample]) -> bool {
         let buffer_len = values.len();
         let solo_track_uid = self.solo_track();
+        let sample_rate = self.sample_rate();
+
+        // Tracks that feed sidechains go first, so that their output is ready
+        // for the entities that they drive.
+        let track_uids = &self.e.track_order;
 
         // First handle all non-aux tracks. As a side effect, we also create empty buffers for the aux tracks.
         let (track_buffers, mut aux_track_buffers): (
             FxHashMap<TrackUid, Vec<StereoSample>>,
             FxHashMap<TrackUid, Vec<StereoSample>>,
-        ) = self.track_repo.uids.iter().fold(
+        ) = track_uids.iter().fold(
             (FxHashMap::default(), FxHashMap::default()),
             |(mut h, mut aux_h), track_uid| {
                 let mut track_buffer = Vec::default();
@@ -155,9 +331,18 @@ impl Generates<StereoSample> for Orchestrator {
                     let should_work = !self.mixer.is_track_muted(*track_uid)
                         && (solo_track_uid.is_none() || solo_track_uid == Some(*track_uid));
                     if should_work {
+                        // Audio clips go first, so that the track's effects
+                        // process them.
+                        self.audio_clips
+                            .render_track(*track_uid, sample_rate, &mut track_buffer);
                         if let Some(entity_uids) = self.entity_repo.uids_for_track.get(track_uid) {
                             entity_uids.iter().for_each(|uid| {
                                 if let Some(entity) = self.entity_repo.entities.get_mut(uid) {
+                                    if let Some(sidechain) =
+                                        self.sidechains.get(uid).and_then(|source| h.get(source))
+                                    {
+                                        entity.set_sidechain(sidechain);
+                                    }
                                     entity.generate(&mut track_buffer);
                                     let humidity = self.humidifier.get_humidity(uid);
                                     if humidity != Normal::zero() {
@@ -202,6 +387,13 @@ impl Generates<StereoSample> for Orchestrator {
                     if let Some(entity_uids) = self.entity_repo.uids_for_track.get(track_uid) {
                         entity_uids.iter().for_each(|uid| {
                             if let Some(entity) = self.entity_repo.entities.get_mut(uid) {
+                                if let Some(sidechain) = self
+                                    .sidechains
+                                    .get(uid)
+                                    .and_then(|source| track_buffers.get(source))
+                                {
+                                    entity.set_sidechain(sidechain);
+                                }
                                 entity.transform(track_buffer);
                             }
                         });
@@ -218,13 +410,23 @@ impl Generates<StereoSample> for Orchestrator {
             .for_each(|(track_uid, buffer)| {
                 let should_mix = !self.mixer.is_track_muted(*track_uid)
                     && (solo_track_uid.is_none() || solo_track_uid == Some(*track_uid));
+                let mut stem = self
+                    .e
+                    .stems
+                    .as_mut()
+                    .map(|stems| stems.entry(*track_uid).or_default());
                 if should_mix {
-                    let output = self.track_output(*track_uid);
+                    let output = self.mixer.track_output(*track_uid);
                     for (dst, src) in values.iter_mut().zip(buffer) {
                         let stereo_sample = *src * output;
                         generated_some_signal |= stereo_sample != StereoSample::default();
                         *dst += stereo_sample;
+                        if let Some(stem) = stem.as_mut() {
+                            stem.push(stereo_sample);
+                        }
                     }
+                } else if let Some(stem) = stem.as_mut() {
+                    stem.resize(stem.len() + buffer_len, StereoSample::SILENCE);
                 }
             });
         generated_some_signal
@@ -268,6 +470,8 @@ impl Serializable for Orchestrator {
     fn after_deser(&mut self) {
         self.track_repo.after_deser();
         self.entity_repo.after_deser();
+        self.audio_clips.after_deser();
+        self.update_track_order();
     }
 }
 
@@ -349,6 +553,52 @@ mod tests {
         );
     }
 
+    #[test]
+    fn orchestrator_orders_tracks_for_sidechains() {
+        let mut orchestrator = Orchestrator::default();
+        let track_1 = orchestrator.create_track().unwrap();
+        let track_2 = orchestrator.create_track().unwrap();
+        let track_3 = orchestrator.create_track().unwrap();
+        let uid_1 = orchestrator
+            .add_entity(track_1, Box::new(TestInstrument::default()))
+            .unwrap();
+        let uid_3 = orchestrator
+            .add_entity(track_3, Box::new(TestInstrument::default()))
+            .unwrap();
+        assert_eq!(orchestrator.e.track_order, vec![track_1, track_2, track_3]);
+
+        assert!(orchestrator.set_sidechain(uid_1, Some(track_3)).is_ok());
+        let position = |orchestrator: &Orchestrator, track_uid: TrackUid| {
+            orchestrator
+                .e
+                .track_order
+                .iter()
+                .position(|t| *t == track_uid)
+                .unwrap()
+        };
+        assert!(
+            position(&orchestrator, track_3) < position(&orchestrator, track_1),
+            "A sidechain source should render before the track that it drives"
+        );
+
+        assert!(
+            orchestrator.set_sidechain(uid_3, Some(track_1)).is_err(),
+            "Sidechains shouldn't be able to form a loop"
+        );
+        assert!(
+            orchestrator
+                .move_entity(uid_1, Some(track_3), None)
+                .is_err(),
+            "An entity shouldn't be able to move onto its sidechain's source track"
+        );
+        assert_eq!(orchestrator.track_for_entity(uid_1), Some(track_1));
+        assert!(orchestrator.move_entity(uid_1, Some(track_2), None).is_ok());
+        assert!(position(&orchestrator, track_3) < position(&orchestrator, track_2));
+
+        assert!(orchestrator.delete_track(track_3).is_ok());
+        assert_eq!(orchestrator.e.track_order, vec![track_1, track_2]);
+    }
+
     #[test]
     fn bus_station_mainline() {
         let mut station = BusStation::default();
@@ -4,11 +4,17 @@
 //! serialization.
 
 use super::MidiRouter;
-use crate::orchestration::{Orchestrator, TrackTitle};
+use crate::orchestration::{
+    command::{ProjectHistoryStep, ProjectSnapshot},
+    AudioClip, AudioClipUid, MidiCcBindings, Orchestrator, ProjectCommand, ProjectCommandOutcome,
+    ProjectHistory, TrackTitle,
+};
+#[cfg(feature = "hound")]
+use crate::orchestration::{util::StereoWavWriter, ProjectExporter, WavExportOptions};
 use crate::{
-    composition::Composer,
+    composition::{Composer, MidiTake},
     prelude::*,
-    types::{ColorScheme, VisualizationQueue},
+    types::{ColorScheme, TempoChange, TempoMap, TimeSignatureChange, VisualizationQueue},
     util::SelectionSet,
 };
 use anyhow::{anyhow, Result};
@@ -21,7 +27,7 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use synonym::Synonym;
 
-use super::{SignalChainItem, TrackInfo, TrackViewMode};
+use super::{SignalChainItem, SignalChainLink, TrackInfo, TrackViewMode};
 
 /// A user-visible project title.
 #[derive(Synonym, Serialize, Deserialize, Derivative)]
@@ -103,6 +109,32 @@ pub struct ProjectEphemerals {
     /// tell the ArrangementWidget that it should select that arrangement.
     pub(crate) new_arrangement_track_uid: Option<TrackUid>,
     pub(crate) new_arrangement_arrangement_uid: Option<ArrangementUid>,
+
+    /// The edits that can be undone and redone.
+    pub history: ProjectHistory,
+
+    /// The tempo and sample rate that the audio clips' frames were last
+    /// calculated for.
+    clip_timing: Option<(Tempo, SampleRate)>,
+
+    /// The track that's armed for MIDI recording, if any.
+    midi_record_track: Option<TrackUid>,
+    midi_record_options: MidiRecordOptions,
+    /// The notes recorded so far, while the project plays with a track armed.
+    midi_take: Option<MidiTake>,
+
+    /// The project as it was when the current automation recording pass
+    /// began. If the pass changes anything, this becomes a single undo step
+    /// when the pass ends.
+    automation_pass_snapshot: Option<ProjectSnapshot>,
+    /// Whether the current automation recording pass has changed anything.
+    automation_pass_has_changes: bool,
+
+    /// Scratch space for the events of the buffer being rendered, so that
+    /// rendering doesn't allocate. See Project::gather_work_events().
+    work_events: Vec<(usize, usize, Option<ControlLinkSource>, WorkEvent)>,
+    /// Scratch space for the events that happen on the same frame.
+    simultaneous_events: Vec<(Option<ControlLinkSource>, WorkEvent)>,
 }
 
 /// A musical piece. Also knows how to render the piece to digital audio.
@@ -134,6 +166,10 @@ pub struct Project {
     #[allow(missing_docs)]
     pub track_to_midi_router: FxHashMap<TrackUid, MidiRouter>,
     track_to_paths: FxHashMap<TrackUid, Vec<PathUid>>,
+    // Which external MIDI controllers drive which entity parameters. Changes
+    // go through ProjectCommands, so that they can be undone.
+    #[serde(default)]
+    midi_cc_bindings: MidiCcBindings,
 
     #[allow(missing_docs)]
     pub view_state: ProjectViewState,
@@ -151,8 +187,14 @@ impl Projects for Project {
     }
 
     fn delete_track(&mut self, uid: TrackUid) -> Result<()> {
+        if let Some(entity_uids) = self.orchestrator.entity_repo.uids_for_track.get(&uid) {
+            entity_uids
+                .iter()
+                .for_each(|entity_uid| self.midi_cc_bindings.unbind_entity(*entity_uid));
+        }
         self.track_to_midi_router.remove(&uid);
         self.orchestrator.aux_track_uids.retain(|t| *t != uid);
+        self.orchestrator.audio_track_uids.retain(|t| *t != uid);
         self.orchestrator.delete_track(uid)
     }
 
@@ -169,6 +211,7 @@ impl Projects for Project {
 
     fn delete_entity(&mut self, uid: Uid) -> Result<()> {
         self.set_midi_receiver_channel(uid, None)?;
+        self.midi_cc_bindings.unbind_entity(uid);
         let track_uid = self.orchestrator.track_for_entity(uid);
         let r = self.orchestrator.delete_entity(uid);
         if let Some(track_uid) = track_uid {
@@ -179,6 +222,7 @@ impl Projects for Project {
 
     fn remove_entity(&mut self, uid: Uid) -> Result<Box<dyn Entity>> {
         self.set_midi_receiver_channel(uid, None)?;
+        self.midi_cc_bindings.unbind_entity(uid);
         let track_uid = self.orchestrator.track_for_entity(uid);
         let r = self.orchestrator.remove_entity(uid);
         if let Some(track_uid) = track_uid {
@@ -201,27 +245,119 @@ impl Projects for Project {
         result
     }
 
+    fn set_is_rendering_offline(&mut self, is_rendering_offline: bool) {
+        self.transport
+            .set_is_rendering_offline(is_rendering_offline);
+        self.update_is_finished();
+    }
+
     fn generate_audio(
         &mut self,
         frames: &mut [StereoSample],
         mut midi_events_fn: Option<&mut MidiMessagesFn>,
     ) {
-        let is_finished_at_start = self.e.is_finished;
+        // If this buffer crosses the end of the loop range, then we render up
+        // to the end of the loop, jump back, and render the rest separately.
+        // That way, no single TimeRange crosses the jump.
+        // A buffer that ends exactly at the end of the loop jumps back, too;
+        // otherwise, the next buffer would start past the loop.
+        if let Some(frames_until_loop_end) = self.transport.frames_until_loop_end() {
+            if frames_until_loop_end <= frames.len() {
+                let (before, after) = frames.split_at_mut(frames_until_loop_end);
+                if !before.is_empty() {
+                    self.generate_audio(before, midi_events_fn.as_deref_mut());
+                }
+                self.wrap_loop();
+                if !after.is_empty() {
+                    self.generate_audio(after, midi_events_fn);
+                }
+                return;
+            }
+        }
+
+        // Everything works once for the whole buffer, and each event comes back
+        // with the time at which it happens. Generation is split at the frames
+        // where events happen, and the events are dispatched in between. That
+        // way, a note or control change takes effect on its own frame rather
+        // than at the start of the buffer, and the output doesn't depend on
+        // the buffer size. While playback is stopped, time doesn't move, so
+        // everything happens at the start of the buffer.
+        let was_finished = self.e.is_finished;
+        let start_frame = self.performing_frame();
+        self.follow_tempo_map();
         let time_range = self.transport.advance(frames.len());
         self.update_time_range(&time_range);
-        self.work(&mut |e| {
-            if let Some( midi_events_fn) = midi_events_fn.as_mut() {
-                match e {
-                    WorkEvent::Midi(channel, message) => midi_events_fn(channel,message),
-                    WorkEvent::MidiForTrack(_track, channel, message) => midi_events_fn(channel,message),
-                    WorkEvent::Control(_control_value) => panic!("generate_frames() received WorkEvent::Control, which should be handled elsewhere"),
-                }
+        self.gather_work_events(start_frame, frames.len());
+
+        // The scratch lists are taken out while they're in use, because
+        // dispatching needs all of self, and then put back, so that their
+        // allocations are reused by the next buffer.
+        let work_events = std::mem::take(&mut self.e.work_events);
+        let mut simultaneous_events = std::mem::take(&mut self.e.simultaneous_events);
+        let mut rendered = 0;
+        let mut events = work_events.iter().peekable();
+        while let Some((offset, ..)) = events.peek() {
+            let offset = *offset;
+            if offset > rendered {
+                self.render_frames(
+                    start_frame.map(|f| f + rendered),
+                    &mut frames[rendered..offset],
+                );
+                rendered = offset;
             }
-        });
-        if !is_finished_at_start && self.e.is_finished {
+            simultaneous_events.clear();
+            while let Some((_, _, source, event)) = events.next_if(|(o, ..)| *o == offset) {
+                simultaneous_events.push((*source, event.clone()));
+            }
+            self.dispatch_work_events(&simultaneous_events, &mut |e| {
+                if let Some(midi_events_fn) = midi_events_fn.as_mut() {
+                    match e {
+                        WorkEvent::Midi(channel, message) => midi_events_fn(channel, message),
+                        WorkEvent::MidiForTrack(_track, channel, message) => {
+                            midi_events_fn(channel, message)
+                        }
+                        WorkEvent::Control(_control_value) => panic!("generate_frames() received WorkEvent::Control, which should be handled elsewhere"),
+                    }
+                }
+            });
+        }
+        self.e.work_events = work_events;
+        self.e.simultaneous_events = simultaneous_events;
+        if rendered < frames.len() {
+            self.render_frames(start_frame.map(|f| f + rendered), &mut frames[rendered..]);
+        }
+        self.update_is_finished();
+        if !was_finished && self.e.is_finished {
             self.stop();
         }
-        self.generate(frames);
+    }
+
+    // Returns the offset within the current buffer of the frame during which
+    // the given time falls. A frame covers the time from its own start up to
+    // the start of the next frame, the same as the ranges that
+    // Transport::advance() hands out, so the answer doesn't depend on where
+    // the buffer starts.
+    fn frame_offset(
+        transport: &Transport,
+        start_frame: Option<usize>,
+        frame_count: usize,
+        when: MusicalTime,
+    ) -> usize {
+        if let Some(start_frame) = start_frame {
+            let last_frame = start_frame + frame_count.saturating_sub(1);
+            let mut frame = transport
+                .time_to_frames(when)
+                .clamp(start_frame, last_frame);
+            while frame > start_frame && transport.frames_to_time(frame) > when {
+                frame -= 1;
+            }
+            while frame < last_frame && transport.frames_to_time(frame + 1) <= when {
+                frame += 1;
+            }
+            frame - start_frame
+        } else {
+            0
+        }
     }
 
     delegate! {
@@ -387,6 +523,7 @@ impl Project {
         let track_uid = self.create_track()?;
         self.track_titles
             .insert(track_uid, TrackTitle(format!("Audio {}", track_uid)));
+        self.orchestrator.audio_track_uids.push(track_uid);
         Ok(track_uid)
     }
 
@@ -430,224 +567,1101 @@ impl Project {
         }
     }
 
-    fn update_is_finished(&mut self) {
-        self.e.is_finished = self.composer.is_finished() && self.orchestrator.is_finished();
+    // Asks everything that produces events to do its work for the current
+    // time range, and collects the events in self.e.work_events, each with the
+    // offset within the buffer of the frame when it happens, the order in
+    // which it arrived, and its source, if known.
+    fn gather_work_events(&mut self, start_frame: Option<usize>, frame_count: usize) {
+        let events = &mut self.e.work_events;
+        events.clear();
+        let transport = &self.transport;
+        let mut push = |source: Option<ControlLinkSource>, when: MusicalTime, event: WorkEvent| {
+            let offset = Self::frame_offset(transport, start_frame, frame_count, when);
+            let order = events.len();
+            events.push((offset, order, source, event));
+        };
+        self.automator
+            .work_as_proxy(&mut |source, when, event| push(Some(source), when, event));
+        self.composer
+            .work_timed(&mut |when, event| push(None, when, event));
+        self.orchestrator
+            .work_as_proxy(&mut |source, when, event| push(Some(source), when, event));
+
+        // Ties are broken by arrival order, so that simultaneous events keep
+        // the order they arrived in. (A stable sort would do the same, but it
+        // allocates.)
+        events.sort_unstable_by_key(|(offset, order, _, _)| (*offset, *order));
     }
 
-    /// Renders the project as a WAV file to the specified path.
-    #[cfg(feature = "hound")]
-    pub fn export_to_wav(&mut self, path: PathBuf) -> anyhow::Result<()> {
-        let spec = hound::WavSpec {
-            channels: 2,
-            sample_rate: self.sample_rate().into(),
-            bits_per_sample: 16,
-            sample_format: hound::SampleFormat::Int,
-        };
-        let mut writer = hound::WavWriter::create(path, spec)?;
+    // Delivers events from gather_work_events() to their destinations.
+    fn dispatch_work_events(
+        &mut self,
+        events: &[(Option<ControlLinkSource>, WorkEvent)],
+        control_events_fn: &mut ControlEventsFn,
+    ) {
+        // Events go out in the order they arrived, so that a note-off followed
+        // by a note-on for the same key retriggers the note.
+        for (source, event) in events {
+            match *event {
+                WorkEvent::Midi(_, _) => {
+                    // This is a logic error because it means that we don't know
+                    // which track created this MIDI event, which means that we
+                    // don't know which entities are eligible to receive it. (We
+                    // confine MIDI events to their originating track to avoid
+                    // the need for a system to route channels to tracks.)
+                    todo!("Project must know a MIDI event's originating track. Please map WorkEvent::Midi to WorkEvent::MidiForTrack before passing it to Project.");
+                }
+                WorkEvent::MidiForTrack(track_uid, channel, message) => {
+                    if let Some(midi_router) = self.track_to_midi_router.get(&track_uid) {
+                        let _ =
+                            midi_router.route(&mut self.orchestrator.entity_repo, channel, message);
+                    }
+                    // Give caller an opportunity to route messages elsewhere.
+                    control_events_fn(event.clone());
+                }
+                WorkEvent::Control(value) => {
+                    if let Some(source) = *source {
+                        self.dispatch_control_event(source, value);
+                    }
+                }
+            }
+        }
+    }
 
-        self.skip_to_start();
+    // Returns the frame at which the next step of playback starts, or None if
+    // playback is stopped.
+    fn performing_frame(&self) -> Option<usize> {
+        if self.transport.is_performing() {
+            Some(self.transport.current_frame())
+        } else {
+            None
+        }
+    }
 
-        let mut renderer = self.render();
-        while let Some(frame) = renderer.next() {
-            let (left, right) = frame.into_i16();
-            let _ = writer.write_sample(left);
-            let _ = writer.write_sample(right);
+    // Generates audio for frames that start at the given frame of the song (or
+    // None if playback is stopped).
+    fn render_frames(&mut self, start_frame: Option<usize>, frames: &mut [StereoSample]) {
+        // The clips' frames depend on the tempo and sample rate, which can
+        // change in ways that Project doesn't see directly, like automation.
+        let clip_timing = (self.transport.tempo(), self.transport.sample_rate());
+        if self.e.clip_timing != Some(clip_timing) {
+            self.e.clip_timing = Some(clip_timing);
+            self.orchestrator.audio_clips.invalidate_frames();
         }
+        let transport = &self.transport;
+        self.orchestrator
+            .audio_clips
+            .update_frames(start_frame, &|time| transport.time_to_frames(time));
+        self.generate(frames);
+    }
 
-        Ok(())
+    fn update_is_finished(&mut self) {
+        self.e.is_finished = self.composer.is_finished()
+            && self.orchestrator.is_finished()
+            && !self.transport.is_looping()
+            && !self.is_recording_midi();
     }
 
-    fn dispatch_control_event(&mut self, source: ControlLinkSource, value: ControlValue) {
-        self.automator.route(
-            &mut self.orchestrator.entity_repo,
-            Some(&mut |link| match link.uid {
-                Self::TRANSPORT_UID => self.transport.control_set_param_by_index(link.param, value),
-                _ => {
-                    eprintln!("Asked to route from unknown source {source}");
-                }
-            }),
-            source,
-            value,
-        );
+    /// Sets the range of the song that playback repeats. None turns off
+    /// looping. See [Transport::loop_range].
+    pub fn set_loop_range(&mut self, loop_range: Option<TimeRange>) {
+        self.transport.loop_range = loop_range;
+        self.update_is_finished();
     }
 
-    pub fn load(path: PathBuf) -> anyhow::Result<Self> {
-        let json = std::fs::read_to_string(&path)?;
-        let mut project = serde_json::from_str::<Self>(&json)?;
-        project.e.load_path = Some(path);
-        project.after_deser();
-        project.e.is_clean = true;
-        Ok(project)
+    #[allow(missing_docs)]
+    pub fn loop_range(&self) -> Option<&TimeRange> {
+        self.transport.loop_range.as_ref()
     }
 
-    pub fn save(&mut self, path: Option<PathBuf>) -> anyhow::Result<PathBuf> {
-        let save_path = {
-            if let Some(path) = path.as_ref() {
-                path.clone()
-            } else if let Some(path) = self.e.load_path.as_ref() {
-                path.clone()
-            } else {
-                return Err(anyhow!("Can't save without a path"));
-            }
-        };
+    /// Sets the range of the song in which recording happens. None allows
+    /// recording anywhere. See [Transport::punch_range].
+    pub fn set_punch_range(&mut self, punch_range: Option<TimeRange>) {
+        self.automator.set_punch_range(punch_range.clone());
+        self.transport.punch_range = punch_range;
+    }
 
-        self.before_ser();
-        let json = serde_json::to_string_pretty(&self)?;
-        std::fs::write(&save_path, json)?;
-        self.e.is_clean = true;
-        Ok(save_path)
+    #[allow(missing_docs)]
+    pub fn punch_range(&self) -> Option<&TimeRange> {
+        self.transport.punch_range.as_ref()
     }
 
-    pub fn load_path(&self) -> Option<&PathBuf> {
-        self.e.load_path.as_ref()
+    /// Adds a change to the [TempoMap], replacing any tempo change at the same
+    /// time. Fails if the tempo is invalid; see [TempoMap::add_tempo_change()].
+    pub fn add_tempo_change(&mut self, change: TempoChange) -> anyhow::Result<()> {
+        self.transport.tempo_map.add_tempo_change(change)?;
+        self.orchestrator.audio_clips.invalidate_frames();
+        Ok(())
     }
 
-    pub fn add_path(&mut self, track_uid: TrackUid, path: SignalPath) -> Result<PathUid> {
-        let path_uid = self.automator.add_path(path)?;
-        self.track_to_paths
-            .entry(track_uid)
-            .or_default()
-            .push(path_uid);
-        Ok(path_uid)
+    /// Removes the [TempoMap]'s tempo change at the given time, if there is
+    /// one.
+    pub fn remove_tempo_change(&mut self, time: MusicalTime) {
+        self.transport.tempo_map.remove_tempo_change(time);
+        self.orchestrator.audio_clips.invalidate_frames();
     }
 
-    /// Picks the next view mode for the given track.
-    pub fn advance_track_view_mode(&mut self, track_uid: TrackUid) {
-        let mode = match self.track_view_mode(track_uid) {
-            TrackViewMode::Composition => self.next_track_view_mode_with_path_uid(track_uid, None),
-            TrackViewMode::Control(path_uid) => {
-                self.next_track_view_mode_with_path_uid(track_uid, Some(path_uid))
-            }
-        };
-        self.set_track_view_mode(track_uid, mode);
+    /// Adds a change to the [TempoMap], replacing any time-signature change at
+    /// the same time.
+    pub fn add_time_signature_change(&mut self, change: TimeSignatureChange) {
+        self.transport.tempo_map.add_time_signature_change(change);
     }
 
-    fn track_view_mode(&self, track_uid: TrackUid) -> TrackViewMode {
-        self.view_state
-            .track_view_mode
-            .get(&track_uid)
-            .copied()
-            .unwrap_or_default()
+    /// Removes the [TempoMap]'s time-signature change at the given time, if
+    /// there is one.
+    pub fn remove_time_signature_change(&mut self, time: MusicalTime) {
+        self.transport.tempo_map.remove_time_signature_change(time);
     }
 
-    pub(crate) fn set_track_view_mode(&mut self, track_uid: TrackUid, mode: TrackViewMode) {
-        self.view_state
-            .track_view_mode
-            .insert(track_uid.clone(), mode);
-        self.regenerate_signal_chain(track_uid);
+    #[allow(missing_docs)]
+    pub fn tempo_map(&self) -> &TempoMap {
+        &self.transport.tempo_map
+    }
+
+    // Keeps entities in step with the tempo and time signature that the
+    // [TempoMap] says are in effect at the current time. The transport's own
+    // tempo and time signature stay put, because they're where the map
+    // starts.
+    fn follow_tempo_map(&mut self) {
+        let tempo = self.transport.current_tempo();
+        if tempo != self.orchestrator.tempo() {
+            self.orchestrator.update_tempo(tempo);
+            self.composer.update_tempo(tempo);
+        }
+        let time_signature = self
+            .transport
+            .time_signature_at(self.transport.current_time());
+        if time_signature != self.orchestrator.time_signature() {
+            self.orchestrator.update_time_signature(time_signature);
+            self.composer.update_time_signature(time_signature);
+        }
     }
 
-    fn next_track_view_mode_with_path_uid(
+    /// Sets how values are transformed as they pass from a control source to
+    /// an existing link's target. None passes them through unchanged.
+    pub fn set_link_mapping(
         &mut self,
-        track_uid: TrackUid,
-        path_uid: Option<PathUid>,
-    ) -> TrackViewMode {
-        match self.next_signal_path(track_uid, path_uid) {
-            Ok(next_path_uid) => TrackViewMode::Control(next_path_uid),
-            Err(err) => match err {
-                SignalPathNextError::ThereAreNone => {
-                    let path = SignalPathBuilder::default().build().unwrap();
-                    if let Ok(new_path_uid) = self.add_path(track_uid, path) {
-                        TrackViewMode::Control(new_path_uid)
-                    } else {
-                        TrackViewMode::Composition
-                    }
-                }
-                SignalPathNextError::ReachedEndOfList => TrackViewMode::Composition,
-            },
+        source: ControlLinkSource,
+        target: Uid,
+        param: ControlIndex,
+        mapping: Option<ControlMapping>,
+    ) -> Result<()> {
+        self.automator
+            .set_link_mapping(source, target, param, mapping)?;
+        if let Some(track_uid) = self.track_for_link_source(source) {
+            self.regenerate_signal_chain(track_uid);
         }
+        Ok(())
     }
 
-    fn next_signal_path(
-        &self,
-        track_uid: TrackUid,
-        current_path_uid: Option<PathUid>,
-    ) -> Result<PathUid, SignalPathNextError> {
-        if let Some(path_uids) = self.track_to_paths.get(&track_uid) {
-            if let Some(current_path_uid) = current_path_uid {
-                if let Some(index) = path_uids.iter().position(|puid| *puid == current_path_uid) {
-                    let next_index = index + 1;
-                    if next_index < path_uids.len() {
-                        return Ok(path_uids[next_index]);
-                    } else {
-                        return Err(SignalPathNextError::ReachedEndOfList);
-                    }
-                }
-            }
-            if let Some(path_uid) = path_uids.first().copied() {
-                return Ok(path_uid);
-            }
+    /// Sets how an existing link's values combine with those of other links to
+    /// the same parameter.
+    pub fn set_link_role(
+        &mut self,
+        source: ControlLinkSource,
+        target: Uid,
+        param: ControlIndex,
+        role: ControlLinkRole,
+    ) -> Result<()> {
+        self.automator.set_link_role(source, target, param, role)?;
+        if let Some(track_uid) = self.track_for_link_source(source) {
+            self.regenerate_signal_chain(track_uid);
         }
-        Err(SignalPathNextError::ThereAreNone)
+        Ok(())
     }
 
-    pub fn notify_transport_sample_rate_change(&mut self) {
-        self.update_sample_rate(self.sample_rate());
+    #[allow(missing_docs)]
+    pub fn link_mapping(
+        &self,
+        source: ControlLinkSource,
+        target: Uid,
+        param: ControlIndex,
+    ) -> Option<ControlMapping> {
+        self.automator.link_mapping(source, target, param)
     }
 
-    pub fn notify_transport_tempo_change(&mut self) {
-        self.update_tempo(self.tempo());
+    /// Returns the role of an existing link, or None if there's no such link.
+    pub fn link_role(
+        &self,
+        source: ControlLinkSource,
+        target: Uid,
+        param: ControlIndex,
+    ) -> Option<ControlLinkRole> {
+        self.automator.link_role(source, target, param)
     }
 
-    pub fn notify_transport_time_signature_change(&mut self) {
-        self.update_time_signature(self.time_signature());
+    // Returns the amount of each send from one track to an aux track. There's
+    // usually at most one.
+    pub(crate) fn send_amounts(&self, src_uid: TrackUid, dst_uid: TrackUid) -> Vec<Normal> {
+        self.orchestrator
+            .bus_station
+            .sends_for_track(&src_uid)
+            .map(|routes| {
+                routes
+                    .iter()
+                    .filter(|route| route.aux_track_uid == dst_uid)
+                    .map(|route| route.amount)
+                    .collect()
+            })
+            .unwrap_or_default()
     }
 
-    /// A convenience method for automated tests to spit out their work product.
-    #[cfg(feature = "hound")]
-    pub fn save_and_export(&mut self, path_prefix: PathBuf) -> anyhow::Result<()> {
-        let mut path = path_prefix.clone();
-        path.set_extension("json");
-        self.save(Some(path))?;
-        let mut path = path_prefix.clone();
-        path.set_extension("wav");
-        self.export_to_wav(path)?;
-        Ok(())
+    /// Arms recording of live parameter changes into automation, or disarms
+    /// it if mode is None. See [Project::record_param_change()].
+    pub fn set_automation_record_mode(&mut self, mode: Option<AutomationRecordMode>) {
+        self.automator.set_record_mode(mode);
+        self.finish_automation_pass();
+        self.begin_automation_pass();
     }
 
-    fn reset_rng(&mut self) {
-        self.e.rng = Rng::new_with_seed(self.rng_seed);
+    #[allow(missing_docs)]
+    pub fn automation_record_mode(&self) -> Option<AutomationRecordMode> {
+        self.automator.record_mode()
     }
 
-    /// Sets this project's random number generator seed, which is used to
-    /// deterministically (repeatably) generate a stream of pseudorandom numbers
-    /// used throughout the project.
-    pub fn set_rng_seed(&mut self, seed: u128) {
-        self.rng_seed = seed;
-        self.reset_rng();
+    /// Records a change that the user made to an entity's parameter, if
+    /// automation recording is armed, the project is playing, and the current
+    /// time is within the punch range (see [Project::set_punch_range()]). The
+    /// change goes into the [SignalPath] linked to the parameter. If there
+    /// isn't one, then a new path is created on the entity's track.
+    pub fn record_param_change(
+        &mut self,
+        uid: Uid,
+        param: ControlIndex,
+        value: ControlValue,
+    ) -> Result<()> {
+        if !self.automator.is_recording() || !self.automator.is_punched_in() {
+            return Ok(());
+        }
+        self.e.automation_pass_has_changes = true;
+        let path_uid = if let Some(path_uid) = self.automator.path_for_param(uid, param) {
+            path_uid
+        } else {
+            let track_uid = self
+                .track_for_entity(uid)
+                .ok_or_else(|| anyhow!("Couldn't find track for entity {uid}"))?;
+            let path_uid = self.add_path(track_uid, SignalPath::default())?;
+            self.automator.link_path(path_uid, uid, param)?;
+            path_uid
+        };
+        self.automator.record(path_uid, value)
+    }
+
+    // Remembers the project as it is, if automation is being recorded, so
+    // that everything the pass changes can be undone in one step. This
+    // happens when recording is armed, playback starts, or an edit ends the
+    // previous pass, rather than at the first recorded change, because
+    // serializing the project during playback would cause a dropout.
+    fn begin_automation_pass(&mut self) {
+        if self.automator.is_recording() && self.e.automation_pass_snapshot.is_none() {
+            self.e.automation_pass_snapshot = self.snapshot().ok();
+            self.e.automation_pass_has_changes = false;
+        }
     }
 
-    /// Regenerates cacheable information associated with a track's entities.
-    /// TODO: figure out easy-to-understand rules about when this needs to be
-    /// called.
-    pub(crate) fn regenerate_signal_chain(&mut self, track_uid: TrackUid) {
-        let track_info = self.e.track_info.entry(track_uid).or_default();
+    // Turns everything that the current automation recording pass changed
+    // into one undo step.
+    fn finish_automation_pass(&mut self) {
+        if let Some(snapshot) = self.e.automation_pass_snapshot.take() {
+            if self.e.automation_pass_has_changes {
+                self.e
+                    .history
+                    .record(ProjectHistoryStep::Snapshot(snapshot));
+            }
+        }
+        self.e.automation_pass_has_changes = false;
+    }
 
-        // TODO: this is awful
-        #[cfg(feature = "egui")]
-        let mut targets = Vec::default();
+    /// Tells the automation recorder that the user let go of the control for
+    /// an entity's parameter. See [Automator::release()].
+    pub fn release_param(&mut self, uid: Uid, param: ControlIndex) {
+        if let Some(path_uid) = self.automator.path_for_param(uid, param) {
+            self.automator.release(path_uid);
+        }
+    }
 
-        // If the current view mode is to show a SignalPath, then we need to
-        // update the cache of controllable states for it.
-        let path_uid = if let Some(mode) = self.view_state.track_view_mode.get(&track_uid) {
-            match mode {
-                TrackViewMode::Composition => None,
-                TrackViewMode::Control(path_uid) => Some(path_uid.clone()),
-            }
+    /// Returns the current value of every parameter of the given entity.
+    /// Comparing snapshots taken before and after the entity's UI runs reveals
+    /// which parameters the user changed.
+    pub fn param_snapshot(&self, uid: Uid) -> Vec<(Uid, ControlIndex, Option<ControlValue>)> {
+        if let Some(entity) = self.orchestrator.entity_repo.entity(uid) {
+            (0..entity.control_index_count())
+                .map(|i| {
+                    let param = ControlIndex(i);
+                    (uid, param, entity.control_get_param_by_index(param))
+                })
+                .collect()
         } else {
-            None
-        };
+            Vec::default()
+        }
+    }
 
-        let signal_chain: Vec<SignalChainItem> = {
-            if let Some(entity_uids) = self.orchestrator.entity_repo.uids_for_track.get(&track_uid)
-            {
-                entity_uids.iter().fold(Vec::default(), |mut v, uid| {
+    /// Records every parameter that has changed since the given
+    /// [Project::param_snapshot()].
+    pub fn record_param_changes_since(
+        &mut self,
+        snapshot: Vec<(Uid, ControlIndex, Option<ControlValue>)>,
+    ) {
+        snapshot.into_iter().for_each(|(uid, param, old_value)| {
+            if let Some(entity) = self.orchestrator.entity_repo.entity(uid) {
+                let new_value = entity.control_get_param_by_index(param);
+                if new_value != old_value {
+                    if let Some(new_value) = new_value {
+                        let _ = self.record_param_change(uid, param, new_value);
+                    }
+                }
+            }
+        });
+    }
+
+    /// Arms the given track for MIDI recording, or disarms recording if None.
+    /// While the project plays, notes that arrive through
+    /// [HandlesMidi::handle_midi_message()] are recorded, and when it stops,
+    /// they become a new [Pattern] arranged on the track at the point where
+    /// recording started.
+    pub fn set_midi_record_track(&mut self, track_uid: Option<TrackUid>) {
+        let _ = self.commit_midi_take();
+        self.e.midi_record_track = track_uid;
+        if self.transport.is_performing() {
+            self.start_midi_take();
+        }
+    }
+
+    #[allow(missing_docs)]
+    pub fn midi_record_track(&self) -> Option<TrackUid> {
+        self.e.midi_record_track
+    }
+
+    #[allow(missing_docs)]
+    pub fn set_midi_record_options(&mut self, options: MidiRecordOptions) {
+        self.e.midi_record_options = options;
+    }
+
+    #[allow(missing_docs)]
+    pub fn midi_record_options(&self) -> MidiRecordOptions {
+        self.e.midi_record_options
+    }
+
+    /// Returns true if a track is armed and the project is playing.
+    pub fn is_recording_midi(&self) -> bool {
+        self.e.midi_take.is_some()
+    }
+
+    fn start_midi_take(&mut self) {
+        if self.e.midi_record_track.is_some() && self.e.midi_take.is_none() {
+            self.e.midi_take = Some(MidiTake::new_with(self.transport.current_time()));
+        }
+    }
+
+    // Turns the notes recorded so far into a pattern on the armed track. If
+    // the pattern doesn't fit among the track's existing arrangements, then
+    // it's still added to the palette, so the performance isn't lost.
+    fn commit_midi_take(&mut self) -> Result<()> {
+        if let Some(take) = self.e.midi_take.take() {
+            if let Some(track_uid) = self.e.midi_record_track {
+                let position = take.start();
+                if let Some(pattern) = take.finish(
+                    self.transport.current_time(),
+                    &self.e.midi_record_options,
+                    self.transport.time_signature_at(position),
+                ) {
+                    // Going through a command lets the user undo the take.
+                    self.execute(ProjectCommand::ArrangeNewPattern(
+                        track_uid, pattern, position,
+                    ))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn regenerate_signal_chain_for_link_source(&mut self, source: ControlLinkSource) {
+        if let Some(track_uid) = self.track_for_link_source(source) {
+            self.regenerate_signal_chain(track_uid);
+        }
+    }
+
+    fn track_for_link_source(&self, source: ControlLinkSource) -> Option<TrackUid> {
+        match source {
+            ControlLinkSource::Entity(uid) => self.track_for_entity(uid),
+            ControlLinkSource::Path(path_uid) => self
+                .track_to_paths
+                .iter()
+                .find(|(_, path_uids)| path_uids.contains(&path_uid))
+                .map(|(track_uid, _)| *track_uid),
+            ControlLinkSource::MidiCc(..) => None,
+        }
+    }
+
+    /// Places an [AudioClip] on the given audio track. Fails if the track isn't
+    /// an audio track (see [Project::new_audio_track()]), or if the clip would
+    /// overlap another clip on the track.
+    pub fn add_audio_clip(
+        &mut self,
+        track_uid: TrackUid,
+        clip: AudioClip,
+    ) -> anyhow::Result<AudioClipUid> {
+        if !self.orchestrator.audio_track_uids.contains(&track_uid) {
+            return Err(anyhow!(
+                "Audio clips can go only on audio tracks, and {track_uid} isn't one"
+            ));
+        }
+        self.orchestrator.audio_clips.add_clip(track_uid, clip)
+    }
+
+    /// Moves an [AudioClip] to a new position on its track. If copy_original
+    /// is true, the original stays put and a copy goes to the new position.
+    pub fn move_audio_clip(
+        &mut self,
+        track_uid: TrackUid,
+        clip_uid: AudioClipUid,
+        new_position: MusicalTime,
+        copy_original: bool,
+    ) -> anyhow::Result<AudioClipUid> {
+        self.orchestrator
+            .audio_clips
+            .move_clip(track_uid, clip_uid, new_position, copy_original)
+    }
+
+    /// Creates a copy of an [AudioClip] immediately after the original.
+    pub fn duplicate_audio_clip(
+        &mut self,
+        track_uid: TrackUid,
+        clip_uid: AudioClipUid,
+    ) -> anyhow::Result<AudioClipUid> {
+        self.orchestrator
+            .audio_clips
+            .duplicate_clip(track_uid, clip_uid)
+    }
+
+    /// Removes an [AudioClip] from its track and returns it.
+    pub fn remove_audio_clip(
+        &mut self,
+        track_uid: TrackUid,
+        clip_uid: AudioClipUid,
+    ) -> anyhow::Result<AudioClip> {
+        self.orchestrator
+            .audio_clips
+            .remove_clip(track_uid, clip_uid)
+    }
+
+    #[allow(missing_docs)]
+    pub fn audio_clip(&self, clip_uid: AudioClipUid) -> Option<&AudioClip> {
+        self.orchestrator.audio_clips.clip(clip_uid)
+    }
+
+    #[allow(missing_docs)]
+    pub fn audio_clip_mut(&mut self, clip_uid: AudioClipUid) -> Option<&mut AudioClip> {
+        self.orchestrator.audio_clips.clip_mut(clip_uid)
+    }
+
+    /// Returns the [AudioClip]s on the given track.
+    pub fn audio_clip_uids(&self, track_uid: TrackUid) -> Option<&[AudioClipUid]> {
+        self.orchestrator.audio_clips.clip_uids(track_uid)
+    }
+
+    /// Feeds the output of the given track to the entity's sidechain input, or
+    /// disconnects the sidechain if None. A compressor with a sidechain ducks
+    /// its own track whenever the source track gets loud.
+    pub fn set_sidechain(
+        &mut self,
+        uid: Uid,
+        source_track_uid: Option<TrackUid>,
+    ) -> anyhow::Result<()> {
+        self.orchestrator.set_sidechain(uid, source_track_uid)
+    }
+
+    /// Returns the track that drives the entity's sidechain input, if any.
+    pub fn sidechain(&self, uid: Uid) -> Option<TrackUid> {
+        self.orchestrator.sidechain(uid)
+    }
+
+    /// Applies an editing operation, remembering how to reverse it with
+    /// [Project::undo()]. If the operation fails, the project is left as it
+    /// was, and nothing is remembered.
+    pub fn execute(&mut self, command: ProjectCommand) -> anyhow::Result<ProjectCommandOutcome> {
+        // Keep the history in order if the user edits during a recording pass.
+        self.finish_automation_pass();
+        let outcome = match self.apply_command(command) {
+            Ok((outcome, step)) => {
+                self.e.history.record(step);
+                Ok(outcome)
+            }
+            Err(e) => Err(e),
+        };
+        // Any recording carries on after the edit as a new pass.
+        self.begin_automation_pass();
+        outcome
+    }
+
+    // Applies a command, and returns what it produced along with the step that
+    // reverses it.
+    fn apply_command(
+        &mut self,
+        command: ProjectCommand,
+    ) -> anyhow::Result<(ProjectCommandOutcome, ProjectHistoryStep)> {
+        // The inverse of a new arrangement has to name it, so it can't be
+        // worked out until the command has created it.
+        if let ProjectCommand::ArrangeNewPattern(track_uid, ..) = command {
+            let outcome = command.apply(self)?;
+            let ProjectCommandOutcome::Arrangement(arrangement_uid) = outcome else {
+                unreachable!("ArrangeNewPattern always creates an arrangement");
+            };
+            let inverse = ProjectCommand::RemoveArrangedPattern(track_uid, arrangement_uid);
+            return Ok((outcome, ProjectHistoryStep::Command(inverse)));
+        }
+
+        let step = match command.inverse(self) {
+            Some(inverse) => ProjectHistoryStep::Command(inverse),
+            None => ProjectHistoryStep::Snapshot(self.snapshot()?),
+        };
+        let outcome = command.apply(self)?;
+        Ok((outcome, step))
+    }
+
+    /// Reverses the most recent [ProjectCommand]. If that fails, then the
+    /// history no longer describes the project, so it's forgotten.
+    pub fn undo(&mut self) -> anyhow::Result<()> {
+        if let Some(step) = self.e.history.pop_undo() {
+            match self.take_history_step(step) {
+                Ok(redo_step) => {
+                    self.e.history.push_redo(redo_step);
+                    Ok(())
+                }
+                Err(e) => {
+                    self.e.history.clear();
+                    Err(e)
+                }
+            }
+        } else {
+            Err(anyhow!("Nothing to undo"))
+        }
+    }
+
+    /// Reapplies the most recently undone [ProjectCommand]. If that fails,
+    /// then the history is forgotten, as with [Project::undo()].
+    pub fn redo(&mut self) -> anyhow::Result<()> {
+        if let Some(step) = self.e.history.pop_redo() {
+            match self.take_history_step(step) {
+                Ok(undo_step) => {
+                    self.e.history.push_undo(undo_step);
+                    Ok(())
+                }
+                Err(e) => {
+                    self.e.history.clear();
+                    Err(e)
+                }
+            }
+        } else {
+            Err(anyhow!("Nothing to redo"))
+        }
+    }
+
+    #[allow(missing_docs)]
+    pub fn can_undo(&self) -> bool {
+        self.e.history.can_undo()
+    }
+
+    #[allow(missing_docs)]
+    pub fn can_redo(&self) -> bool {
+        self.e.history.can_redo()
+    }
+
+    // Moves the project to another point in its history, and returns the step
+    // that leads back.
+    fn take_history_step(
+        &mut self,
+        step: ProjectHistoryStep,
+    ) -> anyhow::Result<ProjectHistoryStep> {
+        match step {
+            ProjectHistoryStep::Command(command) => {
+                self.apply_command(command).map(|(_, back)| back)
+            }
+            ProjectHistoryStep::Snapshot(snapshot) => {
+                let back = ProjectHistoryStep::Snapshot(self.snapshot()?);
+                self.restore_snapshot(snapshot)?;
+                Ok(back)
+            }
+        }
+    }
+
+    fn snapshot(&mut self) -> anyhow::Result<ProjectSnapshot> {
+        self.before_ser();
+        Ok(ProjectSnapshot {
+            json: serde_json::to_string(&self)?,
+            clip_samples: self
+                .orchestrator
+                .audio_clips
+                .clips
+                .iter()
+                .map(|(clip_uid, clip)| {
+                    (
+                        *clip_uid,
+                        (Arc::clone(clip.samples()), clip.source_sample_rate()),
+                    )
+                })
+                .collect(),
+        })
+    }
+
+    // Replaces everything that would be saved to disk with the snapshot's
+    // version, while keeping the parts that describe the current session, such
+    // as the playback position and the audio configuration.
+    fn restore_snapshot(&mut self, snapshot: ProjectSnapshot) -> anyhow::Result<()> {
+        let mut restored = serde_json::from_str::<Self>(&snapshot.json)?;
+
+        // Put the audio back before after_deser(), so that clips that already
+        // have their audio aren't read again.
+        for (clip_uid, (samples, sample_rate)) in snapshot.clip_samples {
+            if let Some(clip) = restored.orchestrator.audio_clips.clip_mut(clip_uid) {
+                clip.set_samples(samples, sample_rate);
+            }
+        }
+        restored.after_deser();
+
+        let was_performing = self.transport.is_performing();
+        let position = self.transport.current_time();
+        let sample_rate = self.sample_rate();
+        restored.e = core::mem::take(&mut self.e);
+        restored.e.track_info.clear();
+        *self = restored;
+
+        let tempo = self.tempo();
+        let time_signature = self.time_signature();
+        self.update_sample_rate(sample_rate);
+        self.update_tempo(tempo);
+        self.update_time_signature(time_signature);
+        let track_uids = self.track_uids().to_vec();
+        track_uids
+            .iter()
+            .for_each(|track_uid| self.regenerate_signal_chain(*track_uid));
+        self.seek(position);
+        if was_performing {
+            self.play();
+        }
+        Ok(())
+    }
+
+    // Jumps back to the start of the loop range. Everything else moves there
+    // just as it would for a seek, so that the next slice restarts cleanly
+    // rather than seeing time run backwards. Unlike a seek, a MIDI recording
+    // keeps going as the same take.
+    fn wrap_loop(&mut self) {
+        let loop_end = self.transport.current_time();
+        self.transport.wrap_loop();
+        let loop_start = self.transport.current_time();
+        self.move_playhead(loop_start);
+        if let Some(take) = self.e.midi_take.as_mut() {
+            take.wrap(loop_end, loop_start);
+        }
+    }
+
+    // Moves everything that keeps time to the given position. Any notes that
+    // are still sounding won't get their note-offs, so we silence them.
+    fn move_playhead(&mut self, position: MusicalTime) {
+        self.transport.seek(position);
+        self.automator.seek(position);
+        self.orchestrator.seek(position);
+        self.composer.seek(position);
+        self.track_to_midi_router
+            .values_mut()
+            .for_each(|router| router.all_notes_off(&mut self.orchestrator.entity_repo));
+    }
+
+    /// Renders the project as a 16-bit WAV file to the specified path.
+    #[cfg(feature = "hound")]
+    pub fn export_to_wav(&mut self, path: PathBuf) -> anyhow::Result<()> {
+        ProjectExporter::export_to_wav(self, path)
+    }
+
+    /// Renders the project as a WAV file to the specified path, as described by
+    /// the given [WavExportOptions].
+    #[cfg(feature = "hound")]
+    pub fn export_to_wav_with_options(
+        &mut self,
+        path: PathBuf,
+        options: &WavExportOptions,
+    ) -> anyhow::Result<()> {
+        ProjectExporter::export_to_wav_with_options(self, path, options)
+    }
+
+    /// Renders each track, including aux tracks, as a separate WAV file, in a
+    /// single pass. All stems start at the beginning of the song and have the
+    /// same length, so they line up when imported elsewhere. Each stem is
+    /// named after path_prefix and its [TrackUid], and the paths are returned
+    /// in track order.
+    ///
+    /// Stems are captured post-fader: each is what its track contributed to
+    /// the mix, after the track's output level. That means a muted track's stem
+    /// is silent (as is every unsoloed track's, if a track is soloed), so unmute
+    /// tracks before exporting them.
+    #[cfg(feature = "hound")]
+    pub fn export_stems(
+        &mut self,
+        path_prefix: PathBuf,
+        options: &WavExportOptions,
+    ) -> anyhow::Result<Vec<(TrackUid, PathBuf)>> {
+        let live_sample_rate = self.sample_rate();
+        let sample_rate = options.sample_rate.unwrap_or(live_sample_rate);
+
+        let mut paths = Vec::default();
+        let mut writers = FxHashMap::default();
+        for track_uid in self.track_uids() {
+            let mut file_name = path_prefix.file_name().unwrap_or_default().to_os_string();
+            file_name.push(format!("-track-{track_uid}.wav"));
+            let path = path_prefix.with_file_name(file_name);
+            writers.insert(
+                *track_uid,
+                StereoWavWriter::create(path.clone(), sample_rate, options)?,
+            );
+            paths.push((*track_uid, path));
+        }
+
+        if sample_rate != live_sample_rate {
+            self.update_sample_rate(sample_rate);
+        }
+        self.skip_to_start();
+        self.orchestrator.set_is_capturing_stems(true);
+        self.set_is_rendering_offline(true);
+        self.play();
+
+        // This follows the same rules as Projects::render(), so the stems are
+        // the same length as a mixdown.
+        let mut result = Ok(());
+        let mut buffer = [StereoSample::SILENCE; 64];
+        while !self.is_finished() {
+            buffer.fill(StereoSample::SILENCE);
+            self.generate_audio(&mut buffer, None);
+            let stems = self.orchestrator.take_stems();
+            if self.is_finished() && buffer.iter().all(|s| s.almost_silent()) {
+                break;
+            }
+            for (track_uid, stem) in stems {
+                if let Some(writer) = writers.get_mut(&track_uid) {
+                    result = writer.write(&stem);
+                    if result.is_err() {
+                        break;
+                    }
+                }
+            }
+            if result.is_err() {
+                break;
+            }
+        }
+
+        self.orchestrator.set_is_capturing_stems(false);
+        self.set_is_rendering_offline(false);
+        if sample_rate != live_sample_rate {
+            self.update_sample_rate(live_sample_rate);
+        }
+        result?;
+        for (_, writer) in writers {
+            writer.finalize()?;
+        }
+        Ok(paths)
+    }
+
+    /// Starts MIDI learn for the given parameter, so that the next external
+    /// MIDI controller (CC) that moves is bound to it. None cancels learn mode.
+    pub fn learn_midi_cc(&mut self, target: Option<(Uid, ControlIndex)>) {
+        self.midi_cc_bindings.learn(target);
+    }
+
+    /// Which external MIDI controllers drive which entity parameters. Change
+    /// them with [ProjectCommand::BindMidiCc] and
+    /// [ProjectCommand::UnbindMidiCc].
+    pub fn midi_cc_bindings(&self) -> &MidiCcBindings {
+        &self.midi_cc_bindings
+    }
+
+    pub(crate) fn bind_midi_cc(
+        &mut self,
+        channel: MidiChannel,
+        cc: u8,
+        uid: Uid,
+        param: ControlIndex,
+    ) -> Result<()> {
+        if uid != Self::TRANSPORT_UID && !self.orchestrator.entity_repo.entities.contains_key(&uid)
+        {
+            return Err(anyhow!("Couldn't find entity {uid}"));
+        }
+        self.midi_cc_bindings.bind(channel, cc, uid, param);
+        Ok(())
+    }
+
+    pub(crate) fn unbind_midi_cc(
+        &mut self,
+        channel: MidiChannel,
+        cc: u8,
+        uid: Uid,
+        param: ControlIndex,
+    ) {
+        self.midi_cc_bindings.unbind(channel, cc, uid, param);
+    }
+
+    pub(crate) fn is_midi_cc_bound(
+        &self,
+        channel: MidiChannel,
+        cc: u8,
+        uid: Uid,
+        param: ControlIndex,
+    ) -> bool {
+        self.midi_cc_bindings
+            .links_for(channel, cc)
+            .iter()
+            .any(|link| link.is_for(uid, param))
+    }
+
+    // Sends an external controller's value to the parameters bound to it.
+    fn dispatch_midi_cc(&mut self, channel: MidiChannel, cc: u8, value: u8) {
+        let links = self.midi_cc_bindings.links_for(channel, cc);
+        if links.is_empty() {
+            return;
+        }
+        self.automator.route_links(
+            &mut self.orchestrator.entity_repo,
+            Some(&mut |link, value| {
+                if link.uid == Self::TRANSPORT_UID {
+                    self.transport.control_set_param_by_index(link.param, value)
+                }
+            }),
+            ControlLinkSource::MidiCc(channel, cc),
+            &links,
+            ControlValue(value as f64 / 127.0),
+        );
+
+        // A controller might be turning a knob that's being recorded.
+        if self.automator.is_recording() {
+            links.iter().for_each(|link| {
+                if let Some(value) = self
+                    .orchestrator
+                    .entity_repo
+                    .entity(link.uid)
+                    .and_then(|entity| entity.control_get_param_by_index(link.param))
+                {
+                    let _ = self.record_param_change(link.uid, link.param, value);
+                }
+            });
+        }
+    }
+
+    fn dispatch_control_event(&mut self, source: ControlLinkSource, value: ControlValue) {
+        self.automator.route(
+            &mut self.orchestrator.entity_repo,
+            Some(&mut |link, value| match link.uid {
+                Self::TRANSPORT_UID => self.transport.control_set_param_by_index(link.param, value),
+                _ => {
+                    eprintln!("Asked to route from unknown source {source}");
+                }
+            }),
+            source,
+            value,
+        );
+    }
+
+    pub fn load(path: PathBuf) -> anyhow::Result<Self> {
+        let json = std::fs::read_to_string(&path)?;
+        let mut project = serde_json::from_str::<Self>(&json)?;
+        project.e.load_path = Some(path);
+        project.after_deser();
+        project.e.is_clean = true;
+        Ok(project)
+    }
+
+    pub fn save(&mut self, path: Option<PathBuf>) -> anyhow::Result<PathBuf> {
+        let save_path = {
+            if let Some(path) = path.as_ref() {
+                path.clone()
+            } else if let Some(path) = self.e.load_path.as_ref() {
+                path.clone()
+            } else {
+                return Err(anyhow!("Can't save without a path"));
+            }
+        };
+
+        self.before_ser();
+        let json = serde_json::to_string_pretty(&self)?;
+        std::fs::write(&save_path, json)?;
+        self.e.is_clean = true;
+        Ok(save_path)
+    }
+
+    pub fn load_path(&self) -> Option<&PathBuf> {
+        self.e.load_path.as_ref()
+    }
+
+    pub fn add_path(&mut self, track_uid: TrackUid, path: SignalPath) -> Result<PathUid> {
+        let path_uid = self.automator.add_path(path)?;
+        self.track_to_paths
+            .entry(track_uid)
+            .or_default()
+            .push(path_uid);
+        Ok(path_uid)
+    }
+
+    /// Picks the next view mode for the given track.
+    pub fn advance_track_view_mode(&mut self, track_uid: TrackUid) {
+        let mode = match self.track_view_mode(track_uid) {
+            TrackViewMode::Composition => self.next_track_view_mode_with_path_uid(track_uid, None),
+            TrackViewMode::Control(path_uid) => {
+                self.next_track_view_mode_with_path_uid(track_uid, Some(path_uid))
+            }
+        };
+        self.set_track_view_mode(track_uid, mode);
+    }
+
+    fn track_view_mode(&self, track_uid: TrackUid) -> TrackViewMode {
+        self.view_state
+            .track_view_mode
+            .get(&track_uid)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn set_track_view_mode(&mut self, track_uid: TrackUid, mode: TrackViewMode) {
+        self.view_state
+            .track_view_mode
+            .insert(track_uid.clone(), mode);
+        self.regenerate_signal_chain(track_uid);
+    }
+
+    fn next_track_view_mode_with_path_uid(
+        &mut self,
+        track_uid: TrackUid,
+        path_uid: Option<PathUid>,
+    ) -> TrackViewMode {
+        match self.next_signal_path(track_uid, path_uid) {
+            Ok(next_path_uid) => TrackViewMode::Control(next_path_uid),
+            Err(err) => match err {
+                SignalPathNextError::ThereAreNone => {
+                    let path = SignalPathBuilder::default().build().unwrap();
+                    if let Ok(new_path_uid) = self.add_path(track_uid, path) {
+                        TrackViewMode::Control(new_path_uid)
+                    } else {
+                        TrackViewMode::Composition
+                    }
+                }
+                SignalPathNextError::ReachedEndOfList => TrackViewMode::Composition,
+            },
+        }
+    }
+
+    fn next_signal_path(
+        &self,
+        track_uid: TrackUid,
+        current_path_uid: Option<PathUid>,
+    ) -> Result<PathUid, SignalPathNextError> {
+        if let Some(path_uids) = self.track_to_paths.get(&track_uid) {
+            if let Some(current_path_uid) = current_path_uid {
+                if let Some(index) = path_uids.iter().position(|puid| *puid == current_path_uid) {
+                    let next_index = index + 1;
+                    if next_index < path_uids.len() {
+                        return Ok(path_uids[next_index]);
+                    } else {
+                        return Err(SignalPathNextError::ReachedEndOfList);
+                    }
+                }
+            }
+            if let Some(path_uid) = path_uids.first().copied() {
+                return Ok(path_uid);
+            }
+        }
+        Err(SignalPathNextError::ThereAreNone)
+    }
+
+    pub fn notify_transport_sample_rate_change(&mut self) {
+        self.update_sample_rate(self.sample_rate());
+    }
+
+    pub fn notify_transport_tempo_change(&mut self) {
+        self.update_tempo(self.tempo());
+    }
+
+    pub fn notify_transport_time_signature_change(&mut self) {
+        self.update_time_signature(self.time_signature());
+    }
+
+    /// A convenience method for automated tests to spit out their work product.
+    #[cfg(feature = "hound")]
+    pub fn save_and_export(&mut self, path_prefix: PathBuf) -> anyhow::Result<()> {
+        let mut path = path_prefix.clone();
+        path.set_extension("json");
+        self.save(Some(path))?;
+        let mut path = path_prefix.clone();
+        path.set_extension("wav");
+        self.export_to_wav(path)?;
+        Ok(())
+    }
+
+    fn reset_rng(&mut self) {
+        self.e.rng = Rng::new_with_seed(self.rng_seed);
+    }
+
+    /// Sets this project's random number generator seed, which is used to
+    /// deterministically (repeatably) generate a stream of pseudorandom numbers
+    /// used throughout the project.
+    pub fn set_rng_seed(&mut self, seed: u128) {
+        self.rng_seed = seed;
+        self.reset_rng();
+    }
+
+    // Describes a link's target as "Entity name: parameter name".
+    fn link_target_name(&self, link: &ControlLink) -> String {
+        if link.uid == Self::TRANSPORT_UID {
+            format!(
+                "Transport: {}",
+                self.transport
+                    .control_name_for_index(link.param)
+                    .unwrap_or_default()
+            )
+        } else if let Some(entity) = self.orchestrator.entity_repo.entity(link.uid) {
+            format!(
+                "{}: {}",
+                entity.name(),
+                entity
+                    .control_name_for_index(link.param)
+                    .unwrap_or_default()
+            )
+        } else {
+            format!("{}: {}", link.uid, link.param)
+        }
+    }
+
+    /// Regenerates cacheable information associated with a track's entities.
+    /// TODO: figure out easy-to-understand rules about when this needs to be
+    /// called.
+    pub(crate) fn regenerate_signal_chain(&mut self, track_uid: TrackUid) {
+        // TODO: this is awful
+        #[cfg(feature = "egui")]
+        let mut targets = Vec::default();
+
+        // If the current view mode is to show a SignalPath, then we need to
+        // update the cache of controllable states for it.
+        let path_uid = if let Some(mode) = self.view_state.track_view_mode.get(&track_uid) {
+            match mode {
+                TrackViewMode::Composition => None,
+                TrackViewMode::Control(path_uid) => Some(path_uid.clone()),
+            }
+        } else {
+            None
+        };
+
+        let signal_chain: Vec<SignalChainItem> = {
+            if let Some(entity_uids) = self.orchestrator.entity_repo.uids_for_track.get(&track_uid)
+            {
+                entity_uids.iter().fold(Vec::default(), |mut v, uid| {
                     if let Some(entity) = self.orchestrator.entity_repo.entity(*uid) {
-                        v.push(SignalChainItem {
-                            uid: *uid,
-                            name: entity.name().to_string(),
-                            is_control_source: true,
-                        });
+                        let links = if let Some(links) = self.automator.control_links(*uid) {
+                            links
+                                .iter()
+                                .map(|link| SignalChainLink {
+                                    link: *link,
+                                    target_name: self.link_target_name(link),
+                                })
+                                .collect()
+                        } else {
+                            Vec::default()
+                        };
                         let mut controllables = Vec::default();
                         for i in 0..entity.control_index_count() {
                             let index = ControlIndex(i);
@@ -665,6 +1679,13 @@ impl Project {
                                 is_linked,
                             ));
                         }
+                        v.push(SignalChainItem {
+                            uid: *uid,
+                            name: entity.name().to_string(),
+                            is_control_source: true,
+                            links,
+                            params: controllables.iter().map(|(name, _)| name.clone()).collect(),
+                        });
                         #[cfg(feature = "egui")]
                         if !controllables.is_empty() {
                             targets.push(crate::egui::TargetInstrument {
@@ -682,6 +1703,7 @@ impl Project {
             }
         };
 
+        let track_info = self.e.track_info.entry(track_uid).or_default();
         #[cfg(feature = "egui")]
         {
             track_info.targets = targets;
@@ -789,12 +1811,18 @@ impl Controls for Project {
         self.automator.play();
         self.orchestrator.play();
         self.composer.play();
+        self.start_midi_take();
+        self.begin_automation_pass();
         self.update_is_finished();
     }
 
     fn stop(&mut self) {
-        self.transport.stop();
+        // The automation pass ends first, so that committing the MIDI take
+        // doesn't begin another one. This can run on the audio thread.
         self.automator.stop();
+        self.finish_automation_pass();
+        let _ = self.commit_midi_take();
+        self.transport.stop();
         self.orchestrator.stop();
         self.composer.stop();
 
@@ -804,11 +1832,25 @@ impl Controls for Project {
     }
 
     fn skip_to_start(&mut self) {
+        let _ = self.commit_midi_take();
         self.reset();
         self.transport.skip_to_start();
         self.automator.skip_to_start();
         self.orchestrator.skip_to_start();
         self.composer.skip_to_start();
+        if self.transport.is_performing() {
+            self.start_midi_take();
+        }
+    }
+
+    fn seek(&mut self, position: MusicalTime) {
+        // Recording continues from the new position as a separate take.
+        let _ = self.commit_midi_take();
+        self.move_playhead(position);
+        if self.transport.is_performing() {
+            self.start_midi_take();
+        }
+        self.update_is_finished();
     }
 
     fn update_time_range(&mut self, time_range: &TimeRange) {
@@ -818,37 +1860,18 @@ impl Controls for Project {
     }
 
     fn work(&mut self, control_events_fn: &mut ControlEventsFn) {
-        let mut events = Vec::default();
-        self.automator
-            .work_as_proxy(&mut |source, event| events.push((Some(source), event)));
-        self.composer.work(&mut |event| events.push((None, event)));
-        self.orchestrator
-            .work_as_proxy(&mut |source, event| events.push((Some(source), event)));
-        while let Some((source, event)) = events.pop() {
-            match event {
-                WorkEvent::Midi(_, _) => {
-                    // This is a logic error because it means that we don't know
-                    // which track created this MIDI event, which means that we
-                    // don't know which entities are eligible to receive it. (We
-                    // confine MIDI events to their originating track to avoid
-                    // the need for a system to route channels to tracks.)
-                    todo!("Project must know a MIDI event's originating track. Please map WorkEvent::Midi to WorkEvent::MidiForTrack before passing it to Project.");
-                }
-                WorkEvent::MidiForTrack(track_uid, channel, message) => {
-                    if let Some(midi_router) = self.track_to_midi_router.get(&track_uid) {
-                        let _ =
-                            midi_router.route(&mut self.orchestrator.entity_repo, channel, message);
-                    }
-                    // Give caller an opportunity to route messages elsewhere.
-                    control_events_fn(event);
-                }
-                WorkEvent::Control(value) => {
-                    if let Some(source) = source {
-                        self.dispatch_control_event(source, value);
-                    }
-                }
-            }
-        }
+        self.gather_work_events(None, 0);
+        let work_events = std::mem::take(&mut self.e.work_events);
+        let mut events = std::mem::take(&mut self.e.simultaneous_events);
+        events.clear();
+        events.extend(
+            work_events
+                .iter()
+                .map(|(_, _, source, event)| (*source, event.clone())),
+        );
+        self.dispatch_work_events(&events, control_events_fn);
+        self.e.work_events = work_events;
+        self.e.simultaneous_events = events;
         self.update_is_finished();
     }
 }
@@ -861,11 +1884,34 @@ impl HandlesMidi for Project {
         message: MidiMessage,
         _midi_messages_fn: &mut MidiMessagesFn,
     ) {
+        if let Some(take) = self.e.midi_take.as_mut() {
+            take.record(
+                channel,
+                message,
+                self.transport.current_time(),
+                self.transport.is_punched_in(),
+            );
+        }
+
+        if let MidiMessage::Controller { controller, value } = message {
+            // In learn mode, the controller is being bound, not played.
+            if let Some((uid, param)) = self.midi_cc_bindings.learning() {
+                self.midi_cc_bindings.learn(None);
+                let _ = self.execute(ProjectCommand::BindMidiCc(
+                    channel,
+                    controller.as_int(),
+                    uid,
+                    param,
+                ));
+                return;
+            }
+            self.dispatch_midi_cc(channel, controller.as_int(), value.as_int());
+        }
         self.track_to_midi_router
             .values_mut()
             .for_each(|midi_router| {
                 let _ = midi_router.route(&mut self.orchestrator.entity_repo, channel, message);
-            })
+            });
     }
 }
 impl Serializable for Project {
@@ -881,7 +1927,10 @@ impl Serializable for Project {
     }
 
     fn after_deser(&mut self) {
+        self.transport.after_deser();
         self.automator.after_deser();
+        self.automator
+            .set_punch_range(self.transport.punch_range.clone());
         self.orchestrator.after_deser();
         self.composer.after_deser();
         self.track_to_midi_router
@@ -896,12 +1945,16 @@ impl Serializable for Project {
 mod tests {
     use super::*;
     use crate::{
-        cores::SimpleConstantAudioSourceCoreBuilder,
+        cores::{
+            DelayCore, GainCore, LfoControllerCoreBuilder, SimpleConstantAudioSourceCoreBuilder,
+        },
         entities::{
-            TestAudioSource, TestControllerAlwaysSendsMidiMessage, TestEffectNegatesInput,
+            Delay, FmSynth, Gain, LfoController, SubtractiveSynth, TestAudioSource,
+            TestControllerAlwaysSendsMidiMessage, TestEffectNegatesInput,
             TestInstrumentCountsMidiMessages,
         },
         traits::Entity,
+        types::TempoTransition,
     };
     use ensnare_proc_macros::{Control, IsEntity, Metadata};
     use std::sync::Arc;
@@ -1263,8 +2316,569 @@ mod tests {
     }
 
     #[test]
-    fn project_routes_midi_to_external() {
+    fn project_routes_midi_to_external() {
+        let mut project = Project::default();
+
+        let pattern_uid = project
+            .add_pattern(
+                PatternBuilder::default()
+                    .note(Note::new_with_midi_note(
+                        MidiNote::A0,
+                        MusicalTime::START,
+                        MusicalTime::DURATION_QUARTER,
+                    ))
+                    .build()
+                    .unwrap(),
+                None,
+            )
+            .unwrap();
+        let track_1_uid = project.new_midi_track().unwrap();
+
+        let _ = project.arrange_pattern(track_1_uid, pattern_uid, None, MusicalTime::START);
+
+        let mut messages = Vec::default();
+        let mut samples = [StereoSample::SILENCE; 4];
+        project.play();
+        project.generate_audio(&mut samples, Some(&mut |c, m| messages.push((c, m))));
+
+        assert!(
+            !messages.is_empty(),
+            "Project should route MIDI messages to caller"
+        );
+        let (channel, message) = messages.first().unwrap();
+        assert_eq!(
+            *channel,
+            MidiChannel::default(),
+            "Received message should be on default channel"
+        );
+        assert!(
+            matches!(message, MidiMessage::NoteOn { .. },),
+            "Received message should be a note-on message"
+        );
+    }
+
+    #[test]
+    fn entities_follow_tempo_map() {
+        let mut project = Project::default();
+        project.update_tempo(Tempo(60.0));
+        project.update_time_signature(TimeSignature::COMMON_TIME);
+        project.update_sample_rate(SampleRate(100));
+        project
+            .add_tempo_change(TempoChange {
+                time: MusicalTime::ONE_BEAT,
+                tempo: Tempo(120.0),
+                transition: Default::default(),
+            })
+            .unwrap();
+        project.add_time_signature_change(TimeSignatureChange {
+            time: MusicalTime::ONE_BEAT,
+            time_signature: TimeSignature::WALTZ_TIME,
+        });
+        let track_uid = project.new_audio_track().unwrap();
+        let uid = project
+            .add_entity(
+                track_uid,
+                Box::new(Delay::new_with(Uid::default(), DelayCore::default())),
+            )
+            .unwrap();
+        let entity_tempo = |project: &Project| {
+            project
+                .orchestrator
+                .entity_repo
+                .entity(uid)
+                .unwrap()
+                .tempo()
+        };
+
+        // The project is empty, so a loop keeps it from finishing right away.
+        project.set_loop_range(Some(TimeRange(
+            MusicalTime::START..MusicalTime::new_with_beats(4),
+        )));
+        let mut samples = [StereoSample::SILENCE; 64];
+        project.play();
+        project.generate_audio(&mut samples, None);
+        assert_eq!(entity_tempo(&project), Tempo(60.0));
+        project.generate_audio(&mut samples, None);
+        project.generate_audio(&mut samples, None);
+        assert_eq!(
+            entity_tempo(&project),
+            Tempo(120.0),
+            "Entities should hear about the tempo change"
+        );
+        assert_eq!(
+            project.orchestrator.time_signature(),
+            TimeSignature::WALTZ_TIME
+        );
+        assert_eq!(
+            project.tempo(),
+            Tempo(60.0),
+            "The project's own tempo is where the map starts, so it shouldn't change"
+        );
+
+        project.stop();
+        project.skip_to_start();
+        project.generate_audio(&mut samples, None);
+        assert_eq!(entity_tempo(&project), Tempo(60.0));
+    }
+
+    #[test]
+    fn project_plays_audio_clips() {
+        let mut project = Project::default();
+        project.update_tempo(Tempo(60.0));
+        project.update_sample_rate(SampleRate(100));
+
+        let track_uid = project.new_audio_track().unwrap();
+        let aux_track_uid = project.new_aux_track().unwrap();
+        let midi_track_uid = project.new_midi_track().unwrap();
+        let clip = AudioClip::new_with_samples(
+            Arc::new(vec![StereoSample::from(0.5); 1000]),
+            SampleRate(100),
+            MusicalTime::ONE_BEAT,
+            MusicalTime::ONE_BEAT,
+        );
+        assert!(
+            project.add_audio_clip(aux_track_uid, clip.clone()).is_err(),
+            "Aux tracks can't hold audio clips"
+        );
+        assert!(
+            project
+                .add_audio_clip(midi_track_uid, clip.clone())
+                .is_err(),
+            "MIDI tracks can't hold audio clips"
+        );
+        let clip_uid = project.add_audio_clip(track_uid, clip).unwrap();
+        assert_eq!(project.audio_clip_uids(track_uid).unwrap(), &[clip_uid]);
+
+        // At 60 BPM and 100 Hz, a beat is 100 frames, which doesn't line up
+        // with the 64-frame buffers.
+        project.play();
+        let mut rendered = Vec::default();
+        let mut samples = [StereoSample::SILENCE; 64];
+        for _ in 0..5 {
+            samples.fill(StereoSample::SILENCE);
+            project.generate_audio(&mut samples, None);
+            rendered.extend_from_slice(&samples);
+        }
+        assert!(
+            rendered[0..100].iter().all(|s| *s == StereoSample::SILENCE),
+            "Nothing should play before the clip"
+        );
+        assert!(
+            rendered[100..200]
+                .iter()
+                .all(|s| *s == StereoSample::from(0.5)),
+            "The clip should play in sync with the transport"
+        );
+        assert!(
+            rendered[200..].iter().all(|s| *s == StereoSample::SILENCE),
+            "Nothing should play after the clip"
+        );
+        assert!(
+            project.is_finished(),
+            "Playback should finish after the last clip ends"
+        );
+    }
+
+    #[cfg(feature = "hound")]
+    #[test]
+    fn project_exports_stems() {
+        let mut project = Project::default();
+        project.update_tempo(Tempo(60.0));
+        project.update_sample_rate(SampleRate(100));
+
+        let clip = |value: f64| {
+            AudioClip::new_with_samples(
+                Arc::new(vec![StereoSample::from(value); 100]),
+                SampleRate(100),
+                MusicalTime::START,
+                MusicalTime::ONE_BEAT,
+            )
+        };
+        let track_uid = project.new_audio_track().unwrap();
+        let muted_track_uid = project.new_audio_track().unwrap();
+        let aux_track_uid = project.new_aux_track().unwrap();
+        let _ = project.add_audio_clip(track_uid, clip(0.5)).unwrap();
+        let _ = project.add_audio_clip(muted_track_uid, clip(0.25)).unwrap();
+        project.mute_track(muted_track_uid, true);
+        assert!(project
+            .add_send(track_uid, aux_track_uid, Normal::from(0.5))
+            .is_ok());
+
+        let mut path_prefix = std::env::temp_dir();
+        path_prefix.push(format!("ensnare-stems-test-{}", std::process::id()));
+        let options = WavExportOptionsBuilder::default()
+            .format(crate::orchestration::WavSampleFormat::Float32)
+            .build()
+            .unwrap();
+        let paths = project.export_stems(path_prefix, &options).unwrap();
+        assert_eq!(
+            paths.iter().map(|(uid, _)| *uid).collect::<Vec<_>>(),
+            vec![track_uid, muted_track_uid, aux_track_uid],
+            "There should be a stem for each track, in track order"
+        );
+
+        let stems: Vec<Vec<f32>> = paths
+            .iter()
+            .map(|(_, path)| {
+                let mut reader = hound::WavReader::open(path).unwrap();
+                let samples = reader.samples::<f32>().map(|s| s.unwrap()).collect();
+                let _ = std::fs::remove_file(path);
+                samples
+            })
+            .collect();
+        // The clips last a beat, which is 100 frames, or 200 interleaved
+        // samples.
+        assert!(stems[0].len() >= 200, "Each stem should cover the song");
+        assert!(
+            stems.iter().all(|stem| stem.len() == stems[0].len()),
+            "Stems should line up"
+        );
+        assert!(stems[0][..200].iter().all(|s| *s == 0.5));
+        assert!(
+            stems[1].iter().all(|s| *s == 0.0),
+            "A muted track's stem should be silent"
+        );
+        assert!(
+            stems[2][..200].iter().all(|s| *s == 0.25),
+            "The aux stem should include what was sent to it"
+        );
+    }
+
+    #[test]
+    fn project_undo_redo() {
+        let mut project = Project::default();
+        assert!(!project.can_undo());
+        assert!(project.undo().is_err(), "Undo with no history should fail");
+
+        let track_uid = match project.execute(ProjectCommand::NewMidiTrack).unwrap() {
+            ProjectCommandOutcome::Track(track_uid) => track_uid,
+            outcome => panic!("unexpected outcome {outcome:?}"),
+        };
+        let entity_uid = match project
+            .execute(ProjectCommand::AddEntity(
+                track_uid,
+                Box::new(TestEffectNegatesInput::default()),
+            ))
+            .unwrap()
+        {
+            ProjectCommandOutcome::Entity(uid) => uid,
+            outcome => panic!("unexpected outcome {outcome:?}"),
+        };
+        assert!(project
+            .execute(ProjectCommand::SetHumidity(entity_uid, Normal::from(0.3)))
+            .is_ok());
+        assert_eq!(project.get_humidity(&entity_uid), Normal::from(0.3));
+
+        assert!(project.undo().is_ok());
+        assert_eq!(
+            project.get_humidity(&entity_uid),
+            Normal::maximum(),
+            "Undo should reverse the humidity change"
+        );
+        assert!(project.undo().is_ok());
+        assert!(
+            project.track_for_entity(entity_uid).is_none(),
+            "Undo should remove the added entity"
+        );
+        assert!(project.undo().is_ok());
+        assert!(
+            project.track_uids().is_empty(),
+            "Undo should remove the added track"
+        );
+        assert!(!project.can_undo());
+        assert!(project.can_redo());
+
+        assert!(project.redo().is_ok());
+        assert!(project.redo().is_ok());
+        assert_eq!(project.track_uids(), &[track_uid]);
+        assert_eq!(project.track_for_entity(entity_uid), Some(track_uid));
+        assert!(project.can_redo());
+
+        assert!(project
+            .execute(ProjectCommand::MuteTrack(track_uid, true))
+            .is_ok());
+        assert!(
+            !project.can_redo(),
+            "A new command should discard the redo history"
+        );
+        assert!(project.is_track_muted(track_uid));
+
+        assert!(
+            project
+                .execute(ProjectCommand::RemoveEntity(Uid(99999)))
+                .is_err(),
+            "Removing a nonexistent entity should fail"
+        );
+        assert!(project.undo().is_ok());
+        assert!(
+            !project.is_track_muted(track_uid),
+            "A failed command shouldn't be recorded in the history"
+        );
+    }
+
+    #[test]
+    fn project_undo_keeps_in_memory_clip_audio() {
+        let mut project = Project::default();
+        let track_uid = match project.execute(ProjectCommand::NewAudioTrack).unwrap() {
+            ProjectCommandOutcome::Track(track_uid) => track_uid,
+            outcome => panic!("unexpected outcome {outcome:?}"),
+        };
+        let samples = Arc::new(vec![StereoSample::from(0.5); 100]);
+        let clip = AudioClip::new_with_samples(
+            Arc::clone(&samples),
+            SampleRate(100),
+            MusicalTime::START,
+            MusicalTime::ONE_BEAT,
+        );
+        let clip_uid = match project
+            .execute(ProjectCommand::AddAudioClip(track_uid, clip))
+            .unwrap()
+        {
+            ProjectCommandOutcome::AudioClip(clip_uid) => clip_uid,
+            outcome => panic!("unexpected outcome {outcome:?}"),
+        };
+        assert!(project
+            .execute(ProjectCommand::RemoveAudioClip(track_uid, clip_uid))
+            .is_ok());
+        assert!(project.audio_clip(clip_uid).is_none());
+
+        assert!(project.undo().is_ok());
+        let restored = project
+            .audio_clip(clip_uid)
+            .expect("undo should bring back the removed clip");
+        assert!(
+            Arc::ptr_eq(restored.samples(), &samples),
+            "A clip that was never on disk should keep its audio across undo"
+        );
+
+        assert!(project.redo().is_ok());
+        assert!(project.audio_clip(clip_uid).is_none());
+        assert!(project.undo().is_ok());
+        assert!(Arc::ptr_eq(
+            project.audio_clip(clip_uid).unwrap().samples(),
+            &samples
+        ));
+    }
+
+    #[test]
+    fn project_undoes_simple_edits_with_inverse_commands() {
+        let mut project = Project::default();
+        let track_uid = project.new_midi_track().unwrap();
+        let pattern_uid = project
+            .add_pattern(PatternBuilder::default().build().unwrap(), None)
+            .unwrap();
+        let arrangement_uid = project
+            .arrange_pattern(track_uid, pattern_uid, None, MusicalTime::START)
+            .unwrap();
+
+        let command = ProjectCommand::MoveArrangement(
+            track_uid,
+            arrangement_uid,
+            MusicalTime::new_with_bars(&project.time_signature(), 2),
+            false,
+        );
+        assert!(
+            command.inverse(&mut project).is_some(),
+            "Moving an arrangement should have a cheap inverse"
+        );
+        assert!(project.execute(command).is_ok());
+        assert!(project
+            .execute(ProjectCommand::MuteTrack(track_uid, true))
+            .is_ok());
+
+        assert!(project.undo().is_ok());
+        assert!(!project.is_track_muted(track_uid));
+        assert!(project.undo().is_ok());
+        assert_eq!(
+            project
+                .composer
+                .arrangements
+                .get(&arrangement_uid)
+                .unwrap()
+                .position,
+            MusicalTime::START,
+            "Undo should put the arrangement back where it was"
+        );
+        assert!(project.redo().is_ok());
+        assert_eq!(
+            project
+                .composer
+                .arrangements
+                .get(&arrangement_uid)
+                .unwrap()
+                .position,
+            MusicalTime::new_with_bars(&project.time_signature(), 2)
+        );
+        assert!(project.redo().is_ok());
+        assert!(project.is_track_muted(track_uid));
+    }
+
+    #[test]
+    fn project_undoes_new_arranged_pattern_without_snapshot() {
+        let mut project = Project::default();
+        let track_uid = project.new_midi_track().unwrap();
+        let position = MusicalTime::new_with_bars(&project.time_signature(), 1);
+        let arrangement_uid = match project
+            .execute(ProjectCommand::ArrangeNewPattern(
+                track_uid,
+                PatternBuilder::default().build().unwrap(),
+                position,
+            ))
+            .unwrap()
+        {
+            ProjectCommandOutcome::Arrangement(arrangement_uid) => arrangement_uid,
+            outcome => panic!("unexpected outcome {outcome:?}"),
+        };
+        let step = project.e.history.pop_undo().unwrap();
+        assert!(
+            matches!(step, ProjectHistoryStep::Command(_)),
+            "A new arranged pattern should be undone by a command, not a snapshot"
+        );
+        project.e.history.push_undo(step);
+
+        assert!(project.undo().is_ok());
+        assert!(!project.composer.arrangements.contains_key(&arrangement_uid));
+        assert!(
+            project.composer.patterns.is_empty(),
+            "Undo should remove the new pattern along with its arrangement"
+        );
+
+        assert!(project.redo().is_ok());
+        assert_eq!(project.composer.arrangements.len(), 1);
+        assert_eq!(project.composer.patterns.len(), 1);
+        let arrangement = project.composer.arrangements.values().next().unwrap();
+        assert_eq!(arrangement.position, position);
+        let step = project.e.history.pop_undo().unwrap();
+        assert!(matches!(step, ProjectHistoryStep::Command(_)));
+        project.e.history.push_undo(step);
+
+        assert!(project.undo().is_ok());
+        assert!(project.composer.patterns.is_empty());
+    }
+
+    #[test]
+    fn project_undoes_transport_edits() {
+        let mut project = Project::default();
+        let range = TimeRange(MusicalTime::ONE_BEAT..MusicalTime::new_with_beats(3));
+        let change = TempoChange {
+            time: MusicalTime::new_with_beats(4),
+            tempo: Tempo(90.0),
+            transition: TempoTransition::Ramp,
+        };
+        let meter_change = TimeSignatureChange {
+            time: MusicalTime::new_with_beats(8),
+            time_signature: TimeSignature::WALTZ_TIME,
+        };
+        for command in [
+            ProjectCommand::SetLoopRange(Some(range.clone())),
+            ProjectCommand::SetPunchRange(Some(range.clone())),
+            ProjectCommand::AddTempoChange(change),
+            ProjectCommand::AddTimeSignatureChange(meter_change),
+        ] {
+            assert!(
+                command.inverse(&mut project).is_some(),
+                "{command:?} should have a cheap inverse"
+            );
+            assert!(project.execute(command).is_ok());
+        }
+        assert_eq!(project.loop_range(), Some(&range));
+        assert_eq!(project.punch_range(), Some(&range));
+        assert_eq!(project.transport.tempo_map.tempo_changes(), &[change]);
+        assert_eq!(
+            project.transport.tempo_map.time_signature_changes(),
+            &[meter_change]
+        );
+
+        assert!(project
+            .execute(ProjectCommand::AddTempoChange(TempoChange {
+                tempo: Tempo(0.0),
+                ..change
+            }))
+            .is_err());
+        assert!(project
+            .execute(ProjectCommand::RemoveTempoChange(MusicalTime::ONE_BEAT))
+            .is_err());
+        assert!(project
+            .execute(ProjectCommand::RemoveTempoChange(change.time))
+            .is_ok());
+        assert!(project.transport.tempo_map.tempo_changes().is_empty());
+
+        for _ in 0..5 {
+            assert!(project.undo().is_ok());
+        }
+        assert!(project.loop_range().is_none());
+        assert!(project.punch_range().is_none());
+        assert!(project.transport.tempo_map.is_empty());
+
+        for _ in 0..5 {
+            assert!(project.redo().is_ok());
+        }
+        assert_eq!(project.loop_range(), Some(&range));
+        assert!(project.transport.tempo_map.tempo_changes().is_empty());
+        assert!(project.undo().is_ok());
+        assert_eq!(
+            project.transport.tempo_map.tempo_changes(),
+            &[change],
+            "Undoing a removed tempo change should put it back"
+        );
+    }
+
+    #[test]
+    fn project_seeks() {
+        let mut project = Project::default();
+        let pattern_uid = project
+            .add_pattern(
+                PatternBuilder::default()
+                    .note(Note::new_with_midi_note(
+                        MidiNote::A0,
+                        MusicalTime::START,
+                        MusicalTime::DURATION_QUARTER,
+                    ))
+                    .note(Note::new_with_midi_note(
+                        MidiNote::B0,
+                        MusicalTime::new_with_beats(2),
+                        MusicalTime::DURATION_QUARTER,
+                    ))
+                    .build()
+                    .unwrap(),
+                None,
+            )
+            .unwrap();
+        let track_uid = project.new_midi_track().unwrap();
+        let _ = project.arrange_pattern(track_uid, pattern_uid, None, MusicalTime::START);
+
+        project.seek(MusicalTime::new_with_beats(2));
+        assert_eq!(
+            project.transport.current_time(),
+            MusicalTime::new_with_beats(2)
+        );
+
+        let mut messages = Vec::default();
+        let mut samples = [StereoSample::SILENCE; 64];
+        project.play();
+        project.generate_audio(&mut samples, Some(&mut |_, m| messages.push(m)));
+        assert!(
+            project.transport.current_time() > MusicalTime::new_with_beats(2),
+            "Playback should continue from the seek position"
+        );
+        assert_eq!(
+            messages
+                .iter()
+                .filter_map(|m| match m {
+                    MidiMessage::NoteOn { key, .. } => Some(key.as_int()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>(),
+            vec![MidiNote::B0 as u8],
+            "Only the note at the seek position should play"
+        );
+    }
+
+    #[test]
+    fn project_loops() {
         let mut project = Project::default();
+        project.update_tempo(Tempo(60.0));
+        project.update_sample_rate(SampleRate(100));
 
         let pattern_uid = project
             .add_pattern(
@@ -1279,29 +2893,123 @@ mod tests {
                 None,
             )
             .unwrap();
-        let track_1_uid = project.new_midi_track().unwrap();
+        let track_uid = project.new_midi_track().unwrap();
+        let _ = project.arrange_pattern(track_uid, pattern_uid, None, MusicalTime::START);
 
-        let _ = project.arrange_pattern(track_1_uid, pattern_uid, None, MusicalTime::START);
+        // At 60 BPM and 100 Hz, a beat is 100 frames, which doesn't line up
+        // with the 64-frame buffers.
+        project.set_loop_range(Some(TimeRange(MusicalTime::START..MusicalTime::ONE_BEAT)));
+        project.transport.loop_count = Some(2);
+        let count_note_ons = |project: &mut Project, buffer_count: usize| {
+            let mut note_on_count = 0;
+            let mut samples = [StereoSample::SILENCE; 64];
+            for _ in 0..buffer_count {
+                project.generate_audio(
+                    &mut samples,
+                    Some(&mut |_, m| {
+                        if matches!(m, MidiMessage::NoteOn { .. }) {
+                            note_on_count += 1;
+                        }
+                    }),
+                );
+            }
+            note_on_count
+        };
+        project.play();
+        assert_eq!(
+            count_note_ons(&mut project, 5),
+            3,
+            "The note at the start of the loop should play once per pass"
+        );
+        assert!(!project.transport.is_looping());
+        assert_eq!(
+            project.transport.current_time(),
+            MusicalTime::new_with_frames(Tempo(60.0), SampleRate(100), 120),
+            "After the last pass, playback should continue past the loop"
+        );
 
-        let mut messages = Vec::default();
-        let mut samples = [StereoSample::SILENCE; 4];
+        project.stop();
+        project.skip_to_start();
+        project.transport.loop_count = None;
         project.play();
-        project.generate_audio(&mut samples, Some(&mut |c, m| messages.push((c, m))));
+        assert_eq!(count_note_ons(&mut project, 10), 7);
+        assert!(
+            !project.is_finished(),
+            "A project that loops forever should never finish"
+        );
 
+        // An offline render can't go on forever, so it takes the loop once.
+        // (If it didn't, then this would never return.)
+        project.stop();
+        project.skip_to_start();
+        let rendered_frames = project.render().count();
         assert!(
-            !messages.is_empty(),
-            "Project should route MIDI messages to caller"
+            rendered_frames >= 200,
+            "Rendering should play the loop, jump back, and then play to the end"
         );
-        let (channel, message) = messages.first().unwrap();
-        assert_eq!(
-            *channel,
-            MidiChannel::default(),
-            "Received message should be on default channel"
+        project.stop();
+        project.skip_to_start();
+        assert!(
+            project.transport.is_looping(),
+            "Live playback should loop forever again after rendering"
         );
+
+        project.set_punch_range(Some(TimeRange(
+            MusicalTime::ONE_BEAT..MusicalTime::new_with_beats(2),
+        )));
         assert!(
-            matches!(message, MidiMessage::NoteOn { .. },),
-            "Received message should be a note-on message"
+            !project.transport.is_punched_in(),
+            "Playback confined to a loop before the punch range should never punch in"
+        );
+    }
+
+    #[test]
+    fn project_loop_restarts_at_loop_start() {
+        let mut project = Project::default();
+        project.update_tempo(Tempo(60.0));
+        project.update_sample_rate(SampleRate(100));
+
+        let pattern_uid = project
+            .add_pattern(
+                PatternBuilder::default()
+                    .note(Note::new_with_midi_note(
+                        MidiNote::A0,
+                        MusicalTime::ONE_BEAT,
+                        MusicalTime::DURATION_SIXTEENTH,
+                    ))
+                    .build()
+                    .unwrap(),
+                None,
+            )
+            .unwrap();
+        let track_uid = project.new_midi_track().unwrap();
+        let _ = project.arrange_pattern(track_uid, pattern_uid, None, MusicalTime::START);
+
+        // At 60 BPM and 100 Hz, a beat is 100 frames, so each pass through
+        // the loop is four 25-frame buffers, and the last one ends exactly at
+        // the end of the loop.
+        project.set_loop_range(Some(TimeRange(
+            MusicalTime::ONE_BEAT..MusicalTime::new_with_beats(2),
+        )));
+        project.play();
+        let mut note_on_buffers = Vec::default();
+        let mut samples = [StereoSample::SILENCE; 25];
+        for i in 0..14 {
+            project.generate_audio(
+                &mut samples,
+                Some(&mut |_, m| {
+                    if matches!(m, MidiMessage::NoteOn { .. }) {
+                        note_on_buffers.push(i);
+                    }
+                }),
+            );
+        }
+        assert_eq!(
+            note_on_buffers,
+            vec![4, 8, 12],
+            "The note at the loop start should play at the start of every pass"
         );
+        assert!(project.transport.current_time() < MusicalTime::new_with_beats(2));
     }
 
     #[test]
@@ -1417,4 +3125,527 @@ mod tests {
         );
         assert_eq!(p.track_midi_channel(track_2_uid), Some(MidiChannel::DRUM));
     }
+
+    #[test]
+    fn project_records_automation() {
+        let mut project = Project::default();
+        let track_uid = project.new_audio_track().unwrap();
+        let uid = project
+            .add_entity(
+                track_uid,
+                Box::new(Gain::new_with(Uid::default(), GainCore::default())),
+            )
+            .unwrap();
+        let param = ControlIndex(0);
+        let beats = MusicalTime::new_with_fractional_beats;
+
+        fn change_at(project: &mut Project, uid: Uid, when: MusicalTime, value: f64) {
+            project.update_time_range(&TimeRange(when..when + MusicalTime::ONE_UNIT));
+            let value = ControlValue(value);
+            if let Some(entity) = project.orchestrator.entity_repo.entity_mut(uid) {
+                entity.control_set_param_by_index(ControlIndex(0), value);
+            }
+            assert!(project
+                .record_param_change(uid, ControlIndex(0), value)
+                .is_ok());
+        }
+        fn value_at(project: &mut Project, uid: Uid, when: MusicalTime) -> f64 {
+            let path_uid = project
+                .automator
+                .path_for_param(uid, ControlIndex(0))
+                .unwrap();
+            let path = project.automator.paths_mut().get(&path_uid).unwrap();
+            ControlValue::from(path.calculate_value(when).unwrap()).0
+        }
+
+        project.play();
+        change_at(&mut project, uid, beats(1.0), 0.25);
+        project.stop();
+        assert!(
+            project.automator.path_for_param(uid, param).is_none(),
+            "Nothing should be recorded unless recording is armed"
+        );
+
+        project.set_automation_record_mode(Some(AutomationRecordMode::Latch));
+        project.play();
+        assert!(
+            project.e.automation_pass_snapshot.is_some(),
+            "The pass should be remembered when playback starts, not at the first change"
+        );
+        change_at(&mut project, uid, beats(1.0), 0.25);
+        change_at(&mut project, uid, beats(2.0), 0.75);
+        project.update_time_range(&TimeRange(beats(3.0)..beats(3.0) + MusicalTime::ONE_UNIT));
+        project.stop();
+        assert!(
+            project.automator.path_for_param(uid, param).is_some(),
+            "Recording should create a path for the parameter"
+        );
+        assert_eq!(value_at(&mut project, uid, beats(0.5)), 0.25);
+        assert_eq!(
+            value_at(&mut project, uid, beats(1.5)),
+            0.25,
+            "The value should hold between changes"
+        );
+        assert_eq!(value_at(&mut project, uid, beats(2.5)), 0.75);
+
+        assert!(project.undo().is_ok());
+        assert!(
+            project.automator.path_for_param(uid, param).is_none(),
+            "Undo should remove everything that a recording pass created"
+        );
+        assert!(project.redo().is_ok());
+        assert_eq!(value_at(&mut project, uid, beats(1.5)), 0.25);
+        assert_eq!(value_at(&mut project, uid, beats(2.5)), 0.75);
+
+        project.set_automation_record_mode(Some(AutomationRecordMode::Touch));
+        project.play();
+        project.update_time_range(&TimeRange(MusicalTime::START..MusicalTime::ONE_UNIT));
+        change_at(&mut project, uid, beats(1.5), 1.0);
+        project.update_time_range(&TimeRange(beats(1.75)..beats(1.75) + MusicalTime::ONE_UNIT));
+        project.release_param(uid, param);
+        project.stop();
+        assert_eq!(value_at(&mut project, uid, beats(1.25)), 0.25);
+        assert_eq!(value_at(&mut project, uid, beats(1.6)), 1.0);
+        assert_eq!(
+            value_at(&mut project, uid, beats(2.5)),
+            0.75,
+            "Existing automation should resume after a touch is released"
+        );
+
+        project.set_automation_record_mode(Some(AutomationRecordMode::Overwrite));
+        project.play();
+        project.update_time_range(&TimeRange(MusicalTime::START..MusicalTime::ONE_UNIT));
+        change_at(&mut project, uid, beats(0.5), 0.5);
+        project.update_time_range(&TimeRange(beats(1.0)..beats(1.0) + MusicalTime::ONE_UNIT));
+        project.stop();
+        assert_eq!(value_at(&mut project, uid, beats(0.25)), 0.25);
+        assert_eq!(
+            value_at(&mut project, uid, beats(2.5)),
+            0.5,
+            "Overwrite should replace everything after the first change"
+        );
+
+        project.set_punch_range(Some(TimeRange(beats(1.0)..beats(2.0))));
+        project.set_automation_record_mode(Some(AutomationRecordMode::Latch));
+        project.play();
+        change_at(&mut project, uid, beats(0.75), 0.9);
+        change_at(&mut project, uid, beats(1.25), 0.1);
+        project.update_time_range(&TimeRange(beats(3.0)..beats(3.0) + MusicalTime::ONE_UNIT));
+        project.stop();
+        assert_eq!(
+            value_at(&mut project, uid, beats(0.75)),
+            0.5,
+            "Changes before the punch range shouldn't be recorded"
+        );
+        assert_eq!(value_at(&mut project, uid, beats(1.5)), 0.1);
+        assert_eq!(
+            value_at(&mut project, uid, beats(2.5)),
+            0.5,
+            "Recording should end at the end of the punch range"
+        );
+        project.set_punch_range(None);
+
+        // A bound MIDI controller is recorded as it's turned.
+        assert!(project
+            .execute(ProjectCommand::BindMidiCc(
+                MidiChannel::default(),
+                1,
+                uid,
+                param
+            ))
+            .is_ok());
+        project.play();
+        project.update_time_range(&TimeRange(beats(3.0)..beats(3.0) + MusicalTime::ONE_UNIT));
+        project.handle_midi_message(
+            MidiChannel::default(),
+            MidiMessage::Controller {
+                controller: 1.into(),
+                value: 127.into(),
+            },
+            &mut |_, _| {},
+        );
+        project.update_time_range(&TimeRange(beats(4.0)..beats(4.0) + MusicalTime::ONE_UNIT));
+        project.stop();
+        assert_eq!(value_at(&mut project, uid, beats(3.5)), 1.0);
+    }
+
+    #[test]
+    fn project_records_midi() {
+        let mut project = Project::default();
+        let track_uid = project.new_midi_track().unwrap();
+        let beats = MusicalTime::new_with_fractional_beats;
+
+        fn advance_to(project: &mut Project, when: MusicalTime) {
+            let frames = project.transport.time_to_frames(when) - project.transport.current_frame();
+            let mut buffer = vec![StereoSample::SILENCE; frames];
+            project.generate_audio(&mut buffer, None);
+        }
+        fn play_note(project: &mut Project, key: u8, on: bool) {
+            let message = if on {
+                MidiMessage::NoteOn {
+                    key: key.into(),
+                    vel: 100.into(),
+                }
+            } else {
+                MidiMessage::NoteOff {
+                    key: key.into(),
+                    vel: 0.into(),
+                }
+            };
+            project.handle_midi_message(MidiChannel::default(), message, &mut |_, _| {});
+        }
+
+        project.play();
+        play_note(&mut project, 60, true);
+        play_note(&mut project, 60, false);
+        project.stop();
+        assert!(
+            project.composer.patterns.is_empty(),
+            "Nothing should be recorded unless a track is armed"
+        );
+
+        project.set_midi_record_track(Some(track_uid));
+        project.set_midi_record_options(MidiRecordOptions {
+            quantize: Some(MusicalTime::ONE_BEAT),
+            ..Default::default()
+        });
+        project.seek(beats(4.0));
+        project.play();
+        assert!(project.is_recording_midi());
+        advance_to(&mut project, beats(5.1));
+        play_note(&mut project, 60, true);
+        advance_to(&mut project, beats(5.9));
+        play_note(&mut project, 60, false);
+        advance_to(&mut project, beats(6.5));
+        assert!(
+            project.transport.is_performing(),
+            "An empty project shouldn't stop playing while it's recording"
+        );
+        project.stop();
+        assert!(!project.is_recording_midi());
+
+        assert_eq!(project.composer.patterns.len(), 1);
+        let pattern_uid = project.composer.ordered_pattern_uids[0];
+        assert_eq!(
+            project.pattern(pattern_uid).unwrap().notes,
+            vec![Note {
+                key: 60,
+                extent: TimeRange(beats(1.0)..beats(2.0)),
+                velocity: 100,
+                release_velocity: 0,
+            }],
+            "The note should be quantized and relative to the start of the recording"
+        );
+        let arrangement_uids = project
+            .composer
+            .tracks_to_ordered_arrangement_uids
+            .get(&track_uid)
+            .unwrap();
+        assert_eq!(arrangement_uids.len(), 1);
+        let arrangement = project
+            .composer
+            .arrangements
+            .get(&arrangement_uids[0])
+            .unwrap();
+        assert_eq!(arrangement.pattern_uid, pattern_uid);
+        assert_eq!(
+            arrangement.position,
+            beats(4.0),
+            "The pattern should be arranged where recording started"
+        );
+
+        assert!(project.undo().is_ok(), "A recorded take should be undoable");
+        assert!(project.composer.patterns.is_empty());
+        assert!(project
+            .composer
+            .tracks_to_ordered_arrangement_uids
+            .get(&track_uid)
+            .map_or(true, |uids| uids.is_empty()));
+    }
+
+    #[test]
+    fn project_midi_learn() {
+        let mut project = Project::default();
+        let track_uid = project.new_audio_track().unwrap();
+        let uid = project
+            .add_entity(
+                track_uid,
+                Box::new(Gain::new_with(Uid::default(), GainCore::default())),
+            )
+            .unwrap();
+        let param = ControlIndex(0);
+        let channel = MidiChannel::default();
+        let cc = |value: u8| MidiMessage::Controller {
+            controller: 74.into(),
+            value: value.into(),
+        };
+        let param_value = |project: &Project| {
+            project
+                .orchestrator
+                .entity_repo
+                .entity(uid)
+                .unwrap()
+                .control_get_param_by_index(param)
+                .unwrap()
+                .0
+        };
+        if let Some(entity) = project.orchestrator.entity_repo.entity_mut(uid) {
+            entity.control_set_param_by_index(param, ControlValue(0.5));
+        }
+
+        project.handle_midi_message(channel, cc(127), &mut |_, _| {});
+        assert_eq!(
+            param_value(&project),
+            0.5,
+            "Unbound CCs shouldn't do anything"
+        );
+
+        project.learn_midi_cc(Some((uid, param)));
+        project.handle_midi_message(channel, cc(0), &mut |_, _| {});
+        assert_eq!(
+            param_value(&project),
+            0.5,
+            "The CC that's being learned shouldn't change the param"
+        );
+        assert_eq!(project.midi_cc_bindings().bindings().len(), 1);
+        assert!(project.undo().is_ok());
+        assert!(
+            project.midi_cc_bindings().bindings().is_empty(),
+            "A learned binding should be undoable"
+        );
+        assert!(project.redo().is_ok());
+        assert_eq!(project.midi_cc_bindings().bindings().len(), 1);
+
+        project.handle_midi_message(channel, cc(127), &mut |_, _| {});
+        assert_eq!(param_value(&project), 1.0);
+        project.handle_midi_message(MidiChannel(3), cc(0), &mut |_, _| {});
+        assert_eq!(
+            param_value(&project),
+            1.0,
+            "A binding should apply only to its channel"
+        );
+
+        project.set_automation_record_mode(Some(AutomationRecordMode::Latch));
+        project.play();
+        project.handle_midi_message(channel, cc(0), &mut |_, _| {});
+        project.stop();
+        assert!(
+            project.automator.path_for_param(uid, param).is_some(),
+            "A bound CC should be recorded into automation"
+        );
+
+        let json = serde_json::to_string(&project).unwrap();
+        let restored: Project = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            restored.midi_cc_bindings().bindings(),
+            project.midi_cc_bindings().bindings(),
+            "Bindings should be saved with the project"
+        );
+
+        assert!(project.remove_entity(uid).is_ok());
+        assert!(
+            project.midi_cc_bindings().bindings().is_empty(),
+            "Removing an entity should remove its bindings"
+        );
+
+        let uid = project
+            .add_entity(
+                track_uid,
+                Box::new(Gain::new_with(Uid::default(), GainCore::default())),
+            )
+            .unwrap();
+        assert!(project
+            .execute(ProjectCommand::BindMidiCc(channel, 74, uid, param))
+            .is_ok());
+        assert!(project.delete_track(track_uid).is_ok());
+        assert!(
+            project.midi_cc_bindings().bindings().is_empty(),
+            "Deleting a track should remove its entities' bindings"
+        );
+    }
+
+    #[test]
+    fn project_retriggers_back_to_back_notes() {
+        // At 60 BPM, a beat is a second, which is much longer than the synth's
+        // release.
+        let mut project = Project::default();
+        project.update_tempo(Tempo(60.0));
+        let track_uid = project.new_midi_track().unwrap();
+        let pattern_uid = project
+            .add_pattern(
+                PatternBuilder::default()
+                    .note(Note::new_with(
+                        60,
+                        MusicalTime::START,
+                        MusicalTime::ONE_BEAT,
+                    ))
+                    .note(Note::new_with(
+                        60,
+                        MusicalTime::ONE_BEAT,
+                        MusicalTime::ONE_BEAT * 2,
+                    ))
+                    .build()
+                    .unwrap(),
+                None,
+            )
+            .unwrap();
+        let _ = project.arrange_pattern(track_uid, pattern_uid, None, MusicalTime::START);
+        let _ = project.add_entity(
+            track_uid,
+            Box::new(SubtractiveSynth::new_with_factory_patch(Uid::default())),
+        );
+
+        // The first note ends on the same frame that the second one starts.
+        // If the second note's note-on went out before the first note's
+        // note-off, then the note-off would cut it short.
+        project.play();
+        let sample_rate = project.sample_rate().0;
+        let mut buffer = vec![StereoSample::SILENCE; sample_rate * 5 / 2];
+        project.generate_audio(&mut buffer, None);
+        assert!(
+            buffer[sample_rate * 2..]
+                .iter()
+                .any(|s| *s != StereoSample::SILENCE),
+            "The second note should still be sounding"
+        );
+    }
+
+    #[test]
+    fn project_stop_releases_sustained_notes() {
+        let mut project = Project::default();
+        let track_uid = project.new_midi_track().unwrap();
+        let _ = project.add_entity(
+            track_uid,
+            Box::new(SubtractiveSynth::new_with_factory_patch(Uid::default())),
+        );
+
+        project.play();
+        for message in [
+            MidiMessage::Controller {
+                controller: 64.into(),
+                value: 127.into(),
+            },
+            MidiMessage::NoteOn {
+                key: 60.into(),
+                vel: 100.into(),
+            },
+            MidiMessage::NoteOff {
+                key: 60.into(),
+                vel: 0.into(),
+            },
+        ] {
+            project.handle_midi_message(MidiChannel::default(), message, &mut |_, _| {});
+        }
+        let sample_rate = project.sample_rate().0;
+        let mut buffer = vec![StereoSample::SILENCE; sample_rate];
+        project.generate_audio(&mut buffer, None);
+        assert!(
+            buffer[sample_rate / 2..]
+                .iter()
+                .any(|s| *s != StereoSample::SILENCE),
+            "The sustain pedal should hold the note"
+        );
+
+        // The synth's release is much shorter than a second.
+        project.stop();
+        project.generate_audio(&mut buffer, None);
+        project.generate_audio(&mut buffer, None);
+        assert!(
+            buffer.iter().all(|s| *s == StereoSample::SILENCE),
+            "Stopping should release notes even while the pedal is down"
+        );
+    }
+
+    #[test]
+    fn project_timing_is_independent_of_buffer_size() {
+        // At 300 BPM, notes, automation and an LFO's signal land between
+        // buffer boundaries.
+        fn render(buffer_size: usize) -> Vec<StereoSample> {
+            let mut project = Project::default();
+            project.update_tempo(Tempo(300.0));
+            let track_uid = project.new_midi_track().unwrap();
+            let pattern_uid = project
+                .add_pattern(
+                    PatternBuilder::default()
+                        .note_sequence(vec![60, 255, 64, 67, 255, 72, 67, 64], None)
+                        .build()
+                        .unwrap(),
+                    None,
+                )
+                .unwrap();
+            let _ = project.arrange_pattern(track_uid, pattern_uid, None, MusicalTime::START);
+            let _ = project.add_entity(
+                track_uid,
+                Box::new(FmSynth::new_with_factory_patch(Uid::default())),
+            );
+            let gain_uid = project
+                .add_entity(
+                    track_uid,
+                    Box::new(Gain::new_with(Uid::default(), GainCore::default())),
+                )
+                .unwrap();
+            let path_uid = project
+                .add_path(
+                    track_uid,
+                    SignalPathBuilder::default()
+                        .point(
+                            SignalPointBuilder::default()
+                                .when(MusicalTime::START)
+                                .value(BipolarNormal::minimum())
+                                .build()
+                                .unwrap(),
+                        )
+                        .point(
+                            SignalPointBuilder::default()
+                                .when(MusicalTime::ONE_BEAT)
+                                .value(BipolarNormal::maximum())
+                                .build()
+                                .unwrap(),
+                        )
+                        .build()
+                        .unwrap(),
+                )
+                .unwrap();
+            assert!(project
+                .link_path(path_uid, gain_uid, ControlIndex(0))
+                .is_ok());
+            let lfo_gain_uid = project
+                .add_entity(
+                    track_uid,
+                    Box::new(Gain::new_with(Uid::default(), GainCore::default())),
+                )
+                .unwrap();
+            let lfo_uid = project
+                .add_entity(
+                    track_uid,
+                    Box::new(LfoController::new_with(
+                        Uid::default(),
+                        LfoControllerCoreBuilder::default().build().unwrap(),
+                    )),
+                )
+                .unwrap();
+            assert!(project.link(lfo_uid, lfo_gain_uid, ControlIndex(0)).is_ok());
+
+            project.play();
+            let mut rendered = Vec::default();
+            let mut buffer = vec![StereoSample::SILENCE; buffer_size];
+            for _ in 0..(16384 / buffer_size) {
+                buffer.fill(StereoSample::SILENCE);
+                project.generate_audio(&mut buffer, None);
+                rendered.extend_from_slice(&buffer);
+            }
+            rendered
+        }
+
+        let rendered_64 = render(64);
+        assert!(
+            rendered_64.iter().any(|s| *s != StereoSample::SILENCE),
+            "The project should make some sound"
+        );
+        assert_eq!(
+            rendered_64,
+            render(1024),
+            "Renders should be identical no matter the buffer size"
+        );
+    }
 }
@@ -243,6 +243,13 @@ impl Controls for EntityRepository {
             e.skip_to_start();
         });
     }
+
+    fn seek(&mut self, position: MusicalTime) {
+        self.entities.values_mut().for_each(|e| {
+            e.seek(position);
+        });
+        self.update_is_finished();
+    }
 }
 impl ControlsAsProxy for EntityRepository {
     fn work_as_proxy(&mut self, control_events_fn: &mut ControlProxyEventsFn) {
@@ -254,7 +261,7 @@ impl ControlsAsProxy for EntityRepository {
 
             // Call each entity's Controls::work(), processing any events it
             // generates.
-            e.work(&mut |inner_event| match inner_event {
+            e.work_timed(&mut |when, inner_event| match inner_event {
                 WorkEvent::Midi(channel, message) => {
                     // We have a MIDI event. Do we know the entity's track Uid?
                     if track_uid.is_none() {
@@ -265,12 +272,13 @@ impl ControlsAsProxy for EntityRepository {
                     }
                     control_events_fn(
                         (*uid).into(),
+                        when,
                         WorkEvent::MidiForTrack(track_uid.unwrap_or_default(), channel, message),
                     );
                 }
                 _ => {
                     // Route other event types without further processing.
-                    control_events_fn((*uid).into(), inner_event)
+                    control_events_fn((*uid).into(), when, inner_event)
                 }
             })
         });
@@ -0,0 +1,981 @@
+// Copyright (c) 2024 Mike Tsao
+
+//! Support for Standard MIDI Files (SMF).
+
+use crate::{
+    orchestration::TrackTitle,
+    prelude::*,
+    types::{MidiEvent, TempoChange, TempoMap, TempoTransition, TimeSignatureChange},
+};
+use anyhow::{anyhow, Result};
+use midly::{
+    num::{u15, u24, u28},
+    Format, Header, MetaMessage, Smf, Timing, TrackEvent, TrackEventKind,
+};
+use rustc_hash::FxHashMap;
+use std::path::PathBuf;
+
+/// The resolution of exported Standard MIDI Files, in ticks per beat.
+const SMF_TICKS_PER_BEAT: u16 = 960;
+
+/// The notes found on a single channel of a single SMF track.
+struct SmfChannelNotes {
+    channel: u8,
+    notes: Vec<Note>,
+}
+
+/// The parts of an SMF track that we know how to import.
+#[derive(Default)]
+struct SmfTrack {
+    title: Option<String>,
+    channels: Vec<SmfChannelNotes>,
+}
+impl SmfTrack {
+    fn notes_mut(&mut self, channel: u8) -> &mut Vec<Note> {
+        let index = if let Some(index) = self.channels.iter().position(|c| c.channel == channel) {
+            index
+        } else {
+            self.channels.push(SmfChannelNotes {
+                channel,
+                notes: Vec::default(),
+            });
+            self.channels.len() - 1
+        };
+        &mut self.channels[index].notes
+    }
+}
+
+/// Everything that [Project::import_smf()] takes from a file, checked and
+/// ready to add to a project.
+struct SmfImport {
+    /// The tempo at the start of the file, if it sets one.
+    tempo: Option<Tempo>,
+    /// The time signature at the start of the file, if it sets one.
+    time_signature: Option<TimeSignature>,
+    /// The project's tempo map with the file's later changes added.
+    tempo_map: TempoMap,
+    /// One entry for each new MIDI track.
+    tracks: Vec<SmfImportTrack>,
+}
+
+/// A new MIDI track that [Project::import_smf()] will add.
+struct SmfImportTrack {
+    title: Option<TrackTitle>,
+    midi_channel: MidiChannel,
+    pattern: Pattern,
+}
+
+impl Project {
+    /// Creates a new [Project] from the Standard MIDI File at the given path.
+    /// See [Project::import_smf()] for details.
+    pub fn load_smf(path: PathBuf) -> Result<Self> {
+        let bytes = std::fs::read(&path)?;
+        let mut project = Self::default();
+        project.import_smf(&bytes)?;
+        Ok(project)
+    }
+
+    /// Imports the contents of a type 0 or type 1 Standard MIDI File.
+    ///
+    /// Each channel that has notes in each SMF track becomes a new MIDI track
+    /// in this project, receiving on that channel. The notes become a single
+    /// [Pattern] that is arranged at the start of the track. Tempo and
+    /// time-signature meta events at the very start of the file set the
+    /// project's tempo and time signature, and later ones are added to the
+    /// [Transport]'s [TempoMap](crate::types::TempoMap).
+    ///
+    /// A MIDI quarter note is treated as one beat. Returns the [TrackUid]s of
+    /// the new tracks, in file order. If the file can't be imported, then the
+    /// project isn't changed.
+    pub fn import_smf(&mut self, bytes: &[u8]) -> Result<Vec<TrackUid>> {
+        // Nothing in the project changes until the whole file has been read
+        // and checked, so a bad file leaves it as it was.
+        let import = self.parse_smf(bytes)?;
+
+        let mut track_uids = Vec::default();
+        let mut pattern_uids = Vec::default();
+        if let Err(e) = self.add_smf_tracks(import.tracks, &mut track_uids, &mut pattern_uids) {
+            // The file was fine, so this shouldn't happen, but if it does, we
+            // take back the tracks that we added.
+            for pattern_uid in pattern_uids {
+                let _ = self.remove_pattern(pattern_uid);
+            }
+            for track_uid in track_uids {
+                self.track_titles.remove(&track_uid);
+                let _ = self.delete_track(track_uid);
+            }
+            return Err(e);
+        }
+
+        self.transport.tempo_map = import.tempo_map;
+        if let Some(tempo) = import.tempo {
+            self.update_tempo(tempo);
+        }
+        if let Some(time_signature) = import.time_signature {
+            self.update_time_signature(time_signature);
+        }
+        Ok(track_uids)
+    }
+
+    // Reads a Standard MIDI File and checks everything in it, without changing
+    // the project.
+    fn parse_smf(&self, bytes: &[u8]) -> Result<SmfImport> {
+        let smf = Smf::parse(bytes)?;
+        if let Format::Sequential = smf.header.format {
+            return Err(anyhow!("SMF type 2 (sequential) files aren't supported"));
+        }
+        let ticks_per_beat = match smf.header.timing {
+            Timing::Metrical(ticks_per_beat) => ticks_per_beat.as_int() as usize,
+            Timing::Timecode(_, _) => {
+                return Err(anyhow!("SMF timecode-based timing isn't supported"));
+            }
+        };
+        if ticks_per_beat == 0 {
+            return Err(anyhow!("SMF ticks per beat can't be zero"));
+        }
+        let ticks_to_time = |ticks: usize| {
+            MusicalTime::new_with_units(ticks * MusicalTime::UNITS_IN_BEAT / ticks_per_beat)
+        };
+
+        let mut tempos = Vec::default();
+        let mut time_signatures = Vec::default();
+        let mut smf_tracks = Vec::default();
+        for track in smf.tracks.iter() {
+            let mut smf_track = SmfTrack::default();
+
+            // Keyed by (channel, key), the start ticks and velocity of each
+            // note that is currently sounding. Overlapping notes of the same
+            // key are matched first-in, first-out.
+            let mut sounding: FxHashMap<(u8, u8), Vec<(usize, u8)>> = FxHashMap::default();
+            let mut ticks = 0;
+            for event in track.iter() {
+                ticks += event.delta.as_int() as usize;
+                match event.kind {
+                    TrackEventKind::Midi { channel, message } => {
+                        let channel = channel.as_int();
+                        match message {
+                            MidiMessage::NoteOn { key, vel } if vel.as_int() != 0 => {
+                                sounding
+                                    .entry((channel, key.as_int()))
+                                    .or_default()
+                                    .push((ticks, vel.as_int()));
+                            }
+                            MidiMessage::NoteOn { key, .. } | MidiMessage::NoteOff { key, .. } => {
+                                // A zero-velocity note-on says nothing about
+                                // how the note was released.
+                                let release_velocity = match message {
+                                    MidiMessage::NoteOff { vel, .. } => vel.as_int(),
+                                    _ => Note::DEFAULT_VELOCITY,
+                                };
+                                let key = key.as_int();
+                                if let Some(starts) = sounding.get_mut(&(channel, key)) {
+                                    if !starts.is_empty() {
+                                        let (start, velocity) = starts.remove(0);
+                                        smf_track.notes_mut(channel).push(Self::smf_note(
+                                            key,
+                                            ticks_to_time(start),
+                                            ticks_to_time(ticks),
+                                            velocity,
+                                            release_velocity,
+                                        ));
+                                    }
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                    TrackEventKind::Meta(MetaMessage::Tempo(us_per_beat)) => {
+                        if us_per_beat.as_int() != 0 {
+                            tempos.push((ticks, Tempo(60_000_000.0 / us_per_beat.as_int() as f64)));
+                        }
+                    }
+                    TrackEventKind::Meta(MetaMessage::TimeSignature(top, bottom_power, _, _)) => {
+                        if let Ok(time_signature) = TimeSignature::new_with(
+                            top as usize,
+                            1usize.checked_shl(bottom_power as u32).unwrap_or_default(),
+                        ) {
+                            time_signatures.push((ticks, time_signature));
+                        }
+                    }
+                    TrackEventKind::Meta(MetaMessage::TrackName(name)) => {
+                        smf_track.title = Some(String::from_utf8_lossy(name).trim().to_string());
+                    }
+                    _ => {}
+                }
+            }
+
+            // Any note that was never released ends with its track.
+            let mut unreleased: Vec<_> = sounding
+                .into_iter()
+                .flat_map(|((channel, key), starts)| {
+                    starts
+                        .into_iter()
+                        .map(move |(start, velocity)| (channel, key, start, velocity))
+                })
+                .collect();
+            unreleased.sort();
+            for (channel, key, start, velocity) in unreleased {
+                smf_track.notes_mut(channel).push(Self::smf_note(
+                    key,
+                    ticks_to_time(start),
+                    ticks_to_time(ticks),
+                    velocity,
+                    Note::DEFAULT_VELOCITY,
+                ));
+            }
+
+            if !smf_track.channels.is_empty() {
+                smf_tracks.push(smf_track);
+            }
+        }
+
+        // Type 1 files usually keep these in the first track, but they're
+        // allowed anywhere, so we need to put them in order.
+        let mut tempo = None;
+        let mut tempo_map = self.transport.tempo_map.clone();
+        tempos.sort_by_key(|(ticks, _)| *ticks);
+        for (ticks, smf_tempo) in tempos {
+            if !smf_tempo.is_valid() {
+                return Err(anyhow!("SMF tempo {smf_tempo} is out of range"));
+            }
+            if ticks == 0 {
+                tempo = Some(smf_tempo);
+            } else {
+                tempo_map.add_tempo_change(TempoChange {
+                    time: ticks_to_time(ticks),
+                    tempo: smf_tempo,
+                    transition: TempoTransition::Jump,
+                })?;
+            }
+        }
+        let mut time_signature = None;
+        time_signatures.sort_by_key(|(ticks, _)| *ticks);
+        for (ticks, smf_time_signature) in time_signatures {
+            if ticks == 0 {
+                time_signature = Some(smf_time_signature);
+            } else {
+                tempo_map.add_time_signature_change(TimeSignatureChange {
+                    time: ticks_to_time(ticks),
+                    time_signature: smf_time_signature,
+                });
+            }
+        }
+
+        let mut tracks = Vec::default();
+        for mut smf_track in smf_tracks {
+            smf_track.channels.sort_by_key(|c| c.channel);
+            let is_multichannel = smf_track.channels.len() > 1;
+            for channel_notes in smf_track.channels {
+                let midi_channel = MidiChannel::new(channel_notes.channel);
+                let title = smf_track
+                    .title
+                    .as_ref()
+                    .filter(|t| !t.is_empty())
+                    .map(|title| {
+                        TrackTitle(if is_multichannel {
+                            format!("{title} (channel {midi_channel})")
+                        } else {
+                            title.clone()
+                        })
+                    });
+
+                let mut notes = channel_notes.notes;
+                notes.sort_by_key(|n| (n.extent.start(), n.key));
+                let pattern = PatternBuilder::default()
+                    .time_signature(time_signature.unwrap_or(self.time_signature()))
+                    .notes(notes)
+                    .build()?;
+                tracks.push(SmfImportTrack {
+                    title,
+                    midi_channel,
+                    pattern,
+                });
+            }
+        }
+
+        Ok(SmfImport {
+            tempo,
+            time_signature,
+            tempo_map,
+            tracks,
+        })
+    }
+
+    // Adds the tracks that parse_smf() found, recording each new track and
+    // pattern as it goes so that the caller can remove them if something
+    // fails.
+    fn add_smf_tracks(
+        &mut self,
+        tracks: Vec<SmfImportTrack>,
+        track_uids: &mut Vec<TrackUid>,
+        pattern_uids: &mut Vec<PatternUid>,
+    ) -> Result<()> {
+        for mut track in tracks {
+            let track_uid = self.new_midi_track()?;
+            track_uids.push(track_uid);
+            if let Some(title) = track.title {
+                self.track_titles.insert(track_uid, title);
+            }
+            self.set_track_midi_channel(track_uid, track.midi_channel);
+
+            track.pattern.color_scheme = self.composer.suggest_next_pattern_color_scheme();
+            let pattern_uid = self.add_pattern(track.pattern, None)?;
+            pattern_uids.push(pattern_uid);
+            self.arrange_pattern(
+                track_uid,
+                pattern_uid,
+                Some(track.midi_channel),
+                MusicalTime::START,
+            )?;
+        }
+        Ok(())
+    }
+
+    // A note-on immediately followed by a note-off would otherwise vanish, so
+    // we give every note at least a little duration.
+    fn smf_note(
+        key: u8,
+        start: MusicalTime,
+        end: MusicalTime,
+        velocity: u8,
+        release_velocity: u8,
+    ) -> Note {
+        let mut note =
+            Note::new_with_start_and_end(key, start, end.max(start + MusicalTime::ONE_PART));
+        note.velocity = Note::clamp_velocity(velocity);
+        note.release_velocity = release_velocity;
+        note
+    }
+
+    /// Writes this project's composition to the given path as a type 1
+    /// Standard MIDI File. See [Project::to_smf_bytes()] for details.
+    pub fn export_to_smf(&self, path: PathBuf) -> Result<()> {
+        std::fs::write(path, self.to_smf_bytes()?)?;
+        Ok(())
+    }
+
+    /// Renders this project's composition as a type 1 Standard MIDI File.
+    ///
+    /// The first SMF track carries the project title and the [Transport]'s
+    /// tempo, time signature, and tempo map. SMF has no tempo ramps, so ramps
+    /// are approximated with a tempo event on each beat. After that, each
+    /// project track that has arranged patterns becomes an SMF track, in
+    /// project track order, with each arrangement's notes sent on that
+    /// arrangement's [MidiChannel]. Tracks without arrangements are skipped.
+    ///
+    /// Returns an error if a time signature has more than 255 beats per
+    /// measure, which SMF can't represent.
+    pub fn to_smf_bytes(&self) -> Result<Vec<u8>> {
+        let time_to_ticks = |time: MusicalTime| {
+            ((time.total_units() * SMF_TICKS_PER_BEAT as usize + MusicalTime::UNITS_IN_BEAT / 2)
+                / MusicalTime::UNITS_IN_BEAT) as u32
+        };
+
+        let mut smf = Smf::new(Header::new(
+            Format::Parallel,
+            Timing::Metrical(u15::new(SMF_TICKS_PER_BEAT)),
+        ));
+
+        let mut conductor_events = Vec::default();
+        if let Some(title) = self.title.as_ref() {
+            conductor_events.push((
+                0,
+                TrackEventKind::Meta(MetaMessage::TrackName(title.0.as_bytes())),
+            ));
+        }
+        let tempo_event = |tempo: Tempo| {
+            let us_per_beat = (60_000_000.0 / tempo.0.max(1.0)).round();
+            TrackEventKind::Meta(MetaMessage::Tempo(u24::new(
+                (us_per_beat as u32).min(u24::max_value().as_int()),
+            )))
+        };
+        let time_signature_event = |time_signature: TimeSignature| -> Result<TrackEventKind> {
+            let top = u8::try_from(time_signature.top).map_err(|_| {
+                anyhow!("Time signature {time_signature} is too large for a MIDI file")
+            })?;
+            Ok(TrackEventKind::Meta(MetaMessage::TimeSignature(
+                top,
+                time_signature.bottom.trailing_zeros() as u8,
+                24,
+                8,
+            )))
+        };
+        conductor_events.push((0, tempo_event(self.tempo())));
+        conductor_events.push((0, time_signature_event(self.time_signature())?));
+        let tempo_map = &self.transport.tempo_map;
+        let mut prior_change_time = MusicalTime::START;
+        for change in tempo_map.tempo_changes() {
+            if change.transition == TempoTransition::Ramp {
+                let mut time = prior_change_time + MusicalTime::ONE_BEAT;
+                while time < change.time {
+                    conductor_events.push((
+                        time_to_ticks(time),
+                        tempo_event(self.transport.tempo_at(time)),
+                    ));
+                    time = time + MusicalTime::ONE_BEAT;
+                }
+            }
+            conductor_events.push((time_to_ticks(change.time), tempo_event(change.tempo)));
+            prior_change_time = change.time;
+        }
+        for change in tempo_map.time_signature_changes() {
+            conductor_events.push((
+                time_to_ticks(change.time),
+                time_signature_event(change.time_signature)?,
+            ));
+        }
+        smf.tracks.push(Self::smf_track(conductor_events));
+
+        for track_uid in self.track_uids() {
+            let mut events = Vec::default();
+            if let Some(arrangement_uids) = self
+                .composer
+                .tracks_to_ordered_arrangement_uids
+                .get(track_uid)
+            {
+                for arrangement in arrangement_uids
+                    .iter()
+                    .filter_map(|auid| self.composer.arrangements.get(auid))
+                {
+                    if let Some(pattern) = self.composer.pattern(arrangement.pattern_uid) {
+                        let channel = u4::new(arrangement.midi_channel.0);
+                        for note in pattern.notes() {
+                            let midi_events: Vec<MidiEvent> = note.clone().into();
+                            events.extend(midi_events.into_iter().map(|event| {
+                                (
+                                    time_to_ticks(arrangement.position + event.time),
+                                    TrackEventKind::Midi {
+                                        channel,
+                                        message: event.message,
+                                    },
+                                )
+                            }));
+                        }
+                    }
+                }
+            }
+            if events.is_empty() {
+                continue;
+            }
+            if let Some(title) = self.track_titles.get(track_uid) {
+                events.push((
+                    0,
+                    TrackEventKind::Meta(MetaMessage::TrackName(title.0.as_bytes())),
+                ));
+            }
+            smf.tracks.push(Self::smf_track(events));
+        }
+
+        let mut bytes = Vec::default();
+        smf.write_std(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    // Turns a list of events at absolute ticks into an SMF track with relative
+    // ticks. At any given tick, meta events come first, then note-offs, so
+    // that a note ending exactly when the next one starts doesn't cut it off.
+    fn smf_track(mut events: Vec<(u32, TrackEventKind)>) -> Vec<TrackEvent> {
+        events.sort_by_key(|(ticks, kind)| {
+            let order = match kind {
+                TrackEventKind::Meta(_) => 0,
+                TrackEventKind::Midi {
+                    message: MidiMessage::NoteOff { .. },
+                    ..
+                } => 1,
+                _ => 2,
+            };
+            (*ticks, order)
+        });
+        let mut last_ticks = 0;
+        let mut track: Vec<TrackEvent> = events
+            .into_iter()
+            .map(|(ticks, kind)| {
+                let delta = ticks - last_ticks;
+                last_ticks = ticks;
+                TrackEvent {
+                    delta: u28::new(delta),
+                    kind,
+                }
+            })
+            .collect();
+        track.push(TrackEvent {
+            delta: u28::new(0),
+            kind: TrackEventKind::Meta(MetaMessage::EndOfTrack),
+        });
+        track
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orchestration::ProjectTitle;
+
+    fn event(delta: u32, kind: TrackEventKind) -> TrackEvent {
+        TrackEvent {
+            delta: u28::new(delta),
+            kind,
+        }
+    }
+
+    fn note_on(delta: u32, channel: u8, key: u8) -> TrackEvent<'static> {
+        event(
+            delta,
+            TrackEventKind::Midi {
+                channel: u4::new(channel),
+                message: MidiMessage::NoteOn {
+                    key: u7::new(key),
+                    vel: u7::new(100),
+                },
+            },
+        )
+    }
+
+    fn note_off(delta: u32, channel: u8, key: u8) -> TrackEvent<'static> {
+        event(
+            delta,
+            TrackEventKind::Midi {
+                channel: u4::new(channel),
+                message: MidiMessage::NoteOff {
+                    key: u7::new(key),
+                    vel: u7::new(64),
+                },
+            },
+        )
+    }
+
+    fn smf_bytes(format: Format, tracks: Vec<Vec<TrackEvent>>) -> Vec<u8> {
+        let mut smf = Smf::new(Header::new(format, Timing::Metrical(u15::new(480))));
+        smf.tracks = tracks;
+        let mut bytes = Vec::default();
+        smf.write_std(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn smf_type_1_import() {
+        let bytes = smf_bytes(
+            Format::Parallel,
+            vec![
+                vec![
+                    event(
+                        0,
+                        TrackEventKind::Meta(MetaMessage::Tempo(u24::new(500000))),
+                    ),
+                    event(
+                        0,
+                        TrackEventKind::Meta(MetaMessage::TimeSignature(3, 2, 24, 8)),
+                    ),
+                    event(0, TrackEventKind::Meta(MetaMessage::EndOfTrack)),
+                ],
+                vec![
+                    event(0, TrackEventKind::Meta(MetaMessage::TrackName(b"Lead"))),
+                    note_on(0, 0, 60),
+                    note_off(480, 0, 60),
+                    note_on(0, 0, 62),
+                    note_off(240, 0, 62),
+                    event(0, TrackEventKind::Meta(MetaMessage::EndOfTrack)),
+                ],
+                vec![
+                    note_on(960, 9, 36),
+                    note_off(120, 9, 36),
+                    event(0, TrackEventKind::Meta(MetaMessage::EndOfTrack)),
+                ],
+            ],
+        );
+
+        let mut project = Project::default();
+        let track_uids = project.import_smf(&bytes).unwrap();
+        assert_eq!(
+            track_uids.len(),
+            2,
+            "The conductor track has no notes, so it shouldn't become a project track"
+        );
+        assert_eq!(project.tempo(), Tempo(120.0));
+        assert_eq!(project.time_signature(), TimeSignature::WALTZ_TIME);
+        assert_eq!(project.track_titles.get(&track_uids[0]).unwrap().0, "Lead");
+        assert_eq!(
+            project.track_midi_channel(track_uids[1]),
+            Some(MidiChannel::new(9))
+        );
+
+        let arrangement_uids = project
+            .composer
+            .tracks_to_ordered_arrangement_uids
+            .get(&track_uids[0])
+            .unwrap();
+        assert_eq!(arrangement_uids.len(), 1);
+        let arrangement = project
+            .composer
+            .arrangements
+            .get(&arrangement_uids[0])
+            .unwrap();
+        assert_eq!(arrangement.position, MusicalTime::START);
+        assert_eq!(arrangement.midi_channel, MidiChannel::new(0));
+        let pattern = project.pattern(arrangement.pattern_uid).unwrap();
+        assert_eq!(
+            pattern.notes(),
+            &vec![
+                Note {
+                    release_velocity: 64,
+                    ..Note::new_with_velocity(60, MusicalTime::START, MusicalTime::ONE_BEAT, 100)
+                },
+                Note {
+                    release_velocity: 64,
+                    ..Note::new_with_velocity(
+                        62,
+                        MusicalTime::ONE_BEAT,
+                        MusicalTime::new_with_fractional_beats(0.5),
+                        100
+                    )
+                },
+            ]
+        );
+        assert_eq!(pattern.time_signature(), TimeSignature::WALTZ_TIME);
+
+        let arrangement_uids = project
+            .composer
+            .tracks_to_ordered_arrangement_uids
+            .get(&track_uids[1])
+            .unwrap();
+        let arrangement = project
+            .composer
+            .arrangements
+            .get(&arrangement_uids[0])
+            .unwrap();
+        assert_eq!(arrangement.midi_channel, MidiChannel::new(9));
+        assert_eq!(
+            project.pattern(arrangement.pattern_uid).unwrap().notes(),
+            &vec![Note {
+                release_velocity: 64,
+                ..Note::new_with_velocity(
+                    36,
+                    MusicalTime::new_with_beats(2),
+                    MusicalTime::new_with_fractional_beats(0.25),
+                    100
+                )
+            }]
+        );
+    }
+
+    #[test]
+    fn smf_type_0_import_splits_channels() {
+        let bytes = smf_bytes(
+            Format::SingleTrack,
+            vec![vec![
+                event(0, TrackEventKind::Meta(MetaMessage::TrackName(b"Song"))),
+                note_on(0, 1, 60),
+                note_on(0, 2, 48),
+                // Note-on with zero velocity is a note-off.
+                event(
+                    480,
+                    TrackEventKind::Midi {
+                        channel: u4::new(1),
+                        message: MidiMessage::NoteOn {
+                            key: u7::new(60),
+                            vel: u7::new(0),
+                        },
+                    },
+                ),
+                // Channel 2's note is never released.
+                event(480, TrackEventKind::Meta(MetaMessage::EndOfTrack)),
+            ]],
+        );
+
+        let mut project = Project::default();
+        let track_uids = project.import_smf(&bytes).unwrap();
+        assert_eq!(track_uids.len(), 2, "Each channel should get its own track");
+        assert_eq!(
+            project.tempo(),
+            Tempo::default(),
+            "A file without a tempo event shouldn't change the tempo"
+        );
+        assert_eq!(
+            project.track_midi_channel(track_uids[0]),
+            Some(MidiChannel::new(1))
+        );
+        assert_eq!(
+            project.track_midi_channel(track_uids[1]),
+            Some(MidiChannel::new(2))
+        );
+        assert_eq!(
+            project.track_titles.get(&track_uids[1]).unwrap().0,
+            "Song (channel 2)"
+        );
+
+        let arrangement_uid =
+            project.composer.tracks_to_ordered_arrangement_uids[&track_uids[1]][0];
+        let pattern_uid = project.composer.arrangements[&arrangement_uid].pattern_uid;
+        assert_eq!(
+            project.pattern(pattern_uid).unwrap().notes(),
+            &vec![Note::new_with_velocity(
+                48,
+                MusicalTime::START,
+                MusicalTime::new_with_beats(2),
+                100
+            )],
+            "An unreleased note should end with its track"
+        );
+    }
+
+    #[test]
+    fn smf_import_rejects_unsupported_files() {
+        let mut project = Project::default();
+        assert!(project.import_smf(&[0, 1, 2, 3]).is_err());
+        assert!(project
+            .import_smf(&smf_bytes(Format::Sequential, vec![vec![]]))
+            .is_err());
+        assert!(project.orchestrator.track_repo.uids().is_empty());
+    }
+
+    #[test]
+    fn smf_import_leaves_project_alone_on_failure() {
+        // Everything before the last event is fine, but a microsecond per beat
+        // is far too fast.
+        let bytes = smf_bytes(
+            Format::SingleTrack,
+            vec![vec![
+                event(
+                    0,
+                    TrackEventKind::Meta(MetaMessage::Tempo(u24::new(500000))),
+                ),
+                event(
+                    0,
+                    TrackEventKind::Meta(MetaMessage::TimeSignature(3, 2, 24, 8)),
+                ),
+                note_on(0, 0, 60),
+                note_off(480, 0, 60),
+                event(480, TrackEventKind::Meta(MetaMessage::Tempo(u24::new(1)))),
+                event(0, TrackEventKind::Meta(MetaMessage::EndOfTrack)),
+            ]],
+        );
+        let mut project = Project::default();
+        let tempo = project.tempo();
+        let time_signature = project.time_signature();
+        assert!(project.import_smf(&bytes).is_err());
+        assert!(project.orchestrator.track_repo.uids().is_empty());
+        assert!(project.composer.patterns.is_empty());
+        assert_eq!(project.tempo(), tempo);
+        assert_eq!(project.time_signature(), time_signature);
+        assert!(project.transport.tempo_map.is_empty());
+    }
+
+    #[test]
+    fn smf_tempo_map() {
+        let mut project = Project::default();
+        project.update_tempo(Tempo(60.0));
+        project
+            .transport
+            .tempo_map
+            .add_tempo_change(TempoChange {
+                time: MusicalTime::new_with_beats(4),
+                tempo: Tempo(120.0),
+                transition: TempoTransition::Jump,
+            })
+            .unwrap();
+        project
+            .transport
+            .tempo_map
+            .add_time_signature_change(TimeSignatureChange {
+                time: MusicalTime::new_with_beats(8),
+                time_signature: TimeSignature::WALTZ_TIME,
+            });
+
+        let mut imported = Project::default();
+        imported
+            .import_smf(&project.to_smf_bytes().unwrap())
+            .unwrap();
+        assert_eq!(imported.tempo(), Tempo(60.0));
+        assert_eq!(imported.time_signature(), TimeSignature::COMMON_TIME);
+        assert_eq!(imported.transport.tempo_map, project.transport.tempo_map);
+
+        // A ramp becomes a series of tempo events, one per beat.
+        project
+            .transport
+            .tempo_map
+            .add_tempo_change(TempoChange {
+                time: MusicalTime::new_with_beats(4),
+                tempo: Tempo(120.0),
+                transition: TempoTransition::Ramp,
+            })
+            .unwrap();
+        let bytes = project.to_smf_bytes().unwrap();
+        let smf = Smf::parse(&bytes).unwrap();
+        let tempo_events: Vec<(u32, u32)> = smf.tracks[0]
+            .iter()
+            .scan(0, |ticks, e| {
+                *ticks += e.delta.as_int();
+                Some((*ticks, e.kind))
+            })
+            .filter_map(|(ticks, kind)| match kind {
+                TrackEventKind::Meta(MetaMessage::Tempo(us_per_beat)) => {
+                    Some((ticks / SMF_TICKS_PER_BEAT as u32, us_per_beat.as_int()))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            tempo_events,
+            vec![
+                (0, 1_000_000),
+                (1, 800_000),
+                (2, 666_667),
+                (3, 571_429),
+                (4, 500_000)
+            ]
+        );
+    }
+
+    #[test]
+    fn smf_export() {
+        let mut project = Project::default();
+        project.title = Some(ProjectTitle("Exported".into()));
+        project.update_tempo(Tempo(100.0));
+        project.update_time_signature(TimeSignature::WALTZ_TIME);
+        let empty_track_uid = project.new_midi_track().unwrap();
+        let track_uid = project.new_midi_track().unwrap();
+        project
+            .track_titles
+            .insert(track_uid, TrackTitle("Bass".into()));
+        let pattern_uid = project
+            .add_pattern(
+                PatternBuilder::default()
+                    .time_signature(TimeSignature::WALTZ_TIME)
+                    .note(Note::new_with_velocity(
+                        40,
+                        MusicalTime::START,
+                        MusicalTime::ONE_BEAT,
+                        90,
+                    ))
+                    .note(Note::new_with(
+                        43,
+                        MusicalTime::ONE_BEAT,
+                        MusicalTime::ONE_BEAT,
+                    ))
+                    .build()
+                    .unwrap(),
+                None,
+            )
+            .unwrap();
+        let position = MusicalTime::new_with_bars(&TimeSignature::WALTZ_TIME, 1);
+        project
+            .arrange_pattern(track_uid, pattern_uid, Some(MidiChannel::new(3)), position)
+            .unwrap();
+
+        let bytes = project.to_smf_bytes().unwrap();
+        let smf = Smf::parse(&bytes).unwrap();
+        assert_eq!(smf.header.format, Format::Parallel);
+        assert_eq!(
+            smf.tracks.len(),
+            2,
+            "The empty track {empty_track_uid} should have been skipped"
+        );
+        assert!(smf.tracks[0]
+            .iter()
+            .any(|e| e.kind == TrackEventKind::Meta(MetaMessage::Tempo(u24::new(600000)))));
+        assert!(smf.tracks[0]
+            .iter()
+            .any(|e| e.kind == TrackEventKind::Meta(MetaMessage::TimeSignature(3, 2, 24, 8))));
+        assert!(smf.tracks[0]
+            .iter()
+            .any(|e| e.kind == TrackEventKind::Meta(MetaMessage::TrackName(b"Exported"))));
+
+        let note_events: Vec<(u32, MidiMessage)> = smf.tracks[1]
+            .iter()
+            .scan(0, |ticks, e| {
+                *ticks += e.delta.as_int();
+                Some((*ticks, e.kind))
+            })
+            .filter_map(|(ticks, kind)| match kind {
+                TrackEventKind::Midi { channel, message } => {
+                    assert_eq!(channel.as_int(), 3);
+                    Some((ticks, message))
+                }
+                _ => None,
+            })
+            .collect();
+        let ticks_per_beat = SMF_TICKS_PER_BEAT as u32;
+        let start = 3 * ticks_per_beat;
+        assert_eq!(
+            note_events
+                .iter()
+                .map(|(ticks, message)| (*ticks, matches!(message, MidiMessage::NoteOn { .. })))
+                .collect::<Vec<_>>(),
+            vec![
+                (start, true),
+                (start + ticks_per_beat, false),
+                (start + ticks_per_beat, true),
+                (start + 2 * ticks_per_beat, false),
+            ],
+            "Notes should be positioned by arrangement, with note-offs ahead of simultaneous note-ons"
+        );
+
+        // What goes around comes around.
+        let mut imported = Project::default();
+        let track_uids = imported.import_smf(&bytes).unwrap();
+        assert_eq!(track_uids.len(), 1);
+        assert_eq!(imported.tempo(), Tempo(100.0));
+        assert_eq!(imported.time_signature(), TimeSignature::WALTZ_TIME);
+        assert_eq!(imported.track_titles.get(&track_uids[0]).unwrap().0, "Bass");
+        let arrangement_uid =
+            imported.composer.tracks_to_ordered_arrangement_uids[&track_uids[0]][0];
+        let pattern_uid = imported.composer.arrangements[&arrangement_uid].pattern_uid;
+        assert_eq!(
+            imported.pattern(pattern_uid).unwrap().notes(),
+            &vec![
+                Note::new_with_velocity(40, position, MusicalTime::ONE_BEAT, 90),
+                Note::new_with(43, position + MusicalTime::ONE_BEAT, MusicalTime::ONE_BEAT),
+            ]
+        );
+    }
+
+    #[test]
+    fn smf_export_edge_cases() {
+        let mut project = Project::default();
+        let track_uid = project.new_midi_track().unwrap();
+        let pattern_uid = project
+            .add_pattern(
+                PatternBuilder::default()
+                    .note(Note::new_with_velocity(
+                        40,
+                        MusicalTime::START,
+                        MusicalTime::ONE_BEAT,
+                        0,
+                    ))
+                    .build()
+                    .unwrap(),
+                None,
+            )
+            .unwrap();
+        project
+            .arrange_pattern(track_uid, pattern_uid, None, MusicalTime::START)
+            .unwrap();
+
+        let mut imported = Project::default();
+        let track_uids = imported
+            .import_smf(&project.to_smf_bytes().unwrap())
+            .unwrap();
+        let arrangement_uid =
+            imported.composer.tracks_to_ordered_arrangement_uids[&track_uids[0]][0];
+        let pattern_uid = imported.composer.arrangements[&arrangement_uid].pattern_uid;
+        assert_eq!(
+            imported.pattern(pattern_uid).unwrap().notes(),
+            &vec![Note::new_with_velocity(
+                40,
+                MusicalTime::START,
+                MusicalTime::ONE_BEAT,
+                1
+            )],
+            "A silent note should survive as the quietest possible note"
+        );
+
+        project.update_time_signature(TimeSignature {
+            top: 256,
+            bottom: 4,
+        });
+        assert!(
+            project.to_smf_bytes().is_err(),
+            "A time signature that SMF can't hold should be an error, not truncated"
+        );
+    }
+}
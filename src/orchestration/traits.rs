@@ -194,12 +194,20 @@ pub trait Projects: Configurable + Controls + Sized {
     fn unlink_path(&mut self, path_uid: PathUid, target_uid: Uid, param: ControlIndex);
 
     /// Returns an [Iterator] that renders the project as [StereoSample]s from
-    /// start to finish.
+    /// start to finish. The project is in offline mode (see
+    /// [Projects::set_is_rendering_offline()]) until the [Iterator] is
+    /// dropped.
     fn render(&mut self) -> impl Iterator<Item = StereoSample> {
+        self.set_is_rendering_offline(true);
         self.play();
         ProjectsRenderer::new_with(self)
     }
 
+    /// Tells the project whether it's being rendered offline rather than
+    /// played live. An offline render has to end, so a project that would
+    /// otherwise loop forever plays its loop only once.
+    fn set_is_rendering_offline(&mut self, _is_rendering_offline: bool) {}
+
     /// Fills the supplied buffer with [StereoSample]s that represent a portion
     /// of the project performance. Renders as of the current position set in
     /// [Controls] and advances the position appropriately. If the performance
@@ -249,6 +257,11 @@ impl<'a, P: Projects> ProjectsRenderer<'a, P> {
         }
     }
 }
+impl<'a, P: Projects> Drop for ProjectsRenderer<'a, P> {
+    fn drop(&mut self) {
+        self.project.set_is_rendering_offline(false);
+    }
+}
 impl<'a, P: Projects> Iterator for ProjectsRenderer<'a, P> {
     type Item = StereoSample;
 
@@ -1,31 +1,279 @@
 // Copyright (c) 2024 Mike Tsao
 
 use crate::prelude::*;
+use derive_builder::Builder;
 use std::path::PathBuf;
 
+/// The sample encodings that a WAV export can use.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum WavSampleFormat {
+    /// 16-bit signed integer, as on a CD.
+    #[default]
+    Int16,
+    /// 24-bit signed integer.
+    Int24,
+    /// 32-bit IEEE floating point.
+    Float32,
+}
+impl WavSampleFormat {
+    #[allow(missing_docs)]
+    pub fn bits_per_sample(&self) -> u16 {
+        match self {
+            WavSampleFormat::Int16 => 16,
+            WavSampleFormat::Int24 => 24,
+            WavSampleFormat::Float32 => 32,
+        }
+    }
+}
+
+/// Describes how [ProjectExporter] should render a WAV file.
+#[derive(Debug, Default, Clone, Builder)]
+#[builder(default)]
+pub struct WavExportOptions {
+    /// How each sample is encoded.
+    pub format: WavSampleFormat,
+
+    /// Whether to add triangular dither before reducing samples to an integer
+    /// format. Dither trades quantization distortion for a little noise. It
+    /// has no effect on floating-point formats.
+    pub dither: bool,
+
+    /// The sample rate of the rendered file. None renders at the project's
+    /// current sample rate. The project is returned to its original sample
+    /// rate after rendering.
+    pub sample_rate: Option<SampleRate>,
+}
+
 /// Exports [Projects] to various formats.
 pub struct ProjectExporter {}
 impl ProjectExporter {
-    /// Renders the project as a WAV file to the specified path.
+    /// Renders the project as a 16-bit WAV file to the specified path.
     #[cfg(feature = "hound")]
     pub fn export_to_wav(project: &mut impl Projects, path: PathBuf) -> anyhow::Result<()> {
+        Self::export_to_wav_with_options(project, path, &WavExportOptions::default())
+    }
+
+    /// Renders the project as a WAV file to the specified path, as described by
+    /// the given [WavExportOptions].
+    #[cfg(feature = "hound")]
+    pub fn export_to_wav_with_options(
+        project: &mut impl Projects,
+        path: PathBuf,
+        options: &WavExportOptions,
+    ) -> anyhow::Result<()> {
+        let live_sample_rate = project.sample_rate();
+        let sample_rate = options.sample_rate.unwrap_or(live_sample_rate);
+        let mut writer = StereoWavWriter::create(path, sample_rate, options)?;
+
+        if sample_rate != live_sample_rate {
+            project.update_sample_rate(sample_rate);
+        }
+        project.skip_to_start();
+
+        let mut result = Ok(());
+        {
+            let mut renderer = project.render();
+            while let Some(frame) = renderer.next() {
+                result = writer.write(&[frame]);
+                if result.is_err() {
+                    break;
+                }
+            }
+        }
+
+        if sample_rate != live_sample_rate {
+            project.update_sample_rate(live_sample_rate);
+        }
+        result?;
+        writer.finalize()
+    }
+}
+
+/// Writes [StereoSample]s to a WAV file in the format that [WavExportOptions]
+/// specifies.
+#[cfg(feature = "hound")]
+pub(crate) struct StereoWavWriter {
+    writer: hound::WavWriter<std::io::BufWriter<std::fs::File>>,
+    format: WavSampleFormat,
+    dither: bool,
+    quantizer: Quantizer,
+}
+#[cfg(feature = "hound")]
+impl StereoWavWriter {
+    pub(crate) fn create(
+        path: PathBuf,
+        sample_rate: SampleRate,
+        options: &WavExportOptions,
+    ) -> anyhow::Result<Self> {
         let spec = hound::WavSpec {
             channels: 2,
-            sample_rate: project.sample_rate().into(),
-            bits_per_sample: 16,
-            sample_format: hound::SampleFormat::Int,
+            sample_rate: sample_rate.into(),
+            bits_per_sample: options.format.bits_per_sample(),
+            sample_format: match options.format {
+                WavSampleFormat::Int16 | WavSampleFormat::Int24 => hound::SampleFormat::Int,
+                WavSampleFormat::Float32 => hound::SampleFormat::Float,
+            },
         };
-        let mut writer = hound::WavWriter::create(path, spec)?;
+        Ok(Self {
+            writer: hound::WavWriter::create(path, spec)?,
+            format: options.format,
+            dither: options.dither,
+            quantizer: Quantizer::new_with(options),
+        })
+    }
 
-        project.skip_to_start();
+    pub(crate) fn write(&mut self, frames: &[StereoSample]) -> anyhow::Result<()> {
+        for frame in frames {
+            match self.format {
+                WavSampleFormat::Int16 if !self.dither => {
+                    // Matches what we've always done for the default format,
+                    // so that existing renders don't change.
+                    let (left, right) = frame.into_i16();
+                    self.writer.write_sample(left)?;
+                    self.writer.write_sample(right)?;
+                }
+                WavSampleFormat::Int16 | WavSampleFormat::Int24 => {
+                    self.writer.write_sample(self.quantizer.quantize(frame.0))?;
+                    self.writer.write_sample(self.quantizer.quantize(frame.1))?;
+                }
+                WavSampleFormat::Float32 => {
+                    self.writer.write_sample(frame.0 .0 as f32)?;
+                    self.writer.write_sample(frame.1 .0 as f32)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn finalize(self) -> anyhow::Result<()> {
+        Ok(self.writer.finalize()?)
+    }
+}
+
+// Converts samples to integers of a given bit depth, optionally with dither.
+#[cfg(feature = "hound")]
+struct Quantizer {
+    max_amplitude: SampleType,
+    dither: bool,
+    rng: Rng,
+}
+#[cfg(feature = "hound")]
+impl Quantizer {
+    // A fixed seed keeps renders reproducible.
+    const DITHER_SEED: u128 = 0x656e_736e_6172_65;
 
-        let mut renderer = project.render();
-        while let Some(frame) = renderer.next() {
-            let (left, right) = frame.into_i16();
-            let _ = writer.write_sample(left);
-            let _ = writer.write_sample(right);
+    fn new_with(options: &WavExportOptions) -> Self {
+        Self {
+            max_amplitude: 2.0f64.powi(options.format.bits_per_sample() as i32 - 1) as SampleType,
+            dither: options.dither,
+            rng: Rng::new_with_seed(Self::DITHER_SEED),
         }
+    }
 
-        Ok(())
+    fn quantize(&mut self, sample: Sample) -> i32 {
+        let mut value = sample.0 * self.max_amplitude;
+        if self.dither {
+            // Triangular-PDF dither spanning plus or minus one LSB.
+            value += (self.rng.rand_float() - self.rng.rand_float()) as SampleType;
+        }
+        value
+            .round()
+            .clamp(-self.max_amplitude, self.max_amplitude - 1.0) as i32
+    }
+}
+
+#[cfg(all(test, feature = "hound"))]
+mod tests {
+    use super::*;
+    use crate::orchestration::AudioClip;
+    use std::sync::Arc;
+
+    // A one-beat project at 60 BPM, which is one second long.
+    fn one_second_project() -> Project {
+        let mut project = Project::default();
+        project.update_tempo(Tempo(60.0));
+        let track_uid = project.new_audio_track().unwrap();
+        let _ = project.add_audio_clip(
+            track_uid,
+            AudioClip::new_with_samples(
+                Arc::new(vec![StereoSample::from(0.5); 100]),
+                SampleRate(100),
+                MusicalTime::START,
+                MusicalTime::ONE_BEAT,
+            ),
+        );
+        project
+    }
+
+    #[test]
+    fn export_options() {
+        // Unit tests don't get CARGO_TARGET_TMPDIR, so keep concurrent test
+        // runs from writing to the same file.
+        let mut path = std::env::temp_dir();
+        path.push(format!(
+            "ensnare-export-options-test-{}.wav",
+            std::process::id()
+        ));
+
+        let mut project = one_second_project();
+        assert!(ProjectExporter::export_to_wav(&mut project, path.clone()).is_ok());
+        let reader = hound::WavReader::open(&path).unwrap();
+        assert_eq!(reader.spec().bits_per_sample, 16);
+        assert_eq!(reader.spec().sample_rate, 44100);
+
+        let options = WavExportOptionsBuilder::default()
+            .format(WavSampleFormat::Float32)
+            .sample_rate(Some(SampleRate(22050)))
+            .build()
+            .unwrap();
+        assert!(
+            ProjectExporter::export_to_wav_with_options(&mut project, path.clone(), &options)
+                .is_ok()
+        );
+        assert_eq!(
+            project.sample_rate(),
+            SampleRate(44100),
+            "Rendering at a different rate shouldn't change the live rate"
+        );
+        let mut reader = hound::WavReader::open(&path).unwrap();
+        assert_eq!(reader.spec().sample_format, hound::SampleFormat::Float);
+        assert_eq!(reader.spec().sample_rate, 22050);
+        let frames = reader.duration() as usize;
+        assert!(
+            (22050..22050 + 128).contains(&frames),
+            "One second at the render rate should be about 22050 frames, not {frames}"
+        );
+        let samples: Vec<f32> = reader.samples::<f32>().map(|s| s.unwrap()).collect();
+        assert!((samples[1000] - 0.5).abs() < 0.0001);
+
+        for dither in [false, true] {
+            let options = WavExportOptionsBuilder::default()
+                .format(WavSampleFormat::Int24)
+                .dither(dither)
+                .build()
+                .unwrap();
+            assert!(ProjectExporter::export_to_wav_with_options(
+                &mut project,
+                path.clone(),
+                &options
+            )
+            .is_ok());
+            let mut reader = hound::WavReader::open(&path).unwrap();
+            assert_eq!(reader.spec().bits_per_sample, 24);
+            let samples: Vec<i32> = reader.samples::<i32>().map(|s| s.unwrap()).collect();
+            let expected = 1 << 22; // 0.5 at 24 bits
+            if dither {
+                assert!(samples[1000..2000]
+                    .iter()
+                    .all(|s| (s - expected).abs() <= 1));
+                assert!(
+                    samples[1000..2000].iter().any(|s| *s != expected),
+                    "Dither should perturb the low bit"
+                );
+            } else {
+                assert!(samples[1000..2000].iter().all(|s| *s == expected));
+            }
+        }
+        let _ = std::fs::remove_file(&path);
     }
 }
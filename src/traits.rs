@@ -4,6 +4,7 @@
 //! the system.
 
 use crate::{
+    elements::SustainPedal,
     prelude::*,
     types::{MidiEvent, MidiNote},
 };
@@ -18,8 +19,8 @@ pub mod prelude {
         ControlProxyEventsFn, Controllable, Controls, ControlsAsProxy, Entity, Generates,
         GeneratesEnvelope, GenerationBuffer, HandlesMidi, HasExtent, HasMetadata, HasSettings,
         IsStereoSampleVoice, IsVoice, MidiMessagesFn, MidiNoteLabelMetadata, PlaysNotes,
-        ProvidesService, Sequences, SequencesMidi, Serializable, StoresVoices, TransformsAudio,
-        WorkEvent,
+        ProvidesService, Sequences, SequencesMidi, Serializable, StoresVoices,
+        TimedControlEventsFn, TransformsAudio, WorkEvent,
     };
     #[cfg(feature = "egui")]
     pub use super::{Displays, DisplaysAction};
@@ -30,6 +31,7 @@ pub mod prelude {
 // still organize them.
 pub use crate::automation::{
     ControlEventsFn, ControlProxyEventsFn, Controllable, Controls, ControlsAsProxy,
+    TimedControlEventsFn,
 };
 
 /// A convenience struct for the fields implied by [Configurable]. Note that
@@ -149,6 +151,21 @@ pub trait PlaysNotes {
     /// Initiates a note-off event, which can take a long time to complete,
     /// depending on how long the envelope's release is.
     fn note_off(&mut self, velocity: u7);
+
+    /// Bends the pitch of the current note, and of any later ones, by the
+    /// given number of semitones (which can be fractional or negative). The
+    /// default implementation ignores pitch bend.
+    fn set_pitch_bend(&mut self, _semitones: f64) {}
+
+    /// Sets the amount of the voice's modulation, usually from a mod wheel.
+    /// The default implementation ignores it.
+    fn set_modulation(&mut self, _modulation: Normal) {}
+
+    /// Silences the voice as quickly as it can without clicking, skipping the
+    /// usual release. The default implementation is an ordinary note-off.
+    fn stop_sound(&mut self) {
+        self.note_off(u7::from(0));
+    }
 }
 
 /// A [StoresVoices] provides access to a collection of voices for a polyphonic
@@ -156,7 +173,7 @@ pub trait PlaysNotes {
 /// handle voice-stealing.
 pub trait StoresVoices: Generates<StereoSample> + Send + Sync + core::fmt::Debug {
     /// The associated type of sample generator for this voice store.
-    type Voice;
+    type Voice: PlaysNotes;
 
     /// Generally, this value won't change after initialization, because we try
     /// not to dynamically allocate new voices.
@@ -168,6 +185,64 @@ pub trait StoresVoices: Generates<StereoSample> + Send + Sync + core::fmt::Debug
     /// Fails if we run out of idle voices and can't steal any active ones.
     fn get_voice(&mut self, key: &u7) -> anyhow::Result<&mut Box<Self::Voice>>;
 
+    /// The voice that was last given the key, if any. Unlike
+    /// [StoresVoices::get_voice()], this never assigns a voice to the key.
+    fn voice_for_key(&mut self, key: &u7) -> Option<&mut Box<Self::Voice>>;
+
+    /// The sustain pedal that holds this store's note-offs.
+    fn sustain_pedal(&self) -> &SustainPedal;
+
+    /// The sustain pedal that holds this store's note-offs, as mutable.
+    fn sustain_pedal_mut(&mut self) -> &mut SustainPedal;
+
+    /// Starts a note on the voice that [StoresVoices::get_voice()] picks, and
+    /// returns that voice.
+    fn note_on(&mut self, key: &u7, velocity: u7) -> anyhow::Result<&mut Box<Self::Voice>> {
+        self.sustain_pedal_mut().forget(*key);
+        let voice = self.get_voice(key)?;
+        voice.note_on(*key, velocity);
+        Ok(voice)
+    }
+
+    /// Releases the voice playing the given key. While the sustain pedal is
+    /// down, the release waits until the pedal comes up.
+    fn note_off(&mut self, key: &u7, velocity: u7) {
+        if self.sustain_pedal_mut().hold(*key) {
+            return;
+        }
+        if let Some(voice) = self.voice_for_key(key) {
+            voice.note_off(velocity);
+        }
+    }
+
+    /// Releases every key that's playing, which is what MIDI CC 123 (all
+    /// notes off) asks for. Like [StoresVoices::note_off()], it leaves the
+    /// notes sounding while the sustain pedal is down.
+    fn all_notes_off(&mut self) {
+        for key in 0..128u8 {
+            let key = u7::from(key);
+            if matches!(self.voice_for_key(&key), Some(voice) if voice.is_playing()) {
+                self.note_off(&key, u7::from(0));
+            }
+        }
+    }
+
+    /// Whether the sustain pedal is down.
+    fn is_sustaining(&self) -> bool {
+        self.sustain_pedal().is_down()
+    }
+
+    /// Presses (true) or lifts (false) the sustain pedal. Lifting it releases
+    /// every note that it was holding.
+    fn set_sustain(&mut self, is_down: bool) {
+        self.sustain_pedal_mut().set(is_down);
+        while let Some(key) = self.sustain_pedal_mut().next_release() {
+            if let Some(voice) = self.voice_for_key(&key) {
+                voice.note_off(u7::from(0));
+            }
+        }
+    }
+
     /// All the voices.
     // Thanks to https://stackoverflow.com/a/58612273/344467 for the lifetime
     // magic
@@ -492,6 +567,13 @@ pub trait TransformsAudio: core::fmt::Debug {
         // Default implementation is passthrough
         input_sample
     }
+
+    /// Provides the audio that should drive this effect during the next
+    /// [TransformsAudio::transform()], in place of its input. This is how a
+    /// compressor on one track ducks it in response to another track. Most
+    /// effects ignore it.
+    #[allow(unused_variables)]
+    fn set_sidechain(&mut self, samples: &[StereoSample]) {}
 }
 
 /// Each app should have a Settings struct that is composed of subsystems having
@@ -24,7 +24,11 @@ pub use {
     },
     queues::VisualizationQueue,
     ranges::{BipolarNormal, Normal},
-    time::{MusicalTime, SampleRate, Seconds, Tempo, TimeRange, TimeSignature, ViewRange},
+    time::{
+        BeatValue, BeatValueModifier, MusicalTime, NoteDivision, SampleRate, Seconds, Tempo,
+        TempoChange, TempoMap, TempoTransition, TimeRange, TimeSignature, TimeSignatureChange,
+        ViewRange,
+    },
     uid::{IsUid, Uid, UidFactory},
 };
 
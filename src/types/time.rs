@@ -51,10 +51,20 @@ impl Tempo {
     pub const fn range() -> core::ops::RangeInclusive<ParameterType> {
         Self::MIN_VALUE..=Self::MAX_VALUE
     }
+
+    /// Whether music can be played at this tempo, which must be above
+    /// [Tempo::MIN_VALUE] and at most [Tempo::MAX_VALUE].
+    pub fn is_valid(&self) -> bool {
+        // Written this way so that NaN fails, too.
+        self.0 > Self::MIN_VALUE && self.0 <= Self::MAX_VALUE
+    }
 }
 
 /// [BeatValue] enumerates numerical divisors used in most music.  
-#[derive(Clone, Debug, Default, FromRepr, IntoStaticStr)]
+#[derive(
+    Clone, Copy, Debug, Default, Eq, FromRepr, IntoStaticStr, PartialEq, Serialize, Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
 pub enum BeatValue {
     /// large/maxima
     Octuple = 128,
@@ -99,6 +109,54 @@ impl BeatValue {
     }
 }
 
+/// Ways to change the length of a [BeatValue].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BeatValueModifier {
+    /// The length is unchanged.
+    #[default]
+    Straight,
+    /// The length is one and a half times as long.
+    Dotted,
+    /// The length is two-thirds as long, so that three of them fit in the
+    /// space of two.
+    Triplet,
+}
+impl BeatValueModifier {
+    /// The factor by which this modifier scales a length.
+    pub fn factor(&self) -> f64 {
+        match self {
+            BeatValueModifier::Straight => 1.0,
+            BeatValueModifier::Dotted => 1.5,
+            BeatValueModifier::Triplet => 2.0 / 3.0,
+        }
+    }
+}
+
+/// A [NoteDivision] is a length of time, such as a dotted eighth note, that is
+/// expressed in musical terms. Its length in seconds depends on the tempo.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct NoteDivision {
+    /// The basic note length.
+    pub value: BeatValue,
+    /// How the basic length is changed.
+    pub modifier: BeatValueModifier,
+}
+impl NoteDivision {
+    #[allow(missing_docs)]
+    pub fn new_with(value: BeatValue, modifier: BeatValueModifier) -> Self {
+        Self { value, modifier }
+    }
+
+    /// The length of this division at the given tempo. The tempo counts the
+    /// note value of the time signature's bottom number.
+    pub fn to_seconds(&self, tempo: Tempo, time_signature: TimeSignature) -> Seconds {
+        let beats = time_signature.bottom as f64 / BeatValue::divisor(self.value);
+        Seconds(beats * self.modifier.factor() / tempo.bps())
+    }
+}
+
 /// [TimeSignature] represents a music [time
 /// signature](https://en.wikipedia.org/wiki/Time_signature).
 ///
@@ -641,6 +699,348 @@ impl Into<cpal::SampleRate> for SampleRate {
     }
 }
 
+/// Describes how the tempo arrives at a [TempoChange].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TempoTransition {
+    /// The tempo holds steady until the change, then jumps to the new tempo.
+    #[default]
+    Jump,
+    /// The tempo moves linearly (in musical time) from the prior tempo to the
+    /// new one, arriving at the new tempo exactly at the change. This is how
+    /// to express an accelerando or ritardando.
+    Ramp,
+}
+
+/// A point on a [TempoMap] where the tempo changes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TempoChange {
+    /// When the new tempo takes effect.
+    pub time: MusicalTime,
+    /// The new tempo.
+    pub tempo: Tempo,
+    /// How the tempo gets from the prior tempo to this one.
+    #[serde(default)]
+    pub transition: TempoTransition,
+}
+
+/// A point on a [TempoMap] where the meter changes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TimeSignatureChange {
+    /// When the new time signature takes effect.
+    pub time: MusicalTime,
+    /// The new time signature.
+    pub time_signature: TimeSignature,
+}
+
+/// A [TempoMap] lists the tempo and time-signature changes that happen over
+/// the course of a song. It doesn't know the song's initial tempo or time
+/// signature, which is why most of its methods ask for them.
+///
+/// With an empty [TempoMap], conversions between frames and [MusicalTime] are
+/// exactly the same as [MusicalTime::new_with_frames()] and
+/// [MusicalTime::as_frames()].
+///
+/// Conversions are fastest when the initial tempo passed to them matches the
+/// one given to [TempoMap::update_initial_tempo()], because the map can then
+/// reuse the tempo segments that it calculated when it last changed. Either
+/// way, they don't allocate, so they're safe to use while rendering.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TempoMap {
+    /// Tempo changes, ordered by time.
+    #[serde(default)]
+    tempo_changes: Vec<TempoChange>,
+    /// Time-signature changes, ordered by time.
+    #[serde(default)]
+    time_signature_changes: Vec<TimeSignatureChange>,
+
+    #[serde(skip)]
+    e: TempoMapEphemerals,
+}
+#[derive(Clone, Debug, Default)]
+struct TempoMapEphemerals {
+    // The initial tempo that the cached segments were built for, or None if
+    // they haven't been built.
+    segments_tempo: Option<Tempo>,
+    segments: Vec<TempoSegment>,
+}
+impl PartialEq for TempoMap {
+    fn eq(&self, other: &Self) -> bool {
+        self.tempo_changes == other.tempo_changes
+            && self.time_signature_changes == other.time_signature_changes
+    }
+}
+impl TempoMap {
+    /// Tells the map which initial tempo most conversions will use, so that it
+    /// can prepare for them.
+    pub fn update_initial_tempo(&mut self, initial_tempo: Tempo) {
+        if self.e.segments_tempo != Some(initial_tempo) {
+            self.e.segments_tempo = Some(initial_tempo);
+            self.rebuild_segments();
+        }
+    }
+
+    /// Adds a tempo change, replacing any existing one at the same time.
+    /// Returns an error, and leaves the map alone, if the new tempo isn't
+    /// above zero and at most [Tempo::MAX_VALUE].
+    pub fn add_tempo_change(&mut self, change: TempoChange) -> anyhow::Result<()> {
+        if !change.tempo.is_valid() {
+            return Err(anyhow!("Tempo {} is out of range", change.tempo));
+        }
+        self.tempo_changes.retain(|c| c.time != change.time);
+        let index = self.tempo_changes.partition_point(|c| c.time < change.time);
+        self.tempo_changes.insert(index, change);
+        self.rebuild_segments();
+        Ok(())
+    }
+
+    /// Removes the tempo change at the given time, if there is one.
+    pub fn remove_tempo_change(&mut self, time: MusicalTime) {
+        self.tempo_changes.retain(|c| c.time != time);
+        self.rebuild_segments();
+    }
+
+    /// Adds a time-signature change, replacing any existing one at the same
+    /// time.
+    pub fn add_time_signature_change(&mut self, change: TimeSignatureChange) {
+        self.remove_time_signature_change(change.time);
+        let index = self
+            .time_signature_changes
+            .partition_point(|c| c.time < change.time);
+        self.time_signature_changes.insert(index, change);
+    }
+
+    /// Removes the time-signature change at the given time, if there is one.
+    pub fn remove_time_signature_change(&mut self, time: MusicalTime) {
+        self.time_signature_changes.retain(|c| c.time != time);
+    }
+
+    /// Removes all changes.
+    pub fn clear(&mut self) {
+        self.tempo_changes.clear();
+        self.time_signature_changes.clear();
+        self.rebuild_segments();
+    }
+
+    #[allow(missing_docs)]
+    pub fn tempo_changes(&self) -> &[TempoChange] {
+        &self.tempo_changes
+    }
+
+    #[allow(missing_docs)]
+    pub fn time_signature_changes(&self) -> &[TimeSignatureChange] {
+        &self.time_signature_changes
+    }
+
+    #[allow(missing_docs)]
+    pub fn is_empty(&self) -> bool {
+        self.tempo_changes.is_empty() && self.time_signature_changes.is_empty()
+    }
+
+    /// Returns the tempo in effect at the given time.
+    pub fn tempo_at(&self, initial_tempo: Tempo, time: MusicalTime) -> Tempo {
+        let beats = Self::units_to_beats(time.total_units());
+        self.with_segments(initial_tempo, |segments| {
+            segments
+                .find(|segment| segment.contains(beats))
+                .map_or(initial_tempo, |segment| Tempo(segment.tempo_at(beats)))
+        })
+    }
+
+    /// Returns the time signature in effect at the given time.
+    pub fn time_signature_at(
+        &self,
+        initial_time_signature: TimeSignature,
+        time: MusicalTime,
+    ) -> TimeSignature {
+        self.time_signature_changes
+            .iter()
+            .take_while(|c| c.time <= time)
+            .last()
+            .map_or(initial_time_signature, |c| c.time_signature)
+    }
+
+    /// Returns the [MusicalTime] at which the given frame occurs.
+    pub fn frames_to_time(
+        &self,
+        initial_tempo: Tempo,
+        sample_rate: SampleRate,
+        frames: usize,
+    ) -> MusicalTime {
+        if self.tempo_changes.is_empty() {
+            return MusicalTime::new_with_frames(initial_tempo, sample_rate, frames);
+        }
+        let mut seconds = frames as f64 / sample_rate.0 as f64;
+        let beats = self.with_segments(initial_tempo, |segments| {
+            for segment in segments {
+                let segment_seconds = segment.seconds(segment.end_beats);
+                if seconds < segment_seconds {
+                    return segment.start_beats + segment.beats(seconds);
+                }
+                seconds -= segment_seconds;
+            }
+            0.0
+        });
+        MusicalTime::new_with_units(Self::beats_to_units(beats))
+    }
+
+    /// Returns the frame at which the given [MusicalTime] occurs.
+    pub fn time_to_frames(
+        &self,
+        initial_tempo: Tempo,
+        sample_rate: SampleRate,
+        time: MusicalTime,
+    ) -> usize {
+        if self.tempo_changes.is_empty() {
+            return time.as_frames(initial_tempo, sample_rate);
+        }
+        let beats = Self::units_to_beats(time.total_units());
+        let seconds = self.with_segments(initial_tempo, |segments| {
+            let mut seconds = 0.0;
+            for segment in segments {
+                if segment.contains(beats) {
+                    seconds += segment.seconds(Some(beats));
+                    break;
+                }
+                seconds += segment.seconds(segment.end_beats);
+            }
+            seconds
+        });
+        (seconds * sample_rate.0 as f64 + 0.5) as usize
+    }
+
+    // Calls f with the segments for the given initial tempo, using the cached
+    // ones if they match. If they don't, the segments are worked out as f
+    // goes, rather than collected, so that this never allocates.
+    fn with_segments<T>(
+        &self,
+        initial_tempo: Tempo,
+        f: impl FnOnce(&mut dyn Iterator<Item = TempoSegment>) -> T,
+    ) -> T {
+        if self.e.segments_tempo == Some(initial_tempo) {
+            f(&mut self.e.segments.iter().copied())
+        } else {
+            f(&mut self.segments(initial_tempo))
+        }
+    }
+
+    fn rebuild_segments(&mut self) {
+        if let Some(initial_tempo) = self.e.segments_tempo {
+            let mut segments = core::mem::take(&mut self.e.segments);
+            segments.clear();
+            segments.extend(self.segments(initial_tempo));
+            self.e.segments = segments;
+        }
+    }
+
+    // Splits the timeline into stretches where the tempo is either constant or
+    // ramping linearly. The last segment is open-ended.
+    fn segments(&self, initial_tempo: Tempo) -> impl Iterator<Item = TempoSegment> + '_ {
+        let mut start_beats = 0.0;
+        let mut start_tempo = initial_tempo.0;
+        self.tempo_changes
+            .iter()
+            .map(Some)
+            .chain(core::iter::once(None))
+            .filter_map(move |change| {
+                if let Some(change) = change {
+                    let end_beats = Self::units_to_beats(change.time.total_units());
+                    let segment = (end_beats > start_beats).then_some(TempoSegment {
+                        start_beats,
+                        end_beats: Some(end_beats),
+                        start_tempo,
+                        end_tempo: match change.transition {
+                            TempoTransition::Jump => start_tempo,
+                            TempoTransition::Ramp => change.tempo.0,
+                        },
+                    });
+                    start_beats = end_beats;
+                    start_tempo = change.tempo.0;
+                    segment
+                } else {
+                    Some(TempoSegment {
+                        start_beats,
+                        end_beats: None,
+                        start_tempo,
+                        end_tempo: start_tempo,
+                    })
+                }
+            })
+    }
+
+    fn units_to_beats(units: usize) -> f64 {
+        units as f64 / MusicalTime::UNITS_IN_BEAT as f64
+    }
+
+    fn beats_to_units(beats: f64) -> usize {
+        (beats * MusicalTime::UNITS_IN_BEAT as f64 + 0.5) as usize
+    }
+}
+
+// A stretch of a [TempoMap] in which the tempo (in BPM) changes linearly with
+// respect to musical time. Constant tempo is just a ramp that doesn't go
+// anywhere.
+#[derive(Clone, Copy, Debug)]
+struct TempoSegment {
+    start_beats: f64,
+    end_beats: Option<f64>,
+    start_tempo: f64,
+    end_tempo: f64,
+}
+impl TempoSegment {
+    // Ramps flatter than this are treated as constant, which avoids dividing
+    // by (nearly) zero.
+    const MIN_SLOPE: f64 = 1e-9;
+
+    fn contains(&self, beats: f64) -> bool {
+        !matches!(self.end_beats, Some(end_beats) if beats >= end_beats)
+    }
+
+    // The change in BPM per beat.
+    fn slope(&self) -> f64 {
+        match self.end_beats {
+            Some(end_beats) => (self.end_tempo - self.start_tempo) / (end_beats - self.start_beats),
+            None => 0.0,
+        }
+    }
+
+    fn tempo_at(&self, beats: f64) -> f64 {
+        self.start_tempo + self.slope() * (beats - self.start_beats)
+    }
+
+    // How many seconds it takes to get from the start of the segment to the
+    // given beat. None means the end of an open-ended segment.
+    fn seconds(&self, beats: Option<f64>) -> f64 {
+        if let Some(beats) = beats {
+            let slope = self.slope();
+            let elapsed_beats = beats - self.start_beats;
+            if slope.abs() < Self::MIN_SLOPE {
+                elapsed_beats * 60.0 / self.start_tempo
+            } else {
+                // dt/db = 60 / tempo(b), integrated over the segment.
+                60.0 / slope * (self.tempo_at(beats) / self.start_tempo).ln()
+            }
+        } else {
+            f64::INFINITY
+        }
+    }
+
+    // The inverse of seconds(): how many beats into the segment we get in the
+    // given number of seconds.
+    fn beats(&self, seconds: f64) -> f64 {
+        let slope = self.slope();
+        if slope.abs() < Self::MIN_SLOPE {
+            seconds * self.start_tempo / 60.0
+        } else {
+            let tempo = self.start_tempo * (seconds * slope / 60.0).exp();
+            (tempo - self.start_tempo) / slope
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1000,6 +1400,214 @@ mod tests {
         }
     }
 
+    #[test]
+    fn tempo_map_jump() {
+        let sample_rate = SampleRate::DEFAULT;
+        let initial_tempo = Tempo(60.0);
+        let mut map = TempoMap::default();
+        assert_eq!(
+            map.time_to_frames(initial_tempo, sample_rate, MusicalTime::new_with_beats(6)),
+            MusicalTime::new_with_beats(6).as_frames(initial_tempo, sample_rate),
+            "An empty map should behave like a constant tempo"
+        );
+
+        map.add_tempo_change(TempoChange {
+            time: MusicalTime::new_with_beats(4),
+            tempo: Tempo(120.0),
+            transition: TempoTransition::Jump,
+        })
+        .unwrap();
+        assert_eq!(
+            map.tempo_at(initial_tempo, MusicalTime::new_with_beats(3)),
+            initial_tempo
+        );
+        assert_eq!(
+            map.tempo_at(initial_tempo, MusicalTime::new_with_beats(4)),
+            Tempo(120.0)
+        );
+
+        // Four beats at 60 BPM, then two at 120 BPM.
+        assert_eq!(
+            map.time_to_frames(initial_tempo, sample_rate, MusicalTime::new_with_beats(4)),
+            4 * sample_rate.0
+        );
+        assert_eq!(
+            map.time_to_frames(initial_tempo, sample_rate, MusicalTime::new_with_beats(6)),
+            5 * sample_rate.0
+        );
+        assert_eq!(
+            map.frames_to_time(initial_tempo, sample_rate, 5 * sample_rate.0),
+            MusicalTime::new_with_beats(6)
+        );
+
+        // Replacing a change at the same time doesn't duplicate it.
+        map.add_tempo_change(TempoChange {
+            time: MusicalTime::new_with_beats(4),
+            tempo: Tempo(30.0),
+            transition: TempoTransition::Jump,
+        })
+        .unwrap();
+        assert_eq!(map.tempo_changes().len(), 1);
+        assert_eq!(
+            map.frames_to_time(initial_tempo, sample_rate, 6 * sample_rate.0),
+            MusicalTime::new_with_beats(5)
+        );
+
+        for tempo in [0.0, -60.0, ParameterType::NAN, Tempo::MAX_VALUE + 1.0] {
+            assert!(
+                map.add_tempo_change(TempoChange {
+                    time: MusicalTime::new_with_beats(8),
+                    tempo: Tempo(tempo),
+                    transition: TempoTransition::Ramp,
+                })
+                .is_err(),
+                "A tempo of {tempo} should be rejected"
+            );
+        }
+        assert_eq!(
+            map.tempo_changes().len(),
+            1,
+            "A rejected change shouldn't be added"
+        );
+    }
+
+    #[test]
+    fn tempo_map_ramp() {
+        let sample_rate = SampleRate::DEFAULT;
+        let initial_tempo = Tempo(60.0);
+        let mut map = TempoMap::default();
+        map.add_tempo_change(TempoChange {
+            time: MusicalTime::new_with_beats(4),
+            tempo: Tempo(120.0),
+            transition: TempoTransition::Ramp,
+        })
+        .unwrap();
+        assert_eq!(
+            map.tempo_at(initial_tempo, MusicalTime::new_with_beats(2)),
+            Tempo(90.0),
+            "Halfway through the ramp should be halfway between the tempos"
+        );
+        assert_eq!(
+            map.tempo_at(initial_tempo, MusicalTime::new_with_beats(8)),
+            Tempo(120.0)
+        );
+
+        // Accelerating from 60 to 120 BPM over four beats takes 4 * ln(2)
+        // seconds, which is less than four beats at 60 BPM and more than four
+        // beats at 120 BPM.
+        let ramp_frames =
+            map.time_to_frames(initial_tempo, sample_rate, MusicalTime::new_with_beats(4));
+        assert_eq!(
+            ramp_frames,
+            (4.0 * 2.0f64.ln() * sample_rate.0 as f64 + 0.5) as usize
+        );
+        assert_eq!(
+            map.time_to_frames(initial_tempo, sample_rate, MusicalTime::new_with_beats(6)),
+            ramp_frames + sample_rate.0
+        );
+
+        for beats in [0.0, 0.5, 1.0, 2.75, 3.99, 4.0, 7.5] {
+            let time = MusicalTime::new_with_fractional_beats(beats);
+            let frames = map.time_to_frames(initial_tempo, sample_rate, time);
+            let round_trip = map.frames_to_time(initial_tempo, sample_rate, frames);
+            assert!(
+                round_trip.total_units().abs_diff(time.total_units()) < 4,
+                "{time} -> {frames} frames -> {round_trip} should round-trip"
+            );
+        }
+    }
+
+    #[test]
+    fn tempo_map_cache_matches_uncached() {
+        let sample_rate = SampleRate(44100);
+        let mut uncached = TempoMap::default();
+        let mut cached = TempoMap::default();
+        cached.update_initial_tempo(Tempo(90.0));
+        for map in [&mut uncached, &mut cached] {
+            map.add_tempo_change(TempoChange {
+                time: MusicalTime::new_with_beats(4),
+                tempo: Tempo(150.0),
+                transition: TempoTransition::Ramp,
+            })
+            .unwrap();
+            map.add_tempo_change(TempoChange {
+                time: MusicalTime::new_with_beats(12),
+                tempo: Tempo(70.0),
+                transition: TempoTransition::Jump,
+            })
+            .unwrap();
+        }
+        assert_eq!(cached, uncached, "The cache shouldn't affect equality");
+
+        // The cache is for 90 BPM, so asking about 100 BPM has to work without
+        // it.
+        for initial_tempo in [Tempo(90.0), Tempo(100.0)] {
+            for beats in [0, 2, 4, 8, 12, 20] {
+                let time = MusicalTime::new_with_beats(beats);
+                assert_eq!(
+                    cached.tempo_at(initial_tempo, time),
+                    uncached.tempo_at(initial_tempo, time)
+                );
+                let frames = uncached.time_to_frames(initial_tempo, sample_rate, time);
+                assert_eq!(
+                    cached.time_to_frames(initial_tempo, sample_rate, time),
+                    frames
+                );
+                assert_eq!(
+                    cached.frames_to_time(initial_tempo, sample_rate, frames),
+                    uncached.frames_to_time(initial_tempo, sample_rate, frames)
+                );
+            }
+        }
+
+        cached.remove_tempo_change(MusicalTime::new_with_beats(4));
+        assert_eq!(
+            cached.tempo_at(Tempo(90.0), MusicalTime::new_with_beats(8)),
+            Tempo(90.0),
+            "Removing a change should update the cache"
+        );
+    }
+
+    #[test]
+    fn tempo_map_time_signatures() {
+        let mut map = TempoMap::default();
+        map.add_time_signature_change(TimeSignatureChange {
+            time: MusicalTime::new_with_beats(8),
+            time_signature: TimeSignature::WALTZ_TIME,
+        });
+        map.add_time_signature_change(TimeSignatureChange {
+            time: MusicalTime::new_with_beats(4),
+            time_signature: TimeSignature::CUT_TIME,
+        });
+        assert_eq!(
+            map.time_signature_changes()
+                .iter()
+                .map(|c| c.time)
+                .collect::<Vec<_>>(),
+            vec![
+                MusicalTime::new_with_beats(4),
+                MusicalTime::new_with_beats(8)
+            ],
+            "Changes should be kept in order"
+        );
+        let initial = TimeSignature::COMMON_TIME;
+        assert_eq!(
+            map.time_signature_at(initial, MusicalTime::new_with_beats(3)),
+            initial
+        );
+        assert_eq!(
+            map.time_signature_at(initial, MusicalTime::new_with_beats(4)),
+            TimeSignature::CUT_TIME
+        );
+        assert_eq!(
+            map.time_signature_at(initial, MusicalTime::new_with_beats(100)),
+            TimeSignature::WALTZ_TIME
+        );
+
+        let json = serde_json::to_string(&map).unwrap();
+        assert_eq!(serde_json::from_str::<TempoMap>(&json).unwrap(), map);
+    }
+
     #[test]
     fn sample_rate_math() {
         let sample_rate = SampleRate::from(44100);
@@ -1027,8 +1635,17 @@ mod tests {
         );
         t.control_set_param_by_index(TEMPO_INDEX, ControlValue::MAX);
         assert_eq!(t.tempo(), Tempo::from(Tempo::MAX_VALUE));
+        assert_eq!(
+            t.control_get_param_by_index(TEMPO_INDEX),
+            Some(ControlValue::MAX)
+        );
         t.control_set_param_by_index(TEMPO_INDEX, ControlValue::MIN);
         assert_eq!(t.tempo(), Tempo::from(Tempo::MIN_VALUE));
+        assert_eq!(
+            t.control_get_param_by_index(ControlIndex(1)),
+            None,
+            "There's no parameter past the last one"
+        );
     }
 
     #[test]
@@ -2,6 +2,7 @@
 
 //! Provides a programmatic way to load music samples.
 
+use super::Paths;
 use crate::types::MidiNote;
 use once_cell::sync::OnceCell;
 use serde::{Deserialize, Serialize};
@@ -26,7 +27,7 @@ pub fn init_sample_libraries() {
 pub struct SampleIndex(pub usize);
 
 /// Generally identifies a sample. TODO: this is hacky and not actually designed
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum SampleSource {
     /// The sample is the Nth in the library
     SampleLibrary(SampleIndex),
@@ -38,6 +39,24 @@ impl Default for SampleSource {
         SampleSource::SampleLibrary(SampleIndex::default())
     }
 }
+impl SampleSource {
+    /// Returns the path, relative to the samples directory, where this sample
+    /// can be found.
+    pub fn build_path(&self) -> anyhow::Result<PathBuf> {
+        match self {
+            SampleSource::SampleLibrary(index) => {
+                if let Some(path) = SampleLibrary::global().path(*index) {
+                    Ok(Paths::global().build_sample(&Vec::default(), Path::new(&path)))
+                } else {
+                    Err(anyhow::anyhow!("Couldn't find sample {index} in library"))
+                }
+            }
+            SampleSource::Path(path_buf) => {
+                Ok(Paths::global().build_sample(&Vec::default(), Path::new(&path_buf)))
+            }
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct SampleItem {
@@ -1,36 +1,22 @@
 // Copyright (c) 2024 Mike Tsao
 
-use ensnare::{entities::Gain, prelude::*, util::init_sample_libraries};
-
-// Demonstrates sidechaining (which could be considered a kind of automation,
-// but it's important enough to put top-level and make sure it's a good
-// experience and not merely possible).
-//
-// There are two tracks: lead and rhythm. The rhythm track's output should
-// inversely affect the lead track's gain, so that the lead track makes acoustic
-// "room" for the rhythm. (Also called "ducking," as in the lead should "duck"
-// out of the way of the rhythm.) Turning off the rhythm track's output, rather
-// than mixing it into the final track, should leave empty spaces in the lead
-// output and make it easier to see the effect in Audacity.
-//
-// TODO: this isn't a useful form of sidechaining, because it uses a
-// sample-by-sample correction, which basically means that the lead track gets
-// all sorts of transients and awfulness, rather than being nicely ducked. The
-// missing piece is an envelope that adjusts relatively slowly to the amplitude
-// of the source signal.
-#[test]
-fn demo_sidechaining() {
-    Paths::set_instance(Paths::default());
-    init_sample_libraries();
-    let factory =
-        SimpleEntities::register(BuiltInEntities::register(EntityFactory::default())).finalize();
-
-    let mut project = Project::default();
+use ensnare::{
+    cores::{CompressorCoreBuilder, CompressorDetection, EnvelopeFollowerCoreBuilder},
+    entities::{Compressor, EnvelopeFollower, FmSynth, Gain},
+    prelude::*,
+    util::init_sample_libraries,
+};
 
+// Sets up the rhythm track, which plays a kick on every beat but isn't heard,
+// and the lead track, which plays a steady tone. Returns their uids, in that
+// order.
+fn set_up_kick_and_lead(
+    project: &mut Project,
+    factory: &EntityFactory<dyn Entity>,
+) -> (TrackUid, TrackUid) {
     // Add the sidechain source track.
     let sidechain_track_uid = project.create_track().unwrap();
     project.set_track_midi_channel(sidechain_track_uid, MidiChannel::DRUM);
-    project.set_track_output(sidechain_track_uid, Normal::from(0.5));
 
     // Drumkit notes
     const RR: u8 = 255; // Rest
@@ -61,30 +47,16 @@ fn demo_sidechaining() {
             MusicalTime::START
         )
         .is_ok());
-    let _drum_instrument_uid = project
-        .add_entity(
-            sidechain_track_uid,
-            factory
-                .new_entity(
-                    &EntityKey::from(SimpleInstrumentDrone::ENTITY_KEY),
-                    Uid::default(),
-                )
-                .unwrap(),
-        )
-        .unwrap();
 
-    // This turns the chain's audio output into Control events.
-    let signal_passthrough_uid = project
-        .add_entity(
-            sidechain_track_uid,
-            factory
-                .new_entity(
-                    &EntityKey::from("signal-amplitude-inverted-passthrough"),
-                    Uid::default(),
-                )
-                .unwrap(),
-        )
+    // A short, punchy synth note stands in for a kick drum.
+    let mut kick = factory
+        .new_entity(&EntityKey::from(FmSynth::ENTITY_KEY), Uid::default())
         .unwrap();
+    for name in ["carrier-envelope-release", "modulator-envelope-release"] {
+        let index = kick.control_index_for_name(name).unwrap();
+        kick.control_set_param_by_index(index, Normal::from(0.002).into());
+    }
+    let _drum_instrument_uid = project.add_entity(sidechain_track_uid, kick).unwrap();
 
     // In this demo, we don't want to hear the kick track.
     project.set_track_output(sidechain_track_uid, Normal::zero());
@@ -119,19 +91,143 @@ fn demo_sidechaining() {
         )
         .unwrap();
 
+    (sidechain_track_uid, lead_track_uid)
+}
+
+// Renders a few beats and compares the lead's level just after each kick with
+// its level just before the next one.
+fn assert_lead_ducks(project: &mut Project) {
+    project.skip_to_start();
+    project.play();
+    let frames_per_beat = project.transport.time_to_frames(MusicalTime::ONE_BEAT);
+    let mut rendered = vec![StereoSample::SILENCE; frames_per_beat * 4];
+    for chunk in rendered.chunks_mut(64) {
+        project.generate_audio(chunk, None);
+    }
+    project.stop();
+
+    let rms = |samples: &[StereoSample]| {
+        (samples.iter().map(|s| s.0 .0 * s.0 .0).sum::<f64>() / samples.len() as f64).sqrt()
+    };
+    let window = frames_per_beat / 16;
+    for beat in 1..4 {
+        let kick_start = beat * frames_per_beat;
+        let ducked = rms(&rendered[kick_start + window..kick_start + window * 2]);
+        let open = rms(&rendered[kick_start + window * 15..kick_start + window * 16]);
+        assert!(open > 0.0, "The lead should be audible between kicks");
+        assert!(
+            ducked < open * 0.5,
+            "The lead should duck under the kick on beat {beat} ({ducked} vs. {open})"
+        );
+    }
+}
+
+// Demonstrates sidechaining (which could be considered a kind of automation,
+// but it's important enough to put top-level and make sure it's a good
+// experience and not merely possible).
+//
+// There are two tracks: lead and rhythm. The rhythm track's output should
+// inversely affect the lead track's gain, so that the lead track makes acoustic
+// "room" for the rhythm. (Also called "ducking," as in the lead should "duck"
+// out of the way of the rhythm.) Turning off the rhythm track's output, rather
+// than mixing it into the final track, should leave dips in the lead output
+// and make it easier to see the effect in Audacity.
+//
+// The ducking is done by a compressor on the lead track whose sidechain input
+// is the rhythm track. Its envelope follower reacts quickly when a kick starts
+// and lets go gradually afterward, so the lead dips smoothly rather than
+// picking up the kick's waveform.
+#[test]
+fn demo_sidechaining() {
+    Paths::set_instance(Paths::default());
+    init_sample_libraries();
+    let factory =
+        SimpleEntities::register(BuiltInEntities::register(EntityFactory::default())).finalize();
+
+    let mut project = Project::default();
+    let (sidechain_track_uid, lead_track_uid) = set_up_kick_and_lead(&mut project, &factory);
+
+    // The compressor listens to the kick track and turns down the lead.
+    let compressor_uid = project
+        .add_entity(
+            lead_track_uid,
+            Box::new(Compressor::new_with(
+                Uid::default(),
+                CompressorCoreBuilder::default()
+                    .threshold(0.05.into())
+                    .ratio(0.1.into())
+                    .attack(0.05.into())
+                    .release(0.05.into())
+                    .detection(CompressorDetection::Rms)
+                    .build()
+                    .unwrap(),
+            )),
+        )
+        .unwrap();
+    assert!(project
+        .set_sidechain(compressor_uid, Some(sidechain_track_uid))
+        .is_ok());
+    assert!(
+        project
+            .set_sidechain(compressor_uid, Some(lead_track_uid))
+            .is_err(),
+        "A track can't drive a sidechain on itself"
+    );
+    assert_eq!(project.sidechain(compressor_uid), Some(sidechain_track_uid));
+
+    let output_prefix: std::path::PathBuf = [env!("CARGO_TARGET_TMPDIR"), "sidechaining"]
+        .iter()
+        .collect();
+    assert!(project.save_and_export(output_prefix).is_ok());
+
+    assert_lead_ducks(&mut project);
+}
+
+// The same scenario as demo_sidechaining(), but done with automation: an
+// envelope follower on the rhythm track drives a gain on the lead track.
+#[test]
+fn demo_envelope_follower_ducking() {
+    Paths::set_instance(Paths::default());
+    init_sample_libraries();
+    let factory =
+        SimpleEntities::register(BuiltInEntities::register(EntityFactory::default())).finalize();
+
+    let mut project = Project::default();
+    let (sidechain_track_uid, lead_track_uid) = set_up_kick_and_lead(&mut project, &factory);
+
+    // The louder the kick, the lower the follower's output.
+    let follower_uid = project
+        .add_entity(
+            sidechain_track_uid,
+            Box::new(EnvelopeFollower::new_with(
+                Uid::default(),
+                EnvelopeFollowerCoreBuilder::default()
+                    .attack(0.02.into())
+                    .release(0.05.into())
+                    .threshold(0.02.into())
+                    .ceiling(0.2.into())
+                    .silent_output(Normal::maximum())
+                    .loud_output(0.1.into())
+                    .build()
+                    .unwrap(),
+            )),
+        )
+        .unwrap();
+
     let entity = factory
         .new_entity(&EntityKey::from(Gain::ENTITY_KEY), Uid::default())
         .unwrap();
     let gain_ceiling_param_index = entity.control_index_for_name("ceiling").unwrap();
     let gain_uid = project.add_entity(lead_track_uid, entity).unwrap();
-
-    // Link the sidechain control to the synth's gain.
     assert!(project
-        .link(signal_passthrough_uid, gain_uid, gain_ceiling_param_index)
+        .link(follower_uid, gain_uid, gain_ceiling_param_index)
         .is_ok());
 
-    let output_prefix: std::path::PathBuf = [env!("CARGO_TARGET_TMPDIR"), "sidechaining"]
-        .iter()
-        .collect();
+    let output_prefix: std::path::PathBuf =
+        [env!("CARGO_TARGET_TMPDIR"), "envelope-follower-ducking"]
+            .iter()
+            .collect();
     assert!(project.save_and_export(output_prefix).is_ok());
+
+    assert_lead_ducks(&mut project);
 }